@@ -0,0 +1,199 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Config file names Cargo reads inside a `.cargo` directory, in precedence order
+///
+/// When both exist Cargo uses the extension-less `config` (and warns about it).
+const CONFIG_FILE_NAMES: [&str; 2] = ["config", "config.toml"];
+
+/// Environment variables that override every config file, in precedence order
+const TARGET_DIR_ENV_VARS: [&str; 2] = ["CARGO_TARGET_DIR", "CARGO_BUILD_TARGET_DIR"];
+
+/// Where a project's target directory setting came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetDirSource {
+    /// One of [`TARGET_DIR_ENV_VARS`]
+    Env(&'static str),
+    /// `[build] target-dir` in a config file inside this `.cargo` directory (or `$CARGO_HOME`)
+    Config(PathBuf),
+    /// No setting; Cargo's default `<project>/target`
+    Default,
+}
+
+impl std::fmt::Display for TargetDirSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Env(var) => write!(f, "set by ${}", var),
+            Self::Config(dir) => write!(f, "set by [build] target-dir in {}", dir.display()),
+            Self::Default => write!(f, "the default <project>/target"),
+        }
+    }
+}
+
+/// Resolve the directory `cargo build` writes artifacts to for the project in `project_dir`
+///
+/// `CARGO_TARGET_DIR` (or `CARGO_BUILD_TARGET_DIR`) wins, resolved against the current
+/// directory like Cargo does; otherwise see [`configured_target_dir`].
+pub fn resolve_target_dir(project_dir: &Path) -> PathBuf {
+    resolve_target_dir_with_source(project_dir).0
+}
+
+/// Like [`resolve_target_dir`], also reporting which setting decided the result
+pub fn resolve_target_dir_with_source(project_dir: &Path) -> (PathBuf, TargetDirSource) {
+    let from_env = TARGET_DIR_ENV_VARS
+        .iter()
+        .find_map(|&name| std::env::var_os(name).filter(|value| !value.is_empty()).map(|value| (name, value)));
+    match from_env {
+        Some((name, value)) => (std::env::current_dir().unwrap_or_default().join(value), TargetDirSource::Env(name)),
+        None => configured_target_dir(project_dir),
+    }
+}
+
+/// Resolve `[build] target-dir` from Cargo config files
+///
+/// Mirrors Cargo's lookup: `.cargo/config.toml` files are read from `project_dir`
+/// upwards (closest wins), then from Cargo's home ([`cargo_home`]). Relative paths are
+/// resolved against the directory containing `.cargo`. Falls back to `<project>/target`.
+fn configured_target_dir(project_dir: &Path) -> (PathBuf, TargetDirSource) {
+    config_dirs(project_dir)
+        .iter()
+        .find_map(|(config_dir, base)| {
+            let target_dir = PathBuf::from(read_target_dir(config_dir)?);
+            let target_dir = if target_dir.is_absolute() { target_dir } else { base.join(target_dir) };
+            Some((target_dir, TargetDirSource::Config(config_dir.clone())))
+        })
+        .unwrap_or_else(|| (project_dir.join("target"), TargetDirSource::Default))
+}
+
+/// The `.cargo` directories whose config files apply to `project_dir`, closest first, each
+/// with the directory relative target dirs in it are resolved against
+fn config_dirs(project_dir: &Path) -> Vec<(PathBuf, PathBuf)> {
+    let mut config_dirs: Vec<(PathBuf, PathBuf)> = project_dir
+        .ancestors()
+        .map(|dir| (dir.join(".cargo"), dir.to_path_buf()))
+        .collect();
+    if let Some(cargo_home) = cargo_home() {
+        let base = cargo_home.parent().map(Path::to_path_buf).unwrap_or_default();
+        if !config_dirs.iter().any(|(config_dir, _)| *config_dir == cargo_home) {
+            config_dirs.push((cargo_home, base));
+        }
+    }
+    config_dirs
+}
+
+/// Every config file that could set the target directory of the project in `project_dir`,
+/// whether it exists or not
+pub fn target_dir_config_files(project_dir: &Path) -> Vec<PathBuf> {
+    config_dirs(project_dir)
+        .into_iter()
+        .flat_map(|(config_dir, _)| CONFIG_FILE_NAMES.map(|name| config_dir.join(name)))
+        .collect()
+}
+
+/// Cargo's home directory: `$CARGO_HOME`, or `.cargo` in the user's home directory
+/// when unset, where Cargo reads the user-wide config file
+pub(crate) fn cargo_home() -> Option<PathBuf> {
+    cargo_home_from(|name| std::env::var_os(name))
+}
+
+/// [`cargo_home`] with the environment looked up through `env`
+fn cargo_home_from(env: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    let non_empty = |name| env(name).filter(|value| !value.is_empty());
+    non_empty("CARGO_HOME").map(PathBuf::from).or_else(|| {
+        non_empty("HOME")
+            .or_else(|| non_empty("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(".cargo"))
+    })
+}
+
+/// Read `build.target-dir` from the first config file present in `config_dir`
+fn read_target_dir(config_dir: &Path) -> Option<String> {
+    let content = CONFIG_FILE_NAMES
+        .iter()
+        .find_map(|name| std::fs::read_to_string(config_dir.join(name)).ok())?;
+    let config: toml::Value = toml::from_str(&content).ok()?;
+    config
+        .get("build")?
+        .get("target-dir")?
+        .as_str()
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_config(dir: &Path, file_name: &str, content: &str) {
+        std::fs::create_dir_all(dir.join(".cargo")).unwrap();
+        std::fs::write(dir.join(".cargo").join(file_name), content).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_target_dir_default() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_eq!(
+            configured_target_dir(temp_dir.path()),
+            (temp_dir.path().join("target"), TargetDirSource::Default)
+        );
+    }
+
+    #[test]
+    fn test_resolve_target_dir_relative_to_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_dir = temp_dir.path().join("app");
+        std::fs::create_dir(&project_dir).unwrap();
+        write_config(&project_dir, "config.toml", "[build]\ntarget-dir = \"../app-target\"\n");
+
+        assert_eq!(
+            configured_target_dir(&project_dir),
+            (project_dir.join("../app-target"), TargetDirSource::Config(project_dir.join(".cargo")))
+        );
+    }
+
+    #[test]
+    fn test_resolve_target_dir_closest_config_wins() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_dir = temp_dir.path().join("app");
+        std::fs::create_dir(&project_dir).unwrap();
+        write_config(temp_dir.path(), "config.toml", "[build]\ntarget-dir = \"/shared/target\"\n");
+        write_config(&project_dir, "config", "[build]\ntarget-dir = \"out\"\n");
+        assert_eq!(configured_target_dir(&project_dir).0, project_dir.join("out"));
+
+        // Configs without a target-dir don't shadow parents
+        std::fs::remove_file(project_dir.join(".cargo/config")).unwrap();
+        write_config(&project_dir, "config.toml", "[net]\noffline = true\n");
+        assert_eq!(configured_target_dir(&project_dir).0, PathBuf::from("/shared/target"));
+    }
+
+    #[test]
+    fn test_cargo_home_falls_back_to_home() {
+        let cargo_home_with = |vars: &[(&str, &str)]| {
+            cargo_home_from(|name| vars.iter().find(|(var, _)| *var == name).map(|(_, value)| value.into()))
+        };
+        let home = Some(PathBuf::from("/home/me/.cargo"));
+        assert_eq!(cargo_home_with(&[("CARGO_HOME", "/opt/cargo"), ("HOME", "/home/me")]), Some("/opt/cargo".into()));
+        assert_eq!(cargo_home_with(&[("HOME", "/home/me")]), home);
+        assert_eq!(cargo_home_with(&[("CARGO_HOME", ""), ("HOME", "/home/me")]), home);
+        assert_eq!(cargo_home_with(&[("USERPROFILE", "/users/me")]), Some(PathBuf::from("/users/me/.cargo")));
+        assert_eq!(cargo_home_with(&[]), None);
+    }
+
+    #[test]
+    fn test_extensionless_config_wins_over_config_toml() {
+        let temp_dir = TempDir::new().unwrap();
+        write_config(temp_dir.path(), "config.toml", "[build]\ntarget-dir = \"from-toml\"\n");
+        write_config(temp_dir.path(), "config", "[build]\ntarget-dir = \"from-config\"\n");
+        assert_eq!(configured_target_dir(temp_dir.path()).0, temp_dir.path().join("from-config"));
+    }
+
+    #[test]
+    fn test_env_references_are_not_expanded() {
+        let temp_dir = TempDir::new().unwrap();
+        write_config(temp_dir.path(), "config.toml", "[build]\ntarget-dir = \"${CARGO_WORKSPACE_DIR}/out\"\n");
+        assert_eq!(
+            configured_target_dir(temp_dir.path()).0,
+            temp_dir.path().join("${CARGO_WORKSPACE_DIR}/out")
+        );
+    }
+}
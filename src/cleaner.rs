@@ -1,7 +1,13 @@
 use anyhow::{Context, Result};
-use crate::project::Project;
-use crate::utils::get_directory_size;
-use std::process::Command;
+use crate::project::{CargoStatus, Project};
+use crate::throttle::IoThrottle;
+use crate::triples::{find_triples, TripleSize};
+use crate::utils::{file_size, file_timestamp, format_duration, get_directory_size, unix_now, SizeOptions};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::Arc;
 
 #[derive(Debug, serde::Serialize)]
 pub struct CleanResult {
@@ -9,69 +15,2097 @@ pub struct CleanResult {
     pub success: bool,
     pub freed_bytes: u64,
     pub error: Option<String>,
+    /// Modification time of the oldest target artifact (seconds since the Unix epoch)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oldest_artifact: Option<u64>,
+    /// Modification time of the newest target artifact (seconds since the Unix epoch)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub newest_artifact: Option<u64>,
+    /// `warning:` lines printed by a successful `cargo clean`; reported as summary warnings
+    #[serde(skip)]
+    pub cargo_warnings: Vec<String>,
+    /// Target files left in place by `--safe` because they are not recognized cargo artifacts
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unrecognized_files: Vec<String>,
+    /// Git-tracked files under the target, relative to it, that were left in place
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tracked_files: Vec<String>,
+    /// Freed allocated blocks, net of filesystem compression where visible (`--physical`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub physical_freed_bytes: Option<u64>,
+    /// Cross-compilation output directories in the target before cleaning
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cross_targets: Vec<TripleSize>,
+    /// Freed bytes per build profile cleaned with `--profile`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub freed_by_profile: BTreeMap<String, u64>,
+    /// Freed bytes per cross-compilation target cleaned with `--triples` or `--non-host-only`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub freed_by_triple: BTreeMap<String, u64>,
+    /// Freed bytes per web framework tool whose output directories were cleaned
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub freed_by_framework: BTreeMap<String, u64>,
+    /// The directory given on the command line this project was found under, set when
+    /// several were given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    /// Bytes freed from documentation output with `--doc-only`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc_freed_bytes: Option<u64>,
+    /// Bytes freed from coverage artifacts with `--coverage`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coverage_freed_bytes: Option<u64>,
+    /// Final executables left in place by `--keep-binaries`, relative to the target
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub kept_binaries: Vec<String>,
+    /// Names of the workspace's member packages, as cargo reported them during discovery
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<String>,
+    /// Why the project was selected and how it was cleaned, present with `--explain`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub explain: Vec<String>,
 }
 
-/// Clean a single Cargo project
-pub fn clean_project(project: &Project, dry_run: bool, _verbose: bool) -> Result<CleanResult> {
-    let target_dir = project.path.join("target");
-    let freed_bytes = if target_dir.exists() {
-        get_directory_size(&target_dir).unwrap_or(0)
-    } else {
-        0
+/// Removal failed because artifacts are owned by a different user
+///
+/// Typical for target directories written from a container with UID remapping.
+#[derive(Debug)]
+pub struct OwnershipMismatch {
+    pub path: PathBuf,
+    pub owner_uid: u32,
+    pub current_uid: u32,
+}
+
+impl fmt::Display for OwnershipMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Permission denied removing {:?}: owned by UID {} but running as UID {}. \
+             The files were likely created in a container with remapped UIDs; \
+             re-run with matching privileges (e.g. inside the container or with sudo)",
+            self.path, self.owner_uid, self.current_uid
+        )
+    }
+}
+
+impl std::error::Error for OwnershipMismatch {}
+
+/// Whole-directory removal was refused because the target doesn't look like cargo's
+#[derive(Debug)]
+pub struct NotACargoTarget {
+    pub path: PathBuf,
+}
+
+impl fmt::Display for NotACargoTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Refusing to delete {:?}: it has neither cargo's CACHEDIR.TAG nor .rustc_info.json, so it may not be \
+             a cargo build directory; check its contents and re-run with --force to delete it anyway",
+            self.path
+        )
+    }
+}
+
+impl std::error::Error for NotACargoTarget {}
+
+/// The project was left alone because cargo or rustc was building it
+#[derive(Debug)]
+pub struct BuildInProgress {
+    pub path: PathBuf,
+    /// What gave the build away, e.g. `cargo holds "target/debug/.cargo-lock"`
+    pub reason: String,
+}
+
+impl fmt::Display for BuildInProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Build in progress in {:?} ({}); skipped (use --wait to wait for it)", self.path, self.reason)
+    }
+}
+
+impl std::error::Error for BuildInProgress {}
+
+/// How often a project being built is checked again with `--wait`
+const BUILD_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Compilers whose running processes mark a project as being built; `cargo` itself is
+/// not among them since `cargo watch` and similar keep it running between builds
+const COMPILER_PROCESSES: &[&str] = &["rustc", "rustdoc", "clippy-driver"];
+
+/// Why the project looks like it is being built right now, if it does
+///
+/// Cargo holds an exclusive lock on `.cargo-lock` in each profile directory it builds
+/// into (`target/debug`, `target/<triple>/release`, ...) for as long as the build runs,
+/// rust-analyzer's `cargo check` included. On Linux, `rustc`, `rustdoc` and
+/// `clippy-driver` processes working inside the project or its target count too.
+pub fn build_in_progress(project: &Project) -> Option<String> {
+    let target_dir = project.target_dir();
+    if let Some(lock) = held_cargo_lock(&target_dir) {
+        return Some(format!("cargo holds {:?}", lock));
+    }
+    #[cfg(target_os = "linux")]
+    if let Some((pid, name)) = running_compiler(&[&project.path, &target_dir]) {
+        return Some(format!("{} (pid {}) is running in it", name, pid));
+    }
+    None
+}
+
+/// A `.cargo-lock` one or two levels under `target_dir` that another process has locked
+fn held_cargo_lock(target_dir: &Path) -> Option<PathBuf> {
+    let subdirs = |dir: &Path| -> Vec<PathBuf> {
+        std::fs::read_dir(dir)
+            .map(|entries| entries.filter_map(|e| e.ok()).filter(|e| e.file_type().is_ok_and(|t| t.is_dir())).map(|e| e.path()).collect())
+            .unwrap_or_default()
     };
+    let locked = |dir: &Path| {
+        let lock = dir.join(".cargo-lock");
+        // Taking the lock is the only portable way to see whether it is held; it is released at once
+        let held = std::fs::File::open(&lock)
+            .is_ok_and(|file| matches!(file.try_lock(), Err(std::fs::TryLockError::WouldBlock)));
+        held.then_some(lock)
+    };
+    subdirs(target_dir)
+        .into_iter()
+        .find_map(|dir| locked(&dir).or_else(|| subdirs(&dir).iter().find_map(|sub| locked(sub))))
+}
+
+/// A compiler process whose working directory is inside one of `dirs`, as its pid and name
+#[cfg(target_os = "linux")]
+fn running_compiler(dirs: &[&Path]) -> Option<(u32, String)> {
+    let dirs: Vec<PathBuf> = dirs.iter().filter_map(|dir| dir.canonicalize().ok()).collect();
+    std::fs::read_dir("/proc").ok()?.filter_map(|e| e.ok()).find_map(|entry| {
+        let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+        let name = std::fs::read_to_string(entry.path().join("comm")).ok()?.trim().to_string();
+        if !COMPILER_PROCESSES.contains(&name.as_str()) {
+            return None;
+        }
+        // Other users' processes can't be inspected, and can't be building our targets anyway
+        let cwd = std::fs::read_link(entry.path().join("cwd")).ok()?;
+        dirs.iter().any(|dir| cwd.starts_with(dir)).then_some((pid, name))
+    })
+}
+
+/// Refuse to clean a project that is being built, after waiting up to `options.wait_for_builds`
+/// for the build to finish; dry runs only report it
+fn ensure_no_build(project: &Project, options: &CleanOptions) -> Result<()> {
+    let Some(mut reason) = build_in_progress(project) else {
+        return Ok(());
+    };
+    if let (Some(limit), false) = (options.wait_for_builds, options.dry_run) {
+        let start = std::time::Instant::now();
+        let limit = std::time::Duration::from_secs(limit);
+        while start.elapsed() < limit {
+            std::thread::sleep(BUILD_POLL_INTERVAL.min(limit - start.elapsed()));
+            match build_in_progress(project) {
+                Some(still) => reason = still,
+                None => return Ok(()),
+            }
+        }
+    }
+    Err(BuildInProgress { path: project.path.clone(), reason }.into())
+}
+
+/// Files cargo writes to the root of every target directory it creates
+const CARGO_TARGET_MARKERS: &[&str] = &["CACHEDIR.TAG", ".rustc_info.json"];
+
+/// The signature line that starts every `CACHEDIR.TAG`, cargo's included
+const CACHEDIR_TAG_SIGNATURE: &str = "Signature: 8a477f597d28d172789f06886806bc55";
+
+/// Whether `dir` is safe to delete wholesale as a build directory: cargo marks the
+/// targets it creates with a `CACHEDIR.TAG` and a `.rustc_info.json`, and an empty
+/// directory holds nothing to lose
+pub fn is_cargo_build_dir(dir: &Path) -> bool {
+    let tagged = std::fs::read_to_string(dir.join("CACHEDIR.TAG")).is_ok_and(|tag| tag.starts_with(CACHEDIR_TAG_SIGNATURE));
+    tagged
+        || dir.join(".rustc_info.json").is_file()
+        || std::fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_none())
+}
+
+/// Refuse to delete the project's target wholesale unless it looks like cargo's (see
+/// [`is_cargo_build_dir`]) or `options.force` is set
+///
+/// The check is on the target root, so deleting one of its profile or triple
+/// directories is covered too.
+fn ensure_cargo_target(project: &Project, options: &CleanOptions) -> Result<()> {
+    let target_root = project.target_dir();
+    if options.force || !target_root.exists() || is_cargo_build_dir(&target_root) {
+        return Ok(());
+    }
+    Err(NotACargoTarget { path: target_root }.into())
+}
+
+/// Find the first entry under `dir` not owned by `uid`
+#[cfg(unix)]
+fn find_foreign_owned(dir: &Path, uid: u32) -> Option<(PathBuf, u32)> {
+    use std::os::unix::fs::MetadataExt;
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok().map(|m| (e.into_path(), m.uid())))
+        .find(|(_, owner)| *owner != uid)
+}
+
+/// Explain a failed removal of `target_dir`, detecting ownership mismatches where possible
+fn removal_error(target_dir: &Path, error: std::io::Error) -> anyhow::Error {
+    #[cfg(unix)]
+    if error.kind() == std::io::ErrorKind::PermissionDenied {
+        // SAFETY: geteuid has no preconditions and cannot fail
+        let current_uid = unsafe { libc::geteuid() };
+        if let Some((path, owner_uid)) = find_foreign_owned(target_dir, current_uid) {
+            return OwnershipMismatch {
+                path,
+                owner_uid,
+                current_uid,
+            }
+            .into();
+        }
+    }
+    anyhow::Error::new(error).context(format!("Failed to remove target directory: {:?}", target_dir))
+}
 
-    if dry_run {
-        return Ok(CleanResult {
+/// Runs external commands; injectable so strategies can be tested without cargo
+pub trait CommandRunner: fmt::Debug + Send + Sync {
+    fn run(&self, program: &str, args: &[String], current_dir: &Path) -> std::io::Result<Output>;
+}
+
+/// Runs commands as real child processes
+#[derive(Debug, Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, program: &str, args: &[String], current_dir: &Path) -> std::io::Result<Output> {
+        Command::new(program).args(args).current_dir(current_dir).output()
+    }
+}
+
+/// Strategy selected with `--strategy`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StrategyKind {
+    /// Run `cargo clean`, deleting the target directory if it fails
+    CargoClean,
+    /// Delete the target directory without invoking cargo
+    RemoveTarget,
+    /// Move the target directory to the OS trash
+    TrashTarget,
+    /// Delete only target files matching `--glob` patterns
+    SelectiveGlob,
+}
+
+/// How a project's build artifacts are removed
+///
+/// Marker-only projects have no manifest, so `CargoClean` falls through to
+/// `RemoveTarget` for them. Every strategy other than `CargoClean` bypasses cargo,
+/// and every one except `TrashTarget` frees the space immediately; trashed
+/// targets are reported as freed but only reclaimed when the trash is emptied.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CleanStrategy {
+    /// Run `cargo clean`; if it fails and `fallback_delete` is set, delete the target directory
+    CargoClean { fallback_delete: bool },
+    /// Delete the target directory directly
+    RemoveTarget,
+    /// Move the target directory to the OS trash so it can be restored
+    TrashTarget,
+    /// Delete only files whose path relative to the target directory matches a glob
+    SelectiveGlob(Vec<String>),
+    /// Delete only recognized cargo artifacts, leaving and reporting anything else
+    Safe(ArtifactPatterns),
+}
+
+/// Extra artifact globs recognized by the `Safe` strategy
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ArtifactPatterns {
+    /// Globs relative to the target directory
+    pub patterns: Vec<String>,
+    /// Recognize only `patterns`, ignoring the built-in artifact rules
+    pub replace_builtin: bool,
+}
+
+impl Default for CleanStrategy {
+    fn default() -> Self {
+        CleanStrategy::CargoClean { fallback_delete: true }
+    }
+}
+
+/// Options controlling how a project is cleaned
+#[derive(Debug, Clone)]
+pub struct CleanOptions {
+    pub dry_run: bool,
+    pub strategy: CleanStrategy,
+    /// How freed space is measured
+    pub size_options: SizeOptions,
+    /// Limits concurrent manual deletions when set
+    pub io_throttle: Option<Arc<IoThrottle>>,
+    /// Runs `cargo` for the `CargoClean` strategy
+    pub runner: Arc<dyn CommandRunner>,
+    /// Record in `CleanResult::explain` which method was used (`--explain`)
+    pub explain: bool,
+    /// Clean only these `target/<triple>` output directories (`--triples`)
+    pub triples: Vec<String>,
+    /// Clean every `target/<triple>` output directory found, leaving host builds (`--non-host-only`)
+    pub non_host_only: bool,
+    /// Clean only these build profiles' output directories (`--profile`)
+    pub profiles: Vec<String>,
+    /// Clean only documentation output, `target/doc` (`--doc-only`)
+    pub doc_only: bool,
+    /// Clean only incremental compilation caches, `target/*/incremental` (`--incremental-only`)
+    pub incremental_only: bool,
+    /// With `incremental_only`, also remove `*.d` dep-info files (`--dep-info`)
+    pub dep_info: bool,
+    /// Keep the final executables in each profile directory (`--keep-binaries`)
+    pub keep_binaries: bool,
+    /// Delete only target files not used within this many seconds (`--sweep-older-than`)
+    pub sweep_older_than: Option<u64>,
+    /// Clean only coverage artifacts, in the project and its target (`--coverage`)
+    pub coverage: bool,
+    /// Also remove the corpora and crash artifacts of cargo-fuzz crates (`--include-corpus`)
+    pub include_corpus: bool,
+    /// Remove Criterion benchmark baselines in `target/criterion` too (`--clean-criterion`)
+    pub clean_criterion: bool,
+    /// Clean wasm-pack and trunk output directories beside the target on full cleans; on
+    /// by default (`--no-framework-outputs` turns it off)
+    pub framework_outputs: bool,
+    /// Delete target directories wholesale even when they lack cargo's markers (`--force`)
+    pub force: bool,
+    /// How many seconds to wait for a build in progress to finish before skipping the
+    /// project; `None` skips it at once (`--wait`)
+    pub wait_for_builds: Option<u64>,
+}
+
+impl Default for CleanOptions {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            strategy: CleanStrategy::default(),
+            size_options: SizeOptions::default(),
+            io_throttle: None,
+            runner: Arc::new(SystemRunner),
+            explain: false,
+            triples: Vec::new(),
+            non_host_only: false,
+            profiles: Vec::new(),
+            doc_only: false,
+            incremental_only: false,
+            dep_info: false,
+            keep_binaries: false,
+            sweep_older_than: None,
+            coverage: false,
+            include_corpus: false,
+            clean_criterion: false,
+            framework_outputs: true,
+            force: false,
+            wait_for_builds: None,
+        }
+    }
+}
+
+/// The part of a target directory being cleaned; the default is all of it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CleanScope<'a> {
+    /// Only `target/<triple>` outputs (`cargo clean --target`)
+    pub triple: Option<&'a str>,
+    /// Only one profile's outputs (`cargo clean --profile`)
+    pub profile: Option<&'a str>,
+    /// Only documentation output (`cargo clean --doc`)
+    pub doc: bool,
+}
+
+/// Build the arguments passed to `cargo` to clean a project, or the part of it in `scope`
+pub fn cargo_clean_args(project: &Project, scope: CleanScope) -> Vec<String> {
+    let mut args = vec![
+        "clean".to_string(),
+        "--manifest-path".to_string(),
+        project.path.join("Cargo.toml").to_string_lossy().to_string(),
+    ];
+    if let Some(triple) = scope.triple {
+        args.extend(["--target".to_string(), triple.to_string()]);
+    }
+    if let Some(profile) = scope.profile {
+        args.extend(["--profile".to_string(), cargo_profile_name(profile).to_string()]);
+    }
+    if scope.doc {
+        args.push("--doc".to_string());
+    }
+    args
+}
+
+/// Name cargo uses for `profile`; `debug` is accepted for the `dev` profile
+pub fn cargo_profile_name(profile: &str) -> &str {
+    if profile == "debug" {
+        "dev"
+    } else {
+        profile
+    }
+}
+
+/// Directory under the target (or `target/<triple>`) holding `profile`'s outputs
+///
+/// Cargo writes the `dev` and `test` profiles to `debug` and `bench` to `release`;
+/// every other profile, custom ones included, to a directory of its own name.
+pub fn profile_dir_name(profile: &str) -> &str {
+    match profile {
+        "dev" | "test" => "debug",
+        "bench" => "release",
+        other => other,
+    }
+}
+
+/// Extract the messages of `warning:` lines from cargo's stderr
+fn parse_cargo_warnings(stderr: &str) -> Vec<String> {
+    stderr
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("warning:"))
+        .map(|message| message.trim().to_string())
+        .filter(|message| !message.is_empty())
+        .collect()
+}
+
+impl CleanResult {
+    /// A successful result for `project`
+    pub fn cleaned(project: &Project, freed_bytes: u64) -> Self {
+        Self {
             path: project.path.to_string_lossy().to_string(),
             success: true,
             freed_bytes,
             error: None,
-        });
+            oldest_artifact: None,
+            newest_artifact: None,
+            cargo_warnings: Vec::new(),
+            unrecognized_files: Vec::new(),
+            tracked_files: Vec::new(),
+            physical_freed_bytes: None,
+            cross_targets: Vec::new(),
+            freed_by_profile: BTreeMap::new(),
+            freed_by_triple: BTreeMap::new(),
+            freed_by_framework: BTreeMap::new(),
+            root: None,
+            doc_freed_bytes: None,
+            coverage_freed_bytes: None,
+            kept_binaries: Vec::new(),
+            members: match project.cargo {
+                CargoStatus::Loaded(ref info) if project.is_workspace => info.members.clone(),
+                _ => Vec::new(),
+            },
+            explain: Vec::new(),
+        }
+    }
+
+    /// A failed result for `project`
+    pub fn failed(project: &Project, error: String) -> Self {
+        Self {
+            success: false,
+            error: Some(error),
+            ..Self::cleaned(project, 0)
+        }
     }
+}
 
-    // Try cargo clean first
-    let output = Command::new("cargo")
-        .arg("clean")
-        .current_dir(&project.path)
-        .output();
+/// Compile `--glob` patterns for the `SelectiveGlob` strategy
+pub fn compile_globs(patterns: &[String]) -> Result<Vec<glob::Pattern>> {
+    patterns
+        .iter()
+        .map(|p| glob::Pattern::new(p).with_context(|| format!("Invalid --glob pattern: {:?}", p)))
+        .collect()
+}
+
+/// Files under `target_dir` whose relative path matches one of `patterns`, or matches none with `invert`
+///
+/// Files whose relative path is in `protected` are never returned.
+fn matching_files(
+    target_dir: &Path,
+    patterns: &[glob::Pattern],
+    invert: bool,
+    protected: &HashSet<PathBuf>,
+) -> Vec<(PathBuf, std::fs::Metadata)> {
+    walkdir::WalkDir::new(target_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            e.path()
+                .strip_prefix(target_dir)
+                .map(|rel| patterns.iter().any(|p| p.matches_path(rel)) != invert && !protected.contains(rel))
+                .unwrap_or(false)
+        })
+        .filter_map(|e| e.metadata().ok().map(|m| (e.into_path(), m)))
+        .collect()
+}
 
+/// Whether a file is an executable, judged by its executable bit
+#[cfg(unix)]
+fn is_executable(_path: &Path, metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+/// Whether a file is an executable, judged by its `.exe` extension
+#[cfg(not(unix))]
+fn is_executable(path: &Path, _metadata: &std::fs::Metadata) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("exe"))
+}
+
+/// Final executables under `dir`, relative to it, for `--keep-binaries`
+///
+/// These are the executables cargo places directly in a profile directory (one holding
+/// `.fingerprint`), such as `target/release/app` or `target/<triple>/debug/app`; copies
+/// under `deps/`, `build/` or `examples/` are intermediate and not included.
+fn final_binaries(dir: &Path) -> HashSet<PathBuf> {
+    walkdir::WalkDir::new(dir)
+        .max_depth(3)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| e.path().parent().is_some_and(|parent| parent.join(".fingerprint").is_dir()))
+        .filter(|e| e.metadata().is_ok_and(|m| is_executable(e.path(), &m)))
+        .filter_map(|e| e.path().strip_prefix(dir).ok().map(Path::to_path_buf))
+        .collect()
+}
+
+/// Target subdirectory where Criterion keeps benchmark baselines and reports, kept
+/// unless `--clean-criterion` is given so later runs can still compare against them
+pub const CRITERION_DIR: &str = "criterion";
+
+/// Files of the Criterion data directory directly under `target_dir`, relative to it;
+/// empty with `options.clean_criterion`
+fn criterion_files(target_dir: &Path, options: &CleanOptions) -> HashSet<PathBuf> {
+    let criterion_dir = target_dir.join(CRITERION_DIR);
+    if options.clean_criterion || !criterion_dir.is_dir() {
+        return HashSet::new();
+    }
+    walkdir::WalkDir::new(&criterion_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.path().strip_prefix(target_dir).ok().map(Path::to_path_buf))
+        .collect()
+}
+
+/// Directories of a cargo-fuzz crate holding inputs found while fuzzing, kept unless
+/// `--include-corpus` is given since they are expensive to regenerate
+pub const FUZZ_CORPUS_DIRS: &[&str] = &["corpus", "artifacts"];
+
+/// Extensions of the LLVM coverage profiles instrumented builds and `llvm-profdata` write
+const COVERAGE_EXTENSIONS: &[&str] = &["profraw", "profdata"];
+
+/// Report files cargo-tarpaulin writes to the project root
+const COVERAGE_FILE_NAMES: &[&str] = &["tarpaulin-report.html", "tarpaulin-report.json", "tarpaulin-report.lcov"];
+
+/// Target subdirectories holding only coverage output, from cargo-tarpaulin and cargo-llvm-cov
+const COVERAGE_DIRS: &[&str] = &["tarpaulin", "llvm-cov-target"];
+
+/// Coverage artifacts of a project: profiles and tarpaulin reports anywhere in the
+/// project, and everything in the target's coverage directories
+///
+/// `.git` is skipped, as are files in `protected` (relative to the project).
+fn coverage_artifacts(
+    project_dir: &Path,
+    target_dir: &Path,
+    protected: &HashSet<PathBuf>,
+) -> Vec<(PathBuf, std::fs::Metadata)> {
+    let is_coverage_file = |path: &Path| {
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        COVERAGE_EXTENSIONS.contains(&extension) || COVERAGE_FILE_NAMES.contains(&file_name)
+    };
+    let in_coverage_dir = |path: &Path| {
+        path.strip_prefix(target_dir)
+            .is_ok_and(|rel| rel.components().any(|c| COVERAGE_DIRS.iter().any(|dir| c.as_os_str() == *dir)))
+    };
+    let mut roots = vec![project_dir];
+    if !target_dir.starts_with(project_dir) {
+        roots.push(target_dir);
+    }
+    roots
+        .into_iter()
+        .flat_map(|root| walkdir::WalkDir::new(root).into_iter().filter_entry(|e| e.file_name() != ".git"))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| is_coverage_file(e.path()) || in_coverage_dir(e.path()))
+        .filter(|e| !e.path().strip_prefix(project_dir).is_ok_and(|rel| protected.contains(rel)))
+        .filter_map(|e| e.metadata().ok().map(|m| (e.into_path(), m)))
+        .collect()
+}
+
+/// Directories in a profile directory that cargo fills with nothing but build outputs
+const ARTIFACT_DIRS: &[&str] = &["deps", "incremental", "build", ".fingerprint"];
+
+/// Entries cargo creates in every profile directory it builds into
+const PROFILE_MARKERS: &[&str] = &[".fingerprint", ".cargo-lock"];
+
+/// Extensions of compiler outputs and dep-info files
+const ARTIFACT_EXTENSIONS: &[&str] = &["rlib", "rmeta", "d", "so", "dylib", "dll", "a", "lib", "pdb", "exe", "wasm"];
+
+/// Bookkeeping files cargo writes into target directories
+const ARTIFACT_FILE_NAMES: &[&str] = &[".cargo-lock", ".rustc_info.json", "CACHEDIR.TAG"];
+
+/// Whether `dir` is one of cargo's profile directories: `debug` and `release`, or any
+/// directory holding cargo's per-profile bookkeeping
+fn is_profile_dir(dir: &Path) -> bool {
+    dir.file_name().is_some_and(|name| name == "debug" || name == "release")
+        || PROFILE_MARKERS.iter().any(|marker| dir.join(marker).exists())
+}
+
+/// Whether `path` (under `target_dir`) is a build output cargo can regenerate
+///
+/// Only cargo's own layout counts: everything under a profile directory's `deps/`,
+/// `build/`, `incremental/` and `.fingerprint/`, where the profile directory is
+/// `target/<profile>` or `target/<triple>/<profile>`; compiler outputs, and binaries
+/// recognized by the `<name>.d` dep-info file cargo writes next to them, directly in a
+/// profile directory or its `examples/`; and cargo's bookkeeping files.
+fn is_cargo_artifact(target_dir: &Path, path: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(target_dir) else {
+        return false;
+    };
+    let dirs: Vec<&std::ffi::OsStr> = relative.parent().into_iter().flat_map(|p| p.iter()).collect();
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    if dirs.is_empty() {
+        return ARTIFACT_FILE_NAMES.contains(&file_name);
+    }
+    // Triples always contain a dash, e.g. `x86_64-unknown-linux-gnu`
+    let profile_depth = if is_profile_dir(&target_dir.join(dirs[0])) {
+        1
+    } else if dirs.len() >= 2
+        && dirs[0].to_string_lossy().contains('-')
+        && is_profile_dir(&target_dir.join(dirs[0]).join(dirs[1]))
+    {
+        2
+    } else {
+        return false;
+    };
+    match dirs[profile_depth..] {
+        [dir, ..] if ARTIFACT_DIRS.iter().any(|artifact_dir| dir == *artifact_dir) => true,
+        [] => {
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+            ARTIFACT_FILE_NAMES.contains(&file_name)
+                || ARTIFACT_EXTENSIONS.contains(&extension)
+                || path.with_extension("d").is_file()
+        }
+        [dir] if dir == "examples" => {
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+            ARTIFACT_EXTENSIONS.contains(&extension) || path.with_extension("d").is_file()
+        }
+        _ => false,
+    }
+}
+
+/// Delete recognized cargo artifacts (except those matching `keep`), reporting the files left behind
+fn remove_safe(
+    project: &Project,
+    target_dir: &Path,
+    keep: &[String],
+    artifacts: &ArtifactPatterns,
+    protected: &HashSet<PathBuf>,
+    options: &CleanOptions,
+) -> Result<CleanResult> {
+    let keep = compile_globs(keep)?;
+    let extra = compile_globs(&artifacts.patterns)?;
+    let is_artifact = |path: &Path| {
+        let matches_extra = path
+            .strip_prefix(target_dir)
+            .is_ok_and(|rel| extra.iter().any(|p| p.matches_path(rel)));
+        matches_extra || (!artifacts.replace_builtin && is_cargo_artifact(target_dir, path))
+    };
+    let mut removable = Vec::new();
+    let mut unrecognized = Vec::new();
+    for (path, metadata) in matching_files(target_dir, &keep, true, protected) {
+        if is_artifact(&path) {
+            removable.push((path, metadata));
+        } else {
+            unrecognized.push(path);
+        }
+    }
+    let freed_bytes = removable.iter().map(|(_, m)| file_size(m, &options.size_options)).sum();
+    let mut result = CleanResult::cleaned(project, freed_bytes);
+    result.unrecognized_files = unrecognized
+        .iter()
+        .filter_map(|path| path.strip_prefix(target_dir).ok())
+        .map(|rel| rel.to_string_lossy().to_string())
+        .collect();
+    result.unrecognized_files.sort();
+    if options.dry_run {
+        return Ok(result);
+    }
+    let _permit = options.io_throttle.as_ref().map(|t| t.acquire());
+    for (path, _) in &removable {
+        std::fs::remove_file(path).map_err(|e| removal_error(path, e))?;
+    }
+    // Drop directories left empty; anything still holding files stays
+    for entry in walkdir::WalkDir::new(target_dir).min_depth(1).contents_first(true).into_iter().flatten() {
+        if entry.file_type().is_dir() {
+            let _ = std::fs::remove_dir(entry.path());
+        }
+    }
+    Ok(result)
+}
+
+/// Delete the whole target directory
+fn remove_target(project: &Project, target_dir: &Path, freed_bytes: u64, options: &CleanOptions) -> Result<CleanResult> {
+    if !target_dir.exists() {
+        return Ok(CleanResult::cleaned(project, 0));
+    }
+    let _permit = options.io_throttle.as_ref().map(|t| t.acquire());
+    if is_symlink(target_dir) {
+        // Deleting the link would free nothing; empty the directory it points to instead
+        for entry in target_entries(target_dir)? {
+            let removed = match entry.is_dir() && !is_symlink(&entry) {
+                true => std::fs::remove_dir_all(&entry),
+                false => std::fs::remove_file(&entry),
+            };
+            removed.map_err(|e| removal_error(&entry, e))?;
+        }
+    } else {
+        std::fs::remove_dir_all(target_dir).map_err(|e| removal_error(target_dir, e))?;
+    }
+    Ok(CleanResult::cleaned(project, freed_bytes))
+}
+
+/// Whether `path` itself is a symlink, such as a target directory linked onto another disk
+fn is_symlink(path: &Path) -> bool {
+    std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink())
+}
+
+/// The entries directly inside a (symlinked) target directory
+fn target_entries(target_dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = std::fs::read_dir(target_dir).with_context(|| format!("Failed to read {:?}", target_dir))?;
+    Ok(entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+}
+
+/// Move the target directory to the OS trash
+fn trash_target(project: &Project, target_dir: &Path, freed_bytes: u64, options: &CleanOptions) -> Result<CleanResult> {
+    if !target_dir.exists() {
+        return Ok(CleanResult::cleaned(project, 0));
+    }
+    let _permit = options.io_throttle.as_ref().map(|t| t.acquire());
+    if is_symlink(target_dir) {
+        // Trashing the link would free nothing; trash what the directory it points to holds
+        let entries = target_entries(target_dir)?;
+        if !entries.is_empty() {
+            trash::delete_all(&entries).with_context(|| format!("Failed to move the contents of {:?} to the trash", target_dir))?;
+        }
+    } else {
+        trash::delete(target_dir).with_context(|| format!("Failed to move {:?} to the trash", target_dir))?;
+    }
+    Ok(CleanResult::cleaned(project, freed_bytes))
+}
+
+/// Delete files under the target directory matching `patterns`, or all others with `keep`
+///
+/// `protected` files (relative to the target) are left alone. With the `TrashTarget`
+/// strategy the files are moved to the trash instead.
+fn remove_matching(
+    project: &Project,
+    target_dir: &Path,
+    patterns: &[String],
+    keep: bool,
+    protected: &HashSet<PathBuf>,
+    options: &CleanOptions,
+) -> Result<CleanResult> {
+    let patterns = compile_globs(patterns)?;
+    let files = matching_files(target_dir, &patterns, keep, protected);
+    remove_files(project, target_dir, &files, options)
+}
+
+/// Delete `files` from the target directory, or move them to the trash with `TrashTarget`
+fn remove_files(
+    project: &Project,
+    target_dir: &Path,
+    files: &[(PathBuf, std::fs::Metadata)],
+    options: &CleanOptions,
+) -> Result<CleanResult> {
+    let freed_bytes = files.iter().map(|(_, m)| file_size(m, &options.size_options)).sum();
+    if options.dry_run {
+        return Ok(CleanResult::cleaned(project, freed_bytes));
+    }
+    let _permit = options.io_throttle.as_ref().map(|t| t.acquire());
+    if options.strategy == CleanStrategy::TrashTarget {
+        trash::delete_all(files.iter().map(|(path, _)| path))
+            .with_context(|| format!("Failed to move files in {:?} to the trash", target_dir))?;
+    } else {
+        for (path, _) in files {
+            std::fs::remove_file(path).map_err(|e| removal_error(path, e))?;
+        }
+    }
+    Ok(CleanResult::cleaned(project, freed_bytes))
+}
+
+/// Delete the files under the target not modified (or with `by_atime`, not accessed)
+/// within `max_age` seconds, like cargo-sweep
+///
+/// Artifacts of active builds are recent and stay, so those builds stay warm; git-tracked
+/// files, Criterion baselines and, with `options.keep_binaries`, final binaries are never
+/// removed.
+fn sweep_target(project: &Project, target_dir: &Path, max_age: u64, options: &CleanOptions) -> Result<CleanResult> {
+    if !target_dir.is_dir() {
+        return Ok(CleanResult::cleaned(project, 0));
+    }
+    let tracked = git_tracked_files(project, target_dir, options);
+    let mut protected = tracked.clone();
+    protected.extend(criterion_files(target_dir, options));
+    if options.keep_binaries {
+        protected.extend(final_binaries(target_dir));
+    }
+    let cutoff = unix_now().saturating_sub(max_age);
+    let files: Vec<_> = matching_files(target_dir, &[], true, &protected)
+        .into_iter()
+        .filter(|(_, metadata)| file_timestamp(metadata, &options.size_options).is_some_and(|time| time < cutoff))
+        .collect();
+    let mut result = remove_files(project, target_dir, &files, options)?;
+    if options.explain {
+        let used = if options.size_options.by_atime { "accessed" } else { "modified" };
+        let dry_run = if options.dry_run { " (dry run, nothing was changed)" } else { "" };
+        result.explain.push(format!(
+            "Method: removed {} file(s) not {} in the last {}{}",
+            files.len(),
+            used,
+            format_duration(max_age),
+            dry_run
+        ));
+    }
+    result.tracked_files = tracked.iter().map(|path| path.to_string_lossy().to_string()).collect();
+    result.tracked_files.sort();
+    Ok(result)
+}
+
+/// Convert a raw path printed by git to a platform path
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+    }
+    #[cfg(not(unix))]
+    PathBuf::from(String::from_utf8_lossy(bytes).as_ref())
+}
+
+/// Files under `target_dir` tracked by git, relative to the target directory
+///
+/// Empty when git is unavailable or the target is not inside a repository.
+fn git_tracked_files(project: &Project, target_dir: &Path, options: &CleanOptions) -> HashSet<PathBuf> {
+    let args = vec![
+        "ls-files".to_string(),
+        "-z".to_string(),
+        "--".to_string(),
+        target_dir.to_string_lossy().to_string(),
+    ];
+    let output = match options.runner.run("git", &args, &project.path) {
+        Ok(output) if output.status.success() => output,
+        _ => return HashSet::new(),
+    };
+    let Ok(target_dir) = target_dir.canonicalize() else {
+        return HashSet::new();
+    };
+    // Paths are relative to the directory git ran in; resolve them before comparing
+    output
+        .stdout
+        .split(|&byte| byte == 0)
+        .filter(|path| !path.is_empty())
+        .filter_map(|path| project.path.join(path_from_bytes(path)).canonicalize().ok())
+        .filter_map(|path| path.strip_prefix(&target_dir).ok().map(Path::to_path_buf))
+        .collect()
+}
+
+/// Run `cargo clean`, optionally deleting the target directory if cargo fails
+fn cargo_clean(
+    project: &Project,
+    target_dir: &Path,
+    freed_bytes: u64,
+    fallback_delete: bool,
+    scope: CleanScope,
+    options: &CleanOptions,
+) -> Result<CleanResult> {
+    let output = options.runner.run("cargo", &cargo_clean_args(project, scope), &project.path);
     match output {
         Ok(output) if output.status.success() => {
+            if let Some(ref throttle) = options.io_throttle {
+                throttle.record_cargo_clean();
+            }
             let after_size = if target_dir.exists() {
-                get_directory_size(&target_dir).unwrap_or(0)
+                get_directory_size(target_dir, &options.size_options).unwrap_or(0)
             } else {
                 0
             };
-            let actually_freed = freed_bytes.saturating_sub(after_size);
+            let mut result = CleanResult::cleaned(project, freed_bytes.saturating_sub(after_size));
+            result.cargo_warnings = parse_cargo_warnings(&String::from_utf8_lossy(&output.stderr));
+            Ok(result)
+        }
+        _ if fallback_delete => {
+            ensure_cargo_target(project, options)?;
+            let mut result = remove_target(project, target_dir, freed_bytes, options)?;
+            if options.explain {
+                result.explain.push("`cargo clean` failed, so the target directory was deleted instead".to_string());
+            }
+            Ok(result)
+        }
+        Ok(output) => anyhow::bail!(
+            "cargo clean failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => Err(anyhow::Error::new(e).context("Failed to run cargo clean")),
+    }
+}
 
-            Ok(CleanResult {
-                path: project.path.to_string_lossy().to_string(),
-                success: true,
-                freed_bytes: actually_freed,
-                error: None,
-            })
+/// Clean a single Cargo project with the strategy in `options`
+///
+/// `target_size` is the already-measured size of the target directory, if known;
+/// otherwise it is measured here. Files tracked by git under the target are never
+/// removed; when there are any, only untracked files are, whatever the strategy.
+/// With `options.triples` only those cross-compilation output directories are cleaned,
+/// and with `options.profiles` or `options.doc_only` only those profiles' directories or
+/// the documentation (within each triple).
+pub fn clean_project(project: &Project, target_size: Option<u64>, options: &CleanOptions) -> Result<CleanResult> {
+    ensure_no_build(project, options)?;
+    let mut result = clean_selected(project, target_size, options)?;
+    if project.fuzz && options.include_corpus {
+        let corpora = clean_fuzz_corpora(project, options)?;
+        result.freed_bytes += corpora.freed_bytes;
+        result.explain.extend(corpora.explain);
+    }
+    if options.framework_outputs && is_full_clean(options) {
+        let outputs = clean_framework_outputs(project, options)?;
+        result.freed_bytes += outputs.freed_bytes;
+        result.freed_by_framework = outputs.freed_by_framework;
+        result.explain.extend(outputs.explain);
+    }
+    Ok(result)
+}
+
+/// Whether the options clean whole targets rather than selecting parts of them or
+/// keeping final outputs
+pub fn is_full_clean(options: &CleanOptions) -> bool {
+    !(options.coverage
+        || options.doc_only
+        || options.incremental_only
+        || options.sweep_older_than.is_some()
+        || options.keep_binaries
+        || !options.profiles.is_empty()
+        || !options.triples.is_empty()
+        || options.non_host_only)
+}
+
+/// Delete the project's wasm-pack and trunk output directories (see
+/// [`Project::framework_outputs`]), removing only untracked files from those with
+/// git-tracked contents
+fn clean_framework_outputs(project: &Project, options: &CleanOptions) -> Result<CleanResult> {
+    let mut combined = CleanResult::cleaned(project, 0);
+    for output in project.framework_outputs() {
+        let tracked = git_tracked_files(project, &output.path, options);
+        let result = if !tracked.is_empty() {
+            remove_matching(project, &output.path, &[], true, &tracked, options)?
+        } else {
+            let size = get_directory_size(&output.path, &options.size_options).unwrap_or(0);
+            match options.strategy {
+                _ if options.dry_run => CleanResult::cleaned(project, size),
+                CleanStrategy::TrashTarget => trash_target(project, &output.path, size, options)?,
+                _ => remove_target(project, &output.path, size, options)?,
+            }
+        };
+        if options.explain {
+            let relative = output.path.strip_prefix(&project.path).unwrap_or(&output.path);
+            let dry_run = if options.dry_run { " (dry run, nothing was changed)" } else { "" };
+            combined
+                .explain
+                .push(format!("Method: removed {} output {}{}", output.tool, relative.display(), dry_run));
         }
-        _ => {
-            // Fallback: remove target directory directly
-            if target_dir.exists() {
-                std::fs::remove_dir_all(&target_dir)
-                    .with_context(|| format!("Failed to remove target directory: {:?}", target_dir))?;
-
-                Ok(CleanResult {
-                    path: project.path.to_string_lossy().to_string(),
-                    success: true,
-                    freed_bytes,
-                    error: None,
-                })
-            } else {
-                Ok(CleanResult {
-                    path: project.path.to_string_lossy().to_string(),
-                    success: true,
-                    freed_bytes: 0,
-                    error: None,
-                })
+        combined.freed_bytes += result.freed_bytes;
+        *combined.freed_by_framework.entry(output.tool.to_string()).or_default() += result.freed_bytes;
+    }
+    Ok(combined)
+}
+
+/// Clean the project's target directory in the mode the options select
+fn clean_selected(project: &Project, target_size: Option<u64>, options: &CleanOptions) -> Result<CleanResult> {
+    let target_dir = project.target_dir();
+    if options.coverage {
+        return clean_coverage(project, &target_dir, options);
+    }
+    if options.doc_only {
+        return clean_docs(project, &target_dir, options);
+    }
+    if options.incremental_only {
+        return clean_incremental(project, &target_dir, options);
+    }
+    if let Some(max_age) = options.sweep_older_than {
+        return sweep_target(project, &target_dir, max_age, options);
+    }
+    if !options.profiles.is_empty() {
+        return clean_profiles(project, &target_dir, options);
+    }
+    if !options.triples.is_empty() || options.non_host_only {
+        return clean_triples(project, &target_dir, options);
+    }
+    clean_dir(project, &target_dir, target_size, CleanScope::default(), options)
+}
+
+/// Clean `dir`: the target directory, or the directory in it holding the outputs in `scope`
+fn clean_dir(
+    project: &Project,
+    dir: &Path,
+    size: Option<u64>,
+    scope: CleanScope,
+    options: &CleanOptions,
+) -> Result<CleanResult> {
+    let tracked = if dir.exists() {
+        git_tracked_files(project, dir, options)
+    } else {
+        HashSet::new()
+    };
+    let binaries = if options.keep_binaries && dir.exists() {
+        final_binaries(dir)
+    } else {
+        HashSet::new()
+    };
+    let criterion = if dir.exists() { criterion_files(dir, options) } else { HashSet::new() };
+    // All are left in place, so every strategy falls back to removing the other files
+    let mut protected: HashSet<PathBuf> = tracked.union(&binaries).cloned().collect();
+    protected.extend(criterion.iter().cloned());
+    // Whatever stays behind, keep cargo's markers so the target can still be removed later
+    if !protected.is_empty() {
+        protected.extend(
+            CARGO_TARGET_MARKERS
+                .iter()
+                .map(PathBuf::from)
+                .filter(|marker| dir.join(marker).is_file()),
+        );
+    }
+    let mut result = clean_target(project, dir, size, scope, &protected, options)?;
+    if options.explain {
+        result.explain.insert(0, clean_method(project, &tracked, binaries.len(), !criterion.is_empty(), options));
+    }
+    result.tracked_files = tracked.iter().map(|path| path.to_string_lossy().to_string()).collect();
+    result.tracked_files.sort();
+    result.kept_binaries = binaries.iter().map(|path| path.to_string_lossy().to_string()).collect();
+    result.kept_binaries.sort();
+    Ok(result)
+}
+
+/// Clean the `target/<triple>` directory of each selected triple, combining the results
+///
+/// Freed bytes are also totalled per triple. Reported file names are relative to the
+/// target directory, so they start with the triple.
+fn clean_triples(project: &Project, target_dir: &Path, options: &CleanOptions) -> Result<CleanResult> {
+    let mut combined = CleanResult::cleaned(project, 0);
+    for triple in selected_triples(target_dir, options) {
+        let dir = target_dir.join(&triple);
+        if !dir.is_dir() {
+            continue;
+        }
+        let scope = CleanScope { triple: Some(&triple), ..Default::default() };
+        let result = clean_dir(project, &dir, None, scope, options)?;
+        absorb(&mut combined, &triple, Some(&triple), result);
+    }
+    if options.explain && combined.explain.is_empty() {
+        let message = if options.non_host_only {
+            "Nothing to clean: no cross-compilation output in the target directory".to_string()
+        } else {
+            format!("Nothing to clean: no output for {} in the target directory", options.triples.join(", "))
+        };
+        combined.explain.push(message);
+    }
+    Ok(combined)
+}
+
+/// The triples named with `--triples`, or with `--non-host-only` every one with output in the target
+fn selected_triples(target_dir: &Path, options: &CleanOptions) -> Vec<String> {
+    if options.non_host_only {
+        find_triples(target_dir)
+    } else {
+        options.triples.clone()
+    }
+}
+
+/// Clean the output directory of each of `options.profiles`, in the target directory or,
+/// with `options.triples`, in each `target/<triple>`, combining the results
+///
+/// Freed bytes are also totalled per profile. Reported file names are relative to the
+/// target directory, so they start with the profile directory (or the triple).
+fn clean_profiles(project: &Project, target_dir: &Path, options: &CleanOptions) -> Result<CleanResult> {
+    let mut combined = CleanResult::cleaned(project, 0);
+    let mut visited = HashSet::new();
+    for triple in output_roots(target_dir, options) {
+        let triple = triple.as_deref();
+        for profile in &options.profiles {
+            let relative = match triple {
+                Some(triple) => format!("{}/{}", triple, profile_dir_name(profile)),
+                None => profile_dir_name(profile).to_string(),
+            };
+            let dir = target_dir.join(&relative);
+            // `dev` and `test` share a directory, as do `release` and `bench`
+            if !dir.is_dir() || !visited.insert(relative.clone()) {
+                continue;
             }
+            let scope = CleanScope { triple, profile: Some(profile), doc: false };
+            let result = clean_dir(project, &dir, None, scope, options)?;
+            *combined.freed_by_profile.entry(profile.clone()).or_default() += result.freed_bytes;
+            absorb(&mut combined, &relative, triple, result);
+        }
+    }
+    if options.explain && combined.explain.is_empty() {
+        combined.explain.push(format!(
+            "Nothing to clean: no {} profile output in the target directory",
+            options.profiles.join(", ")
+        ));
+    }
+    Ok(combined)
+}
+
+/// Clean the documentation output, `target/doc` or with `options.triples` each
+/// `target/<triple>/doc`, combining the results
+fn clean_docs(project: &Project, target_dir: &Path, options: &CleanOptions) -> Result<CleanResult> {
+    let mut combined = CleanResult::cleaned(project, 0);
+    for triple in output_roots(target_dir, options) {
+        let triple = triple.as_deref();
+        let relative = match triple {
+            Some(triple) => format!("{}/doc", triple),
+            None => "doc".to_string(),
+        };
+        let dir = target_dir.join(&relative);
+        if !dir.is_dir() {
+            continue;
+        }
+        let scope = CleanScope { triple, profile: None, doc: true };
+        let result = clean_dir(project, &dir, None, scope, options)?;
+        absorb(&mut combined, &relative, triple, result);
+    }
+    if options.explain && combined.explain.is_empty() {
+        combined.explain.push("Nothing to clean: no documentation in the target directory".to_string());
+    }
+    combined.doc_freed_bytes = Some(combined.freed_bytes);
+    Ok(combined)
+}
+
+/// Delete the project's coverage artifacts (see [`coverage_artifacts`]), keeping files
+/// tracked by git such as committed PGO profiles
+fn clean_coverage(project: &Project, target_dir: &Path, options: &CleanOptions) -> Result<CleanResult> {
+    let tracked = git_tracked_files(project, &project.path, options);
+    let files = coverage_artifacts(&project.path, target_dir, &tracked);
+    let mut result = remove_files(project, target_dir, &files, options)?;
+    if !options.dry_run {
+        // Drop the emptied coverage directories; those still holding tracked files stay
+        let dirs = COVERAGE_DIRS.iter().flat_map(|dir| walkdir::WalkDir::new(target_dir.join(dir)).contents_first(true));
+        for entry in dirs.filter_map(|e| e.ok()).filter(|e| e.file_type().is_dir()) {
+            let _ = std::fs::remove_dir(entry.path());
+        }
+    }
+    if options.explain {
+        let dry_run = if options.dry_run { " (dry run, nothing was changed)" } else { "" };
+        result.explain.push(format!("Method: removed {} coverage artifact(s){}", files.len(), dry_run));
+    }
+    result.coverage_freed_bytes = Some(result.freed_bytes);
+    Ok(result)
+}
+
+/// Delete the corpora and crash artifacts of a cargo-fuzz crate ([`FUZZ_CORPUS_DIRS`]),
+/// keeping files tracked by git such as a committed seed corpus
+fn clean_fuzz_corpora(project: &Project, options: &CleanOptions) -> Result<CleanResult> {
+    let tracked = git_tracked_files(project, &project.path, options);
+    let dirs: Vec<_> = FUZZ_CORPUS_DIRS.iter().map(|dir| project.path.join(dir)).filter(|dir| dir.is_dir()).collect();
+    let files: Vec<_> = dirs
+        .iter()
+        .flat_map(|dir| matching_files(dir, &[], true, &HashSet::new()))
+        .filter(|(path, _)| !path.strip_prefix(&project.path).is_ok_and(|rel| tracked.contains(rel)))
+        .collect();
+    let mut result = remove_files(project, &project.path, &files, options)?;
+    if !options.dry_run {
+        // Drop the emptied directories; those still holding tracked files stay
+        let entries = dirs.iter().flat_map(|dir| walkdir::WalkDir::new(dir).contents_first(true));
+        for entry in entries.filter_map(|e| e.ok()).filter(|e| e.file_type().is_dir()) {
+            let _ = std::fs::remove_dir(entry.path());
+        }
+    }
+    if options.explain {
+        let dry_run = if options.dry_run { " (dry run, nothing was changed)" } else { "" };
+        result
+            .explain
+            .push(format!("Method: removed {} fuzz corpus and artifact file(s){}", files.len(), dry_run));
+    }
+    Ok(result)
+}
+
+/// Delete the incremental compilation caches of every profile (and triple), and with
+/// `options.dep_info` the `*.d` dep-info files, keeping compiled dependencies
+///
+/// Cargo has no option to clean just these, so the files are always removed directly
+/// (or moved to the trash with `TrashTarget`); git-tracked files are left alone.
+fn clean_incremental(project: &Project, target_dir: &Path, options: &CleanOptions) -> Result<CleanResult> {
+    if !target_dir.is_dir() {
+        return Ok(CleanResult::cleaned(project, 0));
+    }
+    let mut patterns = vec!["**/incremental/**".to_string()];
+    if options.dep_info {
+        patterns.push("**/*.d".to_string());
+    }
+    let tracked = git_tracked_files(project, target_dir, options);
+    let mut result = remove_matching(project, target_dir, &patterns, false, &tracked, options)?;
+    if !options.dry_run {
+        // Drop the emptied session directories; cargo recreates `incremental` as needed
+        for entry in walkdir::WalkDir::new(target_dir).min_depth(1).contents_first(true).into_iter().flatten() {
+            let in_incremental = entry
+                .path()
+                .strip_prefix(target_dir)
+                .is_ok_and(|rel| rel.components().any(|c| c.as_os_str() == "incremental"));
+            if entry.file_type().is_dir() && in_incremental {
+                let _ = std::fs::remove_dir(entry.path());
+            }
+        }
+    }
+    if options.explain {
+        let what = if options.dep_info { "incremental caches and dep-info files" } else { "incremental caches" };
+        let dry_run = if options.dry_run { " (dry run, nothing was changed)" } else { "" };
+        result.explain.push(format!("Method: removed only {}, keeping compiled dependencies{}", what, dry_run));
+    }
+    result.tracked_files = tracked.iter().map(|path| path.to_string_lossy().to_string()).collect();
+    result.tracked_files.sort();
+    Ok(result)
+}
+
+/// The triples whose outputs `--profile` and `--doc-only` clean; `None` is the host's
+fn output_roots(target_dir: &Path, options: &CleanOptions) -> Vec<Option<String>> {
+    if options.triples.is_empty() && !options.non_host_only {
+        vec![None]
+    } else {
+        selected_triples(target_dir, options).into_iter().map(Some).collect()
+    }
+}
+
+/// Add the result of cleaning the `relative` directory of a target, within `triple` if
+/// any, to `combined`
+fn absorb(combined: &mut CleanResult, relative: &str, triple: Option<&str>, result: CleanResult) {
+    let within = |files: Vec<String>| files.into_iter().map(move |file| format!("{}/{}", relative, file));
+    if let Some(triple) = triple {
+        *combined.freed_by_triple.entry(triple.to_string()).or_default() += result.freed_bytes;
+    }
+    combined.freed_bytes += result.freed_bytes;
+    combined.cargo_warnings.extend(result.cargo_warnings);
+    combined.unrecognized_files.extend(within(result.unrecognized_files));
+    combined.tracked_files.extend(within(result.tracked_files));
+    combined.kept_binaries.extend(within(result.kept_binaries));
+    combined.explain.extend(result.explain.into_iter().map(|line| format!("{}: {}", relative, line)));
+}
+
+/// Describe the method `clean_target` picks for `project`, for `--explain`
+fn clean_method(
+    project: &Project,
+    tracked: &HashSet<PathBuf>,
+    binaries: usize,
+    criterion: bool,
+    options: &CleanOptions,
+) -> String {
+    let removed = if options.strategy == CleanStrategy::TrashTarget { "moved to the trash" } else { "removed" };
+    let method = match options.strategy {
+        CleanStrategy::Safe(_) => "removed only recognized cargo artifacts (--safe), leaving other files".to_string(),
+        _ if !project.overrides.keep.is_empty() => format!(
+            "{} every file except those matching the {} keep list ({})",
+            removed,
+            crate::config::SIDECAR_FILE_NAME,
+            project.overrides.keep.join(", ")
+        ),
+        CleanStrategy::SelectiveGlob(ref patterns) => format!("removed files matching {}", patterns.join(", ")),
+        _ if criterion => format!(
+            "{} every file except the Criterion benchmark data in {}/ (--clean-criterion removes it) and any git-tracked files",
+            removed, CRITERION_DIR
+        ),
+        _ if binaries > 0 => format!(
+            "{} every file except {} final binary(ies) (--keep-binaries) and any git-tracked files",
+            removed, binaries
+        ),
+        _ if !tracked.is_empty() => format!(
+            "{} untracked files only, because {} file(s) in the target are tracked by git",
+            removed,
+            tracked.len()
+        ),
+        CleanStrategy::CargoClean { .. } if project.marker_only => {
+            "deleted the target directory (marker-only projects have no manifest for `cargo clean`)".to_string()
+        }
+        CleanStrategy::CargoClean { fallback_delete: true } => {
+            "ran `cargo clean`, deleting the target directory if it fails".to_string()
         }
+        CleanStrategy::CargoClean { fallback_delete: false } => "ran `cargo clean` (--no-fallback-delete)".to_string(),
+        CleanStrategy::RemoveTarget => "deleted the target directory (--strategy remove-target)".to_string(),
+        CleanStrategy::TrashTarget => "moved the target directory to the trash (--strategy trash-target)".to_string(),
+    };
+    if options.dry_run {
+        format!("Method: {} (dry run, nothing was changed)", method)
+    } else {
+        format!("Method: {}", method)
     }
 }
 
+fn clean_target(
+    project: &Project,
+    target_dir: &Path,
+    target_size: Option<u64>,
+    scope: CleanScope,
+    tracked: &HashSet<PathBuf>,
+    options: &CleanOptions,
+) -> Result<CleanResult> {
+    let target_size = || match target_size {
+        Some(size) => size,
+        None if target_dir.exists() => get_directory_size(target_dir, &options.size_options).unwrap_or(0),
+        None => 0,
+    };
+
+    // Safe mode never removes unrecognized files, so it honors `keep` on top of its own rules
+    if let CleanStrategy::Safe(ref artifacts) = options.strategy {
+        return remove_safe(project, target_dir, &project.overrides.keep, artifacts, tracked, options);
+    }
+
+    // A sidecar `keep` list preserves those files and removes the rest, whatever the strategy
+    if !project.overrides.keep.is_empty() {
+        return remove_matching(project, target_dir, &project.overrides.keep, true, tracked, options);
+    }
+
+    // Deleting the directory wholesale needs it to be cargo's, dry runs included so they
+    // show the refusal
+    let removes_whole = match options.strategy {
+        CleanStrategy::RemoveTarget | CleanStrategy::TrashTarget => true,
+        CleanStrategy::CargoClean { .. } => project.marker_only,
+        _ => false,
+    };
+    if removes_whole && tracked.is_empty() {
+        ensure_cargo_target(project, options)?;
+    }
+
+    match options.strategy {
+        // Only matching files are removed, so the estimate comes from those files
+        CleanStrategy::SelectiveGlob(ref patterns) => remove_matching(project, target_dir, patterns, false, tracked, options),
+        // `cargo clean` and whole-directory removal would take tracked files with them
+        _ if !tracked.is_empty() => remove_matching(project, target_dir, &[], true, tracked, options),
+        _ if options.dry_run => Ok(CleanResult::cleaned(project, target_size())),
+        // Marker-only projects have no manifest for cargo to use
+        CleanStrategy::CargoClean { .. } if project.marker_only => {
+            remove_target(project, target_dir, target_size(), options)
+        }
+        CleanStrategy::CargoClean { fallback_delete } => {
+            cargo_clean(project, target_dir, target_size(), fallback_delete, scope, options)
+        }
+        CleanStrategy::RemoveTarget => remove_target(project, target_dir, target_size(), options),
+        CleanStrategy::TrashTarget => trash_target(project, target_dir, target_size(), options),
+        CleanStrategy::Safe(_) => unreachable!("safe mode is handled above"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cargo_clean_args() {
+        let project = Project {
+            path: PathBuf::from("/projects/app"),
+            is_workspace: false,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: Default::default(),
+        };
+        assert_eq!(
+            cargo_clean_args(&project, CleanScope::default()),
+            vec!["clean", "--manifest-path", "/projects/app/Cargo.toml"]
+        );
+        assert_eq!(
+            cargo_clean_args(&project, CleanScope { triple: Some("wasm32-unknown-unknown"), ..Default::default() })[3..],
+            ["--target", "wasm32-unknown-unknown"]
+        );
+        let profile = CleanScope { profile: Some("debug"), ..Default::default() };
+        assert_eq!(cargo_clean_args(&project, profile)[3..], ["--profile", "dev"]);
+        assert_eq!(cargo_clean_args(&project, CleanScope { doc: true, ..Default::default() })[3..], ["--doc"]);
+    }
+
+    /// Records cargo invocations and reports a fixed exit status without running anything
+    ///
+    /// `git ls-files` succeeds and lists `tracked`, NUL-separated like `-z` output.
+    #[cfg(unix)]
+    #[derive(Debug)]
+    struct FakeRunner {
+        exit_code: i32,
+        tracked: Vec<String>,
+        calls: std::sync::Mutex<Vec<Vec<String>>>,
+    }
+
+    #[cfg(unix)]
+    impl CommandRunner for FakeRunner {
+        fn run(&self, program: &str, args: &[String], _current_dir: &Path) -> std::io::Result<Output> {
+            use std::os::unix::process::ExitStatusExt;
+            if program == "git" {
+                return Ok(Output {
+                    status: std::process::ExitStatus::from_raw(0),
+                    stdout: self.tracked.iter().flat_map(|path| format!("{}\0", path).into_bytes()).collect(),
+                    stderr: Vec::new(),
+                });
+            }
+            self.calls.lock().unwrap().push(args.to_vec());
+            Ok(Output {
+                status: std::process::ExitStatus::from_raw(self.exit_code << 8),
+                stdout: Vec::new(),
+                stderr: b"error: simulated failure".to_vec(),
+            })
+        }
+    }
+
+    /// A project with a 1 KiB `target/debug/app` and a 10 byte `target/debug/app.d`
+    #[cfg(unix)]
+    fn project_with_target(temp_dir: &tempfile::TempDir) -> Project {
+        let debug_dir = temp_dir.path().join("target/debug");
+        std::fs::create_dir_all(&debug_dir).unwrap();
+        std::fs::write(debug_dir.join("app"), vec![0u8; 1024]).unwrap();
+        std::fs::write(debug_dir.join("app.d"), vec![0u8; 10]).unwrap();
+        std::fs::write(temp_dir.path().join("target/.rustc_info.json"), "").unwrap();
+        Project {
+            path: temp_dir.path().to_path_buf(),
+            is_workspace: false,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: Default::default(),
+        }
+    }
+
+    #[cfg(unix)]
+    fn options_with(strategy: CleanStrategy, exit_code: i32) -> (CleanOptions, Arc<FakeRunner>) {
+        let runner = Arc::new(FakeRunner {
+            exit_code,
+            tracked: Vec::new(),
+            calls: std::sync::Mutex::new(Vec::new()),
+        });
+        let options = CleanOptions {
+            strategy,
+            runner: runner.clone(),
+            ..Default::default()
+        };
+        (options, runner)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cargo_clean_strategy_fallback() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+
+        // cargo succeeded but (being fake) left the target in place
+        let (options, runner) = options_with(CleanStrategy::CargoClean { fallback_delete: true }, 0);
+        let result = clean_project(&project, None, &options).unwrap();
+        assert_eq!(result.freed_bytes, 0);
+        assert_eq!(runner.calls.lock().unwrap().as_slice(), &[cargo_clean_args(&project, CleanScope::default())]);
+
+        let (options, _) = options_with(CleanStrategy::CargoClean { fallback_delete: false }, 101);
+        let error = clean_project(&project, None, &options).unwrap_err();
+        assert!(error.to_string().contains("simulated failure"));
+        assert!(project.target_dir().exists());
+
+        let (options, _) = options_with(CleanStrategy::CargoClean { fallback_delete: true }, 101);
+        let result = clean_project(&project, None, &options).unwrap();
+        assert_eq!(result.freed_bytes, 1034);
+        assert!(!project.target_dir().exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_explain_records_method() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+
+        let (options, _) = options_with(CleanStrategy::CargoClean { fallback_delete: true }, 0);
+        assert!(clean_project(&project, None, &options).unwrap().explain.is_empty());
+
+        let (mut options, _) = options_with(CleanStrategy::CargoClean { fallback_delete: true }, 101);
+        options.explain = true;
+        let result = clean_project(&project, None, &options).unwrap();
+        assert_eq!(
+            result.explain,
+            vec![
+                "Method: ran `cargo clean`, deleting the target directory if it fails",
+                "`cargo clean` failed, so the target directory was deleted instead",
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remove_target_strategy_skips_cargo() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        let (options, runner) = options_with(CleanStrategy::RemoveTarget, 0);
+
+        let result = clean_project(&project, None, &options).unwrap();
+        assert_eq!(result.freed_bytes, 1034);
+        assert!(!project.target_dir().exists());
+        assert!(runner.calls.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_target_is_emptied_not_unlinked() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let other_disk = tempfile::TempDir::new().unwrap();
+        let real_target = other_disk.path().join("app-target");
+        std::fs::create_dir_all(real_target.join("debug")).unwrap();
+        std::fs::write(real_target.join("debug/app"), vec![0u8; 1024]).unwrap();
+        std::fs::write(real_target.join(".rustc_info.json"), "").unwrap();
+        std::os::unix::fs::symlink(&real_target, temp_dir.path().join("target")).unwrap();
+        let project = Project {
+            path: temp_dir.path().to_path_buf(),
+            is_workspace: false,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: Default::default(),
+        };
+        let (options, _) = options_with(CleanStrategy::RemoveTarget, 0);
+
+        let result = clean_project(&project, None, &options).unwrap();
+        assert_eq!(result.freed_bytes, 1024);
+        assert!(is_symlink(&temp_dir.path().join("target")));
+        assert!(real_target.exists());
+        assert_eq!(std::fs::read_dir(&real_target).unwrap().count(), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unrecognized_target_needs_force() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        std::fs::remove_file(project.target_dir().join(".rustc_info.json")).unwrap();
+        let (mut options, _) = options_with(CleanStrategy::RemoveTarget, 0);
+
+        let error = clean_project(&project, None, &options).unwrap_err();
+        assert!(error.downcast_ref::<NotACargoTarget>().is_some());
+        assert!(project.target_dir().join("debug/app").exists());
+
+        std::fs::write(project.target_dir().join("CACHEDIR.TAG"), "Not cargo's\n").unwrap();
+        assert!(clean_project(&project, None, &options).is_err());
+
+        options.force = true;
+        assert_eq!(clean_project(&project, None, &options).unwrap().freed_bytes, 1046);
+        assert!(!project.target_dir().exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_build_in_progress_skipped() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        let lock_path = project.target_dir().join("x86_64-unknown-linux-gnu/release/.cargo-lock");
+        std::fs::create_dir_all(lock_path.parent().unwrap()).unwrap();
+        let lock = std::fs::File::create(&lock_path).unwrap();
+        let (options, _) = options_with(CleanStrategy::RemoveTarget, 0);
+        assert_eq!(build_in_progress(&project), None);
+
+        lock.lock().unwrap();
+        let error = clean_project(&project, None, &options).unwrap_err();
+        let busy = error.downcast_ref::<BuildInProgress>().unwrap();
+        assert_eq!(busy.reason, format!("cargo holds {:?}", lock_path));
+        assert!(project.target_dir().join("debug/app").exists());
+
+        lock.unlock().unwrap();
+        assert_eq!(clean_project(&project, None, &options).unwrap().freed_bytes, 1024 + 10);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_selective_glob_strategy() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        let (mut options, runner) = options_with(CleanStrategy::SelectiveGlob(vec!["**/*.d".to_string()]), 0);
+
+        options.dry_run = true;
+        assert_eq!(clean_project(&project, None, &options).unwrap().freed_bytes, 10);
+        assert!(project.target_dir().join("debug/app.d").exists());
+
+        options.dry_run = false;
+        assert_eq!(clean_project(&project, None, &options).unwrap().freed_bytes, 10);
+        assert!(!project.target_dir().join("debug/app.d").exists());
+        assert!(project.target_dir().join("debug/app").exists());
+        assert!(runner.calls.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_triples_clean_only_selected() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        let wasm_dir = project.target_dir().join("wasm32-unknown-unknown/release");
+        std::fs::create_dir_all(&wasm_dir).unwrap();
+        std::fs::write(wasm_dir.join("app.wasm"), vec![0u8; 500]).unwrap();
+
+        let (mut options, runner) = options_with(CleanStrategy::CargoClean { fallback_delete: true }, 0);
+        options.triples = vec!["wasm32-unknown-unknown".to_string(), "aarch64-linux-android".to_string()];
+        clean_project(&project, None, &options).unwrap();
+        assert_eq!(
+            runner.calls.lock().unwrap().as_slice(),
+            &[cargo_clean_args(&project, CleanScope { triple: Some("wasm32-unknown-unknown"), ..Default::default() })]
+        );
+
+        options.strategy = CleanStrategy::RemoveTarget;
+        let result = clean_project(&project, None, &options).unwrap();
+        assert_eq!(result.freed_bytes, 500);
+        assert_eq!(result.freed_by_triple, BTreeMap::from([("wasm32-unknown-unknown".to_string(), 500)]));
+        assert!(!project.target_dir().join("wasm32-unknown-unknown").exists());
+        assert!(project.target_dir().join("debug/app").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_host_only_cleans_every_triple() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        for (triple, size) in [("aarch64-unknown-linux-gnu", 300), ("wasm32-unknown-unknown", 200)] {
+            let dir = project.target_dir().join(triple).join("release");
+            std::fs::create_dir_all(dir.join(".fingerprint")).unwrap();
+            std::fs::write(dir.join("app"), vec![0u8; size]).unwrap();
+        }
+        std::fs::create_dir_all(project.target_dir().join("debug/.fingerprint")).unwrap();
+        let (mut options, _runner) = options_with(CleanStrategy::RemoveTarget, 0);
+        options.non_host_only = true;
+
+        let result = clean_project(&project, None, &options).unwrap();
+        assert_eq!(result.freed_bytes, 500);
+        assert_eq!(
+            result.freed_by_triple,
+            BTreeMap::from([("aarch64-unknown-linux-gnu".to_string(), 300), ("wasm32-unknown-unknown".to_string(), 200)])
+        );
+        assert!(project.target_dir().join("debug/app").exists());
+        assert!(!project.target_dir().join("wasm32-unknown-unknown").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_profiles_clean_only_selected() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        let release_dir = project.target_dir().join("release");
+        std::fs::create_dir_all(&release_dir).unwrap();
+        std::fs::write(release_dir.join("app"), vec![0u8; 300]).unwrap();
+
+        let (mut options, runner) = options_with(CleanStrategy::CargoClean { fallback_delete: true }, 101);
+        options.profiles = vec!["debug".to_string(), "test".to_string(), "bench-fast".to_string()];
+        options.dry_run = true;
+        let result = clean_project(&project, None, &options).unwrap();
+        assert_eq!(result.freed_bytes, 1034);
+        assert_eq!(result.freed_by_profile, BTreeMap::from([("debug".to_string(), 1034)]));
+
+        // An older cargo without `clean --profile` fails, so the directory is deleted instead
+        options.dry_run = false;
+        assert_eq!(clean_project(&project, None, &options).unwrap().freed_bytes, 1034);
+        assert_eq!(
+            runner.calls.lock().unwrap().as_slice(),
+            &[cargo_clean_args(&project, CleanScope { profile: Some("debug"), ..Default::default() })]
+        );
+        assert!(!project.target_dir().join("debug").exists());
+        assert!(release_dir.join("app").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_doc_only_cleans_documentation() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        let doc_dir = project.target_dir().join("doc/app");
+        std::fs::create_dir_all(&doc_dir).unwrap();
+        std::fs::write(doc_dir.join("index.html"), vec![0u8; 400]).unwrap();
+
+        let (mut options, runner) = options_with(CleanStrategy::CargoClean { fallback_delete: false }, 0);
+        options.doc_only = true;
+        clean_project(&project, None, &options).unwrap();
+        assert_eq!(
+            runner.calls.lock().unwrap().as_slice(),
+            &[cargo_clean_args(&project, CleanScope { doc: true, ..Default::default() })]
+        );
+
+        options.strategy = CleanStrategy::RemoveTarget;
+        let result = clean_project(&project, None, &options).unwrap();
+        assert_eq!((result.freed_bytes, result.doc_freed_bytes), (400, Some(400)));
+        assert!(!project.target_dir().join("doc").exists());
+        assert!(project.target_dir().join("debug/app").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_incremental_only_keeps_dependencies() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        let target_dir = project.target_dir();
+        for (file, size) in [
+            ("debug/incremental/app-1abc/s-xyz/query-cache.bin", 700),
+            ("wasm32-unknown-unknown/release/incremental/app-2def/dep-graph.bin", 200),
+            ("debug/deps/libfoo-1234.rlib", 100),
+            ("debug/deps/foo-1234.d", 5),
+        ] {
+            std::fs::create_dir_all(target_dir.join(file).parent().unwrap()).unwrap();
+            std::fs::write(target_dir.join(file), vec![0u8; size]).unwrap();
+        }
+        let (mut options, runner) = options_with(CleanStrategy::default(), 0);
+        options.incremental_only = true;
+
+        options.dry_run = true;
+        assert_eq!(clean_project(&project, None, &options).unwrap().freed_bytes, 900);
+        options.dep_info = true;
+        assert_eq!(clean_project(&project, None, &options).unwrap().freed_bytes, 915);
+
+        options.dry_run = false;
+        options.dep_info = false;
+        assert_eq!(clean_project(&project, None, &options).unwrap().freed_bytes, 900);
+        assert!(!target_dir.join("debug/incremental/app-1abc").exists());
+        assert!(!target_dir.join("wasm32-unknown-unknown/release/incremental/app-2def").exists());
+        assert!(target_dir.join("debug/deps/libfoo-1234.rlib").exists());
+        assert!(target_dir.join("debug/app.d").exists());
+        assert!(runner.calls.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_keep_binaries_removes_only_intermediates() {
+        use std::os::unix::fs::PermissionsExt;
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        let target_dir = project.target_dir();
+        std::fs::create_dir_all(target_dir.join("debug/.fingerprint/app-1234")).unwrap();
+        std::fs::create_dir_all(target_dir.join("debug/deps")).unwrap();
+        std::fs::write(target_dir.join("debug/deps/app-1234"), vec![0u8; 2000]).unwrap();
+        for binary in ["debug/app", "debug/deps/app-1234"] {
+            std::fs::set_permissions(target_dir.join(binary), std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let (mut options, runner) = options_with(CleanStrategy::default(), 0);
+        options.keep_binaries = true;
+
+        let result = clean_project(&project, None, &options).unwrap();
+        assert_eq!(result.freed_bytes, 2010);
+        assert_eq!(result.kept_binaries, ["debug/app"]);
+        assert!(target_dir.join("debug/app").exists());
+        assert!(!target_dir.join("debug/app.d").exists());
+        assert!(!target_dir.join("debug/deps/app-1234").exists());
+        assert!(runner.calls.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sweep_removes_only_old_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        let old = std::time::SystemTime::now() - std::time::Duration::from_secs(40 * 86400);
+        std::fs::File::options()
+            .write(true)
+            .open(project.target_dir().join("debug/app"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        let (mut options, runner) = options_with(CleanStrategy::default(), 0);
+        options.sweep_older_than = Some(30 * 86400);
+        options.explain = true;
+
+        let result = clean_project(&project, None, &options).unwrap();
+        assert_eq!(result.freed_bytes, 1024);
+        assert_eq!(result.explain, ["Method: removed 1 file(s) not modified in the last 30d"]);
+        assert!(!project.target_dir().join("debug/app").exists());
+        assert!(project.target_dir().join("debug/app.d").exists());
+        assert!(runner.calls.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_coverage_removes_only_coverage_artifacts() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        for (file, size) in [
+            ("default_1234.profraw", 300),
+            ("tarpaulin-report.html", 50),
+            ("target/debug/merged.profdata", 200),
+            ("target/tarpaulin/profraws/app-5678.profraw", 100),
+            ("target/tarpaulin/coverage.json", 25),
+            ("src/main.rs", 5),
+            ("pgo/merged.profdata", 80),
+        ] {
+            std::fs::create_dir_all(project.path.join(file).parent().unwrap()).unwrap();
+            std::fs::write(project.path.join(file), vec![0u8; size]).unwrap();
+        }
+        let runner = Arc::new(FakeRunner {
+            exit_code: 0,
+            tracked: vec!["pgo/merged.profdata".to_string()],
+            calls: std::sync::Mutex::new(Vec::new()),
+        });
+        let options = CleanOptions {
+            coverage: true,
+            runner: runner.clone(),
+            ..Default::default()
+        };
+
+        let result = clean_project(&project, None, &options).unwrap();
+        assert_eq!((result.freed_bytes, result.coverage_freed_bytes), (675, Some(675)));
+        assert!(!project.path.join("default_1234.profraw").exists());
+        assert!(!project.path.join("tarpaulin-report.html").exists());
+        assert!(!project.target_dir().join("tarpaulin").exists());
+        assert!(project.path.join("src/main.rs").exists());
+        assert!(project.path.join("pgo/merged.profdata").exists());
+        assert!(project.target_dir().join("debug/app").exists());
+        assert!(runner.calls.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fuzz_corpora_kept_unless_included() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut project = project_with_target(&temp_dir);
+        project.fuzz = true;
+        for (file, size) in [("corpus/parse/seed", 300), ("corpus/parse/1a2b", 200), ("artifacts/parse/crash-3c4d", 50)] {
+            std::fs::create_dir_all(project.path.join(file).parent().unwrap()).unwrap();
+            std::fs::write(project.path.join(file), vec![0u8; size]).unwrap();
+        }
+        let runner = Arc::new(FakeRunner {
+            exit_code: 0,
+            tracked: vec!["corpus/parse/seed".to_string()],
+            calls: std::sync::Mutex::new(Vec::new()),
+        });
+        let mut options = CleanOptions {
+            strategy: CleanStrategy::RemoveTarget,
+            runner: runner.clone(),
+            dry_run: true,
+            ..Default::default()
+        };
+
+        assert_eq!(clean_project(&project, None, &options).unwrap().freed_bytes, 1034);
+        options.include_corpus = true;
+        assert_eq!(clean_project(&project, None, &options).unwrap().freed_bytes, 1284);
+
+        options.dry_run = false;
+        assert_eq!(clean_project(&project, None, &options).unwrap().freed_bytes, 1284);
+        assert!(!project.target_dir().exists());
+        assert!(project.path.join("corpus/parse/seed").exists());
+        assert!(!project.path.join("corpus/parse/1a2b").exists());
+        assert!(!project.path.join("artifacts").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_criterion_data_kept_unless_cleaned() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        let baseline = project.target_dir().join("criterion/parse/base/estimates.json");
+        std::fs::create_dir_all(baseline.parent().unwrap()).unwrap();
+        std::fs::write(&baseline, vec![0u8; 500]).unwrap();
+        let (mut options, runner) = options_with(CleanStrategy::default(), 0);
+        options.explain = true;
+
+        let result = clean_project(&project, None, &options).unwrap();
+        assert_eq!(result.freed_bytes, 1034);
+        assert!(result.explain[0].contains("except the Criterion benchmark data"));
+        assert!(baseline.exists());
+        assert!(!project.target_dir().join("debug/app").exists());
+        assert!(runner.calls.lock().unwrap().is_empty(), "cargo clean would delete the baselines");
+
+        options.clean_criterion = true;
+        options.strategy = CleanStrategy::RemoveTarget;
+        assert_eq!(clean_project(&project, None, &options).unwrap().freed_bytes, 500);
+        assert!(!project.target_dir().exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_framework_outputs_cleaned_with_target() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        std::fs::write(project.path.join("index.html"), "<link data-trunk rel=\"rust\" />").unwrap();
+        for (file, size) in [("pkg/package.json", 20), ("pkg/app_bg.wasm", 400), ("dist/index.html", 60), (".trunk/staging/app.js", 40)] {
+            std::fs::create_dir_all(project.path.join(file).parent().unwrap()).unwrap();
+            std::fs::write(project.path.join(file), vec![0u8; size]).unwrap();
+        }
+        let (mut options, _runner) = options_with(CleanStrategy::RemoveTarget, 0);
+
+        options.incremental_only = true;
+        assert!(clean_project(&project, None, &options).unwrap().freed_by_framework.is_empty());
+        options.incremental_only = false;
+        let result = clean_project(&project, None, &options).unwrap();
+        assert_eq!(result.freed_bytes, 1554);
+        assert_eq!(
+            result.freed_by_framework,
+            BTreeMap::from([("trunk".to_string(), 100), ("wasm-pack".to_string(), 420)])
+        );
+        for dir in ["target", "pkg", "dist", ".trunk"] {
+            assert!(!project.path.join(dir).exists(), "{} was left behind", dir);
+        }
+        assert!(project.path.join("index.html").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sidecar_keep_overrides_strategy() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut project = project_with_target(&temp_dir);
+        project.overrides.keep = vec!["debug/app".to_string()];
+        let (options, runner) = options_with(CleanStrategy::default(), 0);
+
+        assert_eq!(clean_project(&project, None, &options).unwrap().freed_bytes, 10);
+        assert!(project.target_dir().join("debug/app").exists());
+        assert!(!project.target_dir().join("debug/app.d").exists());
+        assert!(runner.calls.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_safe_strategy_leaves_unrecognized_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        let target_dir = project.target_dir();
+        std::fs::create_dir_all(target_dir.join("debug/deps")).unwrap();
+        std::fs::write(target_dir.join("debug/deps/libfoo-1234.rlib"), vec![0u8; 100]).unwrap();
+        std::fs::write(target_dir.join("data.csv"), vec![0u8; 5000]).unwrap();
+        std::fs::write(target_dir.join("debug/notes"), vec![0u8; 7]).unwrap();
+        // Artifact-like names outside cargo's layout are not recognized
+        std::fs::create_dir_all(target_dir.join("mydata/build")).unwrap();
+        std::fs::write(target_dir.join("mydata/build/report.csv"), vec![0u8; 3]).unwrap();
+        std::fs::write(target_dir.join("mydata/libkeep.a"), vec![0u8; 3]).unwrap();
+        std::fs::create_dir_all(target_dir.join("wasm32-unknown-unknown/release/deps")).unwrap();
+        std::fs::write(target_dir.join("wasm32-unknown-unknown/release/app.wasm"), vec![0u8; 20]).unwrap();
+        std::fs::write(target_dir.join("wasm32-unknown-unknown/release/deps/app-1.wasm"), vec![0u8; 20]).unwrap();
+        std::fs::write(target_dir.join("debug/mydata.a"), vec![0u8; 3]).unwrap();
+        std::fs::create_dir_all(target_dir.join("debug/saved")).unwrap();
+        std::fs::write(target_dir.join("debug/saved/libold.a"), vec![0u8; 3]).unwrap();
+        let (mut options, runner) = options_with(CleanStrategy::Safe(ArtifactPatterns::default()), 0);
+
+        options.dry_run = true;
+        let result = clean_project(&project, None, &options).unwrap();
+        assert_eq!(result.freed_bytes, 1177);
+        assert_eq!(
+            result.unrecognized_files,
+            vec!["data.csv", "debug/notes", "debug/saved/libold.a", "mydata/build/report.csv", "mydata/libkeep.a"]
+        );
+        assert!(target_dir.join("debug/app").exists());
+
+        options.dry_run = false;
+        let result = clean_project(&project, None, &options).unwrap();
+        assert_eq!(result.freed_bytes, 1177);
+        assert!(!target_dir.join("debug/app").exists());
+        assert!(!target_dir.join("debug/deps").exists());
+        assert!(target_dir.join("data.csv").exists());
+        assert!(target_dir.join("debug/notes").exists());
+        assert!(runner.calls.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_safe_strategy_artifact_patterns() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        let target_dir = project.target_dir();
+        std::fs::write(target_dir.join("debug/report.custom-out"), vec![0u8; 100]).unwrap();
+        let safe = |patterns: &[&str], replace_builtin| {
+            let artifacts = ArtifactPatterns {
+                patterns: patterns.iter().map(|p| p.to_string()).collect(),
+                replace_builtin,
+            };
+            let (mut options, _) = options_with(CleanStrategy::Safe(artifacts), 0);
+            options.dry_run = true;
+            clean_project(&project, None, &options).unwrap()
+        };
+
+        let builtin = safe(&[], false);
+        assert_eq!(builtin.freed_bytes, 1034);
+        assert_eq!(builtin.unrecognized_files, vec!["debug/report.custom-out"]);
+
+        let augmented = safe(&["**/*.custom-out"], false);
+        assert_eq!(augmented.freed_bytes, 1134);
+        assert!(augmented.unrecognized_files.is_empty());
+
+        let replaced = safe(&["**/*.custom-out"], true);
+        assert_eq!(replaced.freed_bytes, 100);
+        assert_eq!(replaced.unrecognized_files, vec![".rustc_info.json", "debug/app", "debug/app.d"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_git_tracked_files_are_kept() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = project_with_target(&temp_dir);
+        for strategy in [CleanStrategy::default(), CleanStrategy::RemoveTarget] {
+            let runner = Arc::new(FakeRunner {
+                exit_code: 0,
+                tracked: vec!["target/debug/app".to_string()],
+                calls: std::sync::Mutex::new(Vec::new()),
+            });
+            let options = CleanOptions {
+                strategy,
+                runner: runner.clone(),
+                ..Default::default()
+            };
+
+            let result = clean_project(&project, None, &options).unwrap();
+            assert_eq!(result.tracked_files, vec!["debug/app"]);
+            assert!(project.target_dir().join("debug/app").exists());
+            assert!(!project.target_dir().join("debug/app.d").exists());
+            assert!(runner.calls.lock().unwrap().is_empty(), "cargo clean would delete tracked files");
+            std::fs::write(project.target_dir().join("debug/app.d"), vec![0u8; 10]).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_parse_cargo_warnings() {
+        let stderr = "warning: `.cargo/config` is deprecated in favor of `config.toml`\n\
+                      note: if you need to support cargo 1.38 or earlier, you can symlink `config` to `config.toml`\n\
+                      \x20    Removed 12 files, 3.4MiB total\n\
+                      warning:\n";
+        assert_eq!(
+            parse_cargo_warnings(stderr),
+            vec!["`.cargo/config` is deprecated in favor of `config.toml`"]
+        );
+        assert!(parse_cargo_warnings("     Removed 0 files\n").is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_foreign_owned() {
+        use std::os::unix::fs::MetadataExt;
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("artifact"), "").unwrap();
+        let uid = std::fs::metadata(temp_dir.path()).unwrap().uid();
+
+        assert!(find_foreign_owned(temp_dir.path(), uid).is_none());
+        let (path, owner) = find_foreign_owned(temp_dir.path(), uid + 1).unwrap();
+        assert_eq!(path, temp_dir.path());
+        assert_eq!(owner, uid);
+    }
+
+    #[test]
+    fn test_clean_project_uses_configured_target_dir() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project_dir = temp_dir.path().join("app");
+        let redirected = temp_dir.path().join("app-target");
+        std::fs::create_dir_all(project_dir.join(".cargo")).unwrap();
+        std::fs::write(
+            project_dir.join(".cargo/config.toml"),
+            "[build]\ntarget-dir = \"../app-target\"\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(redirected.join("debug")).unwrap();
+        std::fs::write(redirected.join("debug/artifact"), vec![0u8; 1024]).unwrap();
+        std::fs::write(redirected.join(".rustc_info.json"), "").unwrap();
+
+        // Marker-only projects skip `cargo clean` and remove the target directory directly
+        let project = Project {
+            path: project_dir,
+            is_workspace: false,
+            marker_only: true,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: Default::default(),
+        };
+        let dry_run = clean_project(&project, None, &CleanOptions { dry_run: true, ..Default::default() }).unwrap();
+        assert_eq!(dry_run.freed_bytes, 1024);
+        assert!(redirected.exists());
+
+        let result = clean_project(&project, None, &CleanOptions::default()).unwrap();
+        assert_eq!(result.freed_bytes, 1024);
+        assert!(!redirected.exists());
+    }
+
+    #[test]
+    fn test_ownership_mismatch_message() {
+        let error = OwnershipMismatch {
+            path: PathBuf::from("/projects/app/target/debug"),
+            owner_uid: 100999,
+            current_uid: 1000,
+        };
+        let message = error.to_string();
+        assert!(message.contains("/projects/app/target/debug"));
+        assert!(message.contains("UID 100999"));
+        assert!(message.contains("UID 1000"));
+    }
+}
@@ -0,0 +1,311 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use crate::utils::parse_size;
+
+/// Name of the configuration file looked up in the current and home directories
+pub const CONFIG_FILE_NAME: &str = ".deepclean.toml";
+
+/// Name of the per-project sidecar file holding [`ProjectOverrides`]
+pub const SIDECAR_FILE_NAME: &str = ".deepclean";
+
+/// Tag reported for projects matching no `[tags]` pattern
+pub const UNTAGGED: &str = "untagged";
+
+/// Environment variable selecting a config profile when `--profile-name` is not given
+pub const PROFILE_ENV_VAR: &str = "DEEPCLEAN_PROFILE";
+
+/// Contents of a `.deepclean.toml` file
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    /// Tag names mapped to path globs, relative to the scanned directory
+    #[serde(default)]
+    pub tags: BTreeMap<String, Vec<String>>,
+    /// Default for `--min-size`
+    pub min_size: Option<String>,
+    /// Default for `--exclude`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Extra artifact globs for the safe and selective-glob cleaners
+    #[serde(default)]
+    pub artifact_patterns: ArtifactPatternsConfig,
+    /// Named `[profile.NAME]` tables merged over the base settings when selected
+    #[serde(default)]
+    pub profile: BTreeMap<String, ConfigProfile>,
+}
+
+/// The `[artifact_patterns]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArtifactPatternsConfig {
+    /// Globs relative to the target directory
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// Recognize only the configured patterns in safe mode, ignoring the built-in rules
+    #[serde(default)]
+    pub replace: bool,
+}
+
+/// A `[profile.NAME]` table; each key set here replaces the base config's value
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigProfile {
+    /// Tags added to the base tags, replacing any with the same name
+    #[serde(default)]
+    pub tags: BTreeMap<String, Vec<String>>,
+    pub min_size: Option<String>,
+    pub exclude: Option<Vec<String>>,
+}
+
+impl Config {
+    /// Parse a configuration file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {:?}", path))?;
+        toml::from_str(&content).with_context(|| format!("Invalid config file: {:?}", path))
+    }
+
+    /// Load `.deepclean.toml` from the current directory, falling back to the home directory
+    ///
+    /// Returns the default configuration when neither exists.
+    pub fn load() -> Result<Self> {
+        let candidates = [
+            std::env::current_dir().ok(),
+            std::env::var_os("HOME").map(PathBuf::from),
+        ];
+        for dir in candidates.into_iter().flatten() {
+            let path = dir.join(CONFIG_FILE_NAME);
+            if path.is_file() {
+                return Self::from_file(&path);
+            }
+        }
+        Ok(Self::default())
+    }
+
+    /// Merge the profile called `name` over the base settings
+    ///
+    /// `None` leaves the config unchanged; a name with no `[profile.NAME]` table is an error.
+    pub fn with_profile(mut self, name: Option<&str>) -> Result<Self> {
+        let Some(name) = name else {
+            return Ok(self);
+        };
+        let Some(profile) = self.profile.remove(name) else {
+            let defined: Vec<&str> = self.profile.keys().map(String::as_str).collect();
+            anyhow::bail!(
+                "Unknown config profile {:?} (defined: {})",
+                name,
+                if defined.is_empty() { "none".to_string() } else { defined.join(", ") }
+            );
+        };
+        self.tags.extend(profile.tags);
+        if profile.min_size.is_some() {
+            self.min_size = profile.min_size;
+        }
+        if let Some(exclude) = profile.exclude {
+            self.exclude = exclude;
+        }
+        Ok(self)
+    }
+
+    /// Compile the `[tags]` section into a classifier
+    pub fn tag_set(&self) -> Result<TagSet> {
+        let tags = self
+            .tags
+            .iter()
+            .map(|(tag, patterns)| {
+                let patterns = patterns
+                    .iter()
+                    .map(|p| {
+                        glob::Pattern::new(p)
+                            .with_context(|| format!("Invalid glob {:?} for tag {:?}", p, tag))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok((tag.clone(), patterns))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(TagSet { tags })
+    }
+}
+
+/// Raw contents of a `.deepclean` sidecar file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SidecarFile {
+    #[serde(default)]
+    skip: bool,
+    #[serde(default)]
+    keep: Vec<String>,
+    min_size: Option<String>,
+}
+
+/// Per-project options from a `.deepclean` sidecar, merged over the global options
+///
+/// Lets a repository opt out of or tune cleaning without touching its manifest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectOverrides {
+    /// Never clean this project
+    pub skip: bool,
+    /// Globs (relative to the target directory) of files to keep; everything else is removed
+    pub keep: Vec<String>,
+    /// Replaces `--min-size` for this project
+    pub min_size: Option<u64>,
+}
+
+/// Read the `.deepclean` sidecar in `project_dir`, if there is one
+///
+/// Unknown keys, invalid sizes and invalid globs are errors so typos aren't silently ignored.
+pub fn load_sidecar(project_dir: &Path) -> Result<Option<ProjectOverrides>> {
+    let path = project_dir.join(SIDECAR_FILE_NAME);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {:?}", path))?;
+    let sidecar: SidecarFile = toml::from_str(&content)
+        .with_context(|| format!("Invalid sidecar file: {:?}", path))?;
+    for pattern in &sidecar.keep {
+        glob::Pattern::new(pattern).with_context(|| format!("Invalid keep glob {:?} in {:?}", pattern, path))?;
+    }
+    let min_size = sidecar
+        .min_size
+        .map(|size| parse_size(&size).with_context(|| format!("Invalid min_size {:?} in {:?}", size, path)))
+        .transpose()?;
+    Ok(Some(ProjectOverrides {
+        skip: sidecar.skip,
+        keep: sidecar.keep,
+        min_size,
+    }))
+}
+
+/// Classifies projects into tags by matching their path against glob patterns
+#[derive(Debug, Default)]
+pub struct TagSet {
+    tags: Vec<(String, Vec<glob::Pattern>)>,
+}
+
+impl TagSet {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Tags whose patterns match `path` relative to `root`, or [`UNTAGGED`] if none do
+    pub fn classify(&self, path: &Path, root: &Path) -> Vec<String> {
+        let relative = path.strip_prefix(root).unwrap_or(path).to_string_lossy();
+        let matched: Vec<String> = self
+            .tags
+            .iter()
+            .filter(|(_, patterns)| patterns.iter().any(|p| p.matches(&relative)))
+            .map(|(tag, _)| tag.clone())
+            .collect();
+        if matched.is_empty() {
+            vec![UNTAGGED.to_string()]
+        } else {
+            matched
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_classification() {
+        let config: Config = toml::from_str(
+            r#"
+            [tags]
+            services = ["services/*"]
+            tools = ["tools/*", "services/cli"]
+            "#,
+        )
+        .unwrap();
+        let tag_set = config.tag_set().unwrap();
+        let root = Path::new("/work");
+
+        assert_eq!(tag_set.classify(Path::new("/work/services/api"), root), vec!["services"]);
+        assert_eq!(
+            tag_set.classify(Path::new("/work/services/cli"), root),
+            vec!["services", "tools"]
+        );
+        assert_eq!(tag_set.classify(Path::new("/work/misc/demo"), root), vec![UNTAGGED]);
+    }
+
+    #[test]
+    fn test_load_sidecar() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(load_sidecar(temp_dir.path()).unwrap(), None);
+
+        let sidecar = temp_dir.path().join(SIDECAR_FILE_NAME);
+        std::fs::write(&sidecar, "skip = true\n").unwrap();
+        let overrides = load_sidecar(temp_dir.path()).unwrap().unwrap();
+        assert!(overrides.skip);
+        assert!(overrides.keep.is_empty());
+
+        std::fs::write(&sidecar, "keep = [\"release/**\"]\nmin_size = \"1KB\"\n").unwrap();
+        let overrides = load_sidecar(temp_dir.path()).unwrap().unwrap();
+        assert!(!overrides.skip);
+        assert_eq!(overrides.keep, vec!["release/**"]);
+        assert_eq!(overrides.min_size, Some(1024));
+
+        for malformed in ["skip = \"yes\"", "skp = true", "min_size = \"lots\"", "keep = [\"[\"]"] {
+            std::fs::write(&sidecar, malformed).unwrap();
+            assert!(load_sidecar(temp_dir.path()).is_err(), "{} should be rejected", malformed);
+        }
+    }
+
+    #[test]
+    fn test_profile_merges_over_base() {
+        let config = || -> Config {
+            toml::from_str(
+                r#"
+                min_size = "100MB"
+                exclude = ["**/vendor"]
+
+                [tags]
+                services = ["services/*"]
+
+                [profile.ci]
+                min_size = "1KB"
+
+                [profile.laptop]
+                exclude = []
+                tags = { services = ["svc/*"], tools = ["tools/*"] }
+                "#,
+            )
+            .unwrap()
+        };
+
+        let base = config().with_profile(None).unwrap();
+        assert_eq!(base.min_size.as_deref(), Some("100MB"));
+
+        let ci = config().with_profile(Some("ci")).unwrap();
+        assert_eq!(ci.min_size.as_deref(), Some("1KB"));
+        assert_eq!(ci.exclude, vec!["**/vendor"]);
+        assert_eq!(ci.tags["services"], vec!["services/*"]);
+
+        let laptop = config().with_profile(Some("laptop")).unwrap();
+        assert_eq!(laptop.min_size.as_deref(), Some("100MB"));
+        assert!(laptop.exclude.is_empty());
+        assert_eq!(laptop.tags["services"], vec!["svc/*"]);
+        assert_eq!(laptop.tags.len(), 2);
+
+        let error = config().with_profile(Some("desktop")).unwrap_err().to_string();
+        assert!(error.contains("desktop") && error.contains("ci, laptop"));
+    }
+
+    #[test]
+    fn test_artifact_patterns_section() {
+        let config: Config = toml::from_str("[artifact_patterns]\npatterns = [\"**/*.out\"]\nreplace = true\n").unwrap();
+        assert_eq!(config.artifact_patterns.patterns, vec!["**/*.out"]);
+        assert!(config.artifact_patterns.replace);
+        assert!(toml::from_str::<Config>("[artifact_patterns]\npattern = []\n").is_err());
+    }
+
+    #[test]
+    fn test_invalid_tag_glob() {
+        let config: Config = toml::from_str("[tags]\nbroken = [\"[\"]\n").unwrap();
+        let error = config.tag_set().unwrap_err().to_string();
+        assert!(error.contains("broken"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Policy and state for `daemon`, which cleans targets whenever free disk space runs low
+
+use crate::project::Project;
+use crate::utils::{cache_dir, format_utc, DirectoryStats};
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Name of the daemon's state file inside deepclean's cache directory
+pub const STATE_FILE_NAME: &str = "daemon.json";
+
+/// Which targets the daemon cleans first when space has to be reclaimed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CleanOrder {
+    /// Least recently built first
+    Oldest,
+    /// Largest first
+    Largest,
+}
+
+/// The targets to clean, in order, to reclaim at least `needed` bytes
+///
+/// Empty targets are never picked. When all the others together hold less than
+/// `needed`, every one of them is picked.
+pub fn pick_targets(targets: &[(Project, DirectoryStats)], needed: u64, order: CleanOrder) -> Vec<&(Project, DirectoryStats)> {
+    let mut candidates: Vec<_> = targets.iter().filter(|(_, stats)| stats.size > 0).collect();
+    match order {
+        // Targets without a timestamp hold no files, so none are left here
+        CleanOrder::Oldest => candidates.sort_by_key(|(project, stats)| (stats.newest_mtime, project.path.clone())),
+        CleanOrder::Largest => {
+            candidates.sort_by(|(a, a_stats), (b, b_stats)| b_stats.size.cmp(&a_stats.size).then_with(|| a.path.cmp(&b.path)))
+        }
+    }
+    let mut reclaimed = 0;
+    candidates
+        .into_iter()
+        .take_while(|(_, stats)| {
+            let take = reclaimed < needed;
+            reclaimed += stats.size;
+            take
+        })
+        .collect()
+}
+
+/// What the daemon remembers across checks and restarts
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct DaemonState {
+    /// When free space was last checked, in seconds since the Unix epoch
+    pub last_check: Option<u64>,
+    /// When targets were last cleaned, in seconds since the Unix epoch
+    pub last_cleanup: Option<u64>,
+    /// Cleanups run, each reclaiming space on one filesystem
+    pub cleanups: u64,
+    pub total_freed_bytes: u64,
+    /// When each project's target was last cleaned, in seconds since the Unix epoch
+    pub cleaned: BTreeMap<PathBuf, u64>,
+}
+
+impl DaemonState {
+    /// Where the state is kept: `daemon.json` in deepclean's [cache directory](cache_dir)
+    pub fn default_path() -> Option<PathBuf> {
+        Some(cache_dir()?.join(STATE_FILE_NAME))
+    }
+
+    /// Read the state at `path`; a missing or unreadable file starts afresh
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Write the state to `path`, creating its directory if needed
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write daemon state: {:?}", path))
+    }
+
+    /// Count a finished cleanup and the projects it cleaned
+    pub fn record_cleanup<'a>(&mut self, now: u64, cleaned: impl IntoIterator<Item = (&'a Path, u64)>) {
+        self.last_cleanup = Some(now);
+        self.cleanups += 1;
+        for (path, freed_bytes) in cleaned {
+            self.cleaned.insert(path.to_path_buf(), now);
+            self.total_freed_bytes += freed_bytes;
+        }
+    }
+}
+
+/// One line of the daemon's log, prefixed with the time in UTC
+pub fn log_line(timestamp: u64, message: &str) -> String {
+    format!("[{}] {}", format_utc(timestamp), message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(path: &str, size: u64, newest_mtime: Option<u64>) -> (Project, DirectoryStats) {
+        let project = Project {
+            path: PathBuf::from(path),
+            is_workspace: false,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: Default::default(),
+        };
+        (project, DirectoryStats { size, disk_usage: size, oldest_mtime: newest_mtime, newest_mtime })
+    }
+
+    #[test]
+    fn test_pick_targets() {
+        let targets = [
+            target("/a", 300, Some(30)),
+            target("/b", 100, Some(10)),
+            target("/c", 500, Some(20)),
+            target("/empty", 0, None),
+        ];
+        let paths = |picked: Vec<&(Project, DirectoryStats)>| -> Vec<String> {
+            picked.iter().map(|(project, _)| project.path.display().to_string()).collect()
+        };
+        assert_eq!(paths(pick_targets(&targets, 150, CleanOrder::Oldest)), ["/b", "/c"]);
+        assert_eq!(paths(pick_targets(&targets, 150, CleanOrder::Largest)), ["/c"]);
+        assert_eq!(paths(pick_targets(&targets, 600, CleanOrder::Largest)), ["/c", "/a"]);
+        assert_eq!(paths(pick_targets(&targets, 10_000, CleanOrder::Oldest)), ["/b", "/c", "/a"]);
+        assert!(pick_targets(&targets, 0, CleanOrder::Oldest).is_empty());
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("cache").join(STATE_FILE_NAME);
+        let mut state = DaemonState::load(&path);
+        state.record_cleanup(100, [(Path::new("/a"), 300), (Path::new("/b"), 100)]);
+        state.save(&path).unwrap();
+
+        let state = DaemonState::load(&path);
+        assert_eq!((state.cleanups, state.total_freed_bytes, state.last_cleanup), (1, 400, Some(100)));
+        assert_eq!(state.cleaned[Path::new("/b")], 100);
+        assert_eq!(log_line(0, "started"), "[1970-01-01T00:00:00Z] started");
+    }
+}
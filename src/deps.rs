@@ -1,16 +1,190 @@
 use anyhow::{Context, Result};
+use cargo_metadata::MetadataCommand;
+use crate::cleaner::{CommandRunner, SystemRunner};
+use crate::manifest::{ManifestEdit, ManifestTransaction};
 use crate::project::Project;
 use colored::Colorize;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::sync::Arc;
 use walkdir::WalkDir;
 
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct UnusedDependency {
     pub name: String,
     pub location: String, // e.g., "[dependencies]", "[dev-dependencies]"
+    /// Workspace member declaring the dependency, e.g. "app 0.1.0", when the
+    /// analysis covered more than one package
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    /// Manifest declaring the dependency, when it is not the project's own Cargo.toml
+    /// (e.g. a workspace member's)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest_path: Option<PathBuf>,
+}
+
+impl UnusedDependency {
+    /// Manifest to remove the dependency from, for a project in `project_dir`
+    pub fn manifest(&self, project_dir: &Path) -> PathBuf {
+        self.manifest_path.clone().unwrap_or_else(|| project_dir.join("Cargo.toml"))
+    }
+
+    /// Kind of the table the dependency is declared in
+    pub fn kind(&self) -> Option<DependencyKind> {
+        DependencyKind::of_location(&self.location)
+    }
+
+    /// Manifest table, prefixed with the declaring package when known
+    pub fn origin(&self) -> String {
+        match &self.package {
+            Some(package) => format!("{} {}", package, self.location),
+            None => self.location.clone(),
+        }
+    }
+}
+
+/// One resolved version of a duplicated crate and the packages that pull it in
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DuplicateVersion {
+    pub version: String,
+    pub dependents: Vec<String>, // e.g., "hyper 0.14.28"
+    /// Shortest dependency chain from each local package that pulls this version in,
+    /// e.g. ["app 0.1.0", "reqwest 0.11.27", "hyper 0.14.28"]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub chains: Vec<Vec<String>>,
+}
+
+/// A path dependency whose directory, or the Cargo.toml in it, no longer exists
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct StalePathDependency {
+    pub name: String,
+    pub location: String,
+    /// The `path` value as written in the manifest
+    pub path: String,
+    /// Workspace member declaring the dependency, when the analysis covered more than one package
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    /// Manifest declaring the dependency
+    pub manifest_path: PathBuf,
+}
+
+impl StalePathDependency {
+    /// The entry as a removal target for [`plan_dependency_removal`]
+    pub fn as_dependency(&self) -> UnusedDependency {
+        UnusedDependency {
+            name: self.name.clone(),
+            location: self.location.clone(),
+            package: self.package.clone(),
+            manifest_path: Some(self.manifest_path.clone()),
+        }
+    }
+
+    /// Whether `dep` names this same manifest entry
+    fn matches(&self, dep: &UnusedDependency, project_dir: &Path) -> bool {
+        dep.name == self.name && dep.location == self.location && dep.manifest(project_dir) == self.manifest_path
+    }
+}
+
+/// A dependency with a newer release than the version in Cargo.lock, as reported by
+/// `cargo outdated`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct OutdatedDependency {
+    pub name: String,
+    /// Version locked in Cargo.lock
+    pub project: String,
+    /// Newest version the requirement in Cargo.toml allows ("---" when there is none)
+    pub compat: String,
+    /// Newest published version
+    pub latest: String,
+    /// e.g. "Normal", "Development", "Build"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// Workspace member declaring the dependency, when the analysis covered more than one package
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+}
+
+/// A dependency declared the same way by several workspace members, which can be
+/// inherited from `[workspace.dependencies]` instead (`--hoist-deps`)
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct HoistableDependency {
+    pub name: String,
+    /// Value for `[workspace.dependencies]`, e.g. `"1.0"` or `{ version = "1", default-features = false }`
+    pub workspace_entry: String,
+    /// Already declared that way in `[workspace.dependencies]`, so only members change
+    pub inherited_elsewhere: bool,
+    /// Member entries to rewrite to `workspace = true`
+    pub members: Vec<HoistedEntry>,
+}
+
+/// One member's entry for a [`HoistableDependency`]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct HoistedEntry {
+    /// e.g. "app 0.1.0"
+    pub package: String,
+    /// e.g. "[dev-dependencies]"
+    pub location: String,
+    pub manifest_path: PathBuf,
+}
+
+/// A normal dependency only referenced from test code, examples or benches
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct MisplacedDependency {
+    pub name: String,
+    /// Table it is declared in, e.g. "[dependencies]"
+    pub location: String,
+    /// Table it belongs in, e.g. "[dev-dependencies]"
+    pub suggested_location: String,
+    /// Workspace member declaring the dependency, when the analysis covered more than one package
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    /// Manifest declaring the dependency
+    pub manifest_path: PathBuf,
+}
+
+impl MisplacedDependency {
+    /// e.g. "move rand to [dev-dependencies]"
+    pub fn suggestion(&self) -> String {
+        format!("move {} to {}", self.name, self.suggested_location)
+    }
+}
+
+/// A feature that could be trimmed: a `[features]` entry that nothing enables, or a
+/// feature enabled on a dependency that compilation doesn't need
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct FeatureFinding {
+    /// Workspace member whose manifest holds the feature, e.g. "app 0.1.0", when the
+    /// analysis covered more than one package
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    /// Dependency the feature is enabled on, for dependency features
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dependency: Option<String>,
+    pub feature: String,
+    /// Why it looks unnecessary and how to trim it
+    pub suggestion: String,
+}
+
+impl FeatureFinding {
+    /// `dependency/feature` or just the feature, prefixed with the package when known
+    pub fn describe(&self) -> String {
+        let feature = match &self.dependency {
+            Some(dependency) => format!("{}/{}", dependency, self.feature),
+            None => self.feature.clone(),
+        };
+        match &self.package {
+            Some(package) => format!("{} ({})", feature, package),
+            None => feature,
+        }
+    }
+}
+
+/// A crate that appears in Cargo.lock at more than one version
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DuplicateDependency {
+    pub name: String,
+    pub versions: Vec<DuplicateVersion>,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -19,11 +193,198 @@ pub struct DependencyCleanResult {
     pub success: bool,
     pub unused_deps: Vec<UnusedDependency>,
     pub removed_count: usize,
+    /// Removals restored because `cargo check` failed after them (`--verify`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rolled_back: Vec<UnusedDependency>,
+    /// Unified diff of the manifest changes a dry run would make
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+    pub duplicates: Vec<DuplicateDependency>,
+    /// Features that could be trimmed (`--features`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<FeatureFinding>,
+    /// Path dependencies pointing at missing directories (`--stale-paths`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stale_paths: Vec<StalePathDependency>,
+    /// Stale path dependencies removed or commented out
+    pub stale_paths_fixed: usize,
+    /// Normal dependencies only used by tests, examples or benches (`--dev-deps`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dev_only: Vec<MisplacedDependency>,
+    /// Dependencies moved to a dev-dependencies table (`--dev-deps --apply`)
+    pub moved_to_dev: usize,
+    /// Dependencies with newer releases (`--outdated`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub outdated: Vec<OutdatedDependency>,
+    /// Dependencies members could inherit from `[workspace.dependencies]` (`--hoist-deps`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hoistable: Vec<HoistableDependency>,
+    /// Member entries rewritten to `workspace = true` (`--hoist-deps --apply`)
+    pub hoisted: usize,
+    /// Cargo.lock packages dropped by `cargo update --workspace` after the edits (`--prune-lock`)
+    pub lock_entries_pruned: usize,
     pub error: Option<String>,
 }
 
-/// Extract dependency names from Cargo.toml
-fn extract_dependencies(cargo_toml_path: &Path) -> Result<Vec<(String, String)>> {
+impl DependencyCleanResult {
+    /// Result for a project whose dependency analysis could not run
+    pub fn failed(project: &Project, error: String) -> Self {
+        Self {
+            path: project.path.to_string_lossy().to_string(),
+            success: false,
+            unused_deps: vec![],
+            removed_count: 0,
+            rolled_back: vec![],
+            diff: None,
+            duplicates: vec![],
+            features: vec![],
+            stale_paths: vec![],
+            stale_paths_fixed: 0,
+            dev_only: vec![],
+            moved_to_dev: 0,
+            outdated: vec![],
+            hoistable: vec![],
+            hoisted: 0,
+            lock_entries_pruned: 0,
+            error: Some(error),
+        }
+    }
+}
+
+/// Kind of dependency table, selected with `--only`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DependencyKind {
+    /// `[dependencies]`
+    Normal,
+    /// `[dev-dependencies]`
+    Dev,
+    /// `[build-dependencies]`
+    Build,
+}
+
+impl DependencyKind {
+    /// Kind of the table a location such as `[target.'cfg(unix)'.dev-dependencies]` names
+    pub fn of_location(location: &str) -> Option<Self> {
+        match location_table(location).1 {
+            "dependencies" => Some(Self::Normal),
+            "dev-dependencies" => Some(Self::Dev),
+            "build-dependencies" => Some(Self::Build),
+            _ => None,
+        }
+    }
+}
+
+/// When `cargo check` confirms dependency removals (`--verify`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum VerifyMode {
+    /// Check once after removing a project's unused dependencies; a failure restores them all
+    Batch,
+    /// Check after each removal, restoring only the ones that break the build
+    Each,
+}
+
+/// What `--stale-paths` does with path dependencies pointing at missing directories
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StalePathAction {
+    /// Only report them
+    Report,
+    /// Remove them from the manifest
+    Remove,
+    /// Comment their entries out, keeping them in the manifest for reference
+    Comment,
+}
+
+/// Default number of projects analyzed for dependencies at once (`--dep-jobs`)
+pub const DEFAULT_DEP_JOBS: usize = 2;
+
+/// Options controlling dependency analysis
+#[derive(Debug, Clone)]
+pub struct DepsOptions {
+    pub dry_run: bool,
+    /// Look for unused dependencies
+    pub check_unused: bool,
+    /// Remove unused dependencies (requires `check_unused`)
+    pub remove: bool,
+    /// Report crates locked at multiple versions
+    pub duplicates: bool,
+    /// Suggest features to trim
+    pub features: bool,
+    /// Look for path dependencies pointing at missing directories, and what to do with them
+    pub stale_paths: Option<StalePathAction>,
+    /// Suggest moving dependencies only used by tests, examples or benches to `[dev-dependencies]`
+    pub dev_deps: bool,
+    /// Suggest hoisting dependencies shared by workspace members into `[workspace.dependencies]`
+    pub hoist: bool,
+    /// Perform the changes suggested by `dev_deps` and `hoist`
+    pub apply: bool,
+    /// Report dependencies with newer releases using `cargo outdated`
+    pub outdated: bool,
+    /// Run `cargo update --workspace` after editing manifests so Cargo.lock drops stale entries
+    pub prune_lock: bool,
+    pub verbose: bool,
+    /// Restrict unused-dependency analysis and removal to these kinds; empty means all
+    pub only: Vec<DependencyKind>,
+    /// Run `cargo check` after removing and restore the manifest if it fails
+    pub verify: Option<VerifyMode>,
+    /// Runs `cargo check` for `verify`
+    pub runner: Arc<dyn CommandRunner>,
+}
+
+impl Default for DepsOptions {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            check_unused: false,
+            remove: false,
+            duplicates: false,
+            features: false,
+            stale_paths: None,
+            dev_deps: false,
+            hoist: false,
+            apply: false,
+            outdated: false,
+            prune_lock: false,
+            verbose: false,
+            only: Vec::new(),
+            verify: None,
+            runner: Arc::new(SystemRunner),
+        }
+    }
+}
+
+impl DepsOptions {
+    /// Whether any dependency analysis is enabled
+    pub fn any_analysis(&self) -> bool {
+        self.check_unused || self.duplicates || self.features || self.stale_paths.is_some() || self.dev_deps || self.hoist || self.outdated
+    }
+
+    /// Whether `dep` is of a kind selected by [`only`](Self::only)
+    pub fn includes(&self, dep: &UnusedDependency) -> bool {
+        self.only.is_empty() || dep.kind().is_some_and(|kind| self.only.contains(&kind))
+    }
+}
+
+/// A dependency declared in Cargo.toml
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DeclaredDependency {
+    /// Key in the dependency table, which is also the name code refers to it by
+    name: String,
+    /// Crate it resolves to; differs from `name` when renamed with `package = "..."`
+    package: String,
+    /// e.g. "[dependencies]"
+    location: String,
+    /// Declared with `workspace = true`, inheriting from `[workspace.dependencies]`
+    inherited: bool,
+}
+
+/// Extract declared dependencies from Cargo.toml
+///
+/// `workspace_deps` is the root's `[workspace.dependencies]` table, used to find the
+/// crate an inherited dependency resolves to.
+fn extract_dependencies(
+    cargo_toml_path: &Path,
+    workspace_deps: Option<&toml::value::Table>,
+) -> Result<Vec<DeclaredDependency>> {
     let content = fs::read_to_string(cargo_toml_path)
         .with_context(|| format!("Failed to read Cargo.toml: {:?}", cargo_toml_path))?;
     
@@ -31,27 +392,38 @@ fn extract_dependencies(cargo_toml_path: &Path) -> Result<Vec<(String, String)>>
         .with_context(|| format!("Failed to parse Cargo.toml: {:?}", cargo_toml_path))?;
     
     let mut deps = Vec::new();
-    
-    // Extract [dependencies]
-    if let Some(deps_table) = toml.get("dependencies").and_then(|v| v.as_table()) {
-        for (name, _) in deps_table {
-            // Skip workspace dependencies and path dependencies for now
-            // Only check crates.io dependencies
-            deps.push((name.clone(), "[dependencies]".to_string()));
+    let mut push_table = |table: &toml::value::Table, location: String| {
+        for (name, spec) in table {
+            let inherited = spec.get("workspace").and_then(|w| w.as_bool()).unwrap_or(false);
+            let source = match workspace_deps.and_then(|deps| deps.get(name)) {
+                Some(workspace_spec) if inherited => workspace_spec,
+                _ => spec,
+            };
+            let package = source.get("package").and_then(|p| p.as_str()).unwrap_or(name);
+            deps.push(DeclaredDependency {
+                name: name.clone(),
+                package: package.to_string(),
+                location: location.clone(),
+                inherited,
+            });
         }
-    }
+    };
     
-    // Extract [dev-dependencies]
-    if let Some(dev_deps_table) = toml.get("dev-dependencies").and_then(|v| v.as_table()) {
-        for (name, _) in dev_deps_table {
-            deps.push((name.clone(), "[dev-dependencies]".to_string()));
+    // Extract [dependencies], [dev-dependencies] and [build-dependencies]
+    for kind in ["dependencies", "dev-dependencies", "build-dependencies"] {
+        if let Some(table) = toml.get(kind).and_then(|v| v.as_table()) {
+            push_table(table, format!("[{}]", kind));
         }
     }
-    
-    // Extract [build-dependencies]
-    if let Some(build_deps_table) = toml.get("build-dependencies").and_then(|v| v.as_table()) {
-        for (name, _) in build_deps_table {
-            deps.push((name.clone(), "[build-dependencies]".to_string()));
+
+    // Extract platform-specific tables such as [target.'cfg(unix)'.dependencies]
+    if let Some(targets) = toml.get("target").and_then(|v| v.as_table()) {
+        for (target, tables) in targets {
+            for kind in ["dependencies", "dev-dependencies", "build-dependencies"] {
+                if let Some(table) = tables.get(kind).and_then(|v| v.as_table()) {
+                    push_table(table, format!("[target.'{}'.{}]", target, kind));
+                }
+            }
         }
     }
     
@@ -63,77 +435,206 @@ fn normalize_crate_name(name: &str) -> String {
     name.replace('-', "_")
 }
 
-/// Check if a dependency is used in the source code
-fn is_dependency_used(dep_name: &str, project_path: &Path) -> bool {
-    let normalized_dep = normalize_crate_name(dep_name);
-    let search_patterns = vec![
-        // Direct use statements
-        format!("use {}::", normalized_dep),
-        format!("use {};", normalized_dep),
-        format!("use crate::{}", normalized_dep),
-        // In paths
-        format!("{}::", normalized_dep),
-        // Extern crate (older style)
-        format!("extern crate {}", normalized_dep),
-        // Macro invocations
-        format!("{}!", normalized_dep),
-        // Attribute macros
-        format!("#[{}", normalized_dep),
-    ];
-    
-    // Search in src/ directory
-    let src_dir = project_path.join("src");
-    if src_dir.exists() {
-        if search_in_directory(&src_dir, &search_patterns) {
-            return true;
+/// Collect identifiers in Rust source that may name a crate
+///
+/// An identifier counts when it starts a path (`name::`), invokes a macro (`name!`),
+/// opens an attribute (`#[name`) or follows `use` or `extern crate`. String literals and
+/// comments are not skipped, so a mention there keeps a dependency alive; that errs
+/// on the side of reporting fewer false positives.
+fn collect_crate_references(source: &str, references: &mut HashSet<String>) {
+    let bytes = source.as_bytes();
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    let mut i = 0;
+    while i < bytes.len() {
+        if !(bytes[i].is_ascii_alphabetic() || bytes[i] == b'_') {
+            // Skip the rest of number literals like `1u8` along with any other byte
+            let start = i;
+            i += 1;
+            if bytes[start].is_ascii_digit() {
+                while i < bytes.len() && is_ident(bytes[i]) {
+                    i += 1;
+                }
+            }
+            continue;
         }
-    }
-    
-    // Search in examples/ directory
-    let examples_dir = project_path.join("examples");
-    if examples_dir.exists() {
-        if search_in_directory(&examples_dir, &search_patterns) {
-            return true;
+        let start = i;
+        while i < bytes.len() && is_ident(bytes[i]) {
+            i += 1;
+        }
+        let ident = &source[start..i];
+        let before = source[..start].trim_end();
+        let after = source[i..].trim_start();
+        let follows_keyword = |keyword: &str| {
+            before
+                .strip_suffix(keyword)
+                .is_some_and(|rest| !rest.bytes().next_back().is_some_and(is_ident))
+        };
+        let referenced = after.starts_with("::")
+            || (after.starts_with('!') && !after.starts_with("!="))
+            || before.ends_with("#[")
+            || follows_keyword("use")
+            || before
+                .strip_suffix("crate")
+                .is_some_and(|rest| rest.trim_end().ends_with("extern"));
+        if referenced {
+            references.insert(ident.to_string());
         }
     }
-    
-    // Search in tests/ directory
-    let tests_dir = project_path.join("tests");
-    if tests_dir.exists() {
-        if search_in_directory(&tests_dir, &search_patterns) {
-            return true;
+}
+
+/// Crate references in every Rust file a dependency can be used from: `src/`,
+/// `examples/`, `tests/`, `benches/` and `build.rs`
+fn project_crate_references(project_path: &Path) -> HashSet<String> {
+    let mut references = HashSet::new();
+    for_each_source(project_path, |content| collect_crate_references(content, &mut references));
+    references
+}
+
+/// Call `f` with the contents of every Rust file under the project's `src`, `examples`,
+/// `tests` and `benches` directories and its `build.rs`
+fn for_each_source(project_path: &Path, mut f: impl FnMut(&str)) {
+    let roots = ["src", "examples", "tests", "benches", "build.rs"].map(|root| project_path.join(root));
+    for root in roots.iter().filter(|root| root.exists()) {
+        for entry in WalkDir::new(root).into_iter().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if entry.file_type().is_file() && path.extension().is_some_and(|ext| ext == "rs") {
+                if let Ok(content) = fs::read_to_string(path) {
+                    f(&content);
+                }
+            }
         }
     }
-    
-    // Check build.rs
-    let build_rs = project_path.join("build.rs");
-    if build_rs.exists() {
-        if let Ok(content) = fs::read_to_string(&build_rs) {
-            for pattern in &search_patterns {
-                if content.contains(pattern) {
-                    return true;
+}
+
+/// Index of the `}` closing the `{` at `open`
+///
+/// Braces in strings and comments are counted too, which works as long as they balance.
+fn matching_brace(source: &str, open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, b) in source.bytes().enumerate().skip(open) {
+        match b {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
                 }
             }
+            _ => {}
         }
     }
-    
-    // Check Cargo.toml for feature flags or other references
-    let cargo_toml = project_path.join("Cargo.toml");
-    if let Ok(content) = fs::read_to_string(&cargo_toml) {
-        // Check if it's used in feature definitions or other places
-        // This is a simple check - might need refinement
-        let normalized = normalize_crate_name(dep_name);
-        if content.contains(&format!("{}/", dep_name)) 
-            || content.contains(&format!("{}-", dep_name))
-            || content.contains(&format!("{}/", normalized))
-            || content.contains(&format!("{}-", normalized)) {
-            return true;
+    None
+}
+
+/// Rust source split by whether it is only compiled for tests
+#[derive(Debug, Default, PartialEq, Eq)]
+struct SplitSource {
+    production: String,
+    /// Items under `#[cfg(test)]`
+    test: String,
+    /// Test-only modules declared without a body (`#[cfg(test)] mod name;`), whose
+    /// files hold test code too
+    test_modules: Vec<String>,
+}
+
+/// Split Rust source into code compiled normally and code only compiled for tests
+fn split_test_code(source: &str) -> SplitSource {
+    const ATTRIBUTE: &str = "#[cfg(test)]";
+    let mut production = String::new();
+    let mut test = String::new();
+    let mut test_modules = Vec::new();
+    let mut rest = source;
+    while let Some(start) = rest.find(ATTRIBUTE) {
+        production.push_str(&rest[..start]);
+        let item = &rest[start..];
+        // The item ends at a `;` before any `{`, or at the brace closing its first `{`
+        let end = match (item.find(';'), item.find('{')) {
+            (Some(semi), open) if open.is_none_or(|open| semi < open) => semi + 1,
+            (_, Some(open)) => matching_brace(item, open).map_or(item.len(), |close| close + 1),
+            _ => item.len(),
+        };
+        let (item, after) = item.split_at(end);
+        if item.ends_with(';') {
+            let mut words = item[ATTRIBUTE.len()..].trim_end_matches(';').split_whitespace();
+            if words.any(|word| word == "mod") {
+                test_modules.extend(words.next().map(str::to_string));
+            }
         }
+        test.push_str(item);
+        rest = after;
     }
-    
+    production.push_str(rest);
+    SplitSource { production, test, test_modules }
+}
+
+/// Crate references split by where they occur: code compiled into the package, and
+/// code only built for tests, examples and benches
+///
+/// Test code is `#[cfg(test)]` items under `src/` (including the files of
+/// `#[cfg(test)] mod name;` modules) plus everything in `tests/`, `examples/` and
+/// `benches/`. `build.rs`, which can only use build-dependencies, is left out.
+fn split_crate_references(project_path: &Path) -> (HashSet<String>, HashSet<String>) {
+    let rust_files = |root: &Path| -> Vec<(PathBuf, String)> {
+        WalkDir::new(root)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file() && e.path().extension().is_some_and(|ext| ext == "rs"))
+            .filter_map(|e| Some((e.path().to_path_buf(), fs::read_to_string(e.path()).ok()?)))
+            .collect()
+    };
+
+    let sources: Vec<(PathBuf, SplitSource)> = rust_files(&project_path.join("src"))
+        .into_iter()
+        .map(|(path, content)| (path, split_test_code(&content)))
+        .collect();
+    // A module declared in lib.rs, main.rs or mod.rs lives next to it; elsewhere, in a
+    // directory named after the declaring file
+    let mut test_only = Vec::new();
+    for (path, split) in &sources {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        let base = if ["lib", "main", "mod"].contains(&stem) { dir.to_path_buf() } else { dir.join(stem) };
+        for module in &split.test_modules {
+            test_only.push((base.join(format!("{}.rs", module)), base.join(module)));
+        }
+    }
+
+    let mut production = HashSet::new();
+    let mut dev = HashSet::new();
+    for (path, split) in &sources {
+        if test_only.iter().any(|(file, dir)| path == file || path.starts_with(dir)) {
+            collect_crate_references(&split.production, &mut dev);
+        } else {
+            collect_crate_references(&split.production, &mut production);
+        }
+        collect_crate_references(&split.test, &mut dev);
+    }
+    for root in ["tests", "examples", "benches"] {
+        for (_, content) in rust_files(&project_path.join(root)) {
+            collect_crate_references(&content, &mut dev);
+        }
+    }
+    (production, dev)
+}
+
+/// Check if a dependency is used, given the crate references found in the project
+fn is_dependency_used(dep: &DeclaredDependency, references: &HashSet<String>, manifest: &str) -> bool {
+    if references.contains(&normalize_crate_name(&dep.name)) {
+        return true;
+    }
+
+    // Referenced from [features] (`name/feature`, `dep:name`) keeps optional
+    // dependencies wired up even when the code only uses them behind cfg
+    if manifest.contains(&format!("\"{}/", dep.name))
+        || manifest.contains(&format!("\"{}?/", dep.name))
+        || manifest.contains(&format!("\"dep:{}\"", dep.name))
+    {
+        return true;
+    }
+
     // Check for proc-macro usage (they're used via attributes, not imports)
     // This is a heuristic - proc-macros are tricky
-    if dep_name.contains("proc-macro") || dep_name.contains("derive") {
+    if dep.package.contains("proc-macro") || dep.package.contains("derive") {
         // These are likely used even if not directly imported
         // Be conservative and assume they're used
         return true;
@@ -142,42 +643,23 @@ fn is_dependency_used(dep_name: &str, project_path: &Path) -> bool {
     false
 }
 
-/// Search for patterns in a directory
-fn search_in_directory(dir: &Path, patterns: &[String]) -> bool {
-    for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
-        if entry.file_type().is_file() {
-            let path = entry.path();
-            // Only check Rust files
-            if let Some(ext) = path.extension() {
-                if ext == "rs" {
-                    if let Ok(content) = fs::read_to_string(path) {
-                        for pattern in patterns {
-                            if content.contains(pattern) {
-                                return true;
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-    false
-}
-
-/// Check for unused dependencies in a project
-pub fn check_unused_dependencies(project: &Project) -> Result<Vec<UnusedDependency>> {
-    let cargo_toml = project.path.join("Cargo.toml");
-    if !cargo_toml.exists() {
-        return Ok(vec![]);
-    }
-    
-    let all_deps = extract_dependencies(&cargo_toml)?;
+/// Unused dependencies declared in one manifest, attributed to `package` when set
+///
+/// Also returns every dependency the manifest declares.
+fn unused_in_manifest(
+    cargo_toml: &Path,
+    package: Option<&str>,
+    workspace_deps: Option<&toml::value::Table>,
+) -> Result<(Vec<DeclaredDependency>, Vec<UnusedDependency>)> {
+    let all_deps = extract_dependencies(cargo_toml, workspace_deps)?;
+    let manifest = fs::read_to_string(cargo_toml).unwrap_or_default();
+    let references = project_crate_references(cargo_toml.parent().unwrap_or(Path::new(".")));
     let mut unused = Vec::new();
     
-    for (dep_name, location) in all_deps {
+    for dep in &all_deps {
         // Skip some common dependencies that might be used indirectly
         // These are often used in macros, build scripts, or procedural macros
-        let skip_list = vec![
+        let skip_list = [
             "proc-macro2",
             "quote",
             "syn",
@@ -187,154 +669,1760 @@ pub fn check_unused_dependencies(project: &Project) -> Result<Vec<UnusedDependen
         ];
         
         // Also skip if it's a proc-macro crate (they're used via attributes)
-        if skip_list.contains(&dep_name.as_str()) 
-            || dep_name.ends_with("_derive")
-            || dep_name.contains("proc-macro") {
+        if skip_list.contains(&dep.package.as_str()) 
+            || dep.package.ends_with("_derive")
+            || dep.package.contains("proc-macro") {
             continue;
         }
         
-        if !is_dependency_used(&dep_name, &project.path) {
+        if !is_dependency_used(dep, &references, &manifest) {
             unused.push(UnusedDependency {
-                name: dep_name,
-                location,
+                name: dep.name.clone(),
+                location: dep.location.clone(),
+                package: package.map(str::to_string),
+                manifest_path: package.map(|_| cargo_toml.to_path_buf()),
             });
         }
     }
     
-    Ok(unused)
+    Ok((all_deps, unused))
 }
 
-/// Remove unused dependencies from Cargo.toml
-pub fn remove_unused_dependencies(
-    project: &Project,
-    unused_deps: &[UnusedDependency],
-    dry_run: bool,
-    verbose: bool,
-) -> Result<usize> {
-    if dry_run || unused_deps.is_empty() {
-        return Ok(0);
+/// "name version" from a manifest's `[package]` table
+fn package_label(manifest: &toml::Table) -> Option<String> {
+    let package = manifest.get("package")?;
+    let name = package.get("name")?.as_str()?;
+    Some(match package.get("version").and_then(|v| v.as_str()) {
+        Some(version) => format!("{} {}", name, version),
+        None => name.to_string(),
+    })
+}
+
+/// Packages of the workspace whose root manifest is `manifest_path`, as
+/// ("name version", member manifest) pairs
+///
+/// Asks `cargo metadata --no-deps`, which needs no network; if cargo can't load the
+/// workspace, falls back to [`static_workspace_members`].
+fn workspace_members(manifest_path: &Path) -> Vec<(String, PathBuf)> {
+    match MetadataCommand::new().manifest_path(manifest_path).no_deps().exec() {
+        Ok(metadata) => metadata
+            .workspace_packages()
+            .into_iter()
+            .map(|package| {
+                (
+                    format!("{} {}", package.name, package.version),
+                    package.manifest_path.as_std_path().to_path_buf(),
+                )
+            })
+            .collect(),
+        Err(_) => static_workspace_members(manifest_path),
     }
+}
 
-    // Check if cargo-remove is available first
-    let check_output = Command::new("cargo")
-        .args(&["remove", "--help"])
-        .output();
-    
-    match check_output {
-        Ok(output) if output.status.success() => {
-            // cargo-remove is available
-        }
-        _ => {
-            return Err(anyhow::anyhow!(
-                "cargo-remove is not installed. Install it with: cargo install cargo-edit"
-            ));
+/// Workspace members found by expanding the root manifest's `[workspace] members` globs
+///
+/// Paths listed in `[workspace] exclude` are skipped; the root counts as a member when
+/// it has a `[package]` of its own.
+fn static_workspace_members(manifest_path: &Path) -> Vec<(String, PathBuf)> {
+    let root_dir = manifest_path.parent().unwrap_or(Path::new("."));
+    let read = |path: &Path| fs::read_to_string(path).ok()?.parse::<toml::Table>().ok();
+    let Some(root) = read(manifest_path) else {
+        return Vec::new();
+    };
+    let workspace = root.get("workspace");
+    let paths = |key: &str| -> Vec<String> {
+        workspace
+            .and_then(|w| w.get(key))
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+            .unwrap_or_default()
+    };
+    let excluded: Vec<PathBuf> = paths("exclude").iter().map(|dir| root_dir.join(dir)).collect();
+
+    let mut manifests = vec![manifest_path.to_path_buf()];
+    for pattern in paths("members") {
+        let Ok(dirs) = glob::glob(&root_dir.join(&pattern).to_string_lossy()) else {
+            continue;
+        };
+        for dir in dirs.flatten() {
+            let member = dir.join("Cargo.toml");
+            if member.is_file() && !excluded.contains(&dir) && !manifests.contains(&member) {
+                manifests.push(member);
+            }
         }
     }
+    manifests
+        .into_iter()
+        .filter_map(|manifest| Some((package_label(&read(&manifest)?)?, manifest)))
+        .collect()
+}
 
-    // Use cargo-remove to remove dependencies
-    let mut removed = 0;
-    let mut errors = Vec::new();
-    
-    for dep in unused_deps {
-        if verbose {
-            println!("  {} Attempting to remove dependency: {} ({})", "[DEBUG]".cyan(), dep.name, dep.location);
-        }
-        
-        // Determine which section the dependency is in
-        let is_dev = dep.location.contains("dev-dependencies");
-        let is_build = dep.location.contains("build-dependencies");
-        
-        // Build the cargo remove command with appropriate flags
-        let mut cmd_args = vec!["remove".to_string(), dep.name.clone()];
-        if is_dev {
-            cmd_args.push("--dev".to_string());
-        } else if is_build {
-            cmd_args.push("--build".to_string());
+/// Check for unused dependencies in a project
+///
+/// Needs no external tools: every Rust source file is scanned once for crate
+/// references, which are matched against the names dependencies are declared under
+/// (so renamed dependencies are found by their new name).
+///
+/// A workspace is analyzed per member, each against its own manifest and sources,
+/// and the results name the member and its manifest. An entry of the root's
+/// `[workspace.dependencies]` is reported when no member inherits it, or every
+/// member inheriting it is itself reported as not using it.
+pub fn check_unused_dependencies(project: &Project) -> Result<Vec<UnusedDependency>> {
+    let cargo_toml = project.path.join("Cargo.toml");
+    if !cargo_toml.exists() {
+        return Ok(vec![]);
+    }
+    let overrides = unused_overrides(&cargo_toml)?;
+    if !project.is_workspace {
+        let mut unused = unused_in_manifest(&cargo_toml, None, None)?.1;
+        unused.extend(overrides);
+        return Ok(unused);
+    }
+
+    let root: toml::Table = fs::read_to_string(&cargo_toml)
+        .with_context(|| format!("Failed to read Cargo.toml: {:?}", cargo_toml))?
+        .parse()
+        .with_context(|| format!("Failed to parse Cargo.toml: {:?}", cargo_toml))?;
+    let workspace_deps = root
+        .get("workspace")
+        .and_then(|w| w.get("dependencies"))
+        .and_then(|d| d.as_table());
+
+    let mut unused = Vec::new();
+    let mut inherited = HashSet::new();
+    for (package, manifest) in workspace_members(&cargo_toml) {
+        let (declared, member_unused) = unused_in_manifest(&manifest, Some(&package), workspace_deps)?;
+        inherited.extend(
+            declared
+                .into_iter()
+                .filter(|dep| dep.inherited)
+                .filter(|dep| !member_unused.iter().any(|u| u.name == dep.name && u.location == dep.location))
+                .map(|dep| dep.name),
+        );
+        unused.extend(member_unused);
+    }
+    for name in workspace_deps.into_iter().flat_map(|deps| deps.keys()) {
+        if !inherited.contains(name) {
+            unused.push(UnusedDependency {
+                name: name.clone(),
+                location: "[workspace.dependencies]".to_string(),
+                package: None,
+                manifest_path: Some(cargo_toml.clone()),
+            });
         }
-        
-        let output = Command::new("cargo")
-            .args(&cmd_args)
-            .current_dir(&project.path)
-            .output()
-            .with_context(|| format!("Failed to run `cargo remove {}`", dep.name))?;
-
-        if output.status.success() {
-            removed += 1;
-            if verbose {
-                println!("  {} Successfully removed: {} ({})", "[DEBUG]".green(), dep.name, dep.location);
-            }
+    }
+    unused.extend(overrides);
+    Ok(unused)
+}
+
+/// Normalized form of a git URL for comparing a patch with a Cargo.lock source
+fn git_url_key(url: &str) -> String {
+    let url = url.strip_prefix("git+").unwrap_or(url);
+    let url = url.split(['?', '#']).next().unwrap_or(url);
+    url.trim_end_matches('/').trim_end_matches(".git").to_lowercase()
+}
+
+/// `[patch.*]` and `[replace]` entries of a root manifest that no longer apply
+///
+/// Judged against the Cargo.lock next to the manifest; without one nothing is
+/// reported. A patch is unused when cargo lists it under `[[patch.unused]]`, when no
+/// locked package has the patched crate's name, or when every locked copy of that
+/// crate comes from a different source than the patch (so resolution is the same
+/// without it). A `[replace]` entry is unused when no locked package matches its
+/// `name` or `name:version` key.
+fn unused_overrides(manifest_path: &Path) -> Result<Vec<UnusedDependency>> {
+    let lock_path = manifest_path.with_file_name("Cargo.lock");
+    if !lock_path.exists() {
+        return Ok(vec![]);
+    }
+    let manifest: toml::Table = fs::read_to_string(manifest_path)
+        .with_context(|| format!("Failed to read Cargo.toml: {:?}", manifest_path))?
+        .parse()
+        .with_context(|| format!("Failed to parse Cargo.toml: {:?}", manifest_path))?;
+    if manifest.get("patch").is_none() && manifest.get("replace").is_none() {
+        return Ok(vec![]);
+    }
+    let lock: toml::Table = fs::read_to_string(&lock_path)
+        .with_context(|| format!("Failed to read Cargo.lock: {:?}", lock_path))?
+        .parse()
+        .with_context(|| format!("Failed to parse Cargo.lock: {:?}", lock_path))?;
+
+    fn entries(value: Option<&toml::Value>) -> Vec<&toml::Table> {
+        value
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_table()).collect())
+            .unwrap_or_default()
+    }
+    let field = |table: &toml::Table, key: &str| table.get(key).and_then(|v| v.as_str()).map(str::to_string);
+    let locked = entries(lock.get("package"));
+    let unused_patches: HashSet<String> = entries(lock.get("patch").and_then(|p| p.get("unused")))
+        .into_iter()
+        .filter_map(|patch| field(patch, "name"))
+        .collect();
+
+    let mut unused = Vec::new();
+    let mut flag = |name: &str, location: String| {
+        unused.push(UnusedDependency {
+            name: name.to_string(),
+            location,
+            package: None,
+            manifest_path: None,
+        });
+    };
+    let registries = manifest.get("patch").and_then(|p| p.as_table());
+    for (registry, patches) in registries.into_iter().flatten() {
+        let Some(patches) = patches.as_table() else {
+            continue;
+        };
+        let location = if registry.contains(['/', ':', '.']) {
+            format!("[patch.'{}']", registry)
         } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let error_msg = format!("Failed to remove {} ({}): {}", dep.name, dep.location, stderr);
-            errors.push(error_msg.clone());
-            if verbose {
-                println!("  {} Failed to remove {} ({}): {}", "[DEBUG]".red(), dep.name, dep.location, stderr);
+            format!("[patch.{}]", registry)
+        };
+        for (key, spec) in patches {
+            let crate_name = spec.get("package").and_then(|p| p.as_str()).unwrap_or(key);
+            let copies: Vec<&&toml::Table> = locked
+                .iter()
+                .filter(|package| field(package, "name").as_deref() == Some(crate_name))
+                .collect();
+            let applied = match (spec.get("path"), spec.get("git").and_then(|g| g.as_str())) {
+                (Some(_), _) => copies.iter().any(|package| package.get("source").is_none()),
+                (None, Some(git)) => copies
+                    .iter()
+                    .any(|package| field(package, "source").is_some_and(|source| git_url_key(&source) == git_url_key(git))),
+                // A patch from another registry can't be told apart from the original by source
+                (None, None) => !copies.is_empty(),
+            };
+            if unused_patches.contains(crate_name) || !applied {
+                flag(key, location.clone());
             }
         }
     }
-
-    if !errors.is_empty() && removed == 0 {
-        return Err(anyhow::anyhow!(
-            "Failed to remove dependencies:\n{}",
-            errors.join("\n")
-        ));
+    for key in manifest.get("replace").and_then(|r| r.as_table()).into_iter().flat_map(|r| r.keys()) {
+        let (name, version) = match key.split_once(':') {
+            Some((name, version)) => (name, Some(version)),
+            None => (key.as_str(), None),
+        };
+        let matched = locked.iter().any(|package| {
+            field(package, "name").as_deref() == Some(name)
+                && version.is_none_or(|version| field(package, "version").as_deref() == Some(version))
+        });
+        if !matched {
+            flag(key, "[replace]".to_string());
+        }
     }
+    Ok(unused)
+}
 
-    Ok(removed)
+/// One package's report from `cargo outdated --format json`
+#[derive(serde::Deserialize)]
+struct OutdatedReport {
+    crate_name: Option<String>,
+    #[serde(default)]
+    dependencies: Vec<OutdatedDependency>,
 }
 
-/// Clean unused dependencies for a project
-pub fn clean_dependencies(
-    project: &Project,
-    dry_run: bool,
-    remove: bool,
-    verbose: bool,
-) -> Result<DependencyCleanResult> {
-    let unused_deps = check_unused_dependencies(project)
-        .with_context(|| format!("Failed to check unused dependencies in {:?}", project.path))?;
+/// Parse the output of `cargo outdated --format json`
+///
+/// cargo-outdated prints one JSON object per package (several for `--workspace`). When
+/// there is more than one, each dependency records the package it belongs to.
+pub fn parse_outdated_output(output: &str) -> Result<Vec<OutdatedDependency>> {
+    let reports = serde_json::Deserializer::from_str(output)
+        .into_iter::<OutdatedReport>()
+        .collect::<serde_json::Result<Vec<_>>>()
+        .context("Failed to parse cargo outdated output")?;
+    let multiple = reports.len() > 1;
+    Ok(reports
+        .into_iter()
+        .flat_map(|report| {
+            let package = report.crate_name.filter(|_| multiple);
+            report.dependencies.into_iter().map(move |dep| OutdatedDependency {
+                package: dep.package.or_else(|| package.clone()),
+                ..dep
+            })
+        })
+        .collect())
+}
 
-    let removed_count = if remove && !unused_deps.is_empty() {
-        match remove_unused_dependencies(project, &unused_deps, dry_run, verbose) {
-            Ok(count) => count,
-            Err(e) => {
-                // Return error in the result instead of failing completely
-                return Ok(DependencyCleanResult {
-                    path: project.path.to_string_lossy().to_string(),
-                    success: false,
-                    unused_deps,
-                    removed_count: 0,
-                    error: Some(e.to_string()),
-                });
-            }
+/// Dependencies of the project (each member of a workspace) with newer releases
+///
+/// Runs `cargo outdated --format json --root-deps-only`, which must be installed
+/// (`cargo install cargo-outdated`) and needs access to the registry index.
+pub fn find_outdated_dependencies(project: &Project, options: &DepsOptions) -> Result<Vec<OutdatedDependency>> {
+    if !project.path.join("Cargo.toml").exists() {
+        return Ok(vec![]);
+    }
+    let mut args: Vec<String> = ["outdated", "--format", "json", "--root-deps-only"].map(str::to_string).to_vec();
+    if project.is_workspace {
+        args.push("--workspace".to_string());
+    }
+    let output = options
+        .runner
+        .run("cargo", &args, &project.path)
+        .with_context(|| format!("Failed to run cargo outdated in {:?}", project.path))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("no such command") {
+            anyhow::bail!("cargo outdated is not installed; install it with `cargo install cargo-outdated`");
         }
-    } else {
-        0
-    };
+        anyhow::bail!("cargo outdated failed: {}", stderr.lines().last().unwrap_or_default());
+    }
+    parse_outdated_output(&String::from_utf8_lossy(&output.stdout))
+}
 
-    Ok(DependencyCleanResult {
-        path: project.path.to_string_lossy().to_string(),
-        success: true,
-        unused_deps,
-        removed_count,
-        error: None,
-    })
+/// Report written by `cargo udeps --output json`
+#[derive(serde::Deserialize)]
+struct UdepsReport {
+    /// Keyed by package ID
+    #[serde(default)]
+    unused_deps: BTreeMap<String, UdepsPackage>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Unused dependencies of one package, by dependency kind
+#[derive(serde::Deserialize)]
+struct UdepsPackage {
+    manifest_path: Option<PathBuf>,
+    #[serde(default)]
+    normal: Vec<String>,
+    #[serde(default)]
+    development: Vec<String>,
+    #[serde(default)]
+    build: Vec<String>,
+}
 
-    #[test]
-    fn test_normalize_crate_name() {
-        assert_eq!(normalize_crate_name("my-crate"), "my_crate");
-        assert_eq!(normalize_crate_name("my_crate"), "my_crate");
-        assert_eq!(normalize_crate_name("serde-json"), "serde_json");
+/// "name version" for a Cargo package ID
+///
+/// Handles both the legacy `name version (source)` form and the URL form
+/// (`path+file:///ws/app#0.1.0`, `registry+https://...#name@0.1.0`) used since Cargo 1.77.
+fn package_id_name(id: &str) -> String {
+    if id.contains(' ') {
+        return id.split(" (").next().unwrap_or(id).to_string();
     }
+    let Some((url, fragment)) = id.split_once('#') else {
+        return id.to_string();
+    };
+    match fragment.split_once('@') {
+        Some((name, version)) => format!("{} {}", name, version),
+        None => {
+            let name = url.split('?').next().unwrap_or(url).trim_end_matches('/').rsplit('/').next().unwrap_or(url);
+            format!("{} {}", name, fragment)
+        }
+    }
+}
 
-    #[test]
-    fn test_extract_dependencies() {
-        let temp_dir = tempfile::TempDir::new().unwrap();
-        let cargo_toml = temp_dir.path().join("Cargo.toml");
+/// Parse `cargo udeps --output json` into unused dependencies
+///
+/// Each package's `normal`, `development` and `build` lists map to `[dependencies]`,
+/// `[dev-dependencies]` and `[build-dependencies]`, tagged with the package they
+/// belong to and its manifest. Sorted by package, then kind.
+pub fn parse_udeps_output(json: &str) -> Result<Vec<UnusedDependency>> {
+    let report: UdepsReport = serde_json::from_str(json).context("Failed to parse cargo-udeps JSON output")?;
+    let mut unused = Vec::new();
+    for (id, package) in report.unused_deps {
+        let package_name = package_id_name(&id);
+        for (names, location) in [
+            (package.normal, "[dependencies]"),
+            (package.development, "[dev-dependencies]"),
+            (package.build, "[build-dependencies]"),
+        ] {
+            unused.extend(names.into_iter().map(|name| UnusedDependency {
+                name,
+                location: location.to_string(),
+                package: Some(package_name.clone()),
+                manifest_path: package.manifest_path.clone(),
+            }));
+        }
+    }
+    Ok(unused)
+}
+
+/// Where the table named by a dependency location lives in the manifest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TableParent<'a> {
+    Root,
+    /// `[target.<key>.*]`
+    Target(&'a str),
+    /// `[workspace.dependencies]`
+    Workspace,
+    /// `[patch.<registry>]`
+    Patch,
+}
+
+/// Parent and table name for a dependency location
+///
+/// `[dev-dependencies]` is `(Root, "dev-dependencies")`,
+/// `[target.'cfg(unix)'.dependencies]` is `(Target("cfg(unix)"), "dependencies")`,
+/// `[workspace.dependencies]` is `(Workspace, "dependencies")` and `[patch.crates-io]`
+/// is `(Patch, "crates-io")`.
+fn location_table(location: &str) -> (TableParent<'_>, &str) {
+    let location = location.trim_start_matches('[').trim_end_matches(']');
+    if let Some(table) = location.strip_prefix("workspace.") {
+        return (TableParent::Workspace, table);
+    }
+    if let Some(registry) = location.strip_prefix("patch.") {
+        return (TableParent::Patch, registry.trim_matches(|c| c == '\'' || c == '"'));
+    }
+    match location.strip_prefix("target.").and_then(|rest| rest.rsplit_once('.')) {
+        Some((target, table)) => (TableParent::Target(target.trim_matches(|c| c == '\'' || c == '"')), table),
+        None => (TableParent::Root, location),
+    }
+}
+
+/// Key of the entry for `name` in a dependency table
+///
+/// That is `name` itself, or the key of an entry renaming that crate with
+/// `package = "name"` (ignoring `-`/`_` differences), as tools reporting by crate
+/// name do.
+fn dependency_key(table: &toml_edit::Item, name: &str) -> Option<String> {
+    let table = table.as_table_like()?;
+    if table.contains_key(name) {
+        return Some(name.to_string());
+    }
+    let wanted = normalize_crate_name(name);
+    table
+        .iter()
+        .find(|(_, spec)| {
+            spec.get("package")
+                .and_then(|p| p.as_str())
+                .is_some_and(|package| normalize_crate_name(package) == wanted)
+        })
+        .map(|(key, _)| key.to_string())
+}
+
+/// Compute the manifest with `deps` removed, without touching the file
+///
+/// Dependencies are matched by manifest key or, for renamed entries, by the crate
+/// they rename. They are removed from the table named by their location, including
+/// platform-specific `[target.<cfg>.*]` tables. When a top-level location is stale,
+/// e.g. from a tool that only reports the dependency kind, the first platform-specific
+/// table of the same kind declaring the dependency is used instead. Removing a normal dependency also drops `[features]` entries that enable it
+/// (`dep:name`, `name`, `name/feature` and `name?/feature`).
+pub fn plan_dependency_removal(manifest_path: &Path, deps: &[UnusedDependency]) -> Result<(ManifestEdit, usize)> {
+    let original = fs::read_to_string(manifest_path)
+        .with_context(|| format!("Failed to read Cargo.toml: {:?}", manifest_path))?;
+    let mut doc: toml_edit::DocumentMut = original
+        .parse()
+        .with_context(|| format!("Failed to parse Cargo.toml: {:?}", manifest_path))?;
+
+    let mut removed = 0;
+    for dep in deps {
+        let (parent, table) = location_table(&dep.location);
+        let mut target = match parent {
+            TableParent::Target(target) => Some(target.to_string()),
+            _ => None,
+        };
+        let key_in = |item: Option<&toml_edit::Item>| item.and_then(|t| dependency_key(t, &dep.name));
+        if parent == TableParent::Root && key_in(doc.get(table)).is_none() {
+            target = doc
+                .get("target")
+                .and_then(|t| t.as_table_like())
+                .and_then(|targets| {
+                    targets
+                        .iter()
+                        .find(|(_, tables)| key_in(tables.get(table)).is_some())
+                        .map(|(key, _)| key.to_string())
+                });
+        }
+        let parent_item = match (parent, target) {
+            (TableParent::Workspace, _) => doc.get_mut("workspace"),
+            (TableParent::Patch, _) => doc.get_mut("patch"),
+            (_, Some(target)) => doc.get_mut("target").and_then(|t| t.get_mut(&target)),
+            (_, None) => Some(doc.as_item_mut()),
+        };
+        let Some(deps_item) = parent_item.and_then(|p| p.get_mut(table)) else {
+            continue;
+        };
+        let Some(key) = dependency_key(deps_item, &dep.name) else {
+            continue;
+        };
+        if deps_item.as_table_like_mut().and_then(|t| t.remove(&key)).is_none() {
+            continue;
+        }
+        removed += 1;
+
+        if table == "dependencies" && !matches!(parent, TableParent::Workspace | TableParent::Patch) {
+            if let Some(features) = doc.get_mut("features").and_then(|t| t.as_table_like_mut()) {
+                for (_, enables) in features.iter_mut() {
+                    if let Some(enables) = enables.as_array_mut() {
+                        let before = enables.len();
+                        let single_line = !enables.to_string().contains('\n');
+                        enables.retain(|value| {
+                            let Some(value) = value.as_str() else {
+                                return true;
+                            };
+                            let target = value.strip_prefix("dep:").unwrap_or(value);
+                            let target = target.split('/').next().unwrap_or(target).trim_end_matches('?');
+                            target != key
+                        });
+                        // Re-normalize spacing left behind by a removed leading entry
+                        if enables.len() != before && single_line {
+                            enables.fmt();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let edit = ManifestEdit {
+        path: manifest_path.to_path_buf(),
+        original,
+        edited: doc.to_string(),
+    };
+    Ok((edit, removed))
+}
+
+/// Plan removing `deps` from whichever manifests declare them, e.g. workspace members'
+fn plan_project_removals(project: &Project, deps: &[UnusedDependency]) -> Result<(Vec<ManifestEdit>, usize)> {
+    let mut by_manifest: BTreeMap<PathBuf, Vec<UnusedDependency>> = BTreeMap::new();
+    for dep in deps {
+        by_manifest.entry(dep.manifest(&project.path)).or_default().push(dep.clone());
+    }
+    let mut edits = Vec::with_capacity(by_manifest.len());
+    let mut removed = 0;
+    for (manifest_path, deps) in by_manifest {
+        let (edit, count) = plan_dependency_removal(&manifest_path, &deps)?;
+        edits.push(edit);
+        removed += count;
+    }
+    Ok((edits, removed))
+}
+
+/// Unified diff of removing `deps` from the project's manifests, or `None` when nothing would change
+pub fn preview_dependency_removal(project: &Project, deps: &[UnusedDependency]) -> Result<Option<String>> {
+    let (edits, _) = plan_project_removals(project, deps)?;
+    let diff: String = edits.iter().map(ManifestEdit::unified_diff).collect();
+    Ok((!diff.is_empty()).then_some(diff))
+}
+
+/// Remove unused dependencies from Cargo.toml
+///
+/// All removals, across every manifest involved, are computed in memory and applied
+/// as one [`ManifestTransaction`], so an interrupted run never leaves a half-edited
+/// manifest.
+pub fn remove_unused_dependencies(
+    project: &Project,
+    unused_deps: &[UnusedDependency],
+    dry_run: bool,
+    verbose: bool,
+) -> Result<usize> {
+    if dry_run || unused_deps.is_empty() {
+        return Ok(0);
+    }
+
+    let (edits, removed) = plan_project_removals(project, unused_deps)?;
+    if verbose {
+        for dep in unused_deps {
+            println!("  {} Removing dependency: {} ({})", "[DEBUG]".cyan(), dep.name, dep.origin());
+        }
+    }
+
+    let mut transaction = ManifestTransaction::new();
+    for edit in edits {
+        transaction.push(edit);
+    }
+    transaction
+        .commit()
+        .with_context(|| format!("Failed to remove dependencies from {:?}", project.path))?;
+    Ok(removed)
+}
+
+/// Whether `cargo check --all-targets` succeeds for the project (every member of a workspace)
+fn cargo_check_passes(project: &Project, options: &DepsOptions) -> Result<bool> {
+    let mut args = vec!["check".to_string(), "--quiet".to_string(), "--all-targets".to_string()];
+    if project.is_workspace {
+        args.push("--workspace".to_string());
+    }
+    let output = options
+        .runner
+        .run("cargo", &args, &project.path)
+        .with_context(|| format!("Failed to run cargo check in {:?}", project.path))?;
+    if !output.status.success() && options.verbose {
+        println!(
+            "  {} cargo check failed: {}",
+            "[DEBUG]".cyan(),
+            String::from_utf8_lossy(&output.stderr).lines().next().unwrap_or_default()
+        );
+    }
+    Ok(output.status.success())
+}
+
+/// Number of packages in the project's Cargo.lock, or `None` without a lockfile
+fn locked_package_count(project_path: &Path) -> Result<Option<usize>> {
+    let lock_path = project_path.join("Cargo.lock");
+    if !lock_path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&lock_path)
+        .with_context(|| format!("Failed to read Cargo.lock: {:?}", lock_path))?;
+    let lock: toml::Value = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse Cargo.lock: {:?}", lock_path))?;
+    Ok(Some(lock.get("package").and_then(|v| v.as_array()).map_or(0, Vec::len)))
+}
+
+/// Run `cargo update --workspace` so Cargo.lock forgets packages the edited manifests no
+/// longer pull in, returning how many entries disappeared
+///
+/// Only the workspace's own packages are updated; every other locked version stays as it
+/// was. Projects without a Cargo.lock are left alone.
+fn prune_lockfile(project: &Project, options: &DepsOptions) -> Result<usize> {
+    let Some(before) = locked_package_count(&project.path)? else {
+        return Ok(0);
+    };
+    let args = ["update", "--workspace"].map(str::to_string);
+    let output = options
+        .runner
+        .run("cargo", &args, &project.path)
+        .with_context(|| format!("Failed to run cargo update in {:?}", project.path))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "cargo update failed: {}",
+            String::from_utf8_lossy(&output.stderr).lines().last().unwrap_or_default()
+        );
+    }
+    let after = locked_package_count(&project.path)?.unwrap_or(before);
+    Ok(before.saturating_sub(after))
+}
+
+/// Remove unused dependencies, confirming each batch with `cargo check` when `options.verify` is set
+///
+/// A batch that breaks the build is undone by writing back the manifest (and
+/// `Cargo.lock`, which the check may have rewritten) as it was before the batch.
+/// Returns the number of dependencies that stayed removed and those restored.
+fn remove_with_verification(
+    project: &Project,
+    unused_deps: &[UnusedDependency],
+    options: &DepsOptions,
+) -> Result<(usize, Vec<UnusedDependency>)> {
+    let Some(mode) = options.verify.filter(|_| !options.dry_run) else {
+        let removed = remove_unused_dependencies(project, unused_deps, options.dry_run, options.verbose)?;
+        return Ok((removed, vec![]));
+    };
+    let batches: Vec<&[UnusedDependency]> = match mode {
+        VerifyMode::Batch => vec![unused_deps],
+        VerifyMode::Each => unused_deps.chunks(1).collect(),
+    };
+
+    let mut removed = 0;
+    let mut rolled_back = Vec::new();
+    for batch in batches {
+        let mut snapshot_paths = vec![project.path.join("Cargo.toml"), project.path.join("Cargo.lock")];
+        for dep in batch {
+            let manifest = dep.manifest(&project.path);
+            if !snapshot_paths.contains(&manifest) {
+                snapshot_paths.push(manifest);
+            }
+        }
+        let snapshot = snapshot_files(&snapshot_paths);
+        let count = remove_unused_dependencies(project, batch, false, options.verbose)?;
+        if count == 0 {
+            continue;
+        }
+        // A check that cannot run at all counts as a failure, so nothing unverified stays removed
+        let check = cargo_check_passes(project, options);
+        if matches!(check, Ok(true)) {
+            removed += count;
+            continue;
+        }
+
+        restore_files(snapshot)
+            .with_context(|| format!("Failed to restore {:?} after cargo check failed", project.path))?;
+        if options.verbose {
+            for dep in batch {
+                println!("  {} Restored dependency: {} ({})", "[DEBUG]".cyan(), dep.name, dep.origin());
+            }
+        }
+        rolled_back.extend(batch.iter().cloned());
+        check?;
+    }
+    Ok((removed, rolled_back))
+}
+
+/// Contents of the files among `paths` that exist, to put back with [`restore_files`]
+fn snapshot_files(paths: &[PathBuf]) -> Vec<(PathBuf, String)> {
+    paths
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok().map(|content| (path.clone(), content)))
+        .collect()
+}
+
+/// Write back files saved by [`snapshot_files`] in one transaction
+fn restore_files(snapshot: Vec<(PathBuf, String)>) -> Result<()> {
+    let mut transaction = ManifestTransaction::new();
+    for (path, original) in snapshot {
+        transaction.push(ManifestEdit {
+            original: fs::read_to_string(&path).unwrap_or_default(),
+            path,
+            edited: original,
+        });
+    }
+    transaction.commit()
+}
+
+/// Feature names tested with `feature = "..."` (in `cfg`, `cfg_attr` or `cfg!`) in the project's sources
+fn cfg_feature_references(project_path: &Path) -> HashSet<String> {
+    let mut features = HashSet::new();
+    for_each_source(project_path, |content| {
+        for (start, _) in content.match_indices("feature") {
+            let rest = content[start + "feature".len()..].trim_start();
+            let Some(rest) = rest.strip_prefix('=').map(str::trim_start) else {
+                continue;
+            };
+            if let Some((name, _)) = rest.strip_prefix('"').and_then(|quoted| quoted.split_once('"')) {
+                features.insert(name.to_string());
+            }
+        }
+    });
+    features
+}
+
+/// Every dependency table of a manifest with its location: the top-level ones and
+/// those under `[target.'X']`
+fn dependency_tables(manifest: &toml::Table) -> Vec<(String, &toml::Table)> {
+    let kinds = ["dependencies", "dev-dependencies", "build-dependencies"];
+    let mut tables: Vec<(String, &toml::Table)> = kinds
+        .iter()
+        .filter_map(|kind| Some((format!("[{}]", kind), manifest.get(*kind)?.as_table()?)))
+        .collect();
+    for (target, tables_of) in manifest.get("target").and_then(|t| t.as_table()).into_iter().flatten() {
+        tables.extend(kinds.iter().filter_map(|kind| {
+            Some((format!("[target.'{}'.{}]", target, kind), tables_of.get(*kind)?.as_table()?))
+        }));
+    }
+    tables
+}
+
+/// Features the given manifests request of each crate, keyed by normalized crate name
+///
+/// Counts `features = [...]` on dependency specs (including those inherited from
+/// `[workspace.dependencies]`) and `dep/feature` entries in `[features]`.
+fn requested_features(
+    manifests: &[&toml::Table],
+    workspace_deps: Option<&toml::Table>,
+) -> BTreeMap<String, HashSet<String>> {
+    let string_array = |value: Option<&toml::Value>| -> Vec<String> {
+        value
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+            .unwrap_or_default()
+    };
+    let mut requested: BTreeMap<String, HashSet<String>> = BTreeMap::new();
+    for manifest in manifests {
+        let mut crates = BTreeMap::new();
+        for (_, table) in dependency_tables(manifest) {
+            for (key, spec) in table {
+                let inherited = spec.get("workspace").and_then(|w| w.as_bool()).unwrap_or(false);
+                let workspace_spec = workspace_deps.and_then(|deps| deps.get(key)).filter(|_| inherited);
+                let source = workspace_spec.unwrap_or(spec);
+                let package = source.get("package").and_then(|p| p.as_str()).unwrap_or(key);
+                let name = normalize_crate_name(package);
+                let entry = requested.entry(name.clone()).or_default();
+                entry.extend(string_array(spec.get("features")));
+                entry.extend(string_array(workspace_spec.and_then(|w| w.get("features"))));
+                crates.insert(key.clone(), name);
+            }
+        }
+        let features = manifest.get("features").and_then(|f| f.as_table());
+        for enabled in features.into_iter().flat_map(|f| f.values()).flat_map(|v| string_array(Some(v))) {
+            if let Some((dep, feature)) = enabled.split_once('/') {
+                let dep = dep.trim_end_matches('?');
+                let name = crates.get(dep).cloned().unwrap_or_else(|| normalize_crate_name(dep));
+                requested.entry(name).or_default().insert(feature.to_string());
+            }
+        }
+    }
+    requested
+}
+
+/// Features declared in a package's `[features]` that look unnecessary
+///
+/// A feature is reported when neither `default` nor `requested` (what workspace members
+/// ask of this package) enables it, directly or through other features, or when it is
+/// enabled but enables nothing and no `cfg(feature)` in the sources tests it.
+fn unused_declared_features(
+    manifest: &toml::Table,
+    package_dir: &Path,
+    requested: &HashSet<String>,
+    package: Option<&str>,
+) -> Vec<FeatureFinding> {
+    let Some(features) = manifest.get("features").and_then(|f| f.as_table()) else {
+        return Vec::new();
+    };
+    let enables = |feature: &str| -> Vec<String> {
+        features
+            .get(feature)
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+            .unwrap_or_default()
+    };
+
+    let mut enabled: HashSet<String> = requested.iter().cloned().collect();
+    enabled.insert("default".to_string());
+    let mut queue: Vec<String> = enabled.iter().cloned().collect();
+    while let Some(feature) = queue.pop() {
+        // Plain names enable other features of this package; "dep:x" and "x/y" do not
+        for item in enables(&feature) {
+            if !item.contains(['/', ':']) && features.contains_key(&item) && enabled.insert(item.clone()) {
+                queue.push(item);
+            }
+        }
+    }
+
+    let tested = cfg_feature_references(package_dir);
+    let mut findings = Vec::new();
+    for name in features.keys().filter(|name| *name != "default") {
+        let inert = enables(name).is_empty() && !tested.contains(name);
+        let suggestion = match (enabled.contains(name), inert) {
+            (true, false) => continue,
+            (true, true) => "enables nothing and no cfg(feature) tests it: remove it",
+            (false, true) => "never enabled, enables nothing and no cfg(feature) tests it: remove it",
+            (false, false) => "not enabled by default or by any workspace member: remove it unless other crates use it",
+        };
+        findings.push(FeatureFinding {
+            package: package.map(str::to_string),
+            dependency: None,
+            feature: name.clone(),
+            suggestion: suggestion.to_string(),
+        });
+    }
+    findings
+}
+
+/// Features enabled on dependencies (`features = [...]`) that `cargo check` passes without
+///
+/// Each feature is dropped from the manifest in turn, checked, and the manifest and
+/// `Cargo.lock` put back, so this runs `cargo check` once per feature.
+fn unnecessary_dependency_features(
+    project: &Project,
+    manifest_path: &Path,
+    package: Option<&str>,
+    options: &DepsOptions,
+) -> Result<Vec<FeatureFinding>> {
+    let original = fs::read_to_string(manifest_path)
+        .with_context(|| format!("Failed to read Cargo.toml: {:?}", manifest_path))?;
+    let doc: toml_edit::DocumentMut = original
+        .parse()
+        .with_context(|| format!("Failed to parse Cargo.toml: {:?}", manifest_path))?;
+
+    let mut candidates = Vec::new();
+    for kind in ["dependencies", "dev-dependencies", "build-dependencies"] {
+        let Some(table) = doc.get(kind).and_then(|t| t.as_table_like()) else {
+            continue;
+        };
+        for (key, spec) in table.iter() {
+            let features = spec.get("features").and_then(|f| f.as_array());
+            for feature in features.into_iter().flat_map(|f| f.iter()).filter_map(|f| f.as_str()) {
+                candidates.push((kind, key.to_string(), feature.to_string()));
+            }
+        }
+    }
+
+    let snapshot_paths = [manifest_path.to_path_buf(), project.path.join("Cargo.lock")];
+    let mut findings = Vec::new();
+    for (kind, key, feature) in candidates {
+        let mut doc = doc.clone();
+        if let Some(features) = doc
+            .get_mut(kind)
+            .and_then(|t| t.get_mut(&key))
+            .and_then(|spec| spec.get_mut("features"))
+            .and_then(|f| f.as_array_mut())
+        {
+            features.retain(|f| f.as_str() != Some(feature.as_str()));
+        }
+        let snapshot = snapshot_files(&snapshot_paths);
+        let mut transaction = ManifestTransaction::new();
+        transaction.push(ManifestEdit {
+            path: manifest_path.to_path_buf(),
+            original: original.clone(),
+            edited: doc.to_string(),
+        });
+        transaction.commit()?;
+        let check = cargo_check_passes(project, options);
+        restore_files(snapshot)
+            .with_context(|| format!("Failed to restore {:?} after checking feature {}", manifest_path, feature))?;
+        if check? {
+            findings.push(FeatureFinding {
+                package: package.map(str::to_string),
+                dependency: Some(key),
+                feature,
+                suggestion: "cargo check passes without it: drop it from the dependency's features".to_string(),
+            });
+        }
+    }
+    Ok(findings)
+}
+
+/// Suggest features to trim in a project, or in each member of a workspace
+///
+/// Reports `[features]` entries nothing enables (see [`unused_declared_features`]).
+/// With `options.verify` set, outside dry runs, also tries dropping each feature
+/// enabled on a dependency and reports those `cargo check` passes without.
+pub fn find_feature_findings(project: &Project, options: &DepsOptions) -> Result<Vec<FeatureFinding>> {
+    let cargo_toml = project.path.join("Cargo.toml");
+    if !cargo_toml.exists() {
+        return Ok(vec![]);
+    }
+    let members: Vec<(Option<String>, PathBuf)> = if project.is_workspace {
+        workspace_members(&cargo_toml).into_iter().map(|(label, path)| (Some(label), path)).collect()
+    } else {
+        vec![(None, cargo_toml.clone())]
+    };
+    let read = |path: &Path| -> Result<toml::Table> {
+        fs::read_to_string(path)
+            .with_context(|| format!("Failed to read Cargo.toml: {:?}", path))?
+            .parse()
+            .with_context(|| format!("Failed to parse Cargo.toml: {:?}", path))
+    };
+    let root = read(&cargo_toml)?;
+    let workspace_deps = root
+        .get("workspace")
+        .and_then(|w| w.get("dependencies"))
+        .and_then(|d| d.as_table());
+    let manifests = members
+        .iter()
+        .map(|(_, path)| read(path))
+        .collect::<Result<Vec<_>>>()?;
+    let requested = requested_features(&manifests.iter().collect::<Vec<_>>(), workspace_deps);
+
+    let mut findings = Vec::new();
+    for ((package, path), manifest) in members.iter().zip(&manifests) {
+        let name = manifest
+            .get("package")
+            .and_then(|p| p.get("name"))
+            .and_then(|n| n.as_str())
+            .map(normalize_crate_name);
+        let wanted = name.and_then(|name| requested.get(&name)).cloned().unwrap_or_default();
+        let dir = path.parent().unwrap_or(Path::new("."));
+        findings.extend(unused_declared_features(manifest, dir, &wanted, package.as_deref()));
+        if options.verify.is_some() && !options.dry_run {
+            findings.extend(unnecessary_dependency_features(project, path, package.as_deref(), options)?);
+        }
+    }
+    Ok(findings)
+}
+
+/// Normal dependencies only referenced from test code, examples or benches
+///
+/// Dependencies in `[dependencies]` (or `[target.'X'.dependencies]`) that no code
+/// compiled into the package references, but tests do, are suggested for the matching
+/// dev-dependencies table. Optional dependencies and those `[features]` refer to stay
+/// put, since dev-dependencies can't back features. A workspace is analyzed per member.
+pub fn find_dev_only_dependencies(project: &Project) -> Result<Vec<MisplacedDependency>> {
+    let cargo_toml = project.path.join("Cargo.toml");
+    if !cargo_toml.exists() {
+        return Ok(vec![]);
+    }
+    let root: toml::Table = fs::read_to_string(&cargo_toml)
+        .with_context(|| format!("Failed to read Cargo.toml: {:?}", cargo_toml))?
+        .parse()
+        .with_context(|| format!("Failed to parse Cargo.toml: {:?}", cargo_toml))?;
+    let workspace_deps = root
+        .get("workspace")
+        .and_then(|w| w.get("dependencies"))
+        .and_then(|d| d.as_table());
+    let members: Vec<(Option<String>, PathBuf)> = if project.is_workspace {
+        workspace_members(&cargo_toml).into_iter().map(|(label, path)| (Some(label), path)).collect()
+    } else {
+        vec![(None, cargo_toml.clone())]
+    };
+
+    let mut misplaced = Vec::new();
+    for (package, manifest_path) in members {
+        let content = fs::read_to_string(&manifest_path)
+            .with_context(|| format!("Failed to read Cargo.toml: {:?}", manifest_path))?;
+        let manifest: toml::Table = content
+            .parse()
+            .with_context(|| format!("Failed to parse Cargo.toml: {:?}", manifest_path))?;
+        let tables = dependency_tables(&manifest);
+        let (production, dev) = split_crate_references(manifest_path.parent().unwrap_or(Path::new(".")));
+        for dep in extract_dependencies(&manifest_path, workspace_deps)? {
+            let Some(prefix) = dep.location.strip_suffix("dependencies]").filter(|p| !p.ends_with('-')) else {
+                continue;
+            };
+            let optional = tables
+                .iter()
+                .find(|(location, _)| *location == dep.location)
+                .and_then(|(_, table)| table.get(&dep.name)?.get("optional")?.as_bool())
+                .unwrap_or(false);
+            if optional || is_dependency_used(&dep, &production, &content) || !is_dependency_used(&dep, &dev, "") {
+                continue;
+            }
+            misplaced.push(MisplacedDependency {
+                suggested_location: format!("{}dev-dependencies]", prefix),
+                name: dep.name,
+                location: dep.location,
+                package: package.clone(),
+                manifest_path: manifest_path.clone(),
+            });
+        }
+    }
+    Ok(misplaced)
+}
+
+/// Compute the manifest with each dependency moved to its suggested dev-dependencies
+/// table, creating the table when needed
+///
+/// The entry keeps its formatting and comments; if the dev-dependencies table already
+/// declares the dependency, the normal entry is just dropped.
+fn plan_dev_dependency_moves(manifest_path: &Path, moves: &[MisplacedDependency]) -> Result<(ManifestEdit, usize)> {
+    let original = fs::read_to_string(manifest_path)
+        .with_context(|| format!("Failed to read Cargo.toml: {:?}", manifest_path))?;
+    let mut doc: toml_edit::DocumentMut = original
+        .parse()
+        .with_context(|| format!("Failed to parse Cargo.toml: {:?}", manifest_path))?;
+
+    let mut moved = 0;
+    for dep in moves {
+        let (parent, table) = location_table(&dep.location);
+        let parent_table = match parent {
+            TableParent::Root => Some(doc.as_table_mut()),
+            TableParent::Target(target) => doc
+                .get_mut("target")
+                .and_then(|t| t.get_mut(target))
+                .and_then(|t| t.as_table_mut()),
+            _ => None,
+        };
+        let Some(parent_table) = parent_table else {
+            continue;
+        };
+        let Some((key, item)) = parent_table
+            .get_mut(table)
+            .and_then(|t| t.as_table_mut())
+            .and_then(|t| t.remove_entry(&dep.name))
+        else {
+            continue;
+        };
+        let dev_table = parent_table
+            .entry("dev-dependencies")
+            .or_insert_with(toml_edit::table)
+            .as_table_mut()
+            .with_context(|| format!("dev-dependencies is not a table in {:?}", manifest_path))?;
+        if !dev_table.contains_key(&dep.name) {
+            dev_table.insert_formatted(&key, item);
+        }
+        moved += 1;
+    }
+
+    let edit = ManifestEdit {
+        path: manifest_path.to_path_buf(),
+        original,
+        edited: doc.to_string(),
+    };
+    Ok((edit, moved))
+}
+
+/// Plan moving dev-only dependencies, one edit per manifest
+fn plan_dev_moves(misplaced: &[MisplacedDependency]) -> Result<(Vec<ManifestEdit>, usize)> {
+    let mut by_manifest: BTreeMap<&Path, Vec<MisplacedDependency>> = BTreeMap::new();
+    for dep in misplaced {
+        by_manifest.entry(&dep.manifest_path).or_default().push(dep.clone());
+    }
+    let mut edits = Vec::with_capacity(by_manifest.len());
+    let mut moved = 0;
+    for (manifest_path, deps) in by_manifest {
+        let (edit, count) = plan_dev_dependency_moves(manifest_path, &deps)?;
+        edits.push(edit);
+        moved += count;
+    }
+    Ok((edits, moved))
+}
+
+/// Fields of a member's dependency entry that move to `[workspace.dependencies]`
+const HOISTED_FIELDS: [&str; 4] = ["version", "registry", "default-features", "package"];
+
+/// Fields a member keeps next to `workspace = true`
+const MEMBER_FIELDS: [&str; 2] = ["features", "optional"];
+
+/// Fields of a dependency spec as sorted (key, TOML value) pairs
+type SpecFields = Vec<(String, String)>;
+
+/// The fields of a dependency spec that would move to `[workspace.dependencies]`,
+/// rendered as TOML and sorted, or `None` when the entry can't be inherited
+///
+/// Only registry dependencies with a version qualify; an entry with fields beyond
+/// [`HOISTED_FIELDS`] and [`MEMBER_FIELDS`] (`path`, `git`, `workspace`, ...) is left alone.
+fn hoistable_fields(spec: &toml::Value) -> Option<SpecFields> {
+    if let Some(version) = spec.as_str() {
+        return Some(vec![("version".to_string(), toml::Value::from(version).to_string())]);
+    }
+    let mut fields = Vec::new();
+    for (key, value) in spec.as_table()? {
+        let key = if key == "default_features" { "default-features" } else { key.as_str() };
+        if MEMBER_FIELDS.contains(&key) || (key == "default-features" && value.as_bool() == Some(true)) {
+            continue;
+        }
+        if !HOISTED_FIELDS.contains(&key) {
+            return None;
+        }
+        fields.push((key.to_string(), value.to_string()));
+    }
+    fields.sort();
+    fields.iter().any(|(key, _)| key == "version").then_some(fields)
+}
+
+/// Dependencies two or more workspace members declare with the same key and spec,
+/// and member entries matching a `[workspace.dependencies]` entry they could inherit
+///
+/// Only the version, registry, `default-features` and `package` fields have to agree;
+/// `features` and `optional` stay with each member. When members disagree on a
+/// dependency's spec, the spec declared by the most members is suggested.
+pub fn find_hoistable_dependencies(project: &Project) -> Result<Vec<HoistableDependency>> {
+    let cargo_toml = project.path.join("Cargo.toml");
+    if !project.is_workspace || !cargo_toml.exists() {
+        return Ok(vec![]);
+    }
+    let read = |path: &Path| -> Result<toml::Table> {
+        fs::read_to_string(path)
+            .with_context(|| format!("Failed to read Cargo.toml: {:?}", path))?
+            .parse()
+            .with_context(|| format!("Failed to parse Cargo.toml: {:?}", path))
+    };
+    let root = read(&cargo_toml)?;
+    let workspace_deps = root.get("workspace").and_then(|w| w.get("dependencies")).and_then(|d| d.as_table());
+
+    let mut groups: BTreeMap<(String, SpecFields), Vec<HoistedEntry>> = BTreeMap::new();
+    for (package, manifest_path) in workspace_members(&cargo_toml) {
+        let manifest = read(&manifest_path)?;
+        for (location, table) in dependency_tables(&manifest) {
+            for (key, spec) in table {
+                if let Some(fields) = hoistable_fields(spec) {
+                    groups.entry((key.clone(), fields)).or_default().push(HoistedEntry {
+                        package: package.clone(),
+                        location: location.clone(),
+                        manifest_path: manifest_path.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    let mut candidates: Vec<_> = groups
+        .into_iter()
+        .filter_map(|((name, fields), members)| {
+            let declared = workspace_deps.and_then(|deps| deps.get(&name));
+            let inherited_elsewhere = match declared {
+                Some(spec) if hoistable_fields(spec).as_ref() == Some(&fields) => true,
+                Some(_) => return None,
+                None => false,
+            };
+            let mut packages: Vec<&str> = members.iter().map(|m| m.package.as_str()).collect();
+            packages.dedup();
+            if !inherited_elsewhere && packages.len() < 2 {
+                return None;
+            }
+            let workspace_entry = match fields.as_slice() {
+                [(_, version)] => version.clone(),
+                _ => {
+                    let fields: Vec<String> = fields.iter().map(|(key, value)| format!("{} = {}", key, value)).collect();
+                    format!("{{ {} }}", fields.join(", "))
+                }
+            };
+            Some((packages.len(), HoistableDependency { name, workspace_entry, inherited_elsewhere, members }))
+        })
+        .collect();
+    // One spec per name: the one already in [workspace.dependencies], else the most common
+    candidates.sort_by(|(a_count, a), (b_count, b)| {
+        (a.name.as_str(), !a.inherited_elsewhere, std::cmp::Reverse(*a_count))
+            .cmp(&(b.name.as_str(), !b.inherited_elsewhere, std::cmp::Reverse(*b_count)))
+    });
+    candidates.dedup_by(|(_, later), (_, first)| later.name == first.name);
+    Ok(candidates.into_iter().map(|(_, dep)| dep).collect())
+}
+
+/// Plan hoisting: add entries to the root's `[workspace.dependencies]` and rewrite
+/// member entries to `workspace = true`, one edit per manifest
+///
+/// Rewritten entries keep their `features` and `optional` fields and their position.
+fn plan_hoisting(project: &Project, hoistable: &[HoistableDependency]) -> Result<(Vec<ManifestEdit>, usize)> {
+    let mut docs: BTreeMap<PathBuf, (String, toml_edit::DocumentMut)> = BTreeMap::new();
+    let mut load = |path: &Path| -> Result<PathBuf> {
+        let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if !docs.contains_key(&key) {
+            let original = fs::read_to_string(path).with_context(|| format!("Failed to read Cargo.toml: {:?}", path))?;
+            let doc = original.parse().with_context(|| format!("Failed to parse Cargo.toml: {:?}", path))?;
+            docs.insert(key.clone(), (original, doc));
+        }
+        Ok(key)
+    };
+
+    let root = load(&project.path.join("Cargo.toml"))?;
+    let mut member_keys = Vec::new();
+    for dep in hoistable {
+        for entry in &dep.members {
+            member_keys.push((load(&entry.manifest_path)?, entry, dep));
+        }
+    }
+
+    let root_doc = &mut docs.get_mut(&root).expect("root manifest loaded").1;
+    for dep in hoistable.iter().filter(|dep| !dep.inherited_elsewhere) {
+        let value: toml_edit::Value = dep
+            .workspace_entry
+            .parse()
+            .with_context(|| format!("Invalid workspace entry for {}: {}", dep.name, dep.workspace_entry))?;
+        let workspace = root_doc
+            .entry("workspace")
+            .or_insert_with(toml_edit::table)
+            .as_table_mut()
+            .context("[workspace] is not a table")?;
+        let table = workspace
+            .entry("dependencies")
+            .or_insert_with(toml_edit::table)
+            .as_table_like_mut()
+            .context("[workspace.dependencies] is not a table")?;
+        if !table.contains_key(&dep.name) {
+            table.insert(&dep.name, toml_edit::value(value));
+        }
+    }
+
+    let mut rewritten = 0;
+    for (key, entry, dep) in member_keys {
+        let doc = &mut docs.get_mut(&key).expect("member manifest loaded").1;
+        let (parent, table) = location_table(&entry.location);
+        let parent_item = match parent {
+            TableParent::Target(target) => doc.get_mut("target").and_then(|t| t.get_mut(target)),
+            _ => Some(doc.as_item_mut()),
+        };
+        let Some(deps_table) = parent_item.and_then(|p| p.get_mut(table)).and_then(|t| t.as_table_like_mut()) else {
+            continue;
+        };
+        let Some(old) = deps_table.get_mut(&dep.name) else {
+            continue;
+        };
+        let mut inherited = toml_edit::InlineTable::new();
+        inherited.insert("workspace", true.into());
+        for field in MEMBER_FIELDS {
+            if let Some(value) = old.get(field).and_then(|v| v.as_value()) {
+                inherited.insert(field, value.clone());
+            }
+        }
+        let mut value = toml_edit::Value::InlineTable(inherited);
+        if let Some(previous) = old.as_value() {
+            *value.decor_mut() = previous.decor().clone();
+        }
+        *old = toml_edit::Item::Value(value);
+        rewritten += 1;
+    }
+
+    let edits = docs
+        .into_iter()
+        .map(|(path, (original, doc))| ManifestEdit { path, original, edited: doc.to_string() })
+        .filter(|edit| edit.original != edit.edited)
+        .collect();
+    Ok((edits, rewritten))
+}
+
+/// Path dependencies whose directory, or the Cargo.toml in it, is missing
+///
+/// Checks every dependency table of the project's manifest (each member's, in a
+/// workspace) and the root's `[workspace.dependencies]`. Paths are resolved against
+/// the directory of the manifest declaring them.
+pub fn find_stale_path_dependencies(project: &Project) -> Result<Vec<StalePathDependency>> {
+    let cargo_toml = project.path.join("Cargo.toml");
+    if !cargo_toml.exists() {
+        return Ok(vec![]);
+    }
+    let read = |path: &Path| -> Result<toml::Table> {
+        fs::read_to_string(path)
+            .with_context(|| format!("Failed to read Cargo.toml: {:?}", path))?
+            .parse()
+            .with_context(|| format!("Failed to parse Cargo.toml: {:?}", path))
+    };
+    let members: Vec<(Option<String>, PathBuf)> = if project.is_workspace {
+        workspace_members(&cargo_toml).into_iter().map(|(label, path)| (Some(label), path)).collect()
+    } else {
+        vec![(None, cargo_toml.clone())]
+    };
+
+    let mut stale = Vec::new();
+    let mut check = |table: &toml::Table, location: &str, package: Option<&String>, manifest_path: &Path| {
+        let dir = manifest_path.parent().unwrap_or(Path::new("."));
+        for (name, spec) in table {
+            let Some(path) = spec.get("path").and_then(|p| p.as_str()) else {
+                continue;
+            };
+            if !dir.join(path).join("Cargo.toml").is_file() {
+                stale.push(StalePathDependency {
+                    name: name.clone(),
+                    location: location.to_string(),
+                    path: path.to_string(),
+                    package: package.cloned(),
+                    manifest_path: manifest_path.to_path_buf(),
+                });
+            }
+        }
+    };
+    for (package, manifest_path) in &members {
+        let manifest = read(manifest_path)?;
+        for (location, table) in dependency_tables(&manifest) {
+            check(table, &location, package.as_ref(), manifest_path);
+        }
+    }
+    if project.is_workspace {
+        let root = read(&cargo_toml)?;
+        let workspace_deps = root.get("workspace").and_then(|w| w.get("dependencies")).and_then(|d| d.as_table());
+        if let Some(table) = workspace_deps {
+            check(table, "[workspace.dependencies]", None, &cargo_toml);
+        }
+    }
+    Ok(stale)
+}
+
+/// Compute the manifest with each dependency's entry turned into comments
+///
+/// Inline entries (`name = { ... }`, possibly spanning several lines) and dotted keys
+/// (`name.path = ...`) are commented line by line; a `[dependencies.name]` table is
+/// commented from its header up to the next table. `[features]` entries are left alone.
+fn plan_dependency_comment_out(manifest_path: &Path, deps: &[UnusedDependency]) -> Result<(ManifestEdit, usize)> {
+    let original = fs::read_to_string(manifest_path)
+        .with_context(|| format!("Failed to read Cargo.toml: {:?}", manifest_path))?;
+    let doc = toml_edit::ImDocument::parse(original.as_str())
+        .with_context(|| format!("Failed to parse Cargo.toml: {:?}", manifest_path))?;
+    let lines: Vec<&str> = original.split_inclusive('\n').collect();
+    let line_of = |offset: usize| original[..offset].matches('\n').count();
+
+    let mut commented = BTreeSet::new();
+    let mut count = 0;
+    for dep in deps {
+        let (parent, table) = location_table(&dep.location);
+        let parent_item = match parent {
+            TableParent::Root => Some(doc.as_item()),
+            TableParent::Target(target) => doc.get("target").and_then(|t| t.get(target)),
+            TableParent::Workspace => doc.get("workspace"),
+            TableParent::Patch => doc.get("patch"),
+        };
+        let Some(deps_table) = parent_item.and_then(|p| p.get(table)).and_then(|t| t.as_table_like()) else {
+            continue;
+        };
+        let (Some(key), Some(item)) = (deps_table.key(&dep.name), deps_table.get(&dep.name)) else {
+            continue;
+        };
+        let spans: Vec<(usize, usize)> = match item.as_table() {
+            Some(entry) if !entry.is_dotted() => {
+                let Some(header) = entry.span() else {
+                    continue;
+                };
+                let first = line_of(header.start);
+                let end = (first + 1..lines.len())
+                    .find(|&i| lines[i].trim_start().starts_with('['))
+                    .unwrap_or(lines.len());
+                vec![(first, end - 1)]
+            }
+            Some(dotted) => dotted
+                .iter()
+                .filter_map(|(field, value)| {
+                    let start = dotted.key(field)?.span()?.start;
+                    Some((start, value.span()?.end))
+                })
+                .chain(key.span().map(|span| (span.start, span.end)))
+                .map(|(start, end)| (line_of(start), line_of(end.saturating_sub(1))))
+                .collect(),
+            None => match (key.span(), item.span()) {
+                (Some(key), Some(value)) => vec![(line_of(key.start), line_of(value.end.saturating_sub(1)))],
+                _ => continue,
+            },
+        };
+        for (first, last) in spans {
+            commented.extend(first..=last);
+        }
+        count += 1;
+    }
+
+    let edited = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            if commented.contains(&i) && !line.trim().is_empty() {
+                format!("# {}", line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    Ok((ManifestEdit { path: manifest_path.to_path_buf(), original, edited }, count))
+}
+
+/// Plan removing or commenting out stale path dependencies, one edit per manifest
+fn plan_stale_path_fixes(stale: &[StalePathDependency], action: StalePathAction) -> Result<(Vec<ManifestEdit>, usize)> {
+    let mut by_manifest: BTreeMap<&Path, Vec<UnusedDependency>> = BTreeMap::new();
+    for dep in stale {
+        by_manifest.entry(&dep.manifest_path).or_default().push(dep.as_dependency());
+    }
+    let mut edits = Vec::with_capacity(by_manifest.len());
+    let mut fixed = 0;
+    for (manifest_path, deps) in by_manifest {
+        let (edit, count) = match action {
+            StalePathAction::Report => continue,
+            StalePathAction::Remove => plan_dependency_removal(manifest_path, &deps)?,
+            StalePathAction::Comment => plan_dependency_comment_out(manifest_path, &deps)?,
+        };
+        edits.push(edit);
+        fixed += count;
+    }
+    Ok((edits, fixed))
+}
+
+/// Shortest chain from each root package down to `package`, found by walking the
+/// reverse dependency graph breadth-first
+fn dependency_chains(package: &str, reverse: &BTreeMap<String, Vec<String>>, roots: &HashSet<String>) -> Vec<Vec<String>> {
+    let mut next_hop: BTreeMap<&str, &str> = BTreeMap::new();
+    let mut queue = std::collections::VecDeque::from([package]);
+    let mut chains = Vec::new();
+    while let Some(current) = queue.pop_front() {
+        if roots.contains(current) && current != package {
+            let mut chain = vec![current.to_string()];
+            let mut node = current;
+            while let Some(&child) = next_hop.get(node) {
+                chain.push(child.to_string());
+                node = child;
+            }
+            chains.push(chain);
+        }
+        for parent in reverse.get(current).into_iter().flatten() {
+            if parent != package && !next_hop.contains_key(parent.as_str()) {
+                next_hop.insert(parent, current);
+                queue.push_back(parent);
+            }
+        }
+    }
+    chains
+}
+
+/// Find crates locked at more than one version, with the packages depending on each version
+/// and the dependency chains from the project's own packages that pull it in
+///
+/// Reads the project's Cargo.lock; projects without a lockfile report no duplicates.
+pub fn find_duplicate_dependencies(project_path: &Path) -> Result<Vec<DuplicateDependency>> {
+    let lock_path = project_path.join("Cargo.lock");
+    if !lock_path.exists() {
+        return Ok(vec![]);
+    }
+    let content = fs::read_to_string(&lock_path)
+        .with_context(|| format!("Failed to read Cargo.lock: {:?}", lock_path))?;
+    let lock: toml::Value = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse Cargo.lock: {:?}", lock_path))?;
+
+    let packages = lock
+        .get("package")
+        .and_then(|v| v.as_array())
+        .map(|a| a.as_slice())
+        .unwrap_or_default();
+
+    // "name version" -> packages depending on it, and the local packages (no source)
+    let mut reverse: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut roots = HashSet::new();
+    // name -> version -> dependents
+    let mut versions: BTreeMap<String, BTreeMap<String, Vec<String>>> = BTreeMap::new();
+    for package in packages {
+        let (Some(name), Some(version)) = (
+            package.get("name").and_then(|v| v.as_str()),
+            package.get("version").and_then(|v| v.as_str()),
+        ) else {
+            continue;
+        };
+        versions
+            .entry(name.to_string())
+            .or_default()
+            .entry(version.to_string())
+            .or_default();
+        if package.get("source").is_none() {
+            roots.insert(format!("{} {}", name, version));
+        }
+    }
+
+    for package in packages {
+        let (Some(name), Some(version)) = (
+            package.get("name").and_then(|v| v.as_str()),
+            package.get("version").and_then(|v| v.as_str()),
+        ) else {
+            continue;
+        };
+        let dependencies = package
+            .get("dependencies")
+            .and_then(|v| v.as_array())
+            .map(|a| a.as_slice())
+            .unwrap_or_default();
+        for dependency in dependencies.iter().filter_map(|d| d.as_str()) {
+            // Entries are "name", "name version" or "name version (source)";
+            // the version is only spelled out when the name alone is ambiguous
+            let mut parts = dependency.split_whitespace();
+            let Some(dep_name) = parts.next() else {
+                continue;
+            };
+            let Some(dep_versions) = versions.get_mut(dep_name) else {
+                continue;
+            };
+            let dep_version = match parts.next() {
+                Some(v) => v.to_string(),
+                None if dep_versions.len() == 1 => dep_versions.keys().next().unwrap().clone(),
+                None => continue,
+            };
+            if let Some(dependents) = dep_versions.get_mut(&dep_version) {
+                dependents.push(format!("{} {}", name, version));
+                reverse
+                    .entry(format!("{} {}", dep_name, dep_version))
+                    .or_default()
+                    .push(format!("{} {}", name, version));
+            }
+        }
+    }
+
+    Ok(versions
+        .into_iter()
+        .filter(|(_, by_version)| by_version.len() > 1)
+        .map(|(name, by_version)| DuplicateDependency {
+            versions: by_version
+                .into_iter()
+                .map(|(version, mut dependents)| {
+                    dependents.sort();
+                    let chains = dependency_chains(&format!("{} {}", name, version), &reverse, &roots);
+                    DuplicateVersion { version, dependents, chains }
+                })
+                .collect(),
+            name,
+        })
+        .collect())
+}
+
+/// Remove previously identified dependencies without analyzing the project again
+///
+/// Used to apply the removals recorded in a cleanup plan exactly as reviewed.
+pub fn remove_planned_dependencies(
+    project: &Project,
+    planned: &[UnusedDependency],
+    options: &DepsOptions,
+) -> Result<DependencyCleanResult> {
+    let (removed_count, rolled_back, mut error) = match remove_with_verification(project, planned, options) {
+        Ok((count, rolled_back)) => (count, rolled_back, None),
+        Err(e) => (0, vec![], Some(e.to_string())),
+    };
+    let mut lock_entries_pruned = 0;
+    if options.prune_lock && !options.dry_run && removed_count > 0 {
+        match prune_lockfile(project, options) {
+            Ok(pruned) => lock_entries_pruned = pruned,
+            Err(e) => error = Some(e.to_string()),
+        }
+    }
+    let diff = if options.dry_run {
+        preview_dependency_removal(project, planned).ok().flatten()
+    } else {
+        None
+    };
+    Ok(DependencyCleanResult {
+        path: project.path.to_string_lossy().to_string(),
+        success: error.is_none(),
+        unused_deps: planned.to_vec(),
+        removed_count,
+        rolled_back,
+        diff,
+        duplicates: vec![],
+        features: vec![],
+        stale_paths: vec![],
+        stale_paths_fixed: 0,
+        dev_only: vec![],
+        moved_to_dev: 0,
+        outdated: vec![],
+        hoistable: vec![],
+        hoisted: 0,
+        lock_entries_pruned,
+        error,
+    })
+}
+
+/// Analyze (and optionally clean) dependencies for a project
+pub fn clean_dependencies(project: &Project, options: &DepsOptions) -> Result<DependencyCleanResult> {
+    // Stale path dependencies are fixed first, so later analysis sees the repaired manifests
+    let stale_paths = match options.stale_paths {
+        Some(_) => find_stale_path_dependencies(project)
+            .with_context(|| format!("Failed to check path dependencies in {:?}", project.path))?,
+        None => vec![],
+    };
+    let stale_action = options.stale_paths.filter(|_| !stale_paths.is_empty());
+    let (stale_edits, stale_fixable) = match stale_action {
+        Some(action) => plan_stale_path_fixes(&stale_paths, action)?,
+        None => (vec![], 0),
+    };
+    let mut stale_diff = None;
+    let mut stale_paths_fixed = 0;
+    if options.dry_run {
+        let diff: String = stale_edits.iter().map(ManifestEdit::unified_diff).collect();
+        stale_diff = (!diff.is_empty()).then_some(diff);
+    } else if !stale_edits.is_empty() {
+        let mut transaction = ManifestTransaction::new();
+        for edit in stale_edits {
+            transaction.push(edit);
+        }
+        transaction
+            .commit()
+            .with_context(|| format!("Failed to fix stale path dependencies in {:?}", project.path))?;
+        stale_paths_fixed = stale_fixable;
+    }
+
+    let unused_deps = if options.check_unused {
+        check_unused_dependencies(project)
+            .with_context(|| format!("Failed to check unused dependencies in {:?}", project.path))?
+            .into_iter()
+            .filter(|dep| options.includes(dep))
+            // Entries being fixed as stale paths are not removed a second time
+            .filter(|dep| {
+                !matches!(stale_action, Some(StalePathAction::Remove | StalePathAction::Comment))
+                    || !stale_paths.iter().any(|stale| stale.matches(dep, &project.path))
+            })
+            .collect()
+    } else {
+        vec![]
+    };
+
+    let duplicates = if options.duplicates {
+        find_duplicate_dependencies(&project.path)
+            .with_context(|| format!("Failed to check duplicate dependencies in {:?}", project.path))?
+    } else {
+        vec![]
+    };
+
+    let features = if options.features {
+        find_feature_findings(project, options)
+            .with_context(|| format!("Failed to check features in {:?}", project.path))?
+    } else {
+        vec![]
+    };
+
+    let (removed_count, rolled_back) = if options.remove && !unused_deps.is_empty() {
+        match remove_with_verification(project, &unused_deps, options) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                // Return error in the result instead of failing completely
+                return Ok(DependencyCleanResult {
+                    path: project.path.to_string_lossy().to_string(),
+                    success: false,
+                    unused_deps,
+                    removed_count: 0,
+                    rolled_back: vec![],
+                    diff: stale_diff,
+                    duplicates,
+                    features,
+                    stale_paths,
+                    stale_paths_fixed,
+                    dev_only: vec![],
+                    moved_to_dev: 0,
+                    outdated: vec![],
+                    hoistable: vec![],
+                    hoisted: 0,
+                    lock_entries_pruned: 0,
+                    error: Some(e.to_string()),
+                });
+            }
+        }
+    } else {
+        (0, vec![])
+    };
+    let removal_diff = if options.remove && options.dry_run && !unused_deps.is_empty() {
+        preview_dependency_removal(project, &unused_deps).ok().flatten()
+    } else {
+        None
+    };
+
+    let dev_only = if options.dev_deps {
+        find_dev_only_dependencies(project)
+            .with_context(|| format!("Failed to check dev-only dependencies in {:?}", project.path))?
+    } else {
+        vec![]
+    };
+    let mut moved_to_dev = 0;
+    let mut move_diff = None;
+    if options.apply && !dev_only.is_empty() {
+        let (edits, moved) = plan_dev_moves(&dev_only)?;
+        if options.dry_run {
+            let diff: String = edits.iter().map(ManifestEdit::unified_diff).collect();
+            move_diff = (!diff.is_empty()).then_some(diff);
+        } else {
+            let mut transaction = ManifestTransaction::new();
+            for edit in edits {
+                transaction.push(edit);
+            }
+            transaction
+                .commit()
+                .with_context(|| format!("Failed to move dev-only dependencies in {:?}", project.path))?;
+            moved_to_dev = moved;
+        }
+    }
+
+    let hoistable = if options.hoist {
+        find_hoistable_dependencies(project)
+            .with_context(|| format!("Failed to check workspace dependencies in {:?}", project.path))?
+    } else {
+        vec![]
+    };
+    let mut hoisted = 0;
+    let mut hoist_diff = None;
+    if options.apply && !hoistable.is_empty() {
+        let (edits, rewritten) = plan_hoisting(project, &hoistable)?;
+        if options.dry_run {
+            let diff: String = edits.iter().map(ManifestEdit::unified_diff).collect();
+            hoist_diff = (!diff.is_empty()).then_some(diff);
+        } else {
+            let mut transaction = ManifestTransaction::new();
+            for edit in edits {
+                transaction.push(edit);
+            }
+            transaction
+                .commit()
+                .with_context(|| format!("Failed to hoist workspace dependencies in {:?}", project.path))?;
+            hoisted = rewritten;
+        }
+    }
+    let diff: String = [stale_diff, removal_diff, move_diff, hoist_diff].into_iter().flatten().collect();
+
+    let edited = removed_count + stale_paths_fixed + moved_to_dev + hoisted > 0;
+    let lock_entries_pruned = if options.prune_lock && !options.dry_run && edited {
+        prune_lockfile(project, options)?
+    } else {
+        0
+    };
+
+    let outdated = if options.outdated {
+        // The errors already say what to do, e.g. install cargo-outdated
+        find_outdated_dependencies(project, options)?
+    } else {
+        vec![]
+    };
+    let diff = (!diff.is_empty()).then_some(diff);
+
+    Ok(DependencyCleanResult {
+        path: project.path.to_string_lossy().to_string(),
+        success: true,
+        unused_deps,
+        removed_count,
+        rolled_back,
+        diff,
+        duplicates,
+        features,
+        stale_paths,
+        stale_paths_fixed,
+        dev_only,
+        moved_to_dev,
+        outdated,
+        hoistable,
+        hoisted,
+        lock_entries_pruned,
+        error: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_crate_name() {
+        assert_eq!(normalize_crate_name("my-crate"), "my_crate");
+        assert_eq!(normalize_crate_name("my_crate"), "my_crate");
+        assert_eq!(normalize_crate_name("serde-json"), "serde_json");
+    }
+
+    #[test]
+    fn test_extract_dependencies() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cargo_toml = temp_dir.path().join("Cargo.toml");
         fs::write(
             &cargo_toml,
             r#"
@@ -348,13 +2436,893 @@ tokio = "1.0"
 
 [dev-dependencies]
 tempfile = "3.0"
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+rustix1 = { package = "rustix", version = "1" }
 "#,
         ).unwrap();
 
-        let deps = extract_dependencies(&cargo_toml).unwrap();
+        let deps = extract_dependencies(&cargo_toml, None).unwrap();
         assert!(deps.len() >= 2);
-        let dep_names: Vec<String> = deps.iter().map(|(n, _)| n.clone()).collect();
+        let dep_names: Vec<String> = deps.iter().map(|d| d.name.clone()).collect();
         assert!(dep_names.contains(&"serde".to_string()));
         assert!(dep_names.contains(&"tokio".to_string()));
+        assert!(deps.contains(&DeclaredDependency {
+            name: "rustix1".to_string(),
+            package: "rustix".to_string(),
+            location: "[target.'cfg(unix)'.dependencies]".to_string(),
+            inherited: false,
+        }));
+    }
+
+    #[test]
+    fn test_collect_crate_references() {
+        let source = r#"
+use anyhow::Result;
+use rayon;
+extern crate  libc;
+pub use self::inner::Thing;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let x = lazy_static!(1u8) != other;
+    let _ = walkdir::WalkDir::new(".");
+    plain_call(x);
+    notused_ident.field;
+    Ok(())
+}
+"#;
+        let mut references = HashSet::new();
+        collect_crate_references(source, &mut references);
+        for name in ["anyhow", "rayon", "libc", "self", "inner", "tokio", "lazy_static", "walkdir", "WalkDir"] {
+            assert!(references.contains(name), "{} not found", name);
+        }
+        for name in ["Result", "main", "other", "plain_call", "notused_ident", "u8", "x"] {
+            assert!(!references.contains(name), "{} wrongly found", name);
+        }
+    }
+
+    #[test]
+    fn test_check_unused_dependencies() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("tests")).unwrap();
+        fs::write(
+            root.join("Cargo.toml"),
+            r#"[package]
+name = "app"
+
+[dependencies]
+log = "0.4"
+rand = "0.8"
+regex-lite = "0.1"
+json = { package = "serde_json", version = "1" }
+fast_hash = { package = "ahash", version = "0.8", optional = true }
+old_hash = { package = "fnv", version = "1" }
+
+[dev-dependencies]
+tempfile = "3"
+
+[features]
+fast = ["dep:fast_hash"]
+"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/main.rs"),
+            "fn main() {\n    log::info!(\"rand is unused\");\n    regex_lite::Regex::new(\"x\");\n}\n",
+        )
+        .unwrap();
+        fs::write(root.join("tests/it.rs"), "use tempfile::TempDir;\n").unwrap();
+
+        let project = Project {
+            path: root.to_path_buf(),
+            is_workspace: false,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: Default::default(),
+        };
+        let unused: Vec<String> = check_unused_dependencies(&project).unwrap().into_iter().map(|d| d.name).collect();
+        // `json` renames serde_json, which is on the skip list; `fast_hash` is enabled by a feature
+        assert_eq!(unused, vec!["old_hash", "rand"]);
+    }
+
+    /// A workspace with members `app` and `cli`, sharing `[workspace.dependencies]`
+    fn workspace_fixture(root: &Path) -> Project {
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/old\"]\n\n\
+             [workspace.dependencies]\nlog = \"0.4\"\nrand = \"0.8\"\nfnv = \"1\"\n",
+        );
+        write(
+            "crates/app/Cargo.toml",
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n\
+             [dependencies]\nlog = { workspace = true }\nrand.workspace = true\nitoa = \"1\"\n",
+        );
+        write("crates/app/src/main.rs", "fn main() {\n    log::info!(\"hi\");\n}\n");
+        write(
+            "crates/cli/Cargo.toml",
+            "[package]\nname = \"cli\"\nversion = \"0.2.0\"\n\n[dependencies]\nlog.workspace = true\n",
+        );
+        write("crates/cli/src/main.rs", "fn main() {}\n");
+        write("crates/old/Cargo.toml", "[package]\nname = \"old\"\nversion = \"0.0.1\"\n");
+        Project {
+            path: root.to_path_buf(),
+            is_workspace: true,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_static_workspace_members() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        workspace_fixture(temp_dir.path());
+        let members: Vec<(String, PathBuf)> = static_workspace_members(&temp_dir.path().join("Cargo.toml"));
+        assert_eq!(
+            members,
+            vec![
+                ("app 0.1.0".to_string(), temp_dir.path().join("crates/app/Cargo.toml")),
+                ("cli 0.2.0".to_string(), temp_dir.path().join("crates/cli/Cargo.toml")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_unused_dependencies_workspace() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let project = workspace_fixture(&root);
+
+        let unused = check_unused_dependencies(&project).unwrap();
+        let found: Vec<(Option<&str>, &str, &str)> = unused
+            .iter()
+            .map(|dep| (dep.package.as_deref(), dep.location.as_str(), dep.name.as_str()))
+            .collect();
+        // cli inherits log without using it, but app still needs the workspace entry
+        assert_eq!(
+            found,
+            vec![
+                (Some("app 0.1.0"), "[dependencies]", "itoa"),
+                (Some("app 0.1.0"), "[dependencies]", "rand"),
+                (Some("cli 0.2.0"), "[dependencies]", "log"),
+                (None, "[workspace.dependencies]", "fnv"),
+                (None, "[workspace.dependencies]", "rand"),
+            ]
+        );
+        assert_eq!(unused[0].manifest_path, Some(root.join("crates/app/Cargo.toml")));
+
+        let removed = remove_unused_dependencies(&project, &unused, false, false).unwrap();
+        assert_eq!(removed, 5);
+        let app = fs::read_to_string(root.join("crates/app/Cargo.toml")).unwrap();
+        assert!(app.contains("log = { workspace = true }") && !app.contains("rand") && !app.contains("itoa"));
+        assert!(!fs::read_to_string(root.join("crates/cli/Cargo.toml")).unwrap().contains("log"));
+        let workspace = fs::read_to_string(root.join("Cargo.toml")).unwrap();
+        assert!(workspace.contains("log = \"0.4\"") && !workspace.contains("rand") && !workspace.contains("fnv"));
+    }
+
+    #[test]
+    fn test_parse_udeps_output() {
+        let json = r#"{
+  "success": false,
+  "unused_deps": {
+    "app 0.1.0 (path+file:///ws/app)": {
+      "manifest_path": "/ws/app/Cargo.toml",
+      "normal": ["rand"],
+      "development": ["tempfile"],
+      "build": ["cc"]
+    },
+    "path+file:///ws/cli#0.2.0": {
+      "manifest_path": "/ws/cli/Cargo.toml",
+      "normal": ["log"],
+      "development": [],
+      "build": []
+    },
+    "path+file:///ws/lib#core@0.3.0": {
+      "manifest_path": "/ws/lib/Cargo.toml",
+      "normal": [],
+      "development": ["proptest"],
+      "build": []
+    }
+  },
+  "note": "Note: They might be false-positive."
+}"#;
+        let found: Vec<(String, String, String)> = parse_udeps_output(json)
+            .unwrap()
+            .into_iter()
+            .map(|dep| (dep.package.unwrap(), dep.location, dep.name))
+            .collect();
+        let expected = [
+            ("app 0.1.0", "[dependencies]", "rand"),
+            ("app 0.1.0", "[dev-dependencies]", "tempfile"),
+            ("app 0.1.0", "[build-dependencies]", "cc"),
+            ("cli 0.2.0", "[dependencies]", "log"),
+            ("core 0.3.0", "[dev-dependencies]", "proptest"),
+        ]
+        .map(|(p, l, n)| (p.to_string(), l.to_string(), n.to_string()));
+        assert_eq!(found, expected);
+
+        assert!(parse_udeps_output(r#"{"success": true, "unused_deps": {}}"#).unwrap().is_empty());
+        assert!(parse_udeps_output("error: no such command: `udeps`").is_err());
+    }
+
+    #[test]
+    fn test_plan_dependency_removal() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cargo_toml = temp_dir.path().join("Cargo.toml");
+        let original = r#"[package]
+name = "app"
+
+[dependencies]
+serde = "1.0" # keep formatting
+rand = { version = "0.8", optional = true }
+
+[dev-dependencies]
+tempfile = "3"
+
+[features]
+default = ["rand", "serde/derive"]
+fast = ["dep:rand", "rand?/std"]
+"#;
+        fs::write(&cargo_toml, original).unwrap();
+        let dep = |name: &str, location: &str| UnusedDependency {
+            name: name.to_string(),
+            location: location.to_string(),
+            package: None,
+            manifest_path: None,
+        };
+
+        let (edit, removed) = plan_dependency_removal(
+            &cargo_toml,
+            &[dep("rand", "[dependencies]"), dep("tempfile", "[dev-dependencies]"), dep("missing", "[dependencies]")],
+        )
+        .unwrap();
+        assert_eq!(removed, 2);
+        assert_eq!(edit.original, original);
+        assert!(edit.edited.contains("serde = \"1.0\" # keep formatting"));
+        assert!(!edit.edited.contains("rand"));
+        assert!(!edit.edited.contains("tempfile"));
+        assert!(edit.edited.contains(r#"default = ["serde/derive"]"#));
+        // Planning alone leaves the manifest untouched
+        assert_eq!(fs::read_to_string(&cargo_toml).unwrap(), original);
+    }
+
+    #[test]
+    fn test_plan_dependency_removal_renamed() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cargo_toml = temp_dir.path().join("Cargo.toml");
+        let original = r#"[package]
+name = "app"
+
+[dependencies]
+json = { package = "serde_json", version = "1", optional = true }
+serde_json = { version = "1" }
+hash = { package = "fxhash", version = "0.2" }
+
+[features]
+pretty = ["json/preserve_order"]
+"#;
+        fs::write(&cargo_toml, original).unwrap();
+        let dep = |name: &str| UnusedDependency {
+            name: name.to_string(),
+            location: "[dependencies]".to_string(),
+            package: None,
+            manifest_path: None,
+        };
+
+        // By key, and by the renamed crate's name as cargo-udeps style tools report it
+        let (edit, removed) = plan_dependency_removal(&cargo_toml, &[dep("json"), dep("fxhash")]).unwrap();
+        assert_eq!(removed, 2);
+        assert!(!edit.edited.contains("\njson = {"));
+        assert!(edit.edited.contains("serde_json = { version = \"1\" }"));
+        assert!(!edit.edited.contains("\nhash = {"));
+        assert!(edit.edited.contains("pretty = []"));
+    }
+
+    #[test]
+    fn test_plan_dependency_removal_target_tables() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cargo_toml = temp_dir.path().join("Cargo.toml");
+        let original = r#"[package]
+name = "app"
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2" # unix only
+nix = "0.27"
+
+[target.x86_64-pc-windows-msvc.build-dependencies]
+winres = "0.1"
+
+[target.'cfg(windows)'.dependencies]
+winapi = "0.3"
+
+[features]
+signals = ["nix/signal"]
+"#;
+        fs::write(&cargo_toml, original).unwrap();
+        let dep = |name: &str, location: &str| UnusedDependency {
+            name: name.to_string(),
+            location: location.to_string(),
+            package: None,
+            manifest_path: None,
+        };
+
+        let (edit, removed) = plan_dependency_removal(
+            &cargo_toml,
+            &[
+                dep("nix", "[target.'cfg(unix)'.dependencies]"),
+                dep("winres", "[target.'x86_64-pc-windows-msvc'.build-dependencies]"),
+                dep("libc", "[target.'cfg(windows)'.dependencies]"),
+                // Reported by kind only; resolved to the platform table declaring it
+                dep("winapi", "[dependencies]"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(removed, 3);
+        assert!(!edit.edited.contains("winapi"));
+        assert!(edit.edited.contains("libc = \"0.2\" # unix only"));
+        assert!(!edit.edited.contains("nix ="));
+        assert!(!edit.edited.contains("winres"));
+        assert!(edit.edited.contains("signals = []"));
+    }
+
+    /// Fails `cargo check` whenever the manifest no longer declares `needed`
+    #[cfg(unix)]
+    #[derive(Debug)]
+    struct CheckRunner {
+        needed: &'static str,
+        checks: std::sync::Mutex<usize>,
+    }
+
+    #[cfg(unix)]
+    impl CommandRunner for CheckRunner {
+        fn run(&self, _program: &str, _args: &[String], current_dir: &Path) -> std::io::Result<std::process::Output> {
+            use std::os::unix::process::ExitStatusExt;
+            *self.checks.lock().unwrap() += 1;
+            let manifest = fs::read_to_string(current_dir.join("Cargo.toml"))?;
+            let code = if manifest.contains(self.needed) { 0 } else { 101 };
+            Ok(std::process::Output {
+                status: std::process::ExitStatus::from_raw(code << 8),
+                stdout: Vec::new(),
+                stderr: b"error[E0432]: unresolved import".to_vec(),
+            })
+        }
+    }
+
+    /// Stands in for `cargo update` by rewriting Cargo.lock without `dropped`
+    #[cfg(unix)]
+    #[derive(Debug)]
+    struct UpdateRunner {
+        dropped: &'static str,
+    }
+
+    #[cfg(unix)]
+    impl CommandRunner for UpdateRunner {
+        fn run(&self, _program: &str, args: &[String], current_dir: &Path) -> std::io::Result<std::process::Output> {
+            use std::os::unix::process::ExitStatusExt;
+            assert_eq!(args, ["update", "--workspace"]);
+            let lock = fs::read_to_string(current_dir.join("Cargo.lock"))?;
+            let kept: Vec<&str> = lock.split("\n\n").filter(|entry| !entry.contains(self.dropped)).collect();
+            fs::write(current_dir.join("Cargo.lock"), kept.join("\n\n"))?;
+            Ok(std::process::Output {
+                status: std::process::ExitStatus::from_raw(0),
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+            })
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remove_and_prune_lockfile() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("src")).unwrap();
+        fs::write(temp_dir.path().join("src/lib.rs"), "pub use log::info;\n").unwrap();
+        fs::write(
+            temp_dir.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\nrand = \"0.8\"\nlog = \"0.4\"\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("Cargo.lock"),
+            "version = 3\n\n[[package]]\nname = \"app\"\nversion = \"0.1.0\"\n\n[[package]]\nname = \"log\"\nversion = \"0.4.20\"\n\n[[package]]\nname = \"rand\"\nversion = \"0.8.5\"\n\n[[package]]\nname = \"rand_core\"\nversion = \"0.6.4\"\n",
+        )
+        .unwrap();
+        let project = Project {
+            path: temp_dir.path().to_path_buf(),
+            is_workspace: false,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: Default::default(),
+        };
+        let options = DepsOptions {
+            check_unused: true,
+            remove: true,
+            prune_lock: true,
+            runner: Arc::new(UpdateRunner { dropped: "\"rand" }),
+            ..Default::default()
+        };
+
+        let result = clean_dependencies(&project, &options).unwrap();
+        assert_eq!((result.removed_count, result.lock_entries_pruned), (1, 2));
+        assert_eq!(locked_package_count(temp_dir.path()).unwrap(), Some(2));
+
+        // Nothing left to remove, so cargo update isn't run again
+        let result = clean_dependencies(&project, &options).unwrap();
+        assert_eq!((result.removed_count, result.lock_entries_pruned), (0, 0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remove_with_verification() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let manifest = "[package]\nname = \"app\"\n\n[dependencies]\nrand = \"0.8\"\nlog = \"0.4\"\n";
+        let lock = "version = 3\n";
+        let project = Project {
+            path: temp_dir.path().to_path_buf(),
+            is_workspace: false,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: Default::default(),
+        };
+        let unused: Vec<UnusedDependency> = ["rand", "log"]
+            .map(|name| UnusedDependency {
+                name: name.to_string(),
+                location: "[dependencies]".to_string(),
+                package: None,
+                manifest_path: None,
+            })
+            .to_vec();
+
+        for (mode, expected_removed, expected_checks) in [(VerifyMode::Each, 1, 2), (VerifyMode::Batch, 0, 1)] {
+            fs::write(temp_dir.path().join("Cargo.toml"), manifest).unwrap();
+            fs::write(temp_dir.path().join("Cargo.lock"), lock).unwrap();
+            let runner = Arc::new(CheckRunner { needed: "log", checks: std::sync::Mutex::new(0) });
+            let options = DepsOptions { verify: Some(mode), runner: runner.clone(), ..Default::default() };
+
+            let (removed, rolled_back) = remove_with_verification(&project, &unused, &options).unwrap();
+            assert_eq!(removed, expected_removed, "{:?}", mode);
+            assert_eq!(*runner.checks.lock().unwrap(), expected_checks);
+            let edited = fs::read_to_string(temp_dir.path().join("Cargo.toml")).unwrap();
+            assert!(edited.contains("log = \"0.4\""));
+            match mode {
+                VerifyMode::Each => {
+                    assert_eq!(rolled_back.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(), ["log"]);
+                    assert!(!edited.contains("rand"));
+                }
+                VerifyMode::Batch => {
+                    assert_eq!(rolled_back.len(), 2);
+                    assert_eq!(edited, manifest);
+                }
+            }
+            assert_eq!(fs::read_to_string(temp_dir.path().join("Cargo.lock")).unwrap(), lock);
+        }
+    }
+
+    #[test]
+    fn test_unused_declared_features() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("src")).unwrap();
+        fs::write(
+            temp_dir.path().join("src/lib.rs"),
+            "#[cfg(feature = \"tracing\")]\nfn trace() {}\n#[cfg(feature=\"fast\")]\nfn fast() {}\n",
+        )
+        .unwrap();
+        let lib: toml::Table = r#"
+[package]
+name = "my-lib"
+
+[features]
+default = ["std"]
+std = []
+fast = []
+tracing = ["dep:tracing"]
+legacy = []
+full = ["fast", "extra"]
+extra = []
+"#
+        .parse()
+        .unwrap();
+        let app: toml::Table = r#"
+[dependencies]
+my-lib = { path = "../my-lib", features = ["fast"] }
+"#
+        .parse()
+        .unwrap();
+
+        let requested = requested_features(&[&lib, &app], None);
+        assert_eq!(requested["my_lib"], HashSet::from(["fast".to_string()]));
+        let findings = unused_declared_features(&lib, temp_dir.path(), &requested["my_lib"], Some("my-lib 0.1.0"));
+        let mut reported: Vec<(&str, &str)> = findings
+            .iter()
+            .map(|f| (f.feature.as_str(), f.suggestion.split(':').next().unwrap()))
+            .collect();
+        reported.sort();
+        assert_eq!(
+            reported,
+            [
+                ("extra", "never enabled, enables nothing and no cfg(feature) tests it"),
+                ("full", "not enabled by default or by any workspace member"),
+                ("legacy", "never enabled, enables nothing and no cfg(feature) tests it"),
+                ("std", "enables nothing and no cfg(feature) tests it"),
+                ("tracing", "not enabled by default or by any workspace member"),
+            ]
+        );
+        assert_eq!(findings[0].describe(), format!("{} (my-lib 0.1.0)", findings[0].feature));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unnecessary_dependency_features() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let manifest = "[package]\nname = \"app\"\n\n[dependencies]\nserde = { version = \"1\", features = [\"derive\", \"rc\"] }\n";
+        fs::write(temp_dir.path().join("Cargo.toml"), manifest).unwrap();
+        let project = Project {
+            path: temp_dir.path().to_path_buf(),
+            is_workspace: false,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: Default::default(),
+        };
+        let runner = Arc::new(CheckRunner { needed: "\"derive\"", checks: std::sync::Mutex::new(0) });
+        let options = DepsOptions {
+            features: true,
+            verify: Some(VerifyMode::Batch),
+            runner: runner.clone(),
+            ..Default::default()
+        };
+
+        let findings = find_feature_findings(&project, &options).unwrap();
+        assert_eq!(*runner.checks.lock().unwrap(), 2);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].describe(), "serde/rc");
+        assert_eq!(fs::read_to_string(temp_dir.path().join("Cargo.toml")).unwrap(), manifest);
+    }
+
+    #[test]
+    fn test_find_stale_path_dependencies() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let project = workspace_fixture(root);
+        fs::create_dir_all(root.join("crates/util")).unwrap();
+        fs::write(root.join("crates/util/Cargo.toml"), "[package]\nname = \"util\"\n").unwrap();
+        let mut workspace = fs::read_to_string(root.join("Cargo.toml")).unwrap();
+        workspace.push_str("gone = { path = \"crates/gone\" }\n");
+        fs::write(root.join("Cargo.toml"), workspace).unwrap();
+        let mut app = fs::read_to_string(root.join("crates/app/Cargo.toml")).unwrap();
+        app.push_str("util = { path = \"../util\" }\nold-crate = { path = \"../../old-crate\", version = \"0.1\" }\n\n[dev-dependencies.fixtures]\npath = \"../fixtures\"\n");
+        fs::write(root.join("crates/app/Cargo.toml"), app).unwrap();
+
+        let stale = find_stale_path_dependencies(&project).unwrap();
+        let found: Vec<(&str, &str, &str)> = stale
+            .iter()
+            .map(|dep| (dep.name.as_str(), dep.location.as_str(), dep.path.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("old-crate", "[dependencies]", "../../old-crate"),
+                ("fixtures", "[dev-dependencies]", "../fixtures"),
+                ("gone", "[workspace.dependencies]", "crates/gone"),
+            ]
+        );
+        assert_eq!(stale[0].package.as_deref(), Some("app 0.1.0"));
+        assert_eq!(stale[0].manifest_path, root.join("crates/app/Cargo.toml"));
+
+        let (edits, fixed) = plan_stale_path_fixes(&stale, StalePathAction::Comment).unwrap();
+        assert_eq!(fixed, 3);
+        let app = edits.iter().find(|e| e.path.ends_with("app/Cargo.toml")).unwrap();
+        assert!(app.edited.contains("util = { path = \"../util\" }\n# old-crate = { path"));
+        assert!(app.edited.ends_with("# [dev-dependencies.fixtures]\n# path = \"../fixtures\"\n"));
+        let (edits, fixed) = plan_stale_path_fixes(&stale, StalePathAction::Remove).unwrap();
+        assert_eq!(fixed, 3);
+        let workspace = edits.iter().find(|e| e.path == root.join("Cargo.toml")).unwrap();
+        assert!(!workspace.edited.contains("gone"));
+    }
+
+    #[test]
+    fn test_unused_overrides() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let manifest_path = temp_dir.path().join("Cargo.toml");
+        fs::write(
+            &manifest_path,
+            r#"[package]
+name = "app"
+
+[patch.crates-io]
+local = { path = "vendor/local" }
+stale = { path = "vendor/stale" }
+forked = { git = "https://github.com/me/forked.git", branch = "fix" }
+gone = { path = "vendor/gone" }
+ignored = { path = "vendor/ignored" }
+
+[patch.'https://github.com/org/repo']
+upstream = { path = "vendor/upstream" }
+
+[replace]
+"old:0.1.0" = { path = "vendor/old" }
+"serde:1.0.0" = { path = "vendor/serde" }
+"#,
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("Cargo.lock"),
+            r#"version = 3
+
+[[package]]
+name = "local"
+version = "0.2.0"
+
+[[package]]
+name = "stale"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "forked"
+version = "0.3.0"
+source = "git+https://github.com/me/forked?branch=fix#0123abcd"
+
+[[package]]
+name = "upstream"
+version = "0.1.0"
+
+[[package]]
+name = "serde"
+version = "1.0.0"
+
+[[patch.unused]]
+name = "ignored"
+version = "0.1.0"
+"#,
+        )
+        .unwrap();
+
+        let unused = unused_overrides(&manifest_path).unwrap();
+        let found: Vec<String> = unused.iter().map(|dep| format!("{} {}", dep.location, dep.name)).collect();
+        assert_eq!(
+            found,
+            [
+                "[patch.crates-io] gone",
+                "[patch.crates-io] ignored",
+                "[patch.crates-io] stale",
+                "[replace] old:0.1.0",
+            ]
+        );
+        assert!(unused.iter().all(|dep| dep.kind().is_none()));
+
+        let (edit, removed) = plan_dependency_removal(&manifest_path, &unused).unwrap();
+        assert_eq!(removed, 4);
+        assert!(edit.edited.contains("local = { path"));
+        assert!(edit.edited.contains("forked = {"));
+        assert!(edit.edited.contains("upstream = {"));
+        assert!(edit.edited.contains("\"serde:1.0.0\""));
+        assert!(!edit.edited.contains("stale") && !edit.edited.contains("gone") && !edit.edited.contains("old:"));
+    }
+
+    #[test]
+    fn test_split_test_code() {
+        let source = "use anyhow::Result;\n\n#[cfg(test)]\nuse proptest::prelude::*;\n\n#[cfg(test)]\nmod fixtures;\n\n#[cfg(test)]\nmod tests {\n    fn f() { let _ = format!(\"{}\", rand::random::<u8>()); }\n}\n\nfn after() {}\n";
+        let split = split_test_code(source);
+        assert_eq!(split.production, "use anyhow::Result;\n\n\n\n\n\n\n\nfn after() {}\n");
+        assert!(split.test.contains("proptest::prelude") && split.test.contains("rand::random"));
+        assert_eq!(split.test_modules, ["fixtures"]);
+    }
+
+    #[test]
+    fn test_find_dev_only_dependencies() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[package]\nname = \"app\"\n\n[features]\nextra = [\"dep:approx\"]\n\n[dependencies]\nlog = \"0.4\"\n# used by the test suite\nrand = \"0.8\"\nfake = \"2\"\ncriterion = { version = \"0.5\", default-features = false }\napprox = { version = \"0.5\", optional = true }\n\n[target.'cfg(unix)'.dependencies]\nnix = \"0.29\"\n",
+        );
+        write("src/lib.rs", "pub fn f() { log::info!(\"hi\"); }\n\n#[cfg(test)]\nmod tests {\n    fn t() { rand::random::<u8>(); approx::assert_abs_diff_eq!(1.0, 1.0); }\n}\n\n#[cfg(test)]\nmod helpers;\n");
+        write("src/helpers.rs", "pub fn person() -> String { fake::Faker.to_string() }\n");
+        write("tests/unix.rs", "use nix::unistd;\n");
+        write("benches/speed.rs", "use criterion::Criterion;\n");
+        let project = Project {
+            path: root.to_path_buf(),
+            is_workspace: false,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: Default::default(),
+        };
+
+        let misplaced = find_dev_only_dependencies(&project).unwrap();
+        let suggestions: Vec<String> = misplaced.iter().map(MisplacedDependency::suggestion).collect();
+        assert_eq!(
+            suggestions,
+            [
+                "move criterion to [dev-dependencies]",
+                "move fake to [dev-dependencies]",
+                "move rand to [dev-dependencies]",
+                "move nix to [target.'cfg(unix)'.dev-dependencies]",
+            ]
+        );
+
+        let (edits, moved) = plan_dev_moves(&misplaced).unwrap();
+        assert_eq!(moved, 4);
+        let edited = &edits[0].edited;
+        assert!(edited.contains("[dependencies]\nlog = \"0.4\"\napprox = "));
+        assert!(edited.contains(
+            "[dev-dependencies]\ncriterion = { version = \"0.5\", default-features = false }\nfake = \"2\"\n# used by the test suite\nrand = \"0.8\"\n"
+        ));
+        assert!(edited.contains("[target.'cfg(unix)'.dev-dependencies]\nnix = \"0.29\"\n"));
+    }
+
+    #[test]
+    fn test_parse_outdated_output() {
+        let single = r#"{"crate_name":"app","dependencies":[{"name":"rand","project":"0.7.3","compat":"0.7.3","latest":"0.8.5","kind":"Normal","platform":null}]}"#;
+        let outdated = parse_outdated_output(single).unwrap();
+        assert_eq!(
+            outdated,
+            [OutdatedDependency {
+                name: "rand".to_string(),
+                project: "0.7.3".to_string(),
+                compat: "0.7.3".to_string(),
+                latest: "0.8.5".to_string(),
+                kind: Some("Normal".to_string()),
+                package: None,
+            }]
+        );
+
+        let workspace = format!("{}\n{}\n", single, r#"{"crate_name":"cli","dependencies":[{"name":"clap","project":"3.2.0","compat":"3.2.25","latest":"4.5.0","kind":"Normal","platform":null}]}"#);
+        let outdated = parse_outdated_output(&workspace).unwrap();
+        let packages: Vec<_> = outdated.iter().map(|dep| (dep.name.as_str(), dep.package.as_deref())).collect();
+        assert_eq!(packages, [("rand", Some("app")), ("clap", Some("cli"))]);
+        assert!(parse_outdated_output("error: no such command").is_err());
+    }
+
+    #[test]
+    fn test_hoist_workspace_dependencies() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write("Cargo.toml", "[workspace]\nmembers = [\"a\", \"b\", \"c\"]\n\n[workspace.dependencies]\nlog = \"0.4\"\n");
+        write(
+            "a/Cargo.toml",
+            "[package]\nname = \"a\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = { version = \"1\", features = [\"derive\"] } # keep\nanyhow = \"1\"\nrand = \"0.8\"\n",
+        );
+        write(
+            "b/Cargo.toml",
+            "[package]\nname = \"b\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"1\"\nanyhow = \"1\"\nrand = \"0.7\"\ntoml = { version = \"0.8\", git = \"https://github.com/toml-rs/toml\" }\n",
+        );
+        write(
+            "c/Cargo.toml",
+            "[package]\nname = \"c\"\nversion = \"0.1.0\"\n\n[dependencies]\nrand = { version = \"0.7\", optional = true }\nlog = \"0.4\"\ntoml = { version = \"0.8\", git = \"https://github.com/toml-rs/toml\" }\n\n[dev-dependencies]\nanyhow = \"1\"\n",
+        );
+        for member in ["a", "b", "c"] {
+            write(&format!("{}/src/lib.rs", member), "");
+        }
+        let project = Project {
+            path: root.to_path_buf(),
+            is_workspace: true,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: Default::default(),
+        };
+
+        let hoistable = find_hoistable_dependencies(&project).unwrap();
+        let found: Vec<(&str, &str, bool, usize)> = hoistable
+            .iter()
+            .map(|dep| (dep.name.as_str(), dep.workspace_entry.as_str(), dep.inherited_elsewhere, dep.members.len()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("anyhow", "\"1\"", false, 3),
+                ("log", "\"0.4\"", true, 1),
+                ("rand", "\"0.7\"", false, 2),
+                ("serde", "\"1\"", false, 2),
+            ]
+        );
+
+        let (edits, rewritten) = plan_hoisting(&project, &hoistable).unwrap();
+        assert_eq!((edits.len(), rewritten), (4, 8));
+        let edited = |member: &str| {
+            let path = fs::canonicalize(root.join(member).join("Cargo.toml")).unwrap();
+            edits.iter().find(|edit| edit.path == path).unwrap().edited.clone()
+        };
+        assert!(edited("").ends_with("[workspace.dependencies]\nlog = \"0.4\"\nanyhow = \"1\"\nrand = \"0.7\"\nserde = \"1\"\n"));
+        assert!(edited("a").contains("serde = { workspace = true, features = [\"derive\"] } # keep\nanyhow = { workspace = true }\nrand = \"0.8\"\n"));
+        assert!(edited("c").contains("rand = { workspace = true, optional = true }\nlog = { workspace = true }\ntoml = {"));
+        assert!(edited("c").ends_with("[dev-dependencies]\nanyhow = { workspace = true }\n"));
+    }
+
+    #[test]
+    fn test_dependency_kinds() {
+        assert_eq!(DependencyKind::of_location("[dependencies]"), Some(DependencyKind::Normal));
+        assert_eq!(DependencyKind::of_location("[dev-dependencies]"), Some(DependencyKind::Dev));
+        assert_eq!(
+            DependencyKind::of_location("[target.'cfg(unix)'.build-dependencies]"),
+            Some(DependencyKind::Build)
+        );
+        assert_eq!(DependencyKind::of_location("[features]"), None);
+
+        let dep = |location: &str| UnusedDependency {
+            name: "rand".to_string(),
+            location: location.to_string(),
+            package: None,
+            manifest_path: None,
+        };
+        let options = DepsOptions { only: vec![DependencyKind::Dev, DependencyKind::Build], ..Default::default() };
+        assert!(options.includes(&dep("[dev-dependencies]")));
+        assert!(options.includes(&dep("[target.'cfg(unix)'.build-dependencies]")));
+        assert!(!options.includes(&dep("[dependencies]")));
+        assert!(DepsOptions::default().includes(&dep("[dependencies]")));
+    }
+
+    #[test]
+    fn test_find_duplicate_dependencies() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("Cargo.lock"),
+            r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "bitflags 2.4.0",
+ "legacy",
+]
+
+[[package]]
+name = "bitflags"
+version = "1.3.2"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "bitflags"
+version = "2.4.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "legacy"
+version = "0.3.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = [
+ "bitflags 1.3.2",
+]
+"#,
+        ).unwrap();
+
+        let duplicates = find_duplicate_dependencies(temp_dir.path()).unwrap();
+        assert_eq!(duplicates.len(), 1);
+        let bitflags = &duplicates[0];
+        assert_eq!(bitflags.name, "bitflags");
+        assert_eq!(bitflags.versions.len(), 2);
+        assert_eq!(bitflags.versions[0].version, "1.3.2");
+        assert_eq!(bitflags.versions[0].dependents, vec!["legacy 0.3.0"]);
+        assert_eq!(bitflags.versions[1].version, "2.4.0");
+        assert_eq!(bitflags.versions[1].dependents, vec!["app 0.1.0"]);
+        assert_eq!(bitflags.versions[0].chains, [["app 0.1.0", "legacy 0.3.0", "bitflags 1.3.2"]]);
+        assert_eq!(bitflags.versions[1].chains, [["app 0.1.0", "bitflags 2.4.0"]]);
     }
 }
@@ -0,0 +1,174 @@
+use crate::output::Summary;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Schema migrations, applied in order; `schema_version` records how many have run
+#[cfg(feature = "history")]
+const MIGRATIONS: &[&str] = &["
+    CREATE TABLE runs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        finished_at INTEGER NOT NULL,
+        root TEXT NOT NULL,
+        dry_run INTEGER NOT NULL,
+        total_projects INTEGER NOT NULL,
+        cleaned INTEGER NOT NULL,
+        failed INTEGER NOT NULL,
+        total_freed_bytes INTEGER NOT NULL
+    );
+    CREATE TABLE project_results (
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        path TEXT NOT NULL,
+        success INTEGER NOT NULL,
+        freed_bytes INTEGER NOT NULL,
+        error TEXT
+    );
+    CREATE INDEX project_results_path ON project_results(path);
+"];
+
+/// Bring the database schema up to date, creating it on first use
+#[cfg(feature = "history")]
+fn migrate(conn: &mut rusqlite::Connection) -> Result<()> {
+    let tx = conn.transaction()?;
+    tx.execute_batch("CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL)")?;
+    let version: Option<usize> = tx
+        .query_row("SELECT version FROM schema_version", [], |row| row.get(0))
+        .ok();
+    let current = version.unwrap_or(0);
+    if current > MIGRATIONS.len() {
+        anyhow::bail!(
+            "history database schema version {} is newer than supported ({})",
+            current,
+            MIGRATIONS.len()
+        );
+    }
+    for migration in &MIGRATIONS[current..] {
+        tx.execute_batch(migration)?;
+    }
+    match version {
+        None => tx.execute("INSERT INTO schema_version (version) VALUES (?1)", [MIGRATIONS.len()])?,
+        Some(_) => tx.execute("UPDATE schema_version SET version = ?1", [MIGRATIONS.len()])?,
+    };
+    tx.commit()?;
+    Ok(())
+}
+
+/// Append a finished run and its per-project results to the SQLite database at `db_path`
+///
+/// Returns the new run's id.
+#[cfg(feature = "history")]
+pub fn record_run(db_path: &Path, roots: &[PathBuf], dry_run: bool, summary: &Summary) -> Result<i64> {
+    use anyhow::Context;
+    let mut conn = rusqlite::Connection::open(db_path)
+        .with_context(|| format!("Failed to open history database: {:?}", db_path))?;
+    migrate(&mut conn).with_context(|| format!("Failed to migrate history database: {:?}", db_path))?;
+
+    let finished_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO runs (finished_at, root, dry_run, total_projects, cleaned, failed, total_freed_bytes)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        rusqlite::params![
+            finished_at,
+            roots.iter().map(|root| root.to_string_lossy()).collect::<Vec<_>>().join(", "),
+            dry_run,
+            summary.total_projects as i64,
+            summary.cleaned as i64,
+            summary.failed as i64,
+            summary.total_freed_bytes as i64,
+        ],
+    )?;
+    let run_id = tx.last_insert_rowid();
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO project_results (run_id, path, success, freed_bytes, error) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for result in &summary.results {
+            insert.execute(rusqlite::params![
+                run_id,
+                result.path,
+                result.success,
+                result.freed_bytes as i64,
+                result.error,
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(run_id)
+}
+
+/// Run history is compiled out without the `history` feature
+#[cfg(not(feature = "history"))]
+pub fn record_run(_db_path: &Path, _roots: &[PathBuf], _dry_run: bool, _summary: &Summary) -> Result<i64> {
+    anyhow::bail!("run history is not supported by this build (enable the `history` feature)")
+}
+
+#[cfg(all(test, feature = "history"))]
+mod tests {
+    use super::*;
+    use crate::cleaner::CleanResult;
+
+    fn result(path: &str, freed_bytes: u64) -> CleanResult {
+        CleanResult {
+            path: path.to_string(),
+            success: true,
+            freed_bytes,
+            error: None,
+            oldest_artifact: None,
+            newest_artifact: None,
+            cargo_warnings: Vec::new(),
+            unrecognized_files: Vec::new(),
+            tracked_files: Vec::new(),
+            physical_freed_bytes: None,
+            cross_targets: Vec::new(),
+            freed_by_profile: Default::default(),
+            freed_by_triple: Default::default(),
+            freed_by_framework: Default::default(),
+            root: None,
+            doc_freed_bytes: None,
+            coverage_freed_bytes: None,
+            kept_binaries: Vec::new(),
+            members: Vec::new(),
+            explain: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_record_run_appends_history() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("history.db");
+        let mut summary = Summary::default();
+        summary.record(result("/projects/a", 100), true);
+        summary.record(result("/projects/b", 50), true);
+        summary.total_projects = 2;
+
+        let first = record_run(&db_path, &[PathBuf::from("/projects")], false, &summary).unwrap();
+        let second = record_run(&db_path, &[PathBuf::from("/projects")], true, &summary).unwrap();
+        assert_ne!(first, second);
+
+        let conn = rusqlite::Connection::open(&db_path).unwrap();
+        let version: usize = conn.query_row("SELECT version FROM schema_version", [], |r| r.get(0)).unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+        let freed: i64 = conn
+            .query_row("SELECT SUM(freed_bytes) FROM project_results WHERE path = '/projects/a'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(freed, 200);
+        let runs: i64 = conn.query_row("SELECT COUNT(*) FROM runs WHERE total_freed_bytes = 150", [], |r| r.get(0)).unwrap();
+        assert_eq!(runs, 2);
+    }
+
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("history.db");
+        let conn = rusqlite::Connection::open(&db_path).unwrap();
+        conn.execute_batch("CREATE TABLE schema_version (version INTEGER NOT NULL); INSERT INTO schema_version VALUES (99);")
+            .unwrap();
+        drop(conn);
+
+        let error = record_run(&db_path, &[PathBuf::from("/projects")], false, &Summary::default()).unwrap_err();
+        assert!(format!("{:#}", error).contains("newer than supported"));
+    }
+}
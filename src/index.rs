@@ -0,0 +1,329 @@
+//! Discovered projects and target sizes kept between runs
+//!
+//! Walking a large tree and asking cargo about every manifest is the slow part of a run,
+//! so the results are kept in a JSON file under the user's cache directory. A root's
+//! projects are reused while every directory its walk entered, every manifest and
+//! marker it found, and every Cargo config file that could move a project's target
+//! directory keeps its modification time. A target's size is reused while the
+//! directories in its top [`FINGERPRINT_DEPTH`] levels keep theirs.
+
+use crate::cargo_config::target_dir_config_files;
+use crate::project::{find_cargo_projects, CargoStatus, DiscoveryOptions, Project};
+use crate::utils::{cache_dir, get_directory_stats, DirectoryStats, SizeOptions};
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+/// Format version written to the index; an index with another version is discarded
+pub const INDEX_VERSION: u32 = 1;
+
+/// Name of the index file inside deepclean's cache directory
+pub const INDEX_FILE_NAME: &str = "index.json";
+
+/// Levels below a target directory whose directories fingerprint it; every build adds or
+/// replaces files in `target/<profile>/deps` and `target/<profile>/incremental/<crate>`
+const FINGERPRINT_DEPTH: usize = 3;
+
+/// Where the index is kept: `index.json` in deepclean's [cache directory](cache_dir)
+pub fn default_index_path() -> Option<PathBuf> {
+    Some(cache_dir()?.join(INDEX_FILE_NAME))
+}
+
+/// Projects and target sizes from earlier runs, see the [module docs](self)
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct ProjectIndex {
+    version: u32,
+    roots: BTreeMap<PathBuf, IndexedRoot>,
+    /// Filled in from the parallel sizing phase, hence the lock
+    targets: Mutex<BTreeMap<PathBuf, IndexedTarget>>,
+    #[serde(skip)]
+    path: PathBuf,
+    /// Ignore what is indexed and replace it (`--refresh`)
+    #[serde(skip)]
+    refresh: bool,
+}
+
+/// The projects found under one root
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct IndexedRoot {
+    /// The discovery settings they were found with, see [`options_key`]
+    options: String,
+    /// Modification time of every path the walk looked at and of every config file that
+    /// could set a target directory, in nanoseconds since the Unix epoch; 0 for paths that
+    /// didn't exist
+    mtimes: BTreeMap<PathBuf, u64>,
+    projects: Vec<IndexedProject>,
+}
+
+impl IndexedRoot {
+    /// Whether nothing the walk looked at has changed since
+    fn is_current(&self) -> bool {
+        self.mtimes.par_iter().all(|(path, &recorded)| mtime(path).unwrap_or(0) == recorded)
+    }
+}
+
+/// A [`Project`] as indexed; sidecar overrides are read afresh on every run
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct IndexedProject {
+    path: PathBuf,
+    is_workspace: bool,
+    marker_only: bool,
+    fuzz: bool,
+    cargo: CargoStatus,
+}
+
+impl IndexedProject {
+    fn new(project: &Project) -> Self {
+        Self {
+            path: project.path.clone(),
+            is_workspace: project.is_workspace,
+            marker_only: project.marker_only,
+            fuzz: project.fuzz,
+            cargo: project.cargo.clone(),
+        }
+    }
+
+    fn project(&self) -> Project {
+        Project {
+            path: self.path.clone(),
+            is_workspace: self.is_workspace,
+            marker_only: self.marker_only,
+            fuzz: self.fuzz,
+            cargo: self.cargo.clone(),
+            overrides: Default::default(),
+        }
+    }
+}
+
+/// The last measurement of one target directory
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct IndexedTarget {
+    /// Measured as allocated blocks ([`SizeOptions::on_disk`])
+    on_disk: bool,
+    /// Measured without crossing filesystems ([`SizeOptions::one_filesystem`])
+    one_filesystem: bool,
+    /// See [`fingerprint`]
+    fingerprint: u64,
+    stats: DirectoryStats,
+}
+
+impl ProjectIndex {
+    /// Read the index at `path`; a missing, unreadable or outdated index starts out empty
+    pub fn open(path: &Path, refresh: bool) -> Self {
+        let index = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Self>(&content).ok())
+            .filter(|index| index.version == INDEX_VERSION)
+            .unwrap_or_default();
+        Self { version: INDEX_VERSION, path: path.to_path_buf(), refresh, ..index }
+    }
+
+    /// Projects under `roots`, merged and sorted like
+    /// [`find_cargo_projects_in`](crate::project::find_cargo_projects_in)
+    ///
+    /// A root is only walked when it isn't indexed with the same options or something it
+    /// was found from has changed; its new results replace the indexed ones.
+    pub fn discover(&mut self, roots: &[PathBuf], options: &DiscoveryOptions) -> Result<Vec<Project>> {
+        let key = options_key(options);
+        let mut projects = Vec::new();
+        for root in roots {
+            let indexed = self
+                .roots
+                .get(root)
+                .filter(|indexed| !self.refresh && indexed.options == key && indexed.is_current());
+            if let Some(indexed) = indexed {
+                projects.extend(indexed.projects.iter().map(IndexedProject::project));
+                continue;
+            }
+            let visited = Arc::new(Mutex::new(vec![root.clone()]));
+            let walk_options = DiscoveryOptions { visited: Some(visited.clone()), ..options.clone() };
+            let found = find_cargo_projects(root, &walk_options)?;
+            // Config files sit in `.cargo` directories the walk skips and above the root, and
+            // cargo's answer about each project's target directory depends on them
+            let config_files: BTreeSet<PathBuf> = found.iter().flat_map(|p| target_dir_config_files(&p.path)).collect();
+            let mtimes = std::mem::take(&mut *visited.lock().unwrap())
+                .into_par_iter()
+                .chain(config_files.into_par_iter())
+                .map(|path| {
+                    let mtime = mtime(&path).unwrap_or(0);
+                    (path, mtime)
+                })
+                .collect();
+            let projects_found = found.iter().map(IndexedProject::new).collect();
+            self.roots.insert(root.clone(), IndexedRoot { options: key.clone(), mtimes, projects: projects_found });
+            projects.extend(found);
+        }
+        projects.sort_by_key(|p| p.path.clone());
+        projects.dedup_by(|a, b| a.path == b.path);
+        Ok(projects)
+    }
+
+    /// Stats of `target_dir` like [`get_directory_stats`], reusing the indexed ones while
+    /// its fingerprint is unchanged
+    ///
+    /// Access times change without touching any directory, so sizes measured
+    /// [`by_atime`](SizeOptions::by_atime) are never reused.
+    pub fn target_stats(&self, target_dir: &Path, options: &SizeOptions) -> Result<DirectoryStats> {
+        let fingerprint = match options.by_atime {
+            true => None,
+            false => fingerprint(target_dir),
+        };
+        let Some(fingerprint) = fingerprint else {
+            return get_directory_stats(target_dir, options);
+        };
+        let reusable = |target: &&IndexedTarget| {
+            !self.refresh
+                && target.fingerprint == fingerprint
+                && target.on_disk == options.on_disk
+                && target.one_filesystem == options.one_filesystem
+        };
+        if let Some(target) = self.targets.lock().unwrap().get(target_dir).filter(reusable) {
+            return Ok(target.stats);
+        }
+        let stats = get_directory_stats(target_dir, options)?;
+        let target = IndexedTarget { on_disk: options.on_disk, one_filesystem: options.one_filesystem, fingerprint, stats };
+        self.targets.lock().unwrap().insert(target_dir.to_path_buf(), target);
+        Ok(stats)
+    }
+
+    /// Write the index back, dropping targets that no longer exist
+    pub fn save(&self) -> Result<()> {
+        self.targets.lock().unwrap().retain(|dir, _| dir.is_dir());
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+        }
+        // Renamed into place, so a concurrent run never reads half an index
+        let staged = self.path.with_extension(format!("json.{}.tmp", std::process::id()));
+        std::fs::write(&staged, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write project index: {:?}", staged))?;
+        std::fs::rename(&staged, &self.path).with_context(|| format!("Failed to write project index: {:?}", self.path))
+    }
+}
+
+/// The discovery settings that decide which projects are found, and the environment
+/// variables that decide where cargo builds
+fn options_key(options: &DiscoveryOptions) -> String {
+    let settings = (
+        &options.exclude_patterns,
+        &options.include_patterns,
+        options.follow_root_symlinks,
+        options.follow_symlinks,
+        &options.markers,
+        options.one_filesystem,
+        options.static_workspaces,
+        options.include_vendored,
+        options.no_ignore,
+        options.hidden,
+        options.max_depth,
+        options.prune_projects,
+    );
+    let environment = ["CARGO_TARGET_DIR", "CARGO_BUILD_TARGET_DIR"].map(std::env::var_os);
+    format!("{:?} {:?}", settings, environment)
+}
+
+/// Modification time of `path` in nanoseconds since the Unix epoch
+fn mtime(path: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos() as u64)
+}
+
+/// Hash of the paths and modification times of the directories in the top
+/// [`FINGERPRINT_DEPTH`] levels of `target_dir`, or `None` when it doesn't exist
+fn fingerprint(target_dir: &Path) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    let mut found = false;
+    let dirs = WalkDir::new(target_dir)
+        .max_depth(FINGERPRINT_DEPTH)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_dir());
+    for dir in dirs {
+        found = true;
+        dir.path().hash(&mut hasher);
+        mtime(dir.path()).hash(&mut hasher);
+    }
+    found.then(|| hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn package(dir: &Path, name: &str) {
+        fs::create_dir_all(dir.join("target/debug/deps")).unwrap();
+        fs::write(dir.join("Cargo.toml"), format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n", name)).unwrap();
+        fs::write(dir.join("target/debug/deps/out"), vec![0u8; 100]).unwrap();
+    }
+
+    fn set_mtime(path: &Path, time: std::time::SystemTime) {
+        fs::File::open(path).unwrap().set_modified(time).unwrap();
+    }
+
+    #[test]
+    fn test_index_reuses_until_something_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("code");
+        package(&root.join("app"), "app");
+        let index_path = temp_dir.path().join("cache").join(INDEX_FILE_NAME);
+        let options = DiscoveryOptions { static_workspaces: true, ..Default::default() };
+        let roots = [root.clone()];
+
+        let mut index = ProjectIndex::open(&index_path, false);
+        assert_eq!(index.discover(&roots, &options).unwrap().len(), 1);
+        let target = root.join("app/target");
+        assert_eq!(index.target_stats(&target, &SizeOptions::default()).unwrap().size, 100);
+        index.save().unwrap();
+
+        // A new project behind an unchanged modification time isn't walked into
+        let root_mtime = fs::metadata(&root).unwrap().modified().unwrap();
+        package(&root.join("lib"), "lib");
+        set_mtime(&root, root_mtime);
+        let mut index = ProjectIndex::open(&index_path, false);
+        assert_eq!(index.discover(&roots, &options).unwrap().len(), 1);
+        set_mtime(&root, UNIX_EPOCH);
+        assert_eq!(index.discover(&roots, &options).unwrap().len(), 2);
+
+        // So is a new Cargo config that could move a target, even in an unwalked `.cargo`
+        assert!(index.roots[&root].is_current());
+        fs::create_dir_all(root.join("app/.cargo")).unwrap();
+        fs::write(root.join("app/.cargo/config.toml"), "[build]\ntarget-dir = \"out\"\n").unwrap();
+        assert!(!index.roots[&root].is_current());
+        assert_eq!(index.discover(&roots, &options).unwrap().len(), 2);
+        assert!(index.roots[&root].is_current());
+
+        // Sizes are reused until a directory near the top of the target changes
+        fs::write(root.join("app/target/debug/deps/out"), vec![0u8; 300]).unwrap();
+        assert_eq!(index.target_stats(&target, &SizeOptions::default()).unwrap().size, 100);
+        set_mtime(&root.join("app/target/debug/deps"), UNIX_EPOCH);
+        assert_eq!(index.target_stats(&target, &SizeOptions::default()).unwrap().size, 300);
+
+        // Other options and --refresh always look again
+        let vendored = DiscoveryOptions { include_vendored: true, ..options.clone() };
+        assert_eq!(index.discover(&roots, &vendored).unwrap().len(), 2);
+        assert_ne!(index.roots[&root].options, options_key(&options));
+        fs::write(root.join("app/target/debug/deps/out"), vec![0u8; 500]).unwrap();
+        let refreshed = ProjectIndex { refresh: true, ..index };
+        assert_eq!(refreshed.target_stats(&target, &SizeOptions::default()).unwrap().size, 500);
+    }
+
+    #[test]
+    fn test_index_ignores_unreadable_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let index_path = temp_dir.path().join(INDEX_FILE_NAME);
+        fs::write(&index_path, "{ not json").unwrap();
+        assert!(ProjectIndex::open(&index_path, false).roots.is_empty());
+        fs::write(&index_path, "{\"version\":99,\"roots\":{},\"targets\":{}}").unwrap();
+        let index = ProjectIndex::open(&index_path, false);
+        assert_eq!(index.version, INDEX_VERSION);
+        index.save().unwrap();
+        assert_eq!(ProjectIndex::open(&index_path, false).version, INDEX_VERSION);
+    }
+}
@@ -0,0 +1,202 @@
+//! Discover Cargo projects, measure and clean their build artifacts, and find
+//! unused dependencies and feature flags.
+//!
+//! This is the library behind `cargo deepclean`. The [`Scanner`], [`Cleaner`] and
+//! [`DepsAnalyzer`] types cover the common tasks; the modules expose the lower-level
+//! pieces they are built from.
+//!
+//! ```no_run
+//! use deepclean::{CleanOptions, Cleaner, DiscoveryOptions, Scanner, SizeOptions};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let scanner = Scanner::new(DiscoveryOptions::default(), SizeOptions::default());
+//! let cleaner = Cleaner::new(CleanOptions { dry_run: true, ..Default::default() });
+//! for target in scanner.scan("/home/me/code".as_ref())? {
+//!     let result = cleaner.clean_measured(&target.project, target.stats.size)?;
+//!     println!("{}: would free {} bytes", result.path, result.freed_bytes);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod cargo_config;
+pub mod cleaner;
+pub mod config;
+pub mod daemon;
+pub mod deps;
+pub mod history;
+pub mod index;
+pub mod manifest;
+pub mod notify;
+pub mod orphans;
+pub mod output;
+pub mod plan;
+pub mod project;
+pub mod schedule;
+pub mod throttle;
+pub mod triples;
+pub mod utils;
+pub mod warnings;
+
+pub use cleaner::{CleanOptions, CleanResult, CleanStrategy};
+pub use deps::{DependencyCleanResult, DependencyKind, DepsOptions, DuplicateDependency, FeatureFinding, UnusedDependency};
+pub use output::Summary;
+pub use project::{DiscoveryOptions, Project};
+pub use utils::{DirectoryStats, SizeOptions};
+
+use anyhow::Result;
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// A discovered project with the measured state of its target directory
+#[derive(Debug, Clone)]
+pub struct ScannedTarget {
+    pub project: Project,
+    pub stats: DirectoryStats,
+}
+
+/// Finds Cargo projects under a root and measures their target directories
+#[derive(Debug, Clone, Default)]
+pub struct Scanner {
+    discovery: DiscoveryOptions,
+    sizes: SizeOptions,
+}
+
+impl Scanner {
+    pub fn new(discovery: DiscoveryOptions, sizes: SizeOptions) -> Self {
+        Self { discovery, sizes }
+    }
+
+    /// Every project under `root`, without measuring anything
+    pub fn discover(&self, root: &Path) -> Result<Vec<Project>> {
+        project::find_cargo_projects(root, &self.discovery)
+    }
+
+    /// Every project under `root` with its target directory measured, in parallel
+    ///
+    /// A target directory shared by several projects is listed once, under the first of them.
+    pub fn scan(&self, root: &Path) -> Result<Vec<ScannedTarget>> {
+        Ok(self.measure_all(self.discover(root)?))
+    }
+
+    /// Like [`Scanner::scan`] for several roots, merged; roots inside another are walked once
+    pub fn scan_roots(&self, roots: &[PathBuf]) -> Result<Vec<ScannedTarget>> {
+        let roots = project::dedup_roots(roots.iter().map(|root| root.canonicalize()).collect::<std::io::Result<_>>()?);
+        Ok(self.measure_all(project::find_cargo_projects_in(&roots, &self.discovery)?))
+    }
+
+    fn measure_all(&self, projects: Vec<Project>) -> Vec<ScannedTarget> {
+        let (projects, _shared) = project::dedup_shared_targets(projects);
+        projects
+            .into_par_iter()
+            .map(|project| ScannedTarget {
+                stats: self.measure(&project),
+                project,
+            })
+            .collect()
+    }
+
+    /// Size and artifact age range of one project's target directory
+    pub fn measure(&self, project: &Project) -> DirectoryStats {
+        utils::get_directory_stats(&project.target_dir(), &self.sizes).unwrap_or_default()
+    }
+}
+
+/// Cleans project target directories with the strategy in its [`CleanOptions`]
+#[derive(Default)]
+pub struct Cleaner {
+    options: CleanOptions,
+}
+
+impl Cleaner {
+    pub fn new(options: CleanOptions) -> Self {
+        Self { options }
+    }
+
+    /// Clean one project, measuring its target first
+    pub fn clean(&self, project: &Project) -> Result<CleanResult> {
+        cleaner::clean_project(project, None, &self.options)
+    }
+
+    /// Clean one project whose target size is already known, e.g. from [`Scanner::scan`]
+    pub fn clean_measured(&self, project: &Project, target_size: u64) -> Result<CleanResult> {
+        cleaner::clean_project(project, Some(target_size), &self.options)
+    }
+
+    /// Clean every scanned project in parallel, collecting the results into a [`Summary`]
+    pub fn clean_all(&self, targets: &[ScannedTarget]) -> Summary {
+        let results: Vec<CleanResult> = targets
+            .par_iter()
+            .map(|target| {
+                self.clean_measured(&target.project, target.stats.size)
+                    .unwrap_or_else(|e| CleanResult::failed(&target.project, e.to_string()))
+            })
+            .collect();
+        let mut summary = Summary { total_projects: targets.len(), ..Default::default() };
+        for result in results {
+            summary.record(result, true);
+        }
+        summary
+    }
+}
+
+/// Finds unused and duplicated dependencies and unneeded features, and removes unused
+/// dependencies when asked
+#[derive(Debug, Clone, Default)]
+pub struct DepsAnalyzer {
+    options: DepsOptions,
+}
+
+impl DepsAnalyzer {
+    pub fn new(options: DepsOptions) -> Self {
+        Self { options }
+    }
+
+    /// Run the checks enabled in the options on one project
+    pub fn analyze(&self, project: &Project) -> Result<DependencyCleanResult> {
+        deps::clean_dependencies(project, &self.options)
+    }
+
+    /// Dependencies declared in the project's manifest but never referenced in its sources,
+    /// limited to the kinds in [`DepsOptions::only`]
+    pub fn unused(&self, project: &Project) -> Result<Vec<UnusedDependency>> {
+        let unused = deps::check_unused_dependencies(project)?;
+        Ok(unused.into_iter().filter(|dep| self.options.includes(dep)).collect())
+    }
+
+    /// Crates locked at more than one version, with the packages pulling in each
+    pub fn duplicates(&self, project: &Project) -> Result<Vec<DuplicateDependency>> {
+        deps::find_duplicate_dependencies(&project.path)
+    }
+
+    /// Feature flags that could be trimmed; dependency features are only tried when
+    /// [`DepsOptions::verify`] is set
+    pub fn features(&self, project: &Project) -> Result<Vec<FeatureFinding>> {
+        deps::find_feature_findings(project, &self.options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_and_clean_all() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project_dir = temp_dir.path().join("app");
+        std::fs::create_dir_all(project_dir.join("target/debug")).unwrap();
+        std::fs::write(project_dir.join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\n").unwrap();
+        std::fs::write(project_dir.join("target/debug/app"), vec![0u8; 1000]).unwrap();
+        std::fs::write(project_dir.join("target/.rustc_info.json"), "").unwrap();
+
+        let scanner = Scanner::new(DiscoveryOptions { static_workspaces: true, ..Default::default() }, SizeOptions::default());
+        let targets = scanner.scan(temp_dir.path()).unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].stats.size, 1000);
+
+        let cleaner = Cleaner::new(CleanOptions { strategy: CleanStrategy::RemoveTarget, ..Default::default() });
+        let summary = cleaner.clean_all(&targets);
+        assert_eq!((summary.cleaned, summary.total_freed_bytes), (1, 1000));
+        assert!(!project_dir.join("target").exists());
+    }
+}
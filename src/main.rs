@@ -1,59 +1,516 @@
-mod cleaner;
-mod deps;
-mod output;
-mod project;
-mod utils;
-
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use colored::*;
-use cleaner::{clean_project, CleanResult};
-use deps::clean_dependencies;
-use output::{create_progress_bars, create_project_progress_bar, print_summary, print_verbose_cleaned, print_error, Summary};
-use project::find_cargo_projects;
 use rayon::prelude::*;
-use utils::{get_directory_size, parse_size};
+use deepclean::{history, notify};
+use deepclean::cargo_config::resolve_target_dir_with_source;
+use deepclean::cleaner::{cargo_clean_args, clean_project, CleanScope, CRITERION_DIR, FUZZ_CORPUS_DIRS, compile_globs, is_full_clean, BuildInProgress, CleanOptions, ArtifactPatterns, CleanResult, CleanStrategy, NotACargoTarget, OwnershipMismatch, StrategyKind};
+use deepclean::config::{load_sidecar, Config, PROFILE_ENV_VAR, SIDECAR_FILE_NAME};
+use deepclean::daemon::{log_line, pick_targets, CleanOrder, DaemonState};
+use deepclean::index::{default_index_path, ProjectIndex};
+use deepclean::deps::{check_unused_dependencies, clean_dependencies, remove_planned_dependencies, DependencyCleanResult, DependencyKind, DepsOptions, StalePathAction, VerifyMode, DEFAULT_DEP_JOBS};
+use deepclean::orphans::{find_orphaned_targets, remove_orphan};
+use deepclean::output::{bitflag_exit_code, orphans_to_json, phase_progress, scan_progress, scan_to_json, write_orphans, write_scan, write_would_clean, print_duplicates, print_feature_findings, print_dev_only, print_hoistable, print_manifest_diff, print_outdated, print_stale_paths, print_phase_timings, print_summary, print_throttle_metrics, summary_to_json, summary_to_junit, write_silent_report, print_verbose_cleaned, print_workspace_summary, print_error, DepSummary, FilesystemReclaim, OutputFormat, ScannedProject, PhaseTimings, ProgressDisplay, ReclaimCheck, ResultStream, SkippedProject, Summary, DEFAULT_MAX_VISIBLE_BARS, EXIT_BIT_NOTHING_TO_CLEAN};
+use deepclean::plan::{CleanupPlan, PlannedProject};
+use deepclean::schedule::{cron_entry_in, launch_agent_path, parse_time, replace_cron_entry, systemd_unit_dir, Backend, Frequency, Schedule, JOB_NAME, LAUNCHD_LABEL};
+use deepclean::project::{contains_path, CargoStatus, dedup_roots, dedup_shared_targets, depends_on, is_shared_target, matches_relative, find_cargo_projects_in, projects_from_paths, root_of, DiscoveryOptions, Project};
+use deepclean::throttle::IoThrottle;
+use deepclean::triples::triple_sizes;
+use deepclean::utils::{atime_unreliable, cache_dir, escape_non_utf8, format_age, format_bytes, format_duration, format_command, free_space, get_directory_size, get_directory_stats, network_filesystem, one_path_per_device, same_device, physical_size_caveat, parse_duration, parse_size, unix_now, DirectoryStats, SizeOptions};
+use deepclean::warnings::WarningCollector;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[derive(Parser, Debug)]
 #[command(name = "cargo-deepclean")]
 #[command(about = "Recursively clean Cargo projects with workspace support", long_about = None)]
 #[command(bin_name = "cargo deepclean")]
+#[command(subcommand_precedence_over_arg = true)]
 struct Args {
-    /// Directory to start cleaning from
+    /// Directories to start cleaning from; overlapping ones are scanned once
     #[arg(default_value = ".")]
-    directory: std::path::PathBuf,
+    directories: Vec<std::path::PathBuf>,
 
     /// Dry run mode (don't actually clean, just show what would be cleaned)
-    #[arg(long)]
+    #[arg(long, global = true)]
     dry_run: bool,
 
+    /// Select a `[profile.NAME]` from the config file (default: $DEEPCLEAN_PROFILE)
+    #[arg(long, value_name = "NAME", global = true)]
+    profile_name: Option<String>,
+
     /// Exclude patterns (glob patterns, can be specified multiple times)
-    #[arg(short = 'e', long = "exclude")]
+    #[arg(short = 'e', long = "exclude", global = true)]
     exclude_patterns: Vec<String>,
 
+    /// Only select projects whose path, relative to the directory, matches one of these globs (can be repeated)
+    #[arg(long = "include", value_name = "GLOB", global = true)]
+    include_patterns: Vec<String>,
+
+    /// Clean exactly the projects listed in this file, one path per line (`-` reads stdin),
+    /// instead of discovering them; each must contain a Cargo.toml
+    #[arg(long, value_name = "FILE", global = true)]
+    projects_from: Option<String>,
+
+    /// Skip dependency analysis (but still clean) for projects matching this glob, relative to the directory (can be repeated)
+    #[arg(long = "deps-exclude", value_name = "GLOB", global = true)]
+    deps_exclude: Vec<String>,
+
+    /// Follow symlinked directories directly under the root (deeper symlinks are not followed)
+    #[arg(long, global = true)]
+    follow_root_symlinks: bool,
+
+    /// Follow symlinked directories anywhere during discovery, skipping links that loop back
+    #[arg(long, global = true)]
+    follow_symlinks: bool,
+
+    /// Also treat directories containing this file as projects (can be specified multiple times)
+    #[arg(long = "marker", value_name = "FILENAME", global = true)]
+    markers: Vec<String>,
+
+    /// Only clean projects whose resolved dependency graph contains this crate
+    #[arg(long, value_name = "CRATE", global = true)]
+    depends_on: Option<String>,
+
     /// Number of parallel jobs
-    #[arg(short = 'j', long = "jobs", default_value_t = num_cpus::get())]
+    #[arg(short = 'j', long = "jobs", default_value_t = num_cpus::get(), global = true)]
     jobs: usize,
 
-    /// Verbose output
-    #[arg(short, long)]
-    verbose: bool,
+    /// How target directories are cleaned
+    #[arg(long, value_enum, default_value_t = StrategyKind::CargoClean, global = true)]
+    strategy: StrategyKind,
 
-    /// JSON output
-    #[arg(long)]
+    /// With the cargo-clean strategy, fail instead of deleting the target directory when `cargo clean` fails
+    #[arg(long, global = true)]
+    no_fallback_delete: bool,
+
+    /// Glob (relative to the target directory) of files removed by the selective-glob strategy (can be repeated)
+    #[arg(long = "glob", value_name = "PATTERN", global = true)]
+    globs: Vec<String>,
+
+    /// Glob (relative to the target directory) of extra build outputs for --safe and selective-glob (can be repeated)
+    #[arg(long = "artifact-pattern", value_name = "GLOB", global = true)]
+    artifact_patterns: Vec<String>,
+
+    /// Clean only these cross-compilation targets' `target/<triple>` outputs (comma-separated or
+    /// repeated, e.g. wasm32-unknown-unknown)
+    #[arg(long, visible_alias = "target", value_name = "TRIPLES", value_delimiter = ',', global = true)]
+    triples: Vec<String>,
+
+    /// Clean the outputs of every cross-compilation target found, leaving host builds alone
+    #[arg(long, conflicts_with = "triples", global = true)]
+    non_host_only: bool,
+
+    /// Clean only these build profiles' outputs, e.g. `debug` to keep `target/release` (comma-separated;
+    /// `debug` or `dev`, `release`, or a custom profile name)
+    #[arg(long, value_name = "PROFILES", value_delimiter = ',', global = true)]
+    profile: Vec<String>,
+
+    /// Clean only documentation output (`target/doc`), leaving build artifacts alone
+    #[arg(long, conflicts_with = "profile", global = true)]
+    doc_only: bool,
+
+    /// Clean only incremental compilation caches (`target/*/incremental`), keeping compiled
+    /// dependencies so the next build stays warm
+    #[arg(long, conflicts_with_all = ["profile", "doc_only", "triples", "non_host_only"], global = true)]
+    incremental_only: bool,
+
+    /// Keep the final executables in `target/<profile>` (and `target/<triple>/<profile>`) while
+    /// removing every intermediate artifact
+    #[arg(long, conflicts_with_all = ["doc_only", "incremental_only"], global = true)]
+    keep_binaries: bool,
+
+    /// Delete only target files not modified (with --by-atime, accessed) within this long,
+    /// e.g. "30d", like cargo-sweep
+    #[arg(long, value_name = "DURATION", conflicts_with_all = ["doc_only", "incremental_only", "profile", "triples", "non_host_only"], global = true)]
+    sweep_older_than: Option<String>,
+
+    /// Clean only coverage artifacts: `*.profraw` and `*.profdata` files anywhere in the project,
+    /// tarpaulin reports, and the `tarpaulin` and `llvm-cov-target` directories of the target
+    #[arg(long, conflicts_with_all = ["doc_only", "incremental_only", "profile", "sweep_older_than", "keep_binaries", "triples", "non_host_only"], global = true)]
+    coverage: bool,
+
+    /// Also remove the `corpus` and `artifacts` directories of cargo-fuzz crates, which are kept
+    /// by default since corpora are expensive to regenerate
+    #[arg(long, conflicts_with_all = ["coverage", "doc_only", "incremental_only", "profile", "sweep_older_than", "triples", "non_host_only"], global = true)]
+    include_corpus: bool,
+
+    /// Also remove Criterion benchmark baselines in `target/criterion`, which are kept by default
+    #[arg(long, global = true)]
+    clean_criterion: bool,
+
+    /// Leave wasm-pack (`pkg/`) and trunk (`dist/`, `.trunk`) output directories alone; they are
+    /// cleaned along with the target by default
+    #[arg(long, global = true)]
+    no_framework_outputs: bool,
+
+    /// Delete target directories that lack cargo's CACHEDIR.TAG and .rustc_info.json, and
+    /// apply plans whose projects changed since they were made
+    #[arg(long, global = true)]
+    force: bool,
+
+    /// With --incremental-only, also remove `*.d` dep-info files
+    #[arg(long, requires = "incremental_only", global = true)]
+    dep_info: bool,
+
+    /// Remove only recognized cargo artifacts from targets, leaving and reporting any other files
+    #[arg(long, conflicts_with_all = ["strategy", "no_fallback_delete", "globs"], global = true)]
+    safe: bool,
+
+    /// Compare free disk space before and after the run with the reported freed space, flagging large differences
+    #[arg(long, global = true)]
+    verify_reclaimed: bool,
+
+    /// Clean target directories outside every discovered project (e.g. a global CARGO_TARGET_DIR)
+    #[arg(long, global = true)]
+    allow_global_target: bool,
+
+    /// Clean projects whose target directory is on a network filesystem (NFS, SMB, ...),
+    /// which are skipped with a warning by default
+    #[arg(long, global = true)]
+    allow_network_fs: bool,
+
+    /// Skip projects whose manifest `cargo metadata` can't load (invalid, templated, or without
+    /// targets), listing them as skipped, instead of cleaning their targets
+    #[arg(long, global = true)]
+    skip_unbuildable: bool,
+
+    /// Maximum concurrent dependency analyses; kept separate from --jobs because each one parses every source file
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_DEP_JOBS, global = true)]
+    dep_jobs: usize,
+
+    /// Maximum concurrent manual target deletions (default: --jobs); reduced automatically when IO-bound
+    #[arg(long, value_name = "N", global = true)]
+    io_jobs: Option<usize>,
+
+    /// Threads walking directories during discovery (default: --jobs); 1 walks sequentially
+    #[arg(long = "scan-jobs", value_name = "N", global = true)]
+    scan_jobs: Option<usize>,
+
+    /// Verbose output (-vv for debug metrics)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Explain each decision: why a project was cleaned or skipped, how its target was found and how it was cleaned (implies -v)
+    #[arg(long, global = true)]
+    explain: bool,
+
+    /// Maximum number of per-project progress spinners shown at once
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_VISIBLE_BARS, global = true)]
+    max_visible_bars: usize,
+
+    /// JSON output (shorthand for --format json)
+    #[arg(long, global = true)]
     json: bool,
 
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, global = true)]
+    format: OutputFormat,
+
+    /// Fail instead of lossily converting project or target paths that are not valid UTF-8 (json and jsonl only)
+    #[arg(long, global = true)]
+    strict_json: bool,
+
+    /// Write the report to this file instead of stdout (not available with --format text)
+    #[arg(long, value_name = "PATH", global = true)]
+    summary_file: Option<std::path::PathBuf>,
+
+    /// Profiling aid: only discover and size targets, reporting what would be freed. Spawns no
+    /// subprocesses (workspaces are detected from manifests) and changes nothing
+    #[arg(long, conflicts_with_all = ["depends_on", "clean_deps", "remove_deps", "duplicates", "features", "stale_paths", "dev_deps", "hoist_deps", "outdated", "prune_lock", "notify", "git_idle", "git_reflog", "skip_dirty"], global = true)]
+    measure_only: bool,
+
+    /// Don't cross filesystem boundaries during discovery or sizing (like `find -xdev`)
+    #[arg(long, visible_alias = "one-file-system", global = true)]
+    one_filesystem: bool,
+
+    /// Also discover vendored and third-party crates: `vendor/` and `node_modules/` trees,
+    /// cargo's registry and git checkouts, and crates unpacked or copied by cargo
+    #[arg(long, global = true)]
+    include_vendored: bool,
+
+    /// Don't respect `.gitignore`, `.ignore` or git's exclude files during discovery
+    #[arg(long, global = true)]
+    no_ignore: bool,
+
+    /// Also walk hidden directories during discovery
+    #[arg(long, global = true)]
+    hidden: bool,
+
+    /// Only find projects at most this many directories below each root (0: the root itself)
+    #[arg(long, value_name = "N", global = true)]
+    max_depth: Option<usize>,
+
+    /// Don't look for projects inside other projects, except workspace members and fuzz crates
+    #[arg(long, global = true)]
+    prune_projects: bool,
+
+    /// Reuse projects and target sizes from earlier runs, kept in the project index; sizes
+    /// may lag behind builds that didn't change the target's top directories
+    #[arg(long, global = true)]
+    index: bool,
+
+    /// With --index, rediscover projects and re-measure every target instead of reusing the index
+    #[arg(long, requires = "index", global = true)]
+    refresh: bool,
+
     /// Minimum size threshold (e.g., "100MB", "1GB") - only clean projects above this size
-    #[arg(long)]
+    #[arg(long, global = true)]
     min_size: Option<String>,
 
+    /// Only clean targets whose newest artifact is older than this (e.g. "30d", "12h", "2w")
+    #[arg(long, value_name = "DURATION", global = true)]
+    older_than: Option<String>,
+
+    /// Only clean targets whose newest artifact is newer than this (e.g. "7d")
+    #[arg(long, value_name = "DURATION", global = true)]
+    newer_than: Option<String>,
+
+    /// Only clean projects whose git repository has had no commits for this long (e.g. "90d");
+    /// projects outside a repository are skipped
+    #[arg(long, value_name = "DURATION", global = true)]
+    git_idle: Option<String>,
+
+    /// With --git-idle, also count checkouts, pulls and other HEAD reflog entries as activity
+    #[arg(long, requires = "git_idle", global = true)]
+    git_reflog: bool,
+
+    /// Wait for builds in progress to finish instead of skipping their projects, for at
+    /// most DURATION if given (e.g. "10m")
+    #[arg(long, num_args = 0..=1, value_name = "DURATION", global = true)]
+    wait: Option<Option<String>>,
+
+    /// Leave projects whose git working tree has uncommitted or untracked changes alone
+    #[arg(long, global = true)]
+    skip_dirty: bool,
+
+    /// Judge artifact age by last access instead of last modification (needs atime-enabled mounts)
+    #[arg(long, global = true)]
+    by_atime: bool,
+
+    /// Measure sizes as allocated disk blocks instead of apparent file length (Unix only)
+    #[arg(long, global = true)]
+    on_disk: bool,
+
+    /// Also report physical space freed: allocated blocks, net of filesystem compression where visible
+    #[arg(long, global = true)]
+    physical: bool,
+
     /// Check for unused dependencies (native detection)
-    #[arg(long)]
+    #[arg(long, global = true)]
     clean_deps: bool,
 
-    /// Remove unused dependencies (automatically enables --clean-deps, requires cargo-remove)
-    #[arg(long)]
+    /// Remove unused dependencies (automatically enables --clean-deps)
+    #[arg(long, global = true)]
     remove_deps: bool,
+
+    /// Only analyze and remove these kinds of unused dependencies (comma-separated)
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "KINDS", global = true)]
+    only: Vec<DependencyKind>,
+
+    /// Run `cargo check` after removing dependencies and restore any removal that breaks it
+    /// (`batch`: one check per project; `each`: one check per dependency)
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "batch", value_name = "MODE", global = true)]
+    verify: Option<VerifyMode>,
+
+    /// Show each manifest diff from --remove-deps and ask before writing it
+    #[arg(long, conflicts_with = "dry_run", global = true)]
+    confirm: bool,
+
+    /// Report crates locked at multiple versions and which packages pull in each version
+    #[arg(long, global = true)]
+    duplicates: bool,
+
+    /// Suggest features to trim: `[features]` entries nothing enables and, with --verify,
+    /// features enabled on dependencies that `cargo check` passes without
+    #[arg(long, global = true)]
+    features: bool,
+
+    /// Report path dependencies whose directory (or its Cargo.toml) is missing, and optionally
+    /// `remove` them or `comment` them out
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "report", value_name = "ACTION", global = true)]
+    stale_paths: Option<StalePathAction>,
+
+    /// Suggest moving dependencies only used by tests, examples or benches to `[dev-dependencies]`
+    #[arg(long, global = true)]
+    dev_deps: bool,
+
+    /// Suggest hoisting dependencies that workspace members declare alike into
+    /// `[workspace.dependencies]`, with members inheriting them via `workspace = true`
+    #[arg(long, global = true)]
+    hoist_deps: bool,
+
+    /// Perform the changes suggested by --dev-deps and --hoist-deps
+    #[arg(long, global = true)]
+    apply: bool,
+
+    /// Report dependencies with newer releases, using `cargo outdated` (must be installed)
+    #[arg(long, global = true)]
+    outdated: bool,
+
+    /// After removing or moving dependencies, run `cargo update --workspace` so Cargo.lock
+    /// drops the entries nothing needs any more
+    #[arg(long, global = true)]
+    prune_lock: bool,
+
+    /// Print nothing unless a project failed or warnings were raised, then print the full summary to stderr
+    #[arg(long, global = true)]
+    silent_on_success: bool,
+
+    /// Hide per-project lines for projects that freed nothing (still counted in the summary)
+    #[arg(long, overrides_with = "show_empty", global = true)]
+    hide_empty: bool,
+
+    /// Show per-project lines for projects that freed nothing (default; overrides --hide-empty)
+    #[arg(long, overrides_with = "hide_empty", global = true)]
+    show_empty: bool,
+
+    /// Report each cleaned workspace on one line with its freed total and member count, in
+    /// place of the per-project detail (JSON keeps the member names)
+    #[arg(long, global = true)]
+    workspace_summary: bool,
+
+    /// Skip the project containing the current working directory
+    #[arg(long, global = true)]
+    exclude_cwd: bool,
+
+    /// Append this run's per-project results to a SQLite history database
+    #[arg(long, value_name = "PATH", global = true)]
+    db: Option<std::path::PathBuf>,
+
+    /// Show a desktop notification with the freed total when the run finishes
+    #[arg(long, global = true)]
+    notify: bool,
+
+    /// Encode the outcome in the exit code as bits: 1 = failures, 2 = unused deps found, 4 = nothing to clean
+    #[arg(long, global = true)]
+    bitflag_exit: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List discovered projects with their target sizes, changing nothing
+    Scan {
+        /// Order of the listed projects
+        #[arg(long, value_enum, default_value_t = ScanSort::Size)]
+        sort: ScanSort,
+        /// Also report the space each target takes on disk (allocated blocks) next to its size
+        #[arg(long)]
+        disk_usage: bool,
+    },
+    /// Clean target directories only (the default without a subcommand, minus dependency analysis)
+    Clean,
+    /// List target directories whose project is gone (deleted or moved), changing nothing
+    Orphans {
+        /// Remove the orphaned targets (honors --dry-run, --confirm and --strategy trash-target)
+        #[arg(long)]
+        clean: bool,
+    },
+    /// Analyze dependencies only, leaving target directories alone
+    Deps {
+        /// Remove unused dependencies from Cargo.toml (same as --remove-deps)
+        #[arg(long)]
+        remove: bool,
+        /// Also report crates locked at multiple versions (same as --duplicates)
+        #[arg(long)]
+        duplicates: bool,
+        /// Also suggest features to trim (same as --features)
+        #[arg(long)]
+        features: bool,
+    },
+    /// Produce the full summary of what a run would do, changing nothing (a dry run)
+    Report {
+        /// Include unused and duplicate dependency analysis
+        #[arg(long)]
+        deps: bool,
+    },
+    /// Write a cleanup plan as JSON for review instead of cleaning (takes the same options as a normal run)
+    Plan {
+        /// Write the plan to this file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Execute a reviewed plan exactly, without discovering projects again
+    Apply {
+        /// Plan file written by `plan`
+        #[arg(long, value_name = "FILE")]
+        plan: std::path::PathBuf,
+    },
+    /// Keep running, cleaning targets whenever free disk space drops below a threshold
+    Daemon(DaemonArgs),
+    /// Install, remove or show a periodic cleanup job (systemd timer, cron, launchd or Task Scheduler)
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleAction,
+    },
+}
+
+/// What `schedule` does
+#[derive(Debug, Clone, clap::Subcommand)]
+enum ScheduleAction {
+    /// Install a job cleaning the given directories periodically, replacing any installed one
+    Install {
+        /// How often the job runs
+        #[arg(long, value_enum, default_value_t = Frequency::Weekly)]
+        every: Frequency,
+        /// Local time of day the job runs (24-hour)
+        #[arg(long, value_name = "HH:MM", default_value = "03:00")]
+        at: String,
+        /// Scheduler to install into (default: launchd on macOS, Task Scheduler on Windows,
+        /// a systemd user timer where systemd runs, cron otherwise)
+        #[arg(long, value_enum)]
+        backend: Option<Backend>,
+        /// Options for the scheduled run, after `--`, e.g. `-- --min-size 1GB --older-than 30d`
+        #[arg(last = true, value_name = "OPTIONS")]
+        options: Vec<String>,
+    },
+    /// Remove the installed job
+    Uninstall {
+        /// Scheduler the job was installed into (default: as for `install`)
+        #[arg(long, value_enum)]
+        backend: Option<Backend>,
+    },
+    /// Show the installed job and when it runs
+    Status {
+        /// Scheduler to look in (default: as for `install`)
+        #[arg(long, value_enum)]
+        backend: Option<Backend>,
+    },
+}
+
+/// Options of the `daemon` subcommand
+#[derive(Debug, Clone, clap::Args)]
+struct DaemonArgs {
+    /// Clean when free space on a filesystem holding one of the directories drops below this (e.g. "20GB")
+    #[arg(long, value_name = "SIZE")]
+    when_free_below: String,
+    /// How often to check free space
+    #[arg(long, value_name = "DURATION", default_value = "10m")]
+    interval: String,
+    /// Which targets to clean first
+    #[arg(long, value_enum, default_value_t = CleanOrder::Oldest)]
+    order: CleanOrder,
+    /// Check once and exit instead of running until stopped, e.g. from cron
+    #[arg(long)]
+    once: bool,
+    /// Append the log to this file instead of printing it
+    #[arg(long, value_name = "FILE")]
+    log: Option<PathBuf>,
+}
+
+/// Order of `scan` output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ScanSort {
+    /// Largest target first
+    Size,
+    /// Alphabetical by project path
+    Path,
+    /// Least recently built first
+    Age,
 }
 
 fn main() -> Result<()> {
@@ -64,9 +521,9 @@ fn main() -> Result<()> {
     
     // Check if we're being called as `cargo deepclean` (first arg is "deepclean")
     let first_arg = args_iter.next();
-    let args = if first_arg.as_deref() == Some("deepclean") {
+    let (mut args, matches) = if first_arg.as_deref() == Some("deepclean") {
         // Skip "deepclean" and parse the rest
-        Args::parse_from(args_iter)
+        parse_args(args_iter)
     } else {
         // Called directly as `cargo-deepclean`, reconstruct args
         let mut all_args = vec![program_name.unwrap_or_else(|| "cargo-deepclean".to_string())];
@@ -75,115 +532,818 @@ fn main() -> Result<()> {
             all_args.push(arg);
         }
         all_args.extend(args_iter);
-        Args::parse_from(all_args)
+        parse_args(all_args)
     };
-    
-    let root = args.directory.canonicalize()
-        .with_context(|| format!("Failed to canonicalize path: {:?}", args.directory))?;
 
-    if !args.json {
-        println!("{} {}", "[INFO]".blue().bold(), format!("Starting cargo clean from: {:?}", root));
-        println!("{} Searching for Cargo projects...", "[INFO]".blue().bold());
+    // The task subcommands are shorthands for the equivalent flags
+    match args.command {
+        Some(Command::Clean) if args.clean_deps || args.remove_deps || args.duplicates || args.features || args.stale_paths.is_some() || args.dev_deps || args.hoist_deps || args.outdated => {
+            anyhow::bail!("`clean` only removes targets; use `deps` for dependency analysis");
+        }
+        Some(Command::Deps { remove, duplicates, features }) => {
+            args.clean_deps = true;
+            args.remove_deps |= remove;
+            args.duplicates |= duplicates;
+            args.features |= features;
+        }
+        Some(Command::Report { deps }) => {
+            args.dry_run = true;
+            args.clean_deps |= deps;
+            args.duplicates |= deps;
+            args.features |= deps;
+            if deps && args.stale_paths.is_none() {
+                args.stale_paths = Some(StalePathAction::Report);
+            }
+        }
+        Some(Command::Scan { .. }) if !matches!(args.format, OutputFormat::Text | OutputFormat::Json) => {
+            anyhow::bail!("`scan` supports --format text or json");
+        }
+        Some(Command::Orphans { .. }) if !matches!(args.format, OutputFormat::Text | OutputFormat::Json) => {
+            anyhow::bail!("`orphans` supports --format text or json");
+        }
+        _ => {}
+    }
+    if !args.only.is_empty() && !args.clean_deps && !args.remove_deps {
+        anyhow::bail!("--only requires --clean-deps, --remove-deps or the `deps` subcommand");
+    }
+    if args.verify.is_some() && !args.remove_deps && !args.features && !matches!(args.command, Some(Command::Apply { .. })) {
+        anyhow::bail!("--verify requires --remove-deps, --features or `deps --remove`");
+    }
+    if args.confirm && !args.remove_deps && !matches!(args.command, Some(Command::Apply { .. }) | Some(Command::Orphans { clean: true })) {
+        anyhow::bail!("--confirm requires --remove-deps, `deps --remove` or `orphans --clean`");
+    }
+    if args.apply && !args.dev_deps && !args.hoist_deps {
+        anyhow::bail!("--apply requires --dev-deps or --hoist-deps");
+    }
+    if args.prune_lock && !args.remove_deps && !args.apply && args.stale_paths.is_none_or(|action| action == StalePathAction::Report) && !matches!(args.command, Some(Command::Apply { .. })) {
+        anyhow::bail!("--prune-lock requires --remove-deps, --apply or --stale-paths remove|comment");
+    }
+    if args.confirm && args.apply {
+        anyhow::bail!("--confirm only applies to --remove-deps; use --dry-run to review the changes --apply makes");
+    }
+    if args.confirm && args.stale_paths.is_some_and(|action| action != StalePathAction::Report) {
+        anyhow::bail!("--confirm only applies to --remove-deps; use --dry-run to review --stale-paths fixes");
+    }
+    if args.measure_only && matches!(args.command, Some(Command::Apply { .. })) {
+        anyhow::bail!("--measure-only can't be used with `apply`, which removes the plan's dependencies; use --dry-run to preview it");
+    }
+    if args.measure_only && matches!(args.command, Some(Command::Daemon(_))) {
+        anyhow::bail!("--measure-only can't be used with `daemon`, which cleans targets; use --dry-run to only log what it would free");
+    }
+    if args.measure_only && matches!(args.command, Some(Command::Orphans { clean: true })) {
+        anyhow::bail!("--measure-only can't be used with `orphans --clean`; use `orphans` to list them or --dry-run to preview the removal");
+    }
+    if args.dep_jobs == 0 {
+        anyhow::bail!("--dep-jobs must be at least 1");
+    }
+    let clean_targets = !matches!(args.command, Some(Command::Deps { .. }));
+
+    // Every parallel phase (discovery, sizing, cleaning) runs on this pool
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(args.jobs.max(1))
+        .build_global()
+        .context("Failed to configure the worker thread pool")?;
+    
+    let verbose = args.verbose > 0 || args.explain;
+    let format = if args.json { OutputFormat::Json } else { args.format };
+    // Live human-readable output; `--silent-on-success` defers everything to the end,
+    // and a plan printed to stdout must not be mixed with it
+    let human = format == OutputFormat::Text
+        && !args.silent_on_success
+        && !matches!(args.command, Some(Command::Plan { output: None }));
+    if args.summary_file.is_some() && format == OutputFormat::Text {
+        anyhow::bail!("--summary-file requires a machine-readable --format (json, junit, jsonl or csv)");
+    }
+    if args.strict_json && !matches!(format, OutputFormat::Json | OutputFormat::Jsonl) {
+        anyhow::bail!("--strict-json requires --format json or jsonl");
+    }
+    if args.confirm && args.projects_from.as_deref() == Some("-") {
+        anyhow::bail!("--confirm reads answers from stdin, so the project list can't come from it too");
+    }
+    if args.confirm && !human {
+        anyhow::bail!("--confirm asks on the terminal and needs --format text without --silent-on-success");
+    }
+    if args.confirm && matches!(args.command, Some(Command::Daemon(_))) {
+        anyhow::bail!("--confirm can't ask anything while running as a daemon");
     }
 
-    let projects = find_cargo_projects(&root, &args.exclude_patterns)
-        .context("Failed to find Cargo projects")?;
+    let plan = match args.command {
+        Some(Command::Apply { plan: ref path }) => Some(CleanupPlan::load(path)?),
+        _ => None,
+    };
+    let roots = match plan {
+        Some(ref plan) => vec![plan.root.clone()],
+        None => dedup_roots(
+            args.directories
+                .iter()
+                .map(|dir| dir.canonicalize().with_context(|| format!("Failed to canonicalize path: {:?}", dir)))
+                .collect::<Result<_>>()?,
+        ),
+    };
+    if roots.len() > 1 && matches!(args.command, Some(Command::Plan { .. })) {
+        anyhow::bail!("A plan covers a single directory; run `plan` once per directory");
+    }
+    if let Some(Command::Schedule { ref action }) = args.command {
+        // The job only gets the directories and the options after `--`
+        let dropped = explicit_flags(&matches, &["directories", "dry_run"]);
+        if !dropped.is_empty() {
+            anyhow::bail!(
+                "{} would not apply to the scheduled job; pass options for it after `--`, e.g. `schedule install -- {}`",
+                dropped.join(", "),
+                dropped[0]
+            );
+        }
+        return run_schedule(action, &roots, args.dry_run);
+    }
 
-    if projects.is_empty() {
-        if !args.json {
-            println!("{} No Cargo projects found", "[WARNING]".yellow().bold());
+    if human {
+        for root in &roots {
+            println!("{} Starting cargo clean from: {:?}", "[INFO]".blue().bold(), root);
         }
-        return Ok(());
+        if plan.is_some() {
+            println!("{} Applying cleanup plan...", "[INFO]".blue().bold());
+        } else if !matches!(args.command, Some(Command::Daemon(_))) {
+            println!("{} Searching for Cargo projects...", "[INFO]".blue().bold());
+        }
+    }
+
+    // Command-line flags win over the config file, whose selected profile wins over its base settings
+    let profile_name = args
+        .profile_name
+        .clone()
+        .or_else(|| std::env::var(PROFILE_ENV_VAR).ok().filter(|name| !name.is_empty()));
+    let config = Config::load()?.with_profile(profile_name.as_deref())?;
+    let exclude_patterns = if args.exclude_patterns.is_empty() {
+        config.exclude.clone()
+    } else {
+        args.exclude_patterns.clone()
+    };
+    for pattern in &args.include_patterns {
+        glob::Pattern::new(pattern).with_context(|| format!("Invalid --include pattern: {:?}", pattern))?;
     }
 
-    // Filter by minimum size if specified
-    let min_size_bytes = if let Some(ref min_size_str) = args.min_size {
+    // Validate the minimum size before doing any work
+    let min_size_bytes = if let Some(min_size_str) = args.min_size.as_ref().or(config.min_size.as_ref()) {
         Some(parse_size(min_size_str)
             .with_context(|| format!("Invalid --min-size value: '{}'. Expected format like '100MB' or '1GB'", min_size_str))?)
     } else {
         None
     };
 
-    let projects: Vec<_> = if let Some(min_bytes) = min_size_bytes {
-        projects
-            .into_iter()
-            .filter(|project| {
-                let target_dir = project.path.join("target");
-                if target_dir.exists() {
-                    get_directory_size(&target_dir).unwrap_or(0) >= min_bytes
-                } else {
-                    false
+    // Configured artifact patterns, plus any given on the command line
+    let artifact_patterns: Vec<String> = config
+        .artifact_patterns
+        .patterns
+        .iter()
+        .chain(&args.artifact_patterns)
+        .cloned()
+        .collect();
+    for pattern in &artifact_patterns {
+        glob::Pattern::new(pattern).with_context(|| format!("Invalid artifact pattern: {:?}", pattern))?;
+    }
+    if config.artifact_patterns.replace && artifact_patterns.is_empty() {
+        anyhow::bail!("[artifact_patterns] replace = true requires at least one pattern");
+    }
+
+    let strategy = match args.strategy {
+        _ if args.safe => CleanStrategy::Safe(ArtifactPatterns {
+            patterns: artifact_patterns.clone(),
+            replace_builtin: config.artifact_patterns.replace,
+        }),
+        StrategyKind::CargoClean => CleanStrategy::CargoClean {
+            fallback_delete: !args.no_fallback_delete,
+        },
+        StrategyKind::RemoveTarget => CleanStrategy::RemoveTarget,
+        StrategyKind::TrashTarget => CleanStrategy::TrashTarget,
+        StrategyKind::SelectiveGlob => {
+            let globs: Vec<String> = args.globs.iter().chain(&artifact_patterns).cloned().collect();
+            if globs.is_empty() {
+                anyhow::bail!("--strategy selective-glob requires at least one --glob or artifact pattern");
+            }
+            compile_globs(&globs)?;
+            CleanStrategy::SelectiveGlob(globs)
+        }
+    };
+    let sweep_older_than = args.sweep_older_than.as_deref().map(parse_duration).transpose().with_context(|| {
+        format!("Invalid --sweep-older-than value: {:?}", args.sweep_older_than.as_deref().unwrap_or_default())
+    })?;
+    // Bare --wait waits as long as the build takes
+    let wait_for_builds = match args.wait {
+        Some(Some(ref duration)) => {
+            Some(parse_duration(duration).with_context(|| format!("Invalid --wait value: {:?}", duration))?)
+        }
+        Some(None) => Some(u64::MAX),
+        None => None,
+    };
+    // What gets cleaned; a plan is applied the way it was made
+    let selection = match plan {
+        Some(ref plan) => CleanOptions {
+            strategy: plan.strategy.clone(),
+            triples: plan.triples.clone(),
+            non_host_only: plan.non_host_only,
+            profiles: plan.profiles.clone(),
+            doc_only: plan.doc_only,
+            incremental_only: plan.incremental_only,
+            dep_info: plan.dep_info,
+            keep_binaries: plan.keep_binaries,
+            sweep_older_than: plan.sweep_older_than,
+            coverage: plan.coverage,
+            include_corpus: plan.include_corpus,
+            clean_criterion: plan.clean_criterion,
+            framework_outputs: plan.framework_outputs,
+            ..Default::default()
+        },
+        None => CleanOptions {
+            strategy,
+            triples: args.triples.clone(),
+            non_host_only: args.non_host_only,
+            profiles: args.profile.clone(),
+            doc_only: args.doc_only,
+            incremental_only: args.incremental_only,
+            dep_info: args.dep_info,
+            keep_binaries: args.keep_binaries,
+            sweep_older_than,
+            coverage: args.coverage,
+            include_corpus: args.include_corpus,
+            clean_criterion: args.clean_criterion,
+            framework_outputs: !args.no_framework_outputs,
+            ..Default::default()
+        },
+    };
+    for triple in &selection.triples {
+        if triple.is_empty() || triple.contains(['/', '\\']) || triple == ".." {
+            anyhow::bail!("Invalid target triple in --triples: {:?}", triple);
+        }
+    }
+    for profile in &selection.profiles {
+        if profile.is_empty() || profile.contains(['/', '\\']) || profile.starts_with('.') {
+            anyhow::bail!("Invalid profile name in --profile: {:?}", profile);
+        }
+    }
+
+    let deps_exclude = args
+        .deps_exclude
+        .iter()
+        .map(|p| glob::Pattern::new(p).with_context(|| format!("Invalid --deps-exclude pattern: {:?}", p)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let tag_set = config.tag_set()?;
+
+    let mut timings = PhaseTimings::default();
+    let discovery_options = DiscoveryOptions {
+        exclude_patterns,
+        include_patterns: args.include_patterns.clone(),
+        follow_root_symlinks: args.follow_root_symlinks,
+        follow_symlinks: args.follow_symlinks,
+        markers: args.markers.clone(),
+        one_filesystem: args.one_filesystem,
+        static_workspaces: args.measure_only,
+        include_vendored: args.include_vendored,
+        no_ignore: args.no_ignore,
+        hidden: args.hidden,
+        max_depth: args.max_depth,
+        prune_projects: args.prune_projects,
+        threads: args.scan_jobs.unwrap_or(args.jobs),
+        progress: scan_progress(
+            human && !verbose && plan.is_none() && args.projects_from.is_none() && !matches!(args.command, Some(Command::Daemon(_))),
+        ),
+        visited: None,
+    };
+    if let Some(Command::Orphans { clean }) = args.command {
+        let to_trash = selection.strategy == CleanStrategy::TrashTarget;
+        return run_orphans(&args, &roots, &discovery_options, clean, to_trash, format, human);
+    }
+    if let Some(Command::Daemon(ref daemon_args)) = args.command {
+        let clean_options = CleanOptions {
+            dry_run: args.dry_run,
+            size_options: SizeOptions { on_disk: args.on_disk, by_atime: args.by_atime, one_filesystem: args.one_filesystem },
+            force: args.force,
+            wait_for_builds,
+            ..selection
+        };
+        return Daemon::new(&args, daemon_args, &roots, &discovery_options, &clean_options, min_size_bytes)?.run();
+    }
+    let warnings = WarningCollector::new();
+    let Selection { discovered, projects, skipped, dirty } = match plan {
+        // An approved plan replaces discovery, but only if its projects still look as planned
+        Some(ref plan) => {
+            let phase_start = Instant::now();
+            let (projects, drifted) = plan.check();
+            timings.sizing_ms = phase_start.elapsed().as_millis() as u64;
+            if !drifted.is_empty() {
+                if !args.force {
+                    anyhow::bail!(
+                        "Plan no longer matches {} project(s) (use --force to apply it anyway):\n  {}",
+                        drifted.len(),
+                        drifted.join("\n  ")
+                    );
                 }
-            })
-            .collect()
-    } else {
-        projects
+                for drift in drifted {
+                    warnings.push(None, format!("Applying despite drift (--force): {}", drift));
+                }
+            }
+            Selection { discovered: projects.len(), projects, ..Default::default() }
+        }
+        None => select_projects(&args, &roots, &discovery_options, min_size_bytes, &warnings, human, &mut timings)?,
+    };
+    let size_options = match plan {
+        Some(ref plan) => plan.size_options(),
+        None => SizeOptions { on_disk: args.on_disk, by_atime: args.by_atime, one_filesystem: args.one_filesystem },
     };
+    if args.physical {
+        for root in &roots {
+            if let Some(caveat) = physical_size_caveat(root) {
+                warnings.push(None, format!("--physical: {}", caveat));
+            }
+        }
+    }
 
     if projects.is_empty() {
-        if !args.json {
-            if min_size_bytes.is_some() {
+        if human {
+            for project in &skipped {
+                println!(
+                    "{} Skipped {} ({})",
+                    "[WARNING]".yellow().bold(),
+                    project.path,
+                    project.reason
+                );
+            }
+            for project in &dirty {
+                println!("{} Skipped {} ({})", "[INFO]".blue().bold(), project.path, project.reason);
+            }
+            if discovered == 0 {
+                println!("{} No Cargo projects found", "[WARNING]".yellow().bold());
+            } else if args.depends_on.is_some() {
+                println!("{} No projects found that depend on the requested crate", "[INFO]".blue().bold());
+            } else if min_size_bytes.is_some() {
                 println!("{} No projects found above the minimum size threshold", "[INFO]".blue().bold());
+            } else if args.older_than.is_some() || args.newer_than.is_some() {
+                println!("{} No projects found within the requested artifact age", "[INFO]".blue().bold());
+            } else if args.git_idle.is_some() {
+                println!("{} No projects found with a git repository idle that long", "[INFO]".blue().bold());
+            } else if !dirty.is_empty() && skipped.is_empty() {
+                println!("{} Every discovered project has uncommitted changes", "[INFO]".blue().bold());
             } else {
-                println!("{} No Cargo projects found", "[WARNING]".yellow().bold());
+                println!("{} All discovered projects were skipped", "[INFO]".blue().bold());
+            }
+        }
+        if args.bitflag_exit {
+            std::process::exit(EXIT_BIT_NOTHING_TO_CLEAN);
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Scan { sort, disk_usage }) = args.command {
+        let mut scanned: Vec<ScannedProject> = projects
+            .iter()
+            .map(|(project, stats)| ScannedProject {
+                disk_usage: disk_usage.then_some(stats.disk_usage),
+                ..ScannedProject::new(project, stats)
+            })
+            .collect();
+        match sort {
+            ScanSort::Size => scanned.sort_by(|a, b| b.target_size.cmp(&a.target_size).then_with(|| a.path.cmp(&b.path))),
+            ScanSort::Path => scanned.sort_by(|a, b| a.path.cmp(&b.path)),
+            // Never-built targets have no age and sort last
+            ScanSort::Age => scanned.sort_by_key(|p| (p.newest_artifact.is_none(), p.newest_artifact)),
+        }
+        let skipped: Vec<_> = skipped.iter().chain(&dirty).cloned().collect();
+        if format == OutputFormat::Json {
+            println!("{}", scan_to_json(&scanned, &skipped)?);
+        } else {
+            write_scan(&mut std::io::stdout().lock(), &scanned, &skipped)?;
+        }
+        for warning in warnings.into_warnings() {
+            match warning.path {
+                Some(path) => eprintln!("{} {}: {}", "[WARNING]".yellow().bold(), path, warning.message),
+                None => eprintln!("{} {}", "[WARNING]".yellow().bold(), warning.message),
             }
         }
         return Ok(());
     }
 
-    if !args.json {
+    if human {
         println!("{} Found {} project(s)", "[INFO]".blue().bold(), projects.len());
-        if args.dry_run {
+        if args.measure_only {
+            println!("{} MEASURE ONLY - discovery and sizing only, no subprocesses or changes", "[INFO]".yellow().bold());
+        } else if args.dry_run {
             println!("{} DRY RUN MODE - no changes will be made", "[INFO]".yellow().bold());
+            // Verbose output reports each project's artifact ages as it goes
+            if !verbose && clean_targets && (args.older_than.is_some() || args.newer_than.is_some()) {
+                let scanned: Vec<ScannedProject> = projects.iter().map(|(project, stats)| ScannedProject::new(project, stats)).collect();
+                write_would_clean(&mut std::io::stdout().lock(), &scanned)?;
+            }
         }
         // If --remove-deps is specified, automatically enable --clean-deps
         let clean_deps = args.clean_deps || args.remove_deps;
         if clean_deps {
             println!("{} Dependency cleaning enabled (native detection)", "[INFO]".blue().bold());
             if args.remove_deps {
-                println!("{} Will remove unused dependencies (requires cargo-remove)", "[INFO]".yellow().bold());
+                println!("{} Will remove unused dependencies", "[INFO]".yellow().bold());
+            }
+        }
+        if args.duplicates {
+            println!("{} Duplicate dependency report enabled", "[INFO]".blue().bold());
+        }
+        if args.features {
+            println!("{} Feature flag analysis enabled", "[INFO]".blue().bold());
+        }
+        match args.stale_paths {
+            Some(StalePathAction::Report) => {
+                println!("{} Stale path dependency check enabled", "[INFO]".blue().bold());
+            }
+            Some(StalePathAction::Remove) => {
+                println!("{} Will remove stale path dependencies", "[INFO]".yellow().bold());
+            }
+            Some(StalePathAction::Comment) => {
+                println!("{} Will comment out stale path dependencies", "[INFO]".yellow().bold());
+            }
+            None => {}
+        }
+        if args.outdated {
+            println!("{} Outdated dependency report enabled (cargo outdated)", "[INFO]".blue().bold());
+        }
+        if args.dev_deps {
+            println!("{} Dev-only dependency check enabled", "[INFO]".blue().bold());
+            if args.apply {
+                println!("{} Will move dev-only dependencies to [dev-dependencies]", "[INFO]".yellow().bold());
+            }
+        }
+        if args.hoist_deps {
+            println!("{} Workspace dependency consolidation enabled", "[INFO]".blue().bold());
+            if args.apply {
+                println!("{} Will hoist shared dependencies into [workspace.dependencies]", "[INFO]".yellow().bold());
             }
         }
+        if args.prune_lock {
+            println!("{} Will prune Cargo.lock with cargo update --workspace after manifest changes", "[INFO]".yellow().bold());
+        }
         println!();
     }
 
-    let (multi, overall_pb) = create_progress_bars(projects.len(), !args.json && !args.verbose);
+    // With --confirm, analysis only previews removals; accepted ones are applied afterwards
+    let deps_options = DepsOptions {
+        dry_run: args.dry_run || args.confirm,
+        check_unused: args.clean_deps || args.remove_deps,
+        remove: args.remove_deps,
+        duplicates: args.duplicates,
+        features: args.features,
+        stale_paths: args.stale_paths,
+        dev_deps: args.dev_deps,
+        hoist: args.hoist_deps,
+        apply: args.apply,
+        outdated: args.outdated,
+        prune_lock: args.prune_lock,
+        verbose,
+        only: args.only.clone(),
+        verify: args.verify,
+        ..Default::default()
+    };
+    // Applying a plan performs only the dependency removals it recorded
+    let deps_options = match plan {
+        Some(ref plan) => {
+            let planned_removals = plan.projects.iter().any(|p| !p.dependency_removals.is_empty());
+            DepsOptions {
+                check_unused: planned_removals,
+                remove: planned_removals,
+                duplicates: false,
+                features: false,
+                stale_paths: None,
+                dev_deps: false,
+                hoist: false,
+                apply: false,
+                outdated: false,
+                ..deps_options
+            }
+        }
+        None => deps_options,
+    };
+    let io_throttle = Arc::new(IoThrottle::new(args.io_jobs.unwrap_or(args.jobs)));
+    let clean_options = CleanOptions {
+        dry_run: args.dry_run,
+        size_options,
+        io_throttle: Some(io_throttle.clone()),
+        explain: args.explain,
+        force: args.force,
+        wait_for_builds,
+        ..selection
+    };
+
+    if let Some(Command::Plan { ref output }) = args.command {
+        // Estimates come from a dry run of the selected strategy, so globs and keep lists count
+        let estimate_options = CleanOptions { dry_run: true, ..clean_options.clone() };
+        let planned: Vec<_> = projects
+            .par_iter()
+            .map(|(project, target_stats)| {
+                let expected_freed_bytes = clean_project(project, Some(target_stats.size), &estimate_options)
+                    .map(|r| r.freed_bytes)
+                    .unwrap_or(target_stats.size);
+                let removals = if args.remove_deps && !matches_relative(&project.path, root_of(&project.path, &roots), &deps_exclude) {
+                    check_unused_dependencies(project)
+                        .map(|unused| unused.into_iter().filter(|dep| deps_options.includes(dep)).collect())
+                        .unwrap_or_else(|e| {
+                            warnings.push(Some(&project.path), format!("Failed to check dependencies: {}", e));
+                            Vec::new()
+                        })
+                } else {
+                    Vec::new()
+                };
+                PlannedProject::new(project, target_stats, expected_freed_bytes, removals)
+            })
+            .collect();
+        let cleanup_plan = CleanupPlan::new(&roots[0], &clean_options, planned);
+        let json = serde_json::to_string_pretty(&cleanup_plan)?;
+        match output {
+            Some(path) => {
+                std::fs::write(path, json + "\n").with_context(|| format!("Failed to write plan file: {:?}", path))?;
+                if human {
+                    let expected: u64 = cleanup_plan.projects.iter().map(|p| p.expected_freed_bytes).sum();
+                    println!(
+                        "{} Wrote plan for {} project(s), expected to free {}, to {:?}",
+                        "[SUCCESS]".green().bold(),
+                        cleanup_plan.projects.len(),
+                        format_bytes(expected),
+                        path
+                    );
+                }
+            }
+            None => println!("{}", json),
+        }
+        for warning in warnings.into_warnings() {
+            match warning.path {
+                Some(path) => eprintln!("{} {}: {}", "[WARNING]".yellow().bold(), path, warning.message),
+                None => eprintln!("{} {}", "[WARNING]".yellow().bold(), warning.message),
+            }
+        }
+        return Ok(());
+    }
+
+    // Free space on each filesystem holding a target, sampled at the parent since
+    // the target itself may be gone afterwards
+    let free_space_before: Option<Vec<(std::path::PathBuf, u64)>> = if args.verify_reclaimed && (args.dry_run || args.measure_only) {
+        warnings.push(None, "--verify-reclaimed has no effect when nothing is removed");
+        None
+    } else if args.verify_reclaimed {
+        let sample_paths = one_path_per_device(projects.iter().map(|(project, _)| {
+            let target_dir = project.target_dir();
+            target_dir.parent().map(Path::to_path_buf).unwrap_or(target_dir)
+        }));
+        let samples: Vec<_> = sample_paths
+            .into_iter()
+            .filter_map(|path| free_space(&path).map(|free| (path, free)))
+            .collect();
+        if samples.is_empty() {
+            warnings.push(None, "--verify-reclaimed: free space could not be queried on this platform");
+        }
+        Some(samples)
+    } else {
+        None
+    };
+
+    // Dependency analysis runs first, in its own smaller pool, so heavy analyses
+    // neither compete with cleans for threads nor run --jobs wide
+    let phase_start = Instant::now();
+    let analyze = |project: &Project| match plan {
+        Some(ref plan) => plan
+            .dependency_removals(&project.path)
+            .map(|planned| remove_planned_dependencies(project, planned, &deps_options)),
+        // --remove-deps automatically enables dependency checking
+        None if deps_options.any_analysis()
+            && !matches_relative(&project.path, root_of(&project.path, &roots), &deps_exclude) =>
+        {
+            Some(clean_dependencies(project, &deps_options))
+        }
+        None => None,
+    };
+    let deps_results: Vec<Option<Result<DependencyCleanResult>>> = if deps_options.any_analysis() {
+        let pool = dependency_pool(args.dep_jobs)?;
+        let analysis_progress = phase_progress(projects.len(), "projects analyzed for dependencies", human && !verbose);
+        let results = pool.install(|| {
+            projects
+                .par_iter()
+                .with_min_len(1)
+                .map(|(project, _)| {
+                    let result = analyze(project);
+                    if let Some(ref pb) = analysis_progress {
+                        pb.inc(1);
+                    }
+                    result
+                })
+                .collect()
+        });
+        if let Some(pb) = analysis_progress {
+            pb.finish_and_clear();
+        }
+        results
+    } else {
+        projects.iter().map(|_| None).collect()
+    };
+    let deps_results = if args.confirm {
+        let apply_options = DepsOptions { dry_run: false, ..deps_options.clone() };
+        deps_results
+            .into_iter()
+            .zip(&projects)
+            .map(|(result, (project, _))| match result {
+                Some(Ok(preview)) if preview.diff.is_some() => {
+                    print_manifest_diff(preview.diff.as_deref().unwrap_or_default());
+                    if confirm(&format!("Apply these changes to {}?", project.path.join("Cargo.toml").display())) {
+                        Some(remove_planned_dependencies(project, &preview.unused_deps, &apply_options).map(|applied| {
+                            DependencyCleanResult { duplicates: preview.duplicates, features: preview.features, stale_paths: preview.stale_paths, dev_only: preview.dev_only, outdated: preview.outdated, hoistable: preview.hoistable, ..applied }
+                        }))
+                    } else {
+                        Some(Ok(DependencyCleanResult { diff: None, ..preview }))
+                    }
+                }
+                other => other,
+            })
+            .collect()
+    } else {
+        deps_results
+    };
+    timings.dependency_ms = phase_start.elapsed().as_millis() as u64;
+
+    let progress = ProgressDisplay::new(
+        projects.len(),
+        args.max_visible_bars,
+        human && !verbose,
+    );
+
+    // Streaming formats write each result as it completes instead of buffering them
+    let stream = if format.is_streaming() {
+        let writer: Box<dyn std::io::Write + Send> = match args.summary_file {
+            Some(ref path) => Box::new(
+                std::fs::File::create(path)
+                    .with_context(|| format!("Failed to create summary file: {:?}", path))?,
+            ),
+            None => Box::new(std::io::stdout()),
+        };
+        Some(ResultStream::new(format, writer)?)
+    } else {
+        None
+    };
+    // The history database needs every result, even when streaming
+    let retain_results = stream.is_none() || args.db.is_some();
 
-    let results: Vec<CleanResult> = projects
+    let phase_start = Instant::now();
+    let (mut summary, dep_summary) = projects
         .par_iter()
+        .zip(deps_results.into_par_iter())
         .with_min_len(1)
-        .map(|project| {
+        .map(|((project, target_stats), deps_result)| {
             // Create individual progress bar for this project
-            let project_pb = if let Some(ref multi) = multi {
-                Some(create_project_progress_bar(multi, &project.path))
-            } else {
-                None
-            };
+            let project_pb = progress
+                .as_ref()
+                .and_then(|progress| progress.start_project(&project.path));
+
+            if verbose && human && clean_targets {
+                println!("{} Cleaning: {:?} ({})", "[INFO]".blue().bold(), project.path, project.kind());
+            }
 
-            if args.verbose && !args.json {
-                println!("{} Cleaning: {:?}", "[INFO]".blue().bold(), project.path);
+            if args.dry_run && verbose && human && clean_targets {
+                let action = match clean_options.strategy {
+                    _ if clean_options.coverage => format!("Would remove coverage artifacts in {:?}", project.path),
+                    _ if clean_options.incremental_only => {
+                        format!("Would remove incremental compilation caches in {:?}", project.target_dir())
+                    }
+                    _ if clean_options.doc_only => format!("Would clean documentation in {:?}", project.target_dir()),
+                    _ if !clean_options.profiles.is_empty() => format!(
+                        "Would clean the {} profile outputs in {:?}",
+                        clean_options.profiles.join(", "),
+                        project.target_dir()
+                    ),
+                    _ if clean_options.non_host_only => {
+                        format!("Would clean every cross-compilation output in {:?}", project.target_dir())
+                    }
+                    _ if !clean_options.triples.is_empty() => format!(
+                        "Would clean {} outputs in {:?}",
+                        clean_options.triples.join(", "),
+                        project.target_dir()
+                    ),
+                    CleanStrategy::Safe(_) => format!("Would remove recognized cargo artifacts in {:?}", project.target_dir()),
+                    _ if !project.overrides.keep.is_empty() => format!(
+                        "Would remove files except {} in {:?}",
+                        project.overrides.keep.join(", "),
+                        project.target_dir()
+                    ),
+                    _ if clean_options.sweep_older_than.is_some() => format!(
+                        "Would remove files older than {} in {:?}",
+                        format_duration(clean_options.sweep_older_than.unwrap_or_default()),
+                        project.target_dir()
+                    ),
+                    _ if clean_options.keep_binaries => {
+                        format!("Would remove files except final binaries in {:?}", project.target_dir())
+                    }
+                    CleanStrategy::CargoClean { .. } if !project.marker_only => {
+                        format!("Would run: {}", format_command("cargo", &cargo_clean_args(project, CleanScope::default())))
+                    }
+                    CleanStrategy::TrashTarget => format!("Would move to trash: {:?}", project.target_dir()),
+                    CleanStrategy::SelectiveGlob(ref patterns) => format!(
+                        "Would remove files matching {} in {:?}",
+                        patterns.join(", "),
+                        project.target_dir()
+                    ),
+                    _ => format!("Would remove: {:?}", project.target_dir()),
+                };
+                println!("{} {}", "[DRY RUN]".yellow().bold(), action);
+                if clean_options.framework_outputs && is_full_clean(&clean_options) {
+                    for output in project.framework_outputs() {
+                        println!("{} Would remove {} output: {:?}", "[DRY RUN]".yellow().bold(), output.tool, output.path);
+                    }
+                }
+                let criterion_dir = project.target_dir().join(CRITERION_DIR);
+                if !clean_options.clean_criterion && criterion_dir.is_dir() {
+                    println!("{} Would keep Criterion benchmark data: {:?}", "[DRY RUN]".yellow().bold(), criterion_dir);
+                }
+                if project.fuzz && clean_options.include_corpus {
+                    let dirs: Vec<_> = FUZZ_CORPUS_DIRS.iter().map(|dir| project.path.join(dir)).filter(|dir| dir.is_dir()).collect();
+                    if !dirs.is_empty() {
+                        println!("{} Would remove fuzz corpora: {:?}", "[DRY RUN]".yellow().bold(), dirs);
+                    }
+                }
             }
 
+            // Cross-compilation outputs, reported before cleaning removes them
+            let cross_targets = triple_sizes(&project.target_dir(), &size_options);
+
+            // Allocated size before cleaning, for --physical
+            let physical_before = args.physical.then(|| {
+                get_directory_size(&project.target_dir(), &SizeOptions { on_disk: true, ..size_options }).unwrap_or(0)
+            });
+
             // Clean target directory
-            let result = clean_project(project, args.dry_run, args.verbose);
+            let result = if args.measure_only {
+                Ok(CleanResult::cleaned(project, target_stats.size))
+            } else if !clean_targets {
+                Ok(CleanResult::cleaned(project, 0))
+            } else {
+                clean_project(project, Some(target_stats.size), &clean_options)
+            };
 
-            // Clean unused dependencies if requested (--clean-deps or --remove-deps)
-            // Note: --remove-deps automatically enables dependency checking
-            if args.clean_deps || args.remove_deps {
-                let deps_result = clean_dependencies(project, args.dry_run, args.remove_deps, args.verbose);
+            // Report the dependency analysis done before cleaning
+            let deps_report = if let Some(deps_result) = deps_result {
                 match deps_result {
                     Ok(deps_clean) => {
-                        if !deps_clean.unused_deps.is_empty() {
-                            if !args.json {
-                                // Always show unused dependencies, not just in verbose mode
+                        if !deps_clean.duplicates.is_empty() && human {
+                            print_duplicates(&project.path, &deps_clean.duplicates);
+                        }
+                        if !deps_clean.features.is_empty() && human {
+                            print_feature_findings(&project.path, &deps_clean.features);
+                        }
+                        if !deps_clean.stale_paths.is_empty() && human {
+                            print_stale_paths(&project.path, &deps_clean.stale_paths);
+                            if deps_clean.stale_paths_fixed > 0 {
+                                let verb = match deps_options.stale_paths {
+                                    Some(StalePathAction::Comment) => "Commented out",
+                                    _ => "Removed",
+                                };
                                 println!(
-                                    "{} Found {} unused dependency(ies) in {}:",
-                                    "[INFO]".blue().bold(),
-                                    deps_clean.unused_deps.len(),
+                                    "{} {} {} stale path dependency(ies)",
+                                    "[SUCCESS]".green().bold(),
+                                    verb,
+                                    deps_clean.stale_paths_fixed
+                                );
+                            }
+                        }
+                        if !deps_clean.hoistable.is_empty() && human {
+                            print_hoistable(&project.path, &deps_clean.hoistable);
+                            if deps_clean.hoisted > 0 {
+                                println!(
+                                    "{} Rewrote {} member dependency(ies) to workspace = true",
+                                    "[SUCCESS]".green().bold(),
+                                    deps_clean.hoisted
+                                );
+                            }
+                        }
+                        if !deps_clean.outdated.is_empty() && human {
+                            print_outdated(&project.path, &deps_clean.outdated);
+                        }
+                        if !deps_clean.dev_only.is_empty() && human {
+                            print_dev_only(&project.path, &deps_clean.dev_only);
+                            if deps_clean.moved_to_dev > 0 {
+                                println!(
+                                    "{} Moved {} dependency(ies) to dev-dependencies",
+                                    "[SUCCESS]".green().bold(),
+                                    deps_clean.moved_to_dev
+                                );
+                            }
+                        }
+                        if !deps_clean.unused_deps.is_empty() {
+                            if human {
+                                // Always show unused dependencies, not just in verbose mode
+                                println!(
+                                    "{} Found {} unused dependency(ies) in {}:",
+                                    "[INFO]".blue().bold(),
+                                    deps_clean.unused_deps.len(),
                                     project.path.display()
                                 );
                                 for dep in &deps_clean.unused_deps {
-                                    println!("  {} {} ({})", "•".yellow(), dep.name.bright_yellow(), dep.location);
+                                    println!("  {} {} ({})", "•".yellow(), dep.name.bright_yellow(), dep.origin());
+                                }
+                                if !deps_clean.rolled_back.is_empty() {
+                                    let names: Vec<&str> = deps_clean.rolled_back.iter().map(|dep| dep.name.as_str()).collect();
+                                    let message = format!("Rolled back removal of {} (cargo check failed)", names.join(", "));
+                                    println!("{} {}", "[WARNING]".yellow().bold(), message);
+                                    warnings.push(Some(&project.path), message);
                                 }
                                 if deps_clean.removed_count > 0 {
                                     println!(
@@ -191,7 +1351,7 @@ fn main() -> Result<()> {
                                         "[SUCCESS]".green().bold(),
                                         deps_clean.removed_count
                                     );
-                                } else if args.remove_deps && !args.dry_run {
+                                } else if args.remove_deps && !args.dry_run && deps_clean.rolled_back.is_empty() {
                                     // Check if there was an error
                                     if let Some(ref error) = deps_clean.error {
                                         println!(
@@ -199,11 +1359,15 @@ fn main() -> Result<()> {
                                             "[ERROR]".red().bold(),
                                             error
                                         );
-                                    } else {
+                                    } else if !args.confirm {
                                         println!(
-                                            "{} Could not remove dependencies (install cargo-remove: cargo install cargo-edit)",
+                                            "{} Could not remove dependencies (not found in Cargo.toml)",
                                             "[WARNING]".yellow().bold()
                                         );
+                                        warnings.push(
+                                            Some(&project.path),
+                                            "Could not remove dependencies (not found in Cargo.toml)",
+                                        );
                                     }
                                 } else if args.dry_run {
                                     println!(
@@ -211,11 +1375,25 @@ fn main() -> Result<()> {
                                         "[INFO]".blue().bold(),
                                         deps_clean.unused_deps.len()
                                     );
+                                    if let Some(ref diff) = deps_clean.diff {
+                                        print_manifest_diff(diff);
+                                    }
+                                    if args.remove_deps && verbose {
+                                        for dep in &deps_clean.unused_deps {
+                                            println!(
+                                                "{} Would remove: {} from {} in {:?}",
+                                                "[DRY RUN]".yellow().bold(),
+                                                dep.name,
+                                                dep.location,
+                                                dep.manifest(&project.path)
+                                            );
+                                        }
+                                    }
                                 }
                             }
-                        } else if !args.json {
+                        } else if human && deps_options.check_unused {
                             // Show confirmation that check was performed (only in verbose mode to avoid clutter)
-                            if args.verbose {
+                            if verbose {
                                 println!(
                                     "{} No unused dependencies found in {}",
                                     "[INFO]".blue().bold(),
@@ -223,11 +1401,18 @@ fn main() -> Result<()> {
                                 );
                             }
                         }
-                        
+                        if deps_clean.lock_entries_pruned > 0 && human {
+                            println!(
+                                "{} Pruned {} Cargo.lock entry(ies)",
+                                "[SUCCESS]".green().bold(),
+                                deps_clean.lock_entries_pruned
+                            );
+                        }
+
                         // Check if there was an error even when no unused deps were found
-                        // (e.g., cargo-remove not available when --remove-deps was specified)
+                        // (e.g., the manifest could not be rewritten when --remove-deps was specified)
                         if let Some(ref error) = deps_clean.error {
-                            if !args.json {
+                            if human {
                                 println!(
                                     "{} Error during dependency removal in {:?}: {}",
                                     "[ERROR]".red().bold(),
@@ -236,9 +1421,10 @@ fn main() -> Result<()> {
                                 );
                             }
                         }
+                        Some(deps_clean)
                     }
                     Err(e) => {
-                        if !args.json {
+                        if human {
                             println!(
                                 "{} Failed to check dependencies in {:?}: {}",
                                 "[WARNING]".yellow().bold(),
@@ -246,68 +1432,220 @@ fn main() -> Result<()> {
                                 e
                             );
                         }
+                        warnings.push(Some(&project.path), format!("Failed to check dependencies: {}", e));
+                        Some(DependencyCleanResult::failed(project, e.to_string()))
                     }
                 }
-            }
+            } else {
+                None
+            };
 
-            // Finish individual progress bar
-            if let Some(ref pb) = project_pb {
-                let project_name = project.path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|s| s.to_string())
-                    .unwrap_or_else(|| project.path.to_string_lossy().to_string());
-                pb.finish_with_message(format!("✓ {}", project_name));
+            // Finish individual progress bar and update overall progress
+            if let Some(ref progress) = progress {
+                progress.finish_project(project_pb);
             }
 
-            // Update overall progress
-            if let Some(ref overall) = overall_pb {
-                overall.inc(1);
-            }
+            let explanation = if args.explain {
+                explain_selection(&args, project, target_stats, min_size_bytes, plan.is_some())
+            } else {
+                Vec::new()
+            };
 
             match result {
-                Ok(r) => {
-                    if args.verbose && !args.json {
-                        print_verbose_cleaned(&r);
+                Ok(mut r) => {
+                    r.explain = explanation.into_iter().chain(r.explain).collect();
+                    r.cross_targets = cross_targets;
+                    r.oldest_artifact = target_stats.oldest_mtime;
+                    r.newest_artifact = target_stats.newest_mtime;
+                    r.physical_freed_bytes = physical_before.map(|before| {
+                        if args.dry_run || args.measure_only {
+                            // Nothing was removed; assume the freed share of the target is freed physically too
+                            match target_stats.size {
+                                0 => 0,
+                                size => (before as u128 * r.freed_bytes.min(size) as u128 / size as u128) as u64,
+                            }
+                        } else {
+                            let after = get_directory_size(&project.target_dir(), &SizeOptions { on_disk: true, ..size_options }).unwrap_or(0);
+                            before.saturating_sub(after)
+                        }
+                    });
+                    if verbose {
+                        for warning in r.cargo_warnings.drain(..) {
+                            warnings.push(Some(&project.path), format!("cargo clean: {}", warning));
+                        }
+                    }
+                    if !r.tracked_files.is_empty() {
+                        warnings.push(
+                            Some(&project.path),
+                            format!(
+                                "Kept {} git-tracked file(s) in target, removing only untracked files: {}",
+                                r.tracked_files.len(),
+                                r.tracked_files.join(", ")
+                            ),
+                        );
+                    }
+                    if !r.unrecognized_files.is_empty() {
+                        warnings.push(
+                            Some(&project.path),
+                            format!(
+                                "Left {} unrecognized file(s) in target: {}",
+                                r.unrecognized_files.len(),
+                                r.unrecognized_files.join(", ")
+                            ),
+                        );
+                    }
+                    if args.workspace_summary && project.is_workspace && human && clean_targets {
+                        print_workspace_summary(&r, args.hide_empty);
+                    } else if verbose && human && clean_targets {
+                        print_verbose_cleaned(&r, args.hide_empty);
+                        print_explanation(&project.path, &r.explain);
                     }
-                    Ok(r)
+                    (Ok(r), deps_report)
+                }
+                // A project being built is left for the next run rather than failed
+                Err(e) if e.downcast_ref::<BuildInProgress>().is_some() => {
+                    let reason = e.downcast_ref::<BuildInProgress>().map(|busy| busy.reason.clone()).unwrap_or_default();
+                    if human {
+                        println!("{} Skipped {} (build in progress: {})", "[WARNING]".yellow().bold(), project.path.display(), reason);
+                    }
+                    let skipped = SkippedProject {
+                        path: project.path.to_string_lossy().to_string(),
+                        reason: format!("build in progress: {}", reason),
+                    };
+                    (Err(skipped), deps_report)
                 }
                 Err(e) => {
                     let error_msg = e.to_string();
-                    if !args.json {
+                    if human {
                         print_error(&project.path, &error_msg);
+                        print_explanation(&project.path, &explanation);
                     }
-                    Ok(CleanResult {
-                        path: project.path.to_string_lossy().to_string(),
-                        success: false,
-                        freed_bytes: 0,
-                        error: Some(error_msg),
-                    })
+                    if e.downcast_ref::<OwnershipMismatch>().is_some() || e.downcast_ref::<NotACargoTarget>().is_some() {
+                        warnings.push(Some(&project.path), error_msg.clone());
+                    }
+                    (
+                        Ok(CleanResult {
+                            oldest_artifact: target_stats.oldest_mtime,
+                            newest_artifact: target_stats.newest_mtime,
+                            cross_targets,
+                            explain: explanation,
+                            ..CleanResult::failed(project, error_msg)
+                        }),
+                        deps_report,
+                    )
                 }
             }
         })
-        .collect::<Result<Vec<_>>>()?;
+        .fold(
+            || (Summary::default(), DepSummary::default()),
+            |(mut summary, mut dep_summary), (result, deps_report)| {
+                let result = result.map_err(|busy| summary.skipped.push(busy)).ok();
+                // `deps` leaves targets alone, so there is no clean result to report
+                if let (true, Some(mut result)) = (clean_targets, result) {
+                    let root = root_of(Path::new(&result.path), &roots);
+                    if roots.len() > 1 {
+                        result.root = Some(root.to_string_lossy().to_string());
+                    }
+                    if let Some(ref stream) = stream {
+                        if let Err(e) = stream.write_result(&result) {
+                            warnings.push(None, format!("Failed to write result for {}: {}", result.path, e));
+                        }
+                    }
+                    if !tag_set.is_empty() {
+                        summary.record_tags(tag_set.classify(Path::new(&result.path), root), result.freed_bytes);
+                    }
+                    if roots.len() > 1 {
+                        summary.record_root(root, result.freed_bytes);
+                    }
+                    summary.record(result, retain_results);
+                }
+                if let Some(deps_report) = deps_report {
+                    if let Some(ref stream) = stream {
+                        if let Err(e) = stream.write_dep_result(&deps_report) {
+                            warnings.push(None, format!("Failed to write result for {}: {}", deps_report.path, e));
+                        }
+                    }
+                    dep_summary.record(deps_report, retain_results);
+                }
+                (summary, dep_summary)
+            },
+        )
+        .reduce(
+            || (Summary::default(), DepSummary::default()),
+            |(a, dep_a), (b, dep_b)| (a.merge(b), dep_a.merge(dep_b)),
+        );
 
-    if let Some(ref overall) = overall_pb {
-        overall.finish_with_message("All projects completed!");
+    if let Some(ref progress) = progress {
+        progress.finish();
     }
+    timings.cleaning_ms = phase_start.elapsed().as_millis() as u64;
 
-    let cleaned = results.iter().filter(|r| r.success).count();
-    let failed = results.len() - cleaned;
-    let total_freed: u64 = results.iter().map(|r| r.freed_bytes).sum();
+    if let Some(samples) = free_space_before.filter(|samples| !samples.is_empty()) {
+        let filesystems = samples
+            .into_iter()
+            .map(|(path, free_before)| FilesystemReclaim {
+                free_after: free_space(&path).unwrap_or(free_before),
+                path: path.to_string_lossy().to_string(),
+                free_before,
+            })
+            .collect();
+        summary.reclaim_check = Some(ReclaimCheck::new(filesystems, summary.total_freed_bytes));
+    }
 
-    let summary = Summary {
-        total_projects: projects.len(),
-        cleaned,
-        failed,
-        total_freed_bytes: total_freed,
-        results,
-    };
+    let found_unused_deps = dep_summary.total_unused > 0;
+    summary.total_projects = projects.len();
+    summary.skipped = skipped.into_iter().chain(std::mem::take(&mut summary.skipped)).collect();
+    summary.skipped_dirty = dirty;
+    summary.warnings = warnings.into_warnings();
+    summary.phase_timings = timings;
+    summary.deps_only = !clean_targets;
+    if deps_options.any_analysis() {
+        summary.dep_summary = Some(dep_summary);
+    }
+    let failed = summary.failed;
 
-    if args.json {
-        println!("{}", serde_json::to_string_pretty(&summary)?);
+    if let Some(stream) = stream {
+        stream.finish(&summary)?;
     } else {
-        print_summary(&summary);
+        let report = match format {
+            OutputFormat::Json => Some(summary_to_json(&summary)?),
+            OutputFormat::Junit => Some(summary_to_junit(&summary)),
+            _ => None,
+        };
+        match (report, &args.summary_file) {
+            (Some(report), Some(path)) => std::fs::write(path, report + "\n")
+                .with_context(|| format!("Failed to write summary file: {:?}", path))?,
+            (Some(report), None) => println!("{}", report),
+            (None, _) if args.silent_on_success => {
+                write_silent_report(&mut std::io::stderr().lock(), &summary)?;
+            }
+            (None, _) => {
+                print_summary(&summary);
+                if verbose || args.measure_only {
+                    print_phase_timings(&summary.phase_timings);
+                }
+                if args.verbose > 1 {
+                    print_throttle_metrics(&io_throttle.metrics());
+                }
+            }
+        }
+    }
+
+    if let Some(ref db_path) = args.db {
+        if let Err(e) = history::record_run(db_path, &roots, args.dry_run || args.measure_only, &summary) {
+            eprintln!("{} Failed to record run history: {:#}", "[WARNING]".yellow().bold(), e);
+        }
+    }
+
+    if args.notify {
+        if let Err(e) = notify::notify_completion(&summary) {
+            eprintln!("{} Failed to send desktop notification: {}", "[WARNING]".yellow().bold(), e);
+        }
+    }
+
+    if args.bitflag_exit {
+        let code = bitflag_exit_code(failed, found_unused_deps, clean_targets.then_some(summary.total_freed_bytes));
+        std::process::exit(code);
     }
 
     if failed > 0 {
@@ -316,3 +1654,955 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Projects chosen for cleaning, with their measured targets
+#[derive(Default)]
+struct Selection {
+    /// Number of projects found before any filtering
+    discovered: usize,
+    projects: Vec<(Project, DirectoryStats)>,
+    skipped: Vec<SkippedProject>,
+    /// Projects left alone for uncommitted changes (`--skip-dirty`), reported apart from `skipped`
+    dirty: Vec<SkippedProject>,
+}
+
+/// Why `project` was selected and where its target directory comes from, for `--explain`
+fn explain_selection(
+    args: &Args,
+    project: &Project,
+    target_stats: &DirectoryStats,
+    min_size_bytes: Option<u64>,
+    from_plan: bool,
+) -> Vec<String> {
+    let mut reasons = vec![match (from_plan, project.is_workspace, project.marker_only) {
+        (true, _, _) => "listed in the applied plan".to_string(),
+        (false, _, true) => "found a project marker file".to_string(),
+        (false, _, _) if project.fuzz => "found a cargo-fuzz crate".to_string(),
+        (false, true, _) => "found a Cargo workspace".to_string(),
+        (false, false, _) => "found a Cargo package".to_string(),
+    }];
+    if let Some(ref crate_name) = args.depends_on {
+        reasons.push(format!("depends on {}", crate_name));
+    }
+    if let Some(min_bytes) = project.overrides.min_size.or(min_size_bytes) {
+        reasons.push(format!(
+            "target is {}, at least the minimum size {}",
+            format_bytes(target_stats.size),
+            format_bytes(min_bytes)
+        ));
+    }
+    if let (Some(newest), true) = (target_stats.newest_mtime, args.older_than.is_some() || args.newer_than.is_some()) {
+        reasons.push(format!("newest artifact was built {}, within the requested age", format_age(newest)));
+    }
+
+    let (target_dir, source) = resolve_target_dir_with_source(&project.path);
+    let missing = if target_dir.exists() { "" } else { ", does not exist" };
+    vec![
+        format!("Selected: {}", reasons.join("; ")),
+        format!("Target directory: {} ({}{})", target_dir.display(), source, missing),
+    ]
+}
+
+/// Print `--explain` lines for a project
+fn print_explanation(project_path: &Path, explanation: &[String]) {
+    for line in explanation {
+        println!("{} {}: {}", "[EXPLAIN]".cyan().bold(), project_path.display(), line);
+    }
+}
+
+/// The `orphans` subcommand: list target directories no discovered project builds into,
+/// removing them with `clean`
+fn run_orphans(
+    args: &Args,
+    roots: &[PathBuf],
+    discovery_options: &DiscoveryOptions,
+    clean: bool,
+    to_trash: bool,
+    format: OutputFormat,
+    human: bool,
+) -> Result<()> {
+    let projects = find_cargo_projects_in(roots, discovery_options)?;
+    if let Some(progress) = &discovery_options.progress {
+        progress.finish_and_clear();
+    }
+    let size_options = SizeOptions { on_disk: args.on_disk, by_atime: args.by_atime, one_filesystem: args.one_filesystem };
+    let orphans: Vec<_> = roots
+        .iter()
+        .flat_map(|root| find_orphaned_targets(root, &projects, discovery_options, &size_options))
+        .collect();
+    let mut removed = Vec::new();
+    let mut failures = Vec::new();
+    if clean {
+        for orphan in &orphans {
+            if args.dry_run {
+                if human {
+                    println!("{} Would remove: {:?}", "[DRY RUN]".yellow().bold(), orphan.path);
+                }
+                continue;
+            }
+            if args.confirm && !confirm(&format!("Remove orphaned target {} ({})?", orphan.path.display(), format_bytes(orphan.size))) {
+                continue;
+            }
+            match remove_orphan(orphan, to_trash) {
+                Ok(()) => removed.push(orphan.path.clone()),
+                Err(e) => failures.push(format!("{:#}", e)),
+            }
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", orphans_to_json(&orphans, &removed)?);
+    } else {
+        write_orphans(&mut std::io::stdout().lock(), &orphans, &removed)?;
+    }
+    for failure in &failures {
+        eprintln!("{} {}", "[ERROR]".red().bold(), failure);
+    }
+    if !failures.is_empty() {
+        anyhow::bail!("Failed to remove {} orphaned target(s)", failures.len());
+    }
+    Ok(())
+}
+
+/// The network filesystem `project`'s target is on, with the path that was checked: the
+/// target directory, or the project itself before its first build
+fn network_target(project: &Project, detect: impl Fn(&Path) -> Option<&'static str>) -> Option<(PathBuf, &'static str)> {
+    let target_dir = project.target_dir();
+    let checked = if target_dir.exists() { target_dir } else { project.path.clone() };
+    let fs_type = detect(&checked)?;
+    Some((checked, fs_type))
+}
+
+/// The thread pool dependency analysis runs on, `dep_jobs` wide
+fn dependency_pool(dep_jobs: usize) -> Result<rayon::ThreadPool> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(dep_jobs)
+        .build()
+        .context("Failed to start the dependency analysis thread pool")
+}
+
+/// Parse the command line, keeping the matches to tell given flags from defaults
+fn parse_args(argv: impl IntoIterator<Item = String>) -> (Args, clap::ArgMatches) {
+    let matches = Args::command().get_matches_from(argv);
+    match Args::from_arg_matches(&matches) {
+        Ok(args) => (args, matches),
+        Err(err) => err.exit(),
+    }
+}
+
+/// The flags given on the command line, as `--name`, other than `except`
+fn explicit_flags(matches: &clap::ArgMatches, except: &[&str]) -> Vec<String> {
+    let command = Args::command();
+    matches
+        .ids()
+        .filter(|id| !except.contains(&id.as_str()))
+        .filter(|id| matches.value_source(id.as_str()) == Some(clap::parser::ValueSource::CommandLine))
+        // Skip ids of argument groups
+        .filter_map(|id| command.get_arguments().find(|arg| arg.get_id() == id))
+        .map(|arg| format!("--{}", arg.get_long().unwrap_or(arg.get_id().as_str())))
+        .collect()
+}
+
+/// The `schedule` subcommand; with `dry_run`, files and commands are printed instead
+fn run_schedule(action: &ScheduleAction, roots: &[PathBuf], dry_run: bool) -> Result<()> {
+    match action {
+        ScheduleAction::Install { every, at, backend, options } => {
+            let (hour, minute) = parse_time(at)?;
+            let mut args = roots
+                .iter()
+                .map(|root| root.to_str().map(String::from).with_context(|| format!("Path is not valid UTF-8: {}", escape_non_utf8(root))))
+                .collect::<Result<Vec<_>>>()?;
+            args.extend(options.iter().cloned());
+            let schedule = Schedule {
+                frequency: *every,
+                hour,
+                minute,
+                program: std::env::current_exe().context("Failed to locate the deepclean executable")?,
+                args,
+                path_env: std::env::var("PATH").ok(),
+                log: cache_dir().map(|dir| dir.join("schedule.log")),
+            };
+            let backend = backend.unwrap_or_else(Backend::detect);
+            install_schedule(backend, &schedule, dry_run)?;
+            println!(
+                "{} Scheduled {} with {}: {}",
+                if dry_run { "[DRY RUN]".yellow().bold() } else { "[SUCCESS]".green().bold() },
+                schedule.describe(),
+                backend.name(),
+                format_command(&schedule.program.to_string_lossy(), &schedule.args)
+            );
+            Ok(())
+        }
+        ScheduleAction::Uninstall { backend } => {
+            let backend = backend.unwrap_or_else(Backend::detect);
+            if uninstall_schedule(backend, dry_run)? {
+                println!("{} Removed the scheduled job from {}", "[SUCCESS]".green().bold(), backend.name());
+            } else {
+                println!("{} No scheduled job installed in {}", "[INFO]".blue().bold(), backend.name());
+            }
+            Ok(())
+        }
+        ScheduleAction::Status { backend } => schedule_status(backend.unwrap_or_else(Backend::detect)),
+    }
+}
+
+fn install_schedule(backend: Backend, schedule: &Schedule, dry_run: bool) -> Result<()> {
+    match backend {
+        Backend::Systemd => {
+            let unit_dir = systemd_unit_dir().context("Can't locate the systemd user unit directory (HOME is not set)")?;
+            write_job_file(&unit_dir.join(format!("{}.service", JOB_NAME)), &schedule.systemd_service(), dry_run)?;
+            write_job_file(&unit_dir.join(format!("{}.timer", JOB_NAME)), &schedule.systemd_timer(), dry_run)?;
+            run_scheduler("systemctl", &["--user", "daemon-reload"], dry_run)?;
+            run_scheduler("systemctl", &["--user", "enable", "--now", &format!("{}.timer", JOB_NAME)], dry_run)
+        }
+        Backend::Cron => {
+            let crontab = replace_cron_entry(&read_crontab()?, Some(&schedule.cron_entry()));
+            write_crontab(&crontab, dry_run)
+        }
+        Backend::Launchd => {
+            let plist = launch_agent_path().context("Can't locate ~/Library/LaunchAgents (HOME is not set)")?;
+            let plist_arg = plist.to_string_lossy().to_string();
+            if plist.exists() {
+                run_scheduler("launchctl", &["unload", &plist_arg], dry_run)?;
+            }
+            write_job_file(&plist, &schedule.launchd_plist(), dry_run)?;
+            run_scheduler("launchctl", &["load", "-w", &plist_arg], dry_run)
+        }
+        Backend::TaskScheduler => {
+            let args = schedule.schtasks_create_args();
+            run_scheduler("schtasks", &args.iter().map(String::as_str).collect::<Vec<_>>(), dry_run)
+        }
+    }
+}
+
+/// Remove the job from `backend`, returning whether there was one
+fn uninstall_schedule(backend: Backend, dry_run: bool) -> Result<bool> {
+    match backend {
+        Backend::Systemd => {
+            let unit_dir = systemd_unit_dir().context("Can't locate the systemd user unit directory (HOME is not set)")?;
+            let timer = unit_dir.join(format!("{}.timer", JOB_NAME));
+            if !timer.exists() {
+                return Ok(false);
+            }
+            run_scheduler("systemctl", &["--user", "disable", "--now", &format!("{}.timer", JOB_NAME)], dry_run)?;
+            remove_job_file(&timer, dry_run)?;
+            remove_job_file(&unit_dir.join(format!("{}.service", JOB_NAME)), dry_run)?;
+            run_scheduler("systemctl", &["--user", "daemon-reload"], dry_run)?;
+            Ok(true)
+        }
+        Backend::Cron => {
+            let crontab = read_crontab()?;
+            if cron_entry_in(&crontab).is_none() {
+                return Ok(false);
+            }
+            write_crontab(&replace_cron_entry(&crontab, None), dry_run)?;
+            Ok(true)
+        }
+        Backend::Launchd => {
+            let plist = launch_agent_path().context("Can't locate ~/Library/LaunchAgents (HOME is not set)")?;
+            if !plist.exists() {
+                return Ok(false);
+            }
+            run_scheduler("launchctl", &["unload", "-w", &plist.to_string_lossy()], dry_run)?;
+            remove_job_file(&plist, dry_run)?;
+            Ok(true)
+        }
+        Backend::TaskScheduler => {
+            if !scheduler_succeeds("schtasks", &["/Query", "/TN", JOB_NAME]) {
+                return Ok(false);
+            }
+            run_scheduler("schtasks", &["/Delete", "/TN", JOB_NAME, "/F"], dry_run)?;
+            Ok(true)
+        }
+    }
+}
+
+/// Print the job installed in `backend` and, where the scheduler reports it, its next run
+fn schedule_status(backend: Backend) -> Result<()> {
+    let not_installed = || println!("{} No scheduled job installed in {}", "[INFO]".blue().bold(), backend.name());
+    match backend {
+        Backend::Systemd => {
+            let unit_dir = systemd_unit_dir().context("Can't locate the systemd user unit directory (HOME is not set)")?;
+            let service = unit_dir.join(format!("{}.service", JOB_NAME));
+            let timer = unit_dir.join(format!("{}.timer", JOB_NAME));
+            if !timer.exists() {
+                not_installed();
+                return Ok(());
+            }
+            for path in [&service, &timer] {
+                println!("{} {}:", "[INFO]".blue().bold(), path.display());
+                print!("{}", std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?);
+            }
+            show_scheduler("systemctl", &["--user", "list-timers", &format!("{}.timer", JOB_NAME), "--no-pager"]);
+        }
+        Backend::Cron => match cron_entry_in(&read_crontab()?) {
+            Some(entry) => {
+                println!("{} crontab:", "[INFO]".blue().bold());
+                print!("{}", entry);
+            }
+            None => not_installed(),
+        },
+        Backend::Launchd => {
+            let plist = launch_agent_path().context("Can't locate ~/Library/LaunchAgents (HOME is not set)")?;
+            if !plist.exists() {
+                not_installed();
+                return Ok(());
+            }
+            println!("{} {}:", "[INFO]".blue().bold(), plist.display());
+            print!("{}", std::fs::read_to_string(&plist).with_context(|| format!("Failed to read {:?}", plist))?);
+            show_scheduler("launchctl", &["list", LAUNCHD_LABEL]);
+        }
+        Backend::TaskScheduler => {
+            if !scheduler_succeeds("schtasks", &["/Query", "/TN", JOB_NAME]) {
+                not_installed();
+                return Ok(());
+            }
+            show_scheduler("schtasks", &["/Query", "/TN", JOB_NAME, "/V", "/FO", "LIST"]);
+        }
+    }
+    Ok(())
+}
+
+/// Run a scheduler's command, or only print it with `dry_run`
+fn run_scheduler(program: &str, args: &[&str], dry_run: bool) -> Result<()> {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    if dry_run {
+        println!("{} Would run: {}", "[DRY RUN]".yellow().bold(), format_command(program, &args));
+        return Ok(());
+    }
+    let status = std::process::Command::new(program)
+        .args(&args)
+        .status()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !status.success() {
+        anyhow::bail!("`{}` failed ({})", format_command(program, &args), status);
+    }
+    Ok(())
+}
+
+/// Whether a scheduler's query succeeds, with its output discarded
+fn scheduler_succeeds(program: &str, args: &[&str]) -> bool {
+    std::process::Command::new(program)
+        .args(args)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Pass a scheduler's own report through; it is only informational, so failures are ignored
+fn show_scheduler(program: &str, args: &[&str]) {
+    let _ = std::process::Command::new(program).args(args).status();
+}
+
+fn write_job_file(path: &Path, content: &str, dry_run: bool) -> Result<()> {
+    if dry_run {
+        println!("{} Would write {}:", "[DRY RUN]".yellow().bold(), path.display());
+        print!("{}", content);
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    }
+    std::fs::write(path, content).with_context(|| format!("Failed to write {:?}", path))
+}
+
+fn remove_job_file(path: &Path, dry_run: bool) -> Result<()> {
+    if dry_run {
+        println!("{} Would remove {}", "[DRY RUN]".yellow().bold(), path.display());
+        return Ok(());
+    }
+    std::fs::remove_file(path).with_context(|| format!("Failed to remove {:?}", path))
+}
+
+/// The user's crontab; empty when they have none yet
+fn read_crontab() -> Result<String> {
+    let output = std::process::Command::new("crontab")
+        .arg("-l")
+        .output()
+        .context("Failed to run crontab")?;
+    // `crontab -l` fails with "no crontab for <user>" before the first install
+    Ok(match output.status.success() {
+        true => String::from_utf8_lossy(&output.stdout).into_owned(),
+        false => String::new(),
+    })
+}
+
+fn write_crontab(crontab: &str, dry_run: bool) -> Result<()> {
+    use std::io::Write;
+    if dry_run {
+        println!("{} Would install crontab:", "[DRY RUN]".yellow().bold());
+        print!("{}", crontab);
+        return Ok(());
+    }
+    let mut child = std::process::Command::new("crontab")
+        .arg("-")
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .context("Failed to run crontab")?;
+    child.stdin.take().unwrap().write_all(crontab.as_bytes()).context("Failed to write to crontab")?;
+    let status = child.wait().context("Failed to run crontab")?;
+    if !status.success() {
+        anyhow::bail!("crontab failed to install the new table ({})", status);
+    }
+    Ok(())
+}
+
+/// The `daemon` subcommand: watches free space on the filesystems holding the roots and
+/// cleans targets in [`CleanOrder`] until it is back above the threshold
+struct Daemon<'a> {
+    args: &'a Args,
+    roots: &'a [PathBuf],
+    discovery_options: &'a DiscoveryOptions,
+    clean_options: &'a CleanOptions,
+    min_size_bytes: Option<u64>,
+    threshold: u64,
+    interval: u64,
+    order: CleanOrder,
+    once: bool,
+    log_file: Option<std::fs::File>,
+    state: DaemonState,
+    state_path: Option<PathBuf>,
+}
+
+impl<'a> Daemon<'a> {
+    fn new(
+        args: &'a Args,
+        daemon_args: &DaemonArgs,
+        roots: &'a [PathBuf],
+        discovery_options: &'a DiscoveryOptions,
+        clean_options: &'a CleanOptions,
+        min_size_bytes: Option<u64>,
+    ) -> Result<Self> {
+        let threshold = parse_size(&daemon_args.when_free_below)
+            .with_context(|| format!("Invalid --when-free-below value: {:?}", daemon_args.when_free_below))?;
+        let interval = parse_duration(&daemon_args.interval)
+            .with_context(|| format!("Invalid --interval value: {:?}", daemon_args.interval))?;
+        if interval == 0 {
+            anyhow::bail!("--interval must be longer than zero");
+        }
+        if roots.iter().any(|root| free_space(root).is_none()) {
+            anyhow::bail!("Free space can't be queried on this platform, so the daemon can't watch it");
+        }
+        let log_file = match daemon_args.log {
+            Some(ref path) => Some(
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open log file: {:?}", path))?,
+            ),
+            None => None,
+        };
+        let state_path = DaemonState::default_path();
+        Ok(Self {
+            args,
+            roots,
+            discovery_options,
+            clean_options,
+            min_size_bytes,
+            threshold,
+            interval,
+            order: daemon_args.order,
+            once: daemon_args.once,
+            log_file,
+            state: state_path.as_deref().map(DaemonState::load).unwrap_or_default(),
+            state_path,
+        })
+    }
+
+    /// Check every interval until stopped, or once with `--once`
+    fn run(mut self) -> Result<()> {
+        let roots: Vec<_> = self.roots.iter().map(|root| root.display().to_string()).collect();
+        self.log(format!(
+            "Watching {} for free space below {}, every {}{}",
+            roots.join(", "),
+            format_bytes(self.threshold),
+            format_duration(self.interval),
+            if self.clean_options.dry_run { " (dry run)" } else { "" }
+        ));
+        loop {
+            if let Err(e) = self.check() {
+                if self.once {
+                    return Err(e);
+                }
+                self.log(format!("Check failed: {:#}", e));
+            }
+            if let Some(ref path) = self.state_path {
+                if let Err(e) = self.state.save(path) {
+                    self.log(format!("Failed to save daemon state: {:#}", e));
+                }
+            }
+            if self.once {
+                return Ok(());
+            }
+            std::thread::sleep(Duration::from_secs(self.interval));
+        }
+    }
+
+    /// Sample free space once, cleaning every filesystem that is below the threshold
+    fn check(&mut self) -> Result<()> {
+        self.state.last_check = Some(unix_now());
+        let low: Vec<(PathBuf, u64)> = one_path_per_device(self.roots.to_vec())
+            .into_iter()
+            .filter_map(|path| free_space(&path).map(|free| (path, free)))
+            .filter(|&(_, free)| free < self.threshold)
+            .collect();
+        if low.is_empty() {
+            return Ok(());
+        }
+
+        let warnings = WarningCollector::new();
+        let selection = select_projects(
+            self.args,
+            self.roots,
+            self.discovery_options,
+            self.min_size_bytes,
+            &warnings,
+            false,
+            &mut PhaseTimings::default(),
+        )?;
+        for warning in warnings.into_warnings() {
+            match warning.path {
+                Some(path) => self.log(format!("Warning: {}: {}", path, warning.message)),
+                None => self.log(format!("Warning: {}", warning.message)),
+            }
+        }
+
+        for (path, free) in low {
+            let needed = self.threshold - free;
+            self.log(format!(
+                "{} free on the filesystem holding {}, {} short of {}",
+                format_bytes(free),
+                path.display(),
+                format_bytes(needed),
+                format_bytes(self.threshold)
+            ));
+            let on_filesystem: Vec<_> = selection
+                .projects
+                .iter()
+                .filter(|(project, _)| same_device(&project.target_dir(), &path))
+                .cloned()
+                .collect();
+            let picked = pick_targets(&on_filesystem, needed, self.order);
+            if picked.is_empty() {
+                self.log("Nothing left to clean on this filesystem".to_string());
+                continue;
+            }
+            let mut cleaned = Vec::new();
+            for (project, stats) in picked {
+                match clean_project(project, Some(stats.size), self.clean_options) {
+                    Ok(result) if result.success => {
+                        let verb = if self.clean_options.dry_run { "Would free" } else { "Freed" };
+                        self.log(format!("{} {} from {}", verb, format_bytes(result.freed_bytes), project.path.display()));
+                        cleaned.push((project.path.clone(), result.freed_bytes));
+                    }
+                    Ok(result) => self.log(format!(
+                        "Failed to clean {}: {}",
+                        project.path.display(),
+                        result.error.unwrap_or_default()
+                    )),
+                    Err(e) => match e.downcast_ref::<BuildInProgress>() {
+                        Some(busy) => self.log(format!("Skipped {}, build in progress: {}", project.path.display(), busy.reason)),
+                        None => self.log(format!("Failed to clean {}: {:#}", project.path.display(), e)),
+                    },
+                }
+            }
+            if self.clean_options.dry_run {
+                continue;
+            }
+            self.state.record_cleanup(unix_now(), cleaned.iter().map(|(path, freed)| (path.as_path(), *freed)));
+            if let Some(free) = free_space(&path) {
+                let still_low = if free < self.threshold { ", still below the threshold" } else { "" };
+                self.log(format!("{} now free on the filesystem holding {}{}", format_bytes(free), path.display(), still_low));
+            }
+        }
+        Ok(())
+    }
+
+    /// Print a timestamped line, or append it to the `--log` file
+    fn log(&mut self, message: String) {
+        let line = log_line(unix_now(), &message);
+        match self.log_file {
+            Some(ref mut file) => {
+                use std::io::Write;
+                if let Err(e) = writeln!(file, "{}", line) {
+                    eprintln!("{} Failed to write the daemon log: {}", "[WARNING]".yellow().bold(), e);
+                }
+            }
+            None => println!("{}", line),
+        }
+    }
+}
+
+/// Project paths listed one per line in `source` (`-` for stdin), skipping blank lines
+fn read_project_list(source: &str) -> Result<Vec<PathBuf>> {
+    let content = if source == "-" {
+        std::io::read_to_string(std::io::stdin()).context("Failed to read the project list from stdin")?
+    } else {
+        std::fs::read_to_string(source).with_context(|| format!("Failed to read project list: {:?}", source))?
+    };
+    Ok(content.lines().map(str::trim).filter(|line| !line.is_empty()).map(PathBuf::from).collect())
+}
+
+/// Ask a yes/no question on the terminal; anything but "y" or "yes" (including EOF) is no
+fn confirm(question: &str) -> bool {
+    use std::io::Write;
+    print!("{} {} [y/N] ", "[CONFIRM]".magenta().bold(), question);
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+/// Discover projects under `roots`, apply every selection filter and size their targets
+fn select_projects(
+    args: &Args,
+    roots: &[PathBuf],
+    discovery_options: &DiscoveryOptions,
+    min_size_bytes: Option<u64>,
+    warnings: &WarningCollector,
+    human: bool,
+    timings: &mut PhaseTimings,
+) -> Result<Selection> {
+    let older_than = args.older_than.as_deref().map(parse_duration).transpose()
+        .with_context(|| format!("Invalid --older-than value: {:?}", args.older_than.as_deref().unwrap_or_default()))?;
+    let newer_than = args.newer_than.as_deref().map(parse_duration).transpose()
+        .with_context(|| format!("Invalid --newer-than value: {:?}", args.newer_than.as_deref().unwrap_or_default()))?;
+    let git_idle = args.git_idle.as_deref().map(parse_duration).transpose()
+        .with_context(|| format!("Invalid --git-idle value: {:?}", args.git_idle.as_deref().unwrap_or_default()))?;
+    for root in roots.iter().filter(|root| args.by_atime && atime_unreliable(root)) {
+        warnings.push(
+            None,
+            format!("--by-atime: {:?} is mounted noatime, so access times are stale and age filters may be inaccurate", root),
+        );
+    }
+
+    // Projects and target sizes from earlier runs; --measure-only always does the work it profiles
+    let mut index = match default_index_path() {
+        Some(path) if args.index && !args.measure_only => Some(ProjectIndex::open(&path, args.refresh)),
+        _ => None,
+    };
+    let phase_start = Instant::now();
+    let projects = match (&args.projects_from, &mut index) {
+        (Some(source), _) => projects_from_paths(&read_project_list(source)?, discovery_options)?,
+        (None, Some(index)) => index.discover(roots, discovery_options).context("Failed to find Cargo projects")?,
+        (None, None) => find_cargo_projects_in(roots, discovery_options).context("Failed to find Cargo projects")?,
+    };
+    if let Some(progress) = &discovery_options.progress {
+        progress.finish_and_clear();
+    }
+    let discovered = projects.len();
+    if projects.is_empty() {
+        return Ok(Selection::default());
+    }
+
+    // Paths are reported as UTF-8 strings; flag any that can't be represented faithfully
+    for project in &projects {
+        for path in [project.path.clone(), project.target_dir()] {
+            if path.to_str().is_none() {
+                let message = format!("Path is not valid UTF-8: {}", escape_non_utf8(&path));
+                if args.strict_json {
+                    anyhow::bail!("{} (rejected by --strict-json)", message);
+                }
+                warnings.push(Some(&project.path), format!("{}; reported lossily", message));
+            }
+        }
+    }
+
+    // Filter by dependency graph membership if specified
+    let mut skipped = Vec::new();
+    let projects: Vec<_> = if let Some(ref crate_name) = args.depends_on {
+        let mut selected = Vec::new();
+        for project in projects {
+            match depends_on(&project, crate_name) {
+                Ok(true) => selected.push(project),
+                Ok(false) if args.explain => skipped.push(SkippedProject {
+                    path: project.path.to_string_lossy().to_string(),
+                    reason: format!("does not depend on {} (--depends-on)", crate_name),
+                }),
+                Ok(false) => {}
+                Err(e) => skipped.push(SkippedProject {
+                    path: project.path.to_string_lossy().to_string(),
+                    reason: format!("cargo metadata failed: {}", e),
+                }),
+            }
+        }
+        selected
+    } else {
+        projects
+    };
+
+    // Never clean the project the user is currently working in
+    let projects: Vec<_> = match std::env::current_dir() {
+        Ok(cwd) if args.exclude_cwd => projects
+            .into_iter()
+            .filter(|project| {
+                let contains_cwd = contains_path(project, &cwd);
+                if contains_cwd {
+                    skipped.push(SkippedProject {
+                        path: project.path.to_string_lossy().to_string(),
+                        reason: "contains the current working directory (--exclude-cwd)".to_string(),
+                    });
+                }
+                !contains_cwd
+            })
+            .collect(),
+        _ => projects,
+    };
+
+    // Merge per-project `.deepclean` sidecar overrides; malformed ones are ignored with a warning
+    let projects: Vec<_> = projects
+        .into_iter()
+        .filter_map(|mut project| {
+            match load_sidecar(&project.path) {
+                Ok(Some(overrides)) => project.overrides = overrides,
+                Ok(None) => {}
+                Err(e) => warnings.push(Some(&project.path), format!("Ignoring {}: {:#}", SIDECAR_FILE_NAME, e)),
+            }
+            if project.overrides.skip {
+                skipped.push(SkippedProject {
+                    path: project.path.to_string_lossy().to_string(),
+                    reason: format!("skip = true in {}", SIDECAR_FILE_NAME),
+                });
+                return None;
+            }
+            if let (true, CargoStatus::Unbuildable(ref error)) = (args.skip_unbuildable, &project.cargo) {
+                skipped.push(SkippedProject {
+                    path: project.path.to_string_lossy().to_string(),
+                    reason: format!("cargo can't load the manifest: {}", error),
+                });
+                return None;
+            }
+            Some(project)
+        })
+        .collect();
+
+    // A target outside every discovered project may be shared with unrelated builds
+    let projects: Vec<_> = if args.allow_global_target {
+        projects
+    } else {
+        let (shared, projects): (Vec<_>, Vec<_>) = projects
+            .iter()
+            .cloned()
+            .partition(|project| is_shared_target(&project.target_dir(), &projects));
+        for project in shared {
+            let message = format!(
+                "Target directory {:?} is outside every discovered project and may be shared with other builds; \
+                 skipped for safety (use --allow-global-target to clean it)",
+                project.target_dir()
+            );
+            if human {
+                eprintln!("{} {}: {}", "[WARNING]".yellow().bold(), project.path.display(), message);
+            }
+            warnings.push(Some(&project.path), message);
+            skipped.push(SkippedProject {
+                path: project.path.to_string_lossy().to_string(),
+                reason: "shared target directory outside all projects (--allow-global-target)".to_string(),
+            });
+        }
+        projects
+    };
+
+    // Network filesystems are slow to walk and may not free space the way local ones do
+    let projects: Vec<_> = if args.allow_network_fs {
+        projects
+    } else {
+        projects
+            .into_iter()
+            .filter(|project| {
+                let Some((checked, fs_type)) = network_target(project, network_filesystem) else {
+                    return true;
+                };
+                let message = format!(
+                    "{:?} is on a network filesystem ({}); skipped (use --allow-network-fs to clean it)",
+                    checked, fs_type
+                );
+                if human {
+                    eprintln!("{} {}: {}", "[WARNING]".yellow().bold(), project.path.display(), message);
+                }
+                warnings.push(Some(&project.path), message);
+                skipped.push(SkippedProject {
+                    path: project.path.to_string_lossy().to_string(),
+                    reason: format!("target directory on a network filesystem ({}) (--allow-network-fs)", fs_type),
+                });
+                false
+            })
+            .collect()
+    };
+
+    // Filter by repository activity; projects outside a repository never match
+    let projects: Vec<_> = match git_idle {
+        Some(limit) => {
+            let now = unix_now();
+            let activity: Vec<_> = projects.par_iter().map(|project| project.git_activity(args.git_reflog)).collect();
+            projects
+                .into_iter()
+                .zip(activity)
+                .filter(|(project, activity)| {
+                    let matches = activity.is_some_and(|active| now.saturating_sub(active) > limit);
+                    if !matches && args.explain {
+                        let reason = match activity {
+                            Some(active) => format!("git repository was last active {}, within --git-idle", format_age(*active)),
+                            None => "not in a git repository with commits (--git-idle)".to_string(),
+                        };
+                        skipped.push(SkippedProject { path: project.path.to_string_lossy().to_string(), reason });
+                    }
+                    matches
+                })
+                .map(|(project, _)| project)
+                .collect()
+        }
+        None => projects,
+    };
+
+    // Uncommitted changes suggest active work; projects outside a repository are kept
+    let mut dirty = Vec::new();
+    let projects: Vec<_> = if args.skip_dirty {
+        let changes: Vec<_> = projects.par_iter().map(Project::git_changes).collect();
+        projects
+            .into_iter()
+            .zip(changes)
+            .filter_map(|(project, changes)| match changes {
+                Some(changes) if changes > 0 => {
+                    dirty.push(SkippedProject {
+                        path: project.path.to_string_lossy().to_string(),
+                        reason: format!("{} uncommitted or untracked path(s) (--skip-dirty)", changes),
+                    });
+                    None
+                }
+                _ => Some(project),
+            })
+            .collect()
+    } else {
+        projects
+    };
+
+    // Projects sharing one target directory are measured and cleaned once, under the first of them
+    let (projects, sharing) = dedup_shared_targets(projects);
+    for (project, owner) in sharing {
+        skipped.push(SkippedProject {
+            path: project.path.to_string_lossy().to_string(),
+            reason: format!("shares its target directory {:?} with {}", project.target_dir(), owner.display()),
+        });
+    }
+    timings.discovery_ms = phase_start.elapsed().as_millis() as u64;
+
+    // Size every target directory up front; cleaning reuses these sizes
+    let size_options = SizeOptions { on_disk: args.on_disk, by_atime: args.by_atime, one_filesystem: args.one_filesystem };
+    let phase_start = Instant::now();
+    let sizing_progress = phase_progress(projects.len(), "target directories measured", human && args.verbose == 0 && !args.explain);
+    let projects: Vec<(Project, DirectoryStats)> = projects
+        .into_par_iter()
+        .map(|project| {
+            let target_stats = match &index {
+                Some(index) => index.target_stats(&project.target_dir(), &size_options),
+                None => get_directory_stats(&project.target_dir(), &size_options),
+            }
+            .unwrap_or_default();
+            if let Some(pb) = &sizing_progress {
+                pb.inc(1);
+            }
+            (project, target_stats)
+        })
+        .collect();
+    if let Some(pb) = sizing_progress {
+        pb.finish_and_clear();
+    }
+    if let Some(index) = index {
+        if let Err(e) = index.save() {
+            warnings.push(None, format!("Failed to save the project index: {:#}", e));
+        }
+    }
+
+    // Projects dropped by the size and age filters are only reported with --explain
+    let mut filtered_out = |project: &Project, reason: String| {
+        if args.explain {
+            skipped.push(SkippedProject { path: project.path.to_string_lossy().to_string(), reason });
+        }
+    };
+
+    // Filter by minimum size if specified, globally or by a project's sidecar
+    let projects: Vec<_> = projects
+        .into_iter()
+        .filter(|(project, target_stats)| match project.overrides.min_size.or(min_size_bytes) {
+            Some(_) if !project.target_dir().exists() => {
+                filtered_out(project, "no target directory to measure against the minimum size".to_string());
+                false
+            }
+            Some(min_bytes) if target_stats.size < min_bytes => {
+                filtered_out(
+                    project,
+                    format!("target is {}, below the minimum size {}", format_bytes(target_stats.size), format_bytes(min_bytes)),
+                );
+                false
+            }
+            _ => true,
+        })
+        .collect();
+
+    // Filter by the age of the newest artifact; empty targets have no age and never match
+    let now = unix_now();
+    let projects: Vec<_> = projects
+        .into_iter()
+        .filter(|(project, target_stats)| {
+            let age = target_stats.newest_mtime.map(|newest| now.saturating_sub(newest));
+            let matches = older_than.is_none_or(|limit| age.is_some_and(|age| age > limit))
+                && newer_than.is_none_or(|limit| age.is_some_and(|age| age < limit));
+            if !matches {
+                let reason = match target_stats.newest_mtime {
+                    Some(newest) => format!("newest artifact was built {}, outside the requested age", format_age(newest)),
+                    None => "no artifacts to compare against the requested age".to_string(),
+                };
+                filtered_out(project, reason);
+            }
+            matches
+        })
+        .collect();
+    timings.sizing_ms = phase_start.elapsed().as_millis() as u64;
+
+    Ok(Selection { discovered, projects, skipped, dirty })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_show_empty_overrides_hide_empty() {
+        let args = Args::try_parse_from(["cargo-deepclean", "--hide-empty", "--show-empty"]).unwrap();
+        assert!(!args.hide_empty);
+        // The later of the two wins
+        let args = Args::try_parse_from(["cargo-deepclean", "--show-empty", "--hide-empty"]).unwrap();
+        assert!(args.hide_empty);
+    }
+
+    #[test]
+    fn test_network_target() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = Project {
+            path: temp_dir.path().to_path_buf(),
+            is_workspace: false,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: Default::default(),
+        };
+        let nfs_under = |mount: PathBuf| move |path: &Path| path.starts_with(&mount).then_some("NFS");
+
+        assert_eq!(network_target(&project, |_| None), None);
+        // Before the first build the project directory stands in for its target
+        assert_eq!(network_target(&project, nfs_under(project.path.clone())), Some((project.path.clone(), "NFS")));
+        std::fs::create_dir(project.path.join("target")).unwrap();
+        assert_eq!(
+            network_target(&project, nfs_under(project.path.join("target"))),
+            Some((project.path.join("target"), "NFS"))
+        );
+    }
+
+    #[test]
+    fn test_dependency_pool_size() {
+        assert_eq!(dependency_pool(3).unwrap().install(rayon::current_num_threads), 3);
+        let args = Args::try_parse_from(["cargo-deepclean", "--dep-jobs", "1"]).unwrap();
+        assert_eq!(dependency_pool(args.dep_jobs).unwrap().install(rayon::current_num_threads), 1);
+    }
+}
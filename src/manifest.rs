@@ -0,0 +1,265 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A pending rewrite of one manifest, computed entirely in memory
+#[derive(Debug, Clone)]
+pub struct ManifestEdit {
+    pub path: PathBuf,
+    pub original: String,
+    pub edited: String,
+}
+
+/// Unchanged lines shown around each change in [`ManifestEdit::unified_diff`]
+const DIFF_CONTEXT: usize = 3;
+
+impl ManifestEdit {
+    /// The edit as a unified diff (`diff -u` style); empty when nothing changes
+    pub fn unified_diff(&self) -> String {
+        let old: Vec<&str> = self.original.lines().collect();
+        let new: Vec<&str> = self.edited.lines().collect();
+
+        // lcs[i][j]: longest common subsequence of old[i..] and new[j..]
+        let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                lcs[i][j] = if old[i] == new[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        // (tag, old index, new index); removals come before additions, like diff -u
+        let mut ops = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() || j < new.len() {
+            if i < old.len() && j < new.len() && old[i] == new[j] {
+                ops.push((' ', i, j));
+                i += 1;
+                j += 1;
+            } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+                ops.push(('-', i, j));
+                i += 1;
+            } else {
+                ops.push(('+', i, j));
+                j += 1;
+            }
+        }
+
+        let changes: Vec<usize> = (0..ops.len()).filter(|&k| ops[k].0 != ' ').collect();
+        if changes.is_empty() {
+            return String::new();
+        }
+        let range = |start: usize, len: usize| match len {
+            0 => format!("{},0", start),
+            1 => format!("{}", start + 1),
+            _ => format!("{},{}", start + 1, len),
+        };
+        let mut diff = format!("--- {}\n+++ {}\n", self.path.display(), self.path.display());
+        let mut k = 0;
+        while k < changes.len() {
+            // Changes separated by at most two contexts' worth of lines share a hunk
+            let start = changes[k].saturating_sub(DIFF_CONTEXT);
+            while k + 1 < changes.len() && changes[k + 1] - changes[k] <= 2 * DIFF_CONTEXT + 1 {
+                k += 1;
+            }
+            let end = (changes[k] + DIFF_CONTEXT + 1).min(ops.len());
+            let hunk = &ops[start..end];
+            let old_len = hunk.iter().filter(|op| op.0 != '+').count();
+            let new_len = hunk.iter().filter(|op| op.0 != '-').count();
+            diff.push_str(&format!("@@ -{} +{} @@\n", range(hunk[0].1, old_len), range(hunk[0].2, new_len)));
+            for &(tag, i, j) in hunk {
+                let line = if tag == '+' { new[j] } else { old[i] };
+                diff.push_str(&format!("{}{}\n", tag, line));
+            }
+            k += 1;
+        }
+        diff
+    }
+}
+
+/// A set of manifest rewrites applied in two phases
+///
+/// Every edited manifest is first written and synced to a temporary file next to
+/// the original; only once all of them are staged is each one renamed over its
+/// original. A rename is atomic, so an interruption leaves every manifest either
+/// original or fully edited, never partially written. If a rename fails, the
+/// manifests already replaced are restored from the originals kept in memory.
+#[derive(Debug, Default)]
+pub struct ManifestTransaction {
+    edits: Vec<ManifestEdit>,
+}
+
+/// Temporary file an edit is staged in, in the same directory so rename stays on one filesystem
+fn staging_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    path.with_file_name(format!(".{}.deepclean-tmp", file_name))
+}
+
+/// Write `content` to the staging file for `path` and sync it, preserving permissions
+fn stage(path: &Path, content: &str) -> Result<PathBuf> {
+    use std::io::Write;
+    let staging = staging_path(path);
+    let write = || -> std::io::Result<()> {
+        let mut file = fs::File::create(&staging)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&staging, metadata.permissions())?;
+        }
+        Ok(())
+    };
+    if let Err(e) = write() {
+        let _ = fs::remove_file(&staging);
+        return Err(e).with_context(|| format!("Failed to stage edit for {:?}", path));
+    }
+    Ok(staging)
+}
+
+impl ManifestTransaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue an edit; edits that change nothing are dropped
+    pub fn push(&mut self, edit: ManifestEdit) {
+        if edit.edited != edit.original {
+            self.edits.push(edit);
+        }
+    }
+
+    /// Stage every edit, then swap them all in
+    pub fn commit(self) -> Result<()> {
+        let mut staged = Vec::with_capacity(self.edits.len());
+        for edit in &self.edits {
+            match stage(&edit.path, &edit.edited) {
+                Ok(staging) => staged.push(staging),
+                Err(e) => {
+                    for staging in &staged {
+                        let _ = fs::remove_file(staging);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        for (i, (edit, staging)) in self.edits.iter().zip(&staged).enumerate() {
+            if let Err(e) = fs::rename(staging, &edit.path) {
+                for staging in &staged[i..] {
+                    let _ = fs::remove_file(staging);
+                }
+                let mut error = anyhow::Error::new(e).context(format!("Failed to replace {:?}", edit.path));
+                for applied in &self.edits[..i] {
+                    if let Err(restore_error) = stage(&applied.path, &applied.original)
+                        .and_then(|s| fs::rename(&s, &applied.path).map_err(Into::into))
+                    {
+                        error = error.context(format!(
+                            "Failed to restore {:?} after an aborted transaction: {}",
+                            applied.path, restore_error
+                        ));
+                    }
+                }
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn edit(path: &Path, edited: &str) -> ManifestEdit {
+        ManifestEdit {
+            path: path.to_path_buf(),
+            original: fs::read_to_string(path).unwrap_or_default(),
+            edited: edited.to_string(),
+        }
+    }
+
+    fn leftover_staging_files(dir: &Path) -> usize {
+        walkdir::WalkDir::new(dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".deepclean-tmp"))
+            .count()
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let original = "[package]\nname = \"app\"\n\n[dependencies]\nlog = \"0.4\"\nrand = \"0.8\"\nserde = \"1\"\n\n[dev-dependencies]\ntempfile = \"3\"\n";
+        let manifest_edit = ManifestEdit {
+            path: PathBuf::from("app/Cargo.toml"),
+            original: original.to_string(),
+            edited: original.replace("rand = \"0.8\"\n", "").replace("tempfile = \"3\"\n", "tempfile = \"3.10\"\n"),
+        };
+        assert_eq!(
+            manifest_edit.unified_diff(),
+            "--- app/Cargo.toml\n+++ app/Cargo.toml\n\
+             @@ -3,8 +3,7 @@\n \n [dependencies]\n log = \"0.4\"\n-rand = \"0.8\"\n serde = \"1\"\n \n [dev-dependencies]\n-tempfile = \"3\"\n+tempfile = \"3.10\"\n"
+        );
+
+        let unchanged = ManifestEdit { edited: original.to_string(), ..manifest_edit.clone() };
+        assert_eq!(unchanged.unified_diff(), "");
+
+        let appended = ManifestEdit { edited: format!("{}extra = 1\n", original), ..manifest_edit };
+        assert!(appended.unified_diff().contains("@@ -8,3 +8,4 @@\n"));
+    }
+
+    #[test]
+    fn test_transaction_commits_all_edits() {
+        let temp_dir = TempDir::new().unwrap();
+        let a = temp_dir.path().join("a.toml");
+        let b = temp_dir.path().join("b.toml");
+        fs::write(&a, "a = 1\n").unwrap();
+        fs::write(&b, "b = 1\n").unwrap();
+
+        let mut transaction = ManifestTransaction::new();
+        transaction.push(edit(&a, "a = 2\n"));
+        transaction.push(edit(&b, "b = 2\n"));
+        transaction.commit().unwrap();
+
+        assert_eq!(fs::read_to_string(&a).unwrap(), "a = 2\n");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "b = 2\n");
+        assert_eq!(leftover_staging_files(temp_dir.path()), 0);
+    }
+
+    #[test]
+    fn test_transaction_failed_staging_leaves_originals() {
+        let temp_dir = TempDir::new().unwrap();
+        let a = temp_dir.path().join("a.toml");
+        fs::write(&a, "a = 1\n").unwrap();
+
+        let mut transaction = ManifestTransaction::new();
+        transaction.push(edit(&a, "a = 2\n"));
+        // The second manifest's directory is gone, so staging it fails after the first was written
+        transaction.push(edit(&temp_dir.path().join("missing/b.toml"), "b = 2\n"));
+        assert!(transaction.commit().is_err());
+
+        assert_eq!(fs::read_to_string(&a).unwrap(), "a = 1\n");
+        assert_eq!(leftover_staging_files(temp_dir.path()), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_transaction_failed_rename_restores_applied_edits() {
+        let temp_dir = TempDir::new().unwrap();
+        let a = temp_dir.path().join("a.toml");
+        let b = temp_dir.path().join("b.toml");
+        fs::write(&a, "a = 1\n").unwrap();
+        // A non-empty directory can't be replaced by a file, so the second rename fails
+        fs::create_dir_all(b.join("blocker")).unwrap();
+
+        let mut transaction = ManifestTransaction::new();
+        transaction.push(edit(&a, "a = 2\n"));
+        transaction.push(edit(&b, "b = 2\n"));
+        assert!(transaction.commit().is_err());
+
+        assert_eq!(fs::read_to_string(&a).unwrap(), "a = 1\n");
+        assert_eq!(leftover_staging_files(temp_dir.path()), 0);
+    }
+}
@@ -0,0 +1,49 @@
+use crate::output::Summary;
+use crate::utils::format_bytes;
+
+/// One-line description of a finished run for the notification body
+#[cfg_attr(not(feature = "notify"), allow(dead_code))]
+fn notification_body(summary: &Summary) -> String {
+    let mut body = format!(
+        "Cleaned {} project(s), freed {}",
+        summary.cleaned,
+        format_bytes(summary.total_freed_bytes)
+    );
+    if summary.failed > 0 {
+        body.push_str(&format!(", {} failed", summary.failed));
+    }
+    body
+}
+
+/// Show a desktop notification summarizing the run
+#[cfg(feature = "notify")]
+pub fn notify_completion(summary: &Summary) -> anyhow::Result<()> {
+    notify_rust::Notification::new()
+        .summary("cargo deepclean finished")
+        .body(&notification_body(summary))
+        .show()?;
+    Ok(())
+}
+
+/// Desktop notifications are compiled out without the `notify` feature
+#[cfg(not(feature = "notify"))]
+pub fn notify_completion(_summary: &Summary) -> anyhow::Result<()> {
+    anyhow::bail!("desktop notifications are not supported by this build (enable the `notify` feature)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notification_body() {
+        let mut summary = Summary {
+            cleaned: 3,
+            total_freed_bytes: 2048,
+            ..Default::default()
+        };
+        assert_eq!(notification_body(&summary), "Cleaned 3 project(s), freed 2.00 KB");
+        summary.failed = 1;
+        assert_eq!(notification_body(&summary), "Cleaned 3 project(s), freed 2.00 KB, 1 failed");
+    }
+}
@@ -0,0 +1,115 @@
+use crate::project::{is_excluded, DiscoveryOptions, Project};
+use crate::utils::{get_directory_stats, SizeOptions};
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// A target directory with no project left to build into it
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct OrphanedTarget {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Modification time of the newest file, in seconds since the Unix epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub newest_artifact: Option<u64>,
+}
+
+/// Whether `dir` looks like a Cargo target directory: Cargo tags every target with a
+/// `CACHEDIR.TAG` and caches rustc's version in `.rustc_info.json` at its root
+pub fn is_cargo_target(dir: &Path) -> bool {
+    dir.join("CACHEDIR.TAG").is_file() && dir.join(".rustc_info.json").is_file()
+}
+
+/// Target directories under `root` that no project builds into any more
+///
+/// A target counts as orphaned when none of `projects` resolves its target there and
+/// its parent has no `Cargo.toml`, e.g. the project was deleted or moved while its
+/// out-of-tree target stayed. Projects elsewhere that still use a target under `root`
+/// can't be seen, so `root` should hold the projects as well as their targets. The walk
+/// honors the exclusions in `options` and never descends into a target. Sorted by path.
+pub fn find_orphaned_targets(
+    root: &Path,
+    projects: &[Project],
+    options: &DiscoveryOptions,
+    sizes: &SizeOptions,
+) -> Vec<OrphanedTarget> {
+    let canonical = |path: PathBuf| path.canonicalize().unwrap_or(path);
+    let owned: HashSet<PathBuf> = projects.iter().map(|project| canonical(project.target_dir())).collect();
+    let mut candidates = Vec::new();
+    let mut walker = WalkDir::new(root)
+        .same_file_system(options.one_filesystem)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !is_excluded(e, root, &options.exclude_patterns));
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if !entry.file_type().is_dir() || !is_cargo_target(entry.path()) {
+            continue;
+        }
+        walker.skip_current_dir();
+        let has_manifest = entry.path().parent().is_some_and(|parent| parent.join("Cargo.toml").is_file());
+        if !has_manifest && !owned.contains(&canonical(entry.path().to_path_buf())) {
+            candidates.push(entry.into_path());
+        }
+    }
+    let mut orphans: Vec<OrphanedTarget> = candidates
+        .into_par_iter()
+        .map(|path| {
+            let stats = get_directory_stats(&path, sizes).unwrap_or_default();
+            OrphanedTarget { path, size: stats.size, newest_artifact: stats.newest_mtime }
+        })
+        .collect();
+    orphans.sort_by(|a, b| a.path.cmp(&b.path));
+    orphans
+}
+
+/// Delete an orphaned target, or move it to the trash with `to_trash`
+pub fn remove_orphan(orphan: &OrphanedTarget, to_trash: bool) -> Result<()> {
+    if to_trash {
+        trash::delete(&orphan.path).with_context(|| format!("Failed to move {:?} to the trash", orphan.path))
+    } else {
+        std::fs::remove_dir_all(&orphan.path).with_context(|| format!("Failed to remove {:?}", orphan.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::find_cargo_projects;
+    use std::fs;
+
+    fn write_target(dir: &Path) {
+        fs::create_dir_all(dir.join("debug")).unwrap();
+        fs::write(dir.join("CACHEDIR.TAG"), "Signature: 8a477f597d28d172789f06886806bc55\n").unwrap();
+        fs::write(dir.join(".rustc_info.json"), "{}").unwrap();
+        fs::write(dir.join("debug/app"), vec![0u8; 100]).unwrap();
+    }
+
+    #[test]
+    fn test_find_orphaned_targets() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("app/.cargo")).unwrap();
+        fs::write(root.join("app/Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\n").unwrap();
+        fs::write(root.join("app/.cargo/config.toml"), "[build]\ntarget-dir = \"../targets/app\"\n").unwrap();
+        for target in ["app/target", "targets/app", "targets/deleted", "moved/target"] {
+            write_target(&root.join(target));
+        }
+        // Looks like a target but lacks the rustc cache, so it's not one
+        fs::create_dir_all(root.join("cache")).unwrap();
+        fs::write(root.join("cache/CACHEDIR.TAG"), "").unwrap();
+
+        let options = DiscoveryOptions { static_workspaces: true, ..Default::default() };
+        let projects = find_cargo_projects(root, &options).unwrap();
+        let orphans = find_orphaned_targets(root, &projects, &options, &SizeOptions::default());
+        let paths: Vec<_> = orphans.iter().map(|orphan| orphan.path.clone()).collect();
+        assert_eq!(paths, [root.join("moved/target"), root.join("targets/deleted")]);
+        assert!(orphans.iter().all(|orphan| orphan.size > 100));
+
+        remove_orphan(&orphans[0], false).unwrap();
+        assert!(!root.join("moved/target").exists());
+    }
+}
@@ -1,132 +1,2051 @@
 use crate::cleaner::CleanResult;
-use crate::utils::format_bytes;
+use crate::orphans::OrphanedTarget;
+use crate::deps::{DependencyCleanResult, DuplicateDependency, FeatureFinding, HoistableDependency, MisplacedDependency, OutdatedDependency, StalePathDependency};
+use crate::project::Project;
+use crate::throttle::ThrottleMetrics;
+use crate::triples::is_wasm;
+use anyhow::Result;
+use crate::utils::{format_age, format_bytes, DirectoryStats};
+use crate::warnings::Warning;
 use colored::Colorize;
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use std::sync::Arc;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-#[derive(Debug, serde::Serialize)]
+/// Exit code bit set by `--bitflag-exit` when at least one project failed to clean
+pub const EXIT_BIT_FAILURES: i32 = 1;
+/// Exit code bit set by `--bitflag-exit` when unused dependencies were found
+pub const EXIT_BIT_UNUSED_DEPS: i32 = 2;
+/// Exit code bit set by `--bitflag-exit` when there was nothing to clean
+pub const EXIT_BIT_NOTHING_TO_CLEAN: i32 = 4;
+
+/// A discovered project that was deliberately not cleaned
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SkippedProject {
+    pub path: String,
+    pub reason: String,
+}
+
+/// A project listed by `scan`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ScannedProject {
+    pub path: String,
+    /// `workspace`, `package` or `marker` (found by a `--marker` file)
+    pub kind: &'static str,
+    pub target_dir: String,
+    pub target_size: u64,
+    /// Bytes the target takes on disk, present with `scan --disk-usage`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_usage: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oldest_artifact: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub newest_artifact: Option<u64>,
+}
+
+impl ScannedProject {
+    pub fn new(project: &Project, stats: &DirectoryStats) -> Self {
+        let kind = match (project.marker_only, project.is_workspace) {
+            (true, _) => "marker",
+            (false, true) => "workspace",
+            (false, false) => "package",
+        };
+        Self {
+            path: project.path.to_string_lossy().to_string(),
+            kind,
+            target_dir: project.target_dir().to_string_lossy().to_string(),
+            target_size: stats.size,
+            disk_usage: None,
+            oldest_artifact: stats.oldest_mtime,
+            newest_artifact: stats.newest_mtime,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ScanReport<'a> {
+    projects: &'a [ScannedProject],
+    total_target_bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_disk_usage: Option<u64>,
+    skipped: &'a [SkippedProject],
+}
+
+/// Serialize `scan` output as one JSON document
+pub fn scan_to_json(projects: &[ScannedProject], skipped: &[SkippedProject]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&ScanReport {
+        projects,
+        total_target_bytes: projects.iter().map(|p| p.target_size).sum(),
+        total_disk_usage: projects.iter().map(|p| p.disk_usage).sum::<Option<u64>>().filter(|_| !projects.is_empty()),
+        skipped,
+    })?)
+}
+
+/// Write `scan` output as a human-readable list, one project per line
+pub fn write_scan(out: &mut impl Write, projects: &[ScannedProject], skipped: &[SkippedProject]) -> std::io::Result<()> {
+    for project in projects {
+        let built = match project.newest_artifact {
+            Some(newest) => format!(", built {}", format_age(newest)),
+            None => String::new(),
+        };
+        let on_disk = match project.disk_usage {
+            Some(disk_usage) => format!(", {} on disk", format_bytes(disk_usage)),
+            None => String::new(),
+        };
+        writeln!(
+            out,
+            "{:>10}  {} ({}{}{})",
+            format_bytes(project.target_size),
+            project.path,
+            project.kind,
+            on_disk,
+            built
+        )?;
+    }
+    let total: u64 = projects.iter().map(|p| p.target_size).sum();
+    let total_on_disk = match projects.iter().map(|p| p.disk_usage).sum::<Option<u64>>() {
+        Some(disk_usage) if !projects.is_empty() => format!(" ({} on disk)", format_bytes(disk_usage)),
+        _ => String::new(),
+    };
+    writeln!(
+        out,
+        "{} {} project(s), {} in target directories{}",
+        "[INFO]".blue().bold(),
+        projects.len(),
+        format_bytes(total),
+        total_on_disk
+    )?;
+    for project in skipped {
+        writeln!(out, "{} Skipped {} ({})", "[WARNING]".yellow().bold(), project.path, project.reason)?;
+    }
+    Ok(())
+}
+
+/// Write the dry-run list of what an age filter selected, one line per target directory
+///
+/// Members sharing their workspace's target are listed once, under the first project
+/// that builds into it; never-built targets are left out.
+pub fn write_would_clean(out: &mut impl Write, projects: &[ScannedProject]) -> std::io::Result<()> {
+    let mut listed = HashSet::new();
+    for project in projects {
+        let Some(newest) = project.newest_artifact else {
+            continue;
+        };
+        if !listed.insert(project.target_dir.as_str()) {
+            continue;
+        }
+        writeln!(
+            out,
+            "{} Would clean {} (last built {}, {})",
+            "[DRY RUN]".yellow().bold(),
+            project.path,
+            format_age(newest),
+            format_bytes(project.target_size)
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(serde::Serialize)]
+struct OrphanReport<'a> {
+    orphans: &'a [OrphanedTarget],
+    total_bytes: u64,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    removed: &'a [PathBuf],
+}
+
+/// Serialize `orphans` output as one JSON document
+pub fn orphans_to_json(orphans: &[OrphanedTarget], removed: &[PathBuf]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&OrphanReport {
+        orphans,
+        total_bytes: orphans.iter().map(|o| o.size).sum(),
+        removed,
+    })?)
+}
+
+/// Write `orphans` output as a human-readable list, one target per line, marking those
+/// in `removed`
+pub fn write_orphans(out: &mut impl Write, orphans: &[OrphanedTarget], removed: &[PathBuf]) -> std::io::Result<()> {
+    for orphan in orphans {
+        let built = match orphan.newest_artifact {
+            Some(newest) => format!(" (built {})", format_age(newest)),
+            None => String::new(),
+        };
+        let status = if removed.contains(&orphan.path) { " [removed]" } else { "" };
+        writeln!(out, "{:>10}  {}{}{}", format_bytes(orphan.size), orphan.path.display(), built, status)?;
+    }
+    let total: u64 = orphans.iter().map(|o| o.size).sum();
+    writeln!(
+        out,
+        "{} {} orphaned target(s), {}",
+        "[INFO]".blue().bold(),
+        orphans.len(),
+        format_bytes(total)
+    )?;
+    if !removed.is_empty() {
+        let freed: u64 = orphans.iter().filter(|o| removed.contains(&o.path)).map(|o| o.size).sum();
+        writeln!(out, "{} Removed {} orphaned target(s), freed {}", "[SUCCESS]".green().bold(), removed.len(), format_bytes(freed))?;
+    }
+    Ok(())
+}
+
+/// Format of the final report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Colored human-readable output
+    Text,
+    /// A single JSON document
+    Json,
+    /// JUnit XML, one test case per project
+    Junit,
+    /// One JSON object per line, streamed as projects finish
+    Jsonl,
+    /// CSV rows, streamed as projects finish
+    Csv,
+}
+
+impl OutputFormat {
+    /// Whether results are written as they complete rather than buffered in the summary
+    pub fn is_streaming(self) -> bool {
+        matches!(self, OutputFormat::Jsonl | OutputFormat::Csv)
+    }
+}
+
+/// Aggregated dependency analysis results for a run
+#[derive(Debug, Default, serde::Serialize)]
+pub struct DepSummary {
+    pub projects_checked: usize,
+    pub total_unused: usize,
+    pub total_removed: usize,
+    /// Removals undone because `cargo check` failed (`--verify`)
+    pub total_rolled_back: usize,
+    pub total_duplicates: usize,
+    /// Features suggested for trimming (`--features`)
+    pub total_feature_findings: usize,
+    /// Path dependencies pointing at missing directories (`--stale-paths`)
+    pub total_stale_paths: usize,
+    /// Stale path dependencies removed or commented out
+    pub total_stale_paths_fixed: usize,
+    /// Normal dependencies only used by tests, examples or benches (`--dev-deps`)
+    pub total_dev_only: usize,
+    /// Dev-only dependencies moved to dev-dependencies (`--apply`)
+    pub total_moved_to_dev: usize,
+    /// Dependencies with newer releases (`--outdated`)
+    pub total_outdated: usize,
+    /// Dependencies members could inherit from `[workspace.dependencies]` (`--hoist-deps`)
+    pub total_hoistable: usize,
+    /// Member entries rewritten to `workspace = true`
+    pub total_hoisted: usize,
+    /// Cargo.lock entries dropped after the manifest edits (`--prune-lock`)
+    pub total_lock_entries_pruned: usize,
+    pub failed: usize,
+    pub results: Vec<DependencyCleanResult>,
+}
+
+impl DepSummary {
+    /// Count a project's result, keeping it in `results` only when `retain` is set
+    pub fn record(&mut self, result: DependencyCleanResult, retain: bool) {
+        self.projects_checked += 1;
+        self.total_unused += result.unused_deps.len();
+        self.total_removed += result.removed_count;
+        self.total_rolled_back += result.rolled_back.len();
+        self.total_duplicates += result.duplicates.len();
+        self.total_feature_findings += result.features.len();
+        self.total_stale_paths += result.stale_paths.len();
+        self.total_stale_paths_fixed += result.stale_paths_fixed;
+        self.total_dev_only += result.dev_only.len();
+        self.total_moved_to_dev += result.moved_to_dev;
+        self.total_outdated += result.outdated.len();
+        self.total_hoistable += result.hoistable.len();
+        self.total_hoisted += result.hoisted;
+        self.total_lock_entries_pruned += result.lock_entries_pruned;
+        if !result.success {
+            self.failed += 1;
+        }
+        if retain {
+            self.results.push(result);
+        }
+    }
+
+    /// Combine partial summaries from parallel workers
+    pub fn merge(mut self, other: Self) -> Self {
+        self.projects_checked += other.projects_checked;
+        self.total_unused += other.total_unused;
+        self.total_removed += other.total_removed;
+        self.total_rolled_back += other.total_rolled_back;
+        self.total_duplicates += other.total_duplicates;
+        self.total_feature_findings += other.total_feature_findings;
+        self.total_stale_paths += other.total_stale_paths;
+        self.total_stale_paths_fixed += other.total_stale_paths_fixed;
+        self.total_dev_only += other.total_dev_only;
+        self.total_moved_to_dev += other.total_moved_to_dev;
+        self.total_outdated += other.total_outdated;
+        self.total_hoistable += other.total_hoistable;
+        self.total_hoisted += other.total_hoisted;
+        self.total_lock_entries_pruned += other.total_lock_entries_pruned;
+        self.failed += other.failed;
+        self.results.extend(other.results);
+        self
+    }
+}
+
+/// Reported and actual freed space may differ by this share of the reported figure
+const RECLAIM_TOLERANCE: f64 = 0.10;
+
+/// Differences up to this many bytes are never flagged, to absorb unrelated disk activity
+const RECLAIM_SLACK_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Free space on one filesystem before and after the run
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FilesystemReclaim {
+    /// A path on the filesystem, the parent of a target directory
+    pub path: String,
+    pub free_before: u64,
+    pub free_after: u64,
+}
+
+/// End-of-run comparison of actual free space with the reported freed bytes (`--verify-reclaimed`)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ReclaimCheck {
+    pub filesystems: Vec<FilesystemReclaim>,
+    /// Net change of free space over all filesystems; negative if space was consumed
+    pub reclaimed_bytes: i64,
+    /// `reclaimed_bytes` is far from the reported `total_freed_bytes`
+    pub discrepancy: bool,
+}
+
+impl ReclaimCheck {
+    pub fn new(filesystems: Vec<FilesystemReclaim>, reported_freed_bytes: u64) -> Self {
+        let reclaimed_bytes = filesystems
+            .iter()
+            .map(|fs| fs.free_after as i64 - fs.free_before as i64)
+            .sum::<i64>();
+        let difference = reclaimed_bytes.abs_diff(reported_freed_bytes as i64);
+        let tolerance = (reported_freed_bytes as f64 * RECLAIM_TOLERANCE) as u64;
+        Self {
+            filesystems,
+            reclaimed_bytes,
+            discrepancy: difference > tolerance.max(RECLAIM_SLACK_BYTES),
+        }
+    }
+}
+
+/// Wall-clock time spent in each phase of a run, in milliseconds
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct PhaseTimings {
+    /// Walking the filesystem and applying project filters
+    pub discovery_ms: u64,
+    /// Measuring target directory sizes
+    pub sizing_ms: u64,
+    /// Analyzing dependencies (`--clean-deps`, `--remove-deps`, `--duplicates`, `--features`, `--stale-paths`, `--dev-deps`, `--hoist-deps`, `--outdated`)
+    pub dependency_ms: u64,
+    /// Cleaning targets
+    pub cleaning_ms: u64,
+}
+
+#[derive(Debug, Default, serde::Serialize)]
 pub struct Summary {
     pub total_projects: usize,
     pub cleaned: usize,
     pub failed: usize,
     pub total_freed_bytes: u64,
+    /// Physical space freed, present with `--physical`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_physical_freed_bytes: Option<u64>,
+    /// Per-project results; always empty with streaming formats (jsonl, csv)
     pub results: Vec<CleanResult>,
+    pub skipped: Vec<SkippedProject>,
+    /// Projects left alone because their git working tree has uncommitted changes (`--skip-dirty`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped_dirty: Vec<SkippedProject>,
+    pub warnings: Vec<Warning>,
+    pub phase_timings: PhaseTimings,
+    /// Freed bytes per `[tags]` config tag; a project counts toward every tag it matches
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub freed_by_tag: BTreeMap<String, u64>,
+    /// Size of each cross-compilation target's outputs across all projects, before cleaning
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub cross_target_bytes: BTreeMap<String, u64>,
+    /// Freed bytes per build profile cleaned with `--profile`, across all projects
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub freed_by_profile: BTreeMap<String, u64>,
+    /// Freed bytes per cleaned cross-compilation target, across all projects
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub freed_by_triple: BTreeMap<String, u64>,
+    /// Freed bytes per web framework tool (wasm-pack, trunk), across all projects
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub freed_by_framework: BTreeMap<String, u64>,
+    /// Freed bytes per directory given on the command line, when several were given
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub freed_by_root: BTreeMap<String, u64>,
+    /// Bytes freed from documentation output (`--doc-only`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_doc_freed_bytes: Option<u64>,
+    /// Bytes freed from coverage artifacts (`--coverage`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_coverage_freed_bytes: Option<u64>,
+    /// Free space actually reclaimed, present with `--verify-reclaimed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reclaim_check: Option<ReclaimCheck>,
+    /// Dependency analysis results, present when any dependency check ran
+    #[serde(skip)]
+    pub dep_summary: Option<DepSummary>,
+    /// Only dependencies were analyzed (`deps`), so there are no clean results to show
+    #[serde(skip)]
+    pub deps_only: bool,
 }
 
-/// Create progress bars for cleaning operations
-pub fn create_progress_bars(
-    project_count: usize,
-    show_progress: bool,
-) -> (Option<Arc<MultiProgress>>, Option<ProgressBar>) {
-    if !show_progress {
-        return (None, None);
+impl Summary {
+    /// Count a project's result, keeping it in `results` only when `retain` is set
+    pub fn record(&mut self, result: CleanResult, retain: bool) {
+        if result.success {
+            self.cleaned += 1;
+        } else {
+            self.failed += 1;
+        }
+        self.total_freed_bytes += result.freed_bytes;
+        if let Some(physical) = result.physical_freed_bytes {
+            *self.total_physical_freed_bytes.get_or_insert(0) += physical;
+        }
+        for cross_target in &result.cross_targets {
+            *self.cross_target_bytes.entry(cross_target.triple.clone()).or_default() += cross_target.size;
+        }
+        for (profile, freed_bytes) in &result.freed_by_profile {
+            *self.freed_by_profile.entry(profile.clone()).or_default() += freed_bytes;
+        }
+        for (triple, freed_bytes) in &result.freed_by_triple {
+            *self.freed_by_triple.entry(triple.clone()).or_default() += freed_bytes;
+        }
+        for (tool, freed_bytes) in &result.freed_by_framework {
+            *self.freed_by_framework.entry(tool.clone()).or_default() += freed_bytes;
+        }
+        if let Some(doc) = result.doc_freed_bytes {
+            *self.total_doc_freed_bytes.get_or_insert(0) += doc;
+        }
+        if let Some(coverage) = result.coverage_freed_bytes {
+            *self.total_coverage_freed_bytes.get_or_insert(0) += coverage;
+        }
+        if retain {
+            self.results.push(result);
+        }
     }
 
-    let multi = Arc::new(MultiProgress::new());
-    let overall_pb = {
-        let pb = multi.add(ProgressBar::new(project_count as u64));
-        pb.set_style(
+    /// Attribute freed bytes to each of a project's tags
+    pub fn record_tags(&mut self, tags: Vec<String>, freed_bytes: u64) {
+        for tag in tags {
+            *self.freed_by_tag.entry(tag).or_default() += freed_bytes;
+        }
+    }
+
+    /// Attribute freed bytes to the directory a project was found under
+    pub fn record_root(&mut self, root: &Path, freed_bytes: u64) {
+        *self.freed_by_root.entry(root.to_string_lossy().to_string()).or_default() += freed_bytes;
+    }
+
+    /// Combine partial summaries from parallel workers
+    pub fn merge(mut self, other: Self) -> Self {
+        self.cleaned += other.cleaned;
+        self.failed += other.failed;
+        self.total_freed_bytes += other.total_freed_bytes;
+        if let Some(physical) = other.total_physical_freed_bytes {
+            *self.total_physical_freed_bytes.get_or_insert(0) += physical;
+        }
+        self.results.extend(other.results);
+        self.skipped.extend(other.skipped);
+        for (tag, freed_bytes) in other.freed_by_tag {
+            *self.freed_by_tag.entry(tag).or_default() += freed_bytes;
+        }
+        for (triple, size) in other.cross_target_bytes {
+            *self.cross_target_bytes.entry(triple).or_default() += size;
+        }
+        for (profile, freed_bytes) in other.freed_by_profile {
+            *self.freed_by_profile.entry(profile).or_default() += freed_bytes;
+        }
+        for (triple, freed_bytes) in other.freed_by_triple {
+            *self.freed_by_triple.entry(triple).or_default() += freed_bytes;
+        }
+        for (tool, freed_bytes) in other.freed_by_framework {
+            *self.freed_by_framework.entry(tool).or_default() += freed_bytes;
+        }
+        for (root, freed_bytes) in other.freed_by_root {
+            *self.freed_by_root.entry(root).or_default() += freed_bytes;
+        }
+        if let Some(doc) = other.total_doc_freed_bytes {
+            *self.total_doc_freed_bytes.get_or_insert(0) += doc;
+        }
+        if let Some(coverage) = other.total_coverage_freed_bytes {
+            *self.total_coverage_freed_bytes.get_or_insert(0) += coverage;
+        }
+        self
+    }
+}
+
+/// Writer for streaming formats, emitting each result as soon as it is known
+///
+/// Only aggregate counters are kept in memory, so `Summary::results` stays empty.
+/// `jsonl` writes one object per line tagged with a `type` field (`clean_result`,
+/// `dep_result`, and finally `summary`/`dep_summary`); `csv` writes one row per
+/// cleaned project and nothing else.
+pub struct ResultStream {
+    format: OutputFormat,
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl ResultStream {
+    pub fn new(format: OutputFormat, writer: Box<dyn Write + Send>) -> Result<Self> {
+        let stream = Self {
+            format,
+            writer: Mutex::new(writer),
+        };
+        if format == OutputFormat::Csv {
+            stream.write_line("path,success,freed_bytes,error")?;
+        }
+        Ok(stream)
+    }
+
+    pub fn write_result(&self, result: &CleanResult) -> Result<()> {
+        match self.format {
+            OutputFormat::Csv => self.write_line(&format!(
+                "{},{},{},{}",
+                csv_field(&result.path),
+                result.success,
+                result.freed_bytes,
+                csv_field(result.error.as_deref().unwrap_or(""))
+            )),
+            _ => self.write_tagged("clean_result", result),
+        }
+    }
+
+    pub fn write_dep_result(&self, result: &DependencyCleanResult) -> Result<()> {
+        match self.format {
+            OutputFormat::Csv => Ok(()),
+            _ => self.write_tagged("dep_result", result),
+        }
+    }
+
+    /// Write the aggregate summary (jsonl only) and flush
+    pub fn finish(&self, summary: &Summary) -> Result<()> {
+        if self.format == OutputFormat::Jsonl {
+            self.write_tagged("summary", summary)?;
+            if let Some(ref dep_summary) = summary.dep_summary {
+                self.write_tagged("dep_summary", dep_summary)?;
+            }
+        }
+        self.writer.lock().unwrap().flush()?;
+        Ok(())
+    }
+
+    fn write_tagged<T: serde::Serialize>(&self, kind: &str, value: &T) -> Result<()> {
+        let mut json = serde_json::to_value(value)?;
+        if let serde_json::Value::Object(ref mut map) = json {
+            map.insert("type".to_string(), kind.into());
+        }
+        self.write_line(&json.to_string())
+    }
+
+    fn write_line(&self, line: &str) -> Result<()> {
+        writeln!(self.writer.lock().unwrap(), "{}", line)?;
+        Ok(())
+    }
+}
+
+/// Quote a CSV field when it contains separators, quotes or line breaks
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// JSON document covering both target cleaning and dependency analysis
+#[derive(serde::Serialize)]
+struct CombinedReport<'a> {
+    clean_summary: &'a Summary,
+    dep_summary: Option<&'a DepSummary>,
+}
+
+/// Serialize a summary as JSON
+///
+/// The document always has the same shape: a `clean_summary` section, and a
+/// `dep_summary` section that is `null` unless dependencies were analyzed.
+pub fn summary_to_json(summary: &Summary) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&CombinedReport {
+        clean_summary: summary,
+        dep_summary: summary.dep_summary.as_ref(),
+    })?)
+}
+
+/// Default number of per-project spinners shown at once
+pub const DEFAULT_MAX_VISIBLE_BARS: usize = 8;
+
+#[derive(Debug, Default)]
+struct BarState {
+    visible: usize,
+    hidden: usize,
+    overflow_shown: bool,
+}
+
+/// Progress display for a cleaning run
+///
+/// Shows an overall bar plus at most `max_visible` per-project spinners. Projects
+/// started while all slots are taken are summarized in a single "+N more" line, and
+/// finished spinners are removed so the display never grows past the terminal.
+pub struct ProgressDisplay {
+    multi: MultiProgress,
+    overall: ProgressBar,
+    overflow: ProgressBar,
+    max_visible: usize,
+    state: Mutex<BarState>,
+}
+
+impl ProgressDisplay {
+    /// Create the progress display, or `None` when progress output is disabled
+    pub fn new(project_count: usize, max_visible: usize, show_progress: bool) -> Option<Self> {
+        if !show_progress {
+            return None;
+        }
+        Some(Self::with_draw_target(project_count, max_visible, ProgressDrawTarget::stderr()))
+    }
+
+    fn with_draw_target(project_count: usize, max_visible: usize, target: ProgressDrawTarget) -> Self {
+        let multi = MultiProgress::with_draw_target(target);
+        let overall = multi.add(ProgressBar::new(project_count as u64));
+        overall.set_style(
             ProgressStyle::default_bar()
                 .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} projects completed")
                 .unwrap()
                 .progress_chars("#>-"),
         );
-        pb.set_message("Starting...");
-        pb
-    };
+        overall.set_message("Starting...");
+
+        let overflow = ProgressBar::new_spinner();
+        overflow.set_style(ProgressStyle::default_spinner().template("  {msg}").unwrap());
+
+        Self {
+            multi,
+            overall,
+            overflow,
+            max_visible,
+            state: Mutex::new(BarState::default()),
+        }
+    }
+
+    /// Register a project as started, returning its spinner if a display slot is free
+    pub fn start_project(&self, project_path: &Path) -> Option<ProgressBar> {
+        let mut state = self.state.lock().unwrap();
+        if state.visible >= self.max_visible {
+            state.hidden += 1;
+            self.update_overflow(&mut state);
+            return None;
+        }
+        state.visible += 1;
+
+        let pb = if state.overflow_shown {
+            self.multi.insert_before(&self.overflow, ProgressBar::new_spinner())
+        } else {
+            self.multi.add(ProgressBar::new_spinner())
+        };
+        pb.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.green} {msg}")
+                .unwrap()
+                .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]),
+        );
+        pb.set_message(format!("Cleaning: {}", project_display_name(project_path)));
+        pb.enable_steady_tick(std::time::Duration::from_millis(100));
+        Some(pb)
+    }
+
+    /// Mark a project as finished, removing its spinner from the display
+    pub fn finish_project(&self, pb: Option<ProgressBar>) {
+        let mut state = self.state.lock().unwrap();
+        match pb {
+            Some(pb) => {
+                pb.finish_and_clear();
+                self.multi.remove(&pb);
+                state.visible -= 1;
+            }
+            None => {
+                state.hidden -= 1;
+                self.update_overflow(&mut state);
+            }
+        }
+        self.overall.inc(1);
+    }
+
+    /// Finish the overall bar once every project is done
+    pub fn finish(&self) {
+        self.overall.finish_with_message("All projects completed!");
+    }
 
-    (Some(multi), Some(overall_pb))
+    fn update_overflow(&self, state: &mut BarState) {
+        if state.hidden > 0 {
+            self.overflow.set_message(format!("+{} more", state.hidden));
+            if !state.overflow_shown {
+                self.multi.add(self.overflow.clone());
+                state.overflow_shown = true;
+            }
+        } else if state.overflow_shown {
+            self.overflow.finish_and_clear();
+            self.multi.remove(&self.overflow);
+            state.overflow_shown = false;
+        }
+    }
 }
 
-/// Create a progress bar for an individual project
-pub fn create_project_progress_bar(
-    multi: &Arc<MultiProgress>,
-    project_path: &std::path::Path,
-) -> ProgressBar {
-    let pb = multi.add(ProgressBar::new_spinner());
+/// Progress bar for a whole-run phase over `len` projects, or `None` when progress output is disabled
+pub fn phase_progress(len: usize, message: &'static str, show_progress: bool) -> Option<ProgressBar> {
+    if !show_progress {
+        return None;
+    }
+    let pb = ProgressBar::new(len as u64);
     pb.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.green} {msg}")
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
             .unwrap()
-            .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]),
+            .progress_chars("#>-"),
     );
-    let project_name = project_path
+    pb.set_message(message);
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
+    Some(pb)
+}
+
+/// Spinner counting the directories discovery has walked, or `None` when progress output is disabled
+pub fn scan_progress(show_progress: bool) -> Option<ProgressBar> {
+    if !show_progress {
+        return None;
+    }
+    let pb = ProgressBar::new_spinner();
+    pb.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.green} [{elapsed_precise}] Scanning: {human_pos} directories ({per_sec})")
+            .unwrap(),
+    );
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
+    Some(pb)
+}
+
+/// Short name used to identify a project in progress output
+fn project_display_name(project_path: &Path) -> String {
+    project_path
         .file_name()
         .and_then(|n| n.to_str())
         .map(|s| s.to_string())
-        .unwrap_or_else(|| project_path.to_string_lossy().to_string());
-    pb.set_message(format!("Cleaning: {}", project_name));
-    pb.enable_steady_tick(std::time::Duration::from_millis(100));
-    pb
+        .unwrap_or_else(|| project_path.to_string_lossy().to_string())
 }
 
-/// Print initial information
-pub fn print_start_info(root: &std::path::Path, project_count: usize, dry_run: bool) {
-    println!("{} {}", "[INFO]".blue().bold(), format!("Starting cargo clean from: {:?}", root));
-    println!("{} Searching for Cargo projects...", "[INFO]".blue().bold());
-    println!("{} Found {} project(s)", "[INFO]".blue().bold(), project_count);
-    if dry_run {
-        println!("{} DRY RUN MODE - no changes will be made", "[INFO]".yellow().bold());
-    }
-    println!();
+/// List cross-compilation targets with their sizes, marking WebAssembly ones
+fn format_cross_targets<'a>(targets: impl Iterator<Item = (&'a str, u64)>) -> String {
+    targets
+        .map(|(triple, size)| {
+            let wasm = if is_wasm(triple) { " [wasm]" } else { "" };
+            format!("{} {}{}", triple, format_bytes(size), wasm)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// List build profiles or framework tools with the bytes freed from each
+fn format_freed_by(freed_bytes: &BTreeMap<String, u64>) -> String {
+    freed_bytes
+        .iter()
+        .map(|(name, freed_bytes)| format!("{} {}", name, format_bytes(*freed_bytes)))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 /// Print summary
 pub fn print_summary(summary: &Summary) {
-    println!();
-    println!("{} {}", "[INFO]".blue().bold(), "=== SUMMARY ===");
-    println!(
+    let _ = write_summary(&mut std::io::stdout().lock(), summary);
+}
+
+/// Write the cleaning counters and space totals of the summary
+fn write_clean_totals(out: &mut impl Write, summary: &Summary) -> std::io::Result<()> {
+    writeln!(
+        out,
         "{} Successfully cleaned: {} project(s)",
         "[SUCCESS]".green().bold(),
         summary.cleaned
-    );
+    )?;
 
     if summary.total_freed_bytes > 0 {
-        println!(
+        writeln!(
+            out,
             "{} Total storage freed: {}",
             "[SUCCESS]".green().bold(),
             format_bytes(summary.total_freed_bytes)
-        );
+        )?;
     } else {
-        println!("{} No storage was freed", "[INFO]".blue().bold());
+        writeln!(out, "{} No storage was freed", "[INFO]".blue().bold())?;
+    }
+    if let Some(physical) = summary.total_physical_freed_bytes {
+        writeln!(out, "{} Physical space freed: {}", "[INFO]".blue().bold(), format_bytes(physical))?;
+    }
+    if let Some(ref check) = summary.reclaim_check {
+        let reclaimed = match u64::try_from(check.reclaimed_bytes) {
+            Ok(bytes) => format_bytes(bytes),
+            Err(_) => format!("-{}", format_bytes(check.reclaimed_bytes.unsigned_abs())),
+        };
+        writeln!(
+            out,
+            "{} Free space reclaimed: {} on {} filesystem(s)",
+            "[INFO]".blue().bold(),
+            reclaimed,
+            check.filesystems.len()
+        )?;
+        if check.discrepancy {
+            writeln!(
+                out,
+                "{} Reclaimed space differs from the reported {}; hardlinks, shared targets, compression, \
+                 the trash or other disk activity may explain the difference",
+                "[WARNING]".yellow().bold(),
+                format_bytes(summary.total_freed_bytes)
+            )?;
+        }
+    }
+
+    if !summary.freed_by_tag.is_empty() {
+        let by_tag: Vec<String> = summary
+            .freed_by_tag
+            .iter()
+            .map(|(tag, freed_bytes)| format!("{}: {}", tag, format_bytes(*freed_bytes)))
+            .collect();
+        writeln!(out, "{} Freed by tag: {}", "[INFO]".blue().bold(), by_tag.join(", "))?;
+    }
+
+    if !summary.freed_by_profile.is_empty() {
+        writeln!(
+            out,
+            "{} Freed by profile: {}",
+            "[INFO]".blue().bold(),
+            format_freed_by(&summary.freed_by_profile)
+        )?;
+    }
+
+    if !summary.freed_by_triple.is_empty() {
+        writeln!(
+            out,
+            "{} Freed by target triple: {}",
+            "[INFO]".blue().bold(),
+            format_cross_targets(summary.freed_by_triple.iter().map(|(triple, size)| (triple.as_str(), *size)))
+        )?;
+    }
+
+    if !summary.freed_by_framework.is_empty() {
+        writeln!(
+            out,
+            "{} Freed by framework output: {}",
+            "[INFO]".blue().bold(),
+            format_freed_by(&summary.freed_by_framework)
+        )?;
+    }
+
+    if !summary.freed_by_root.is_empty() {
+        let by_root: Vec<String> = summary
+            .freed_by_root
+            .iter()
+            .map(|(root, freed_bytes)| format!("{}: {}", root, format_bytes(*freed_bytes)))
+            .collect();
+        writeln!(out, "{} Freed by directory: {}", "[INFO]".blue().bold(), by_root.join(", "))?;
+    }
+
+    if let Some(doc) = summary.total_doc_freed_bytes {
+        writeln!(out, "{} Documentation freed: {}", "[INFO]".blue().bold(), format_bytes(doc))?;
+    }
+
+    if let Some(coverage) = summary.total_coverage_freed_bytes {
+        writeln!(out, "{} Coverage artifacts freed: {}", "[INFO]".blue().bold(), format_bytes(coverage))?;
+    }
+
+    if !summary.cross_target_bytes.is_empty() {
+        writeln!(
+            out,
+            "{} Cross-compilation targets: {}",
+            "[INFO]".blue().bold(),
+            format_cross_targets(summary.cross_target_bytes.iter().map(|(triple, size)| (triple.as_str(), *size)))
+        )?;
+    }
+    Ok(())
+}
+
+/// Write the human-readable summary to `out`
+pub fn write_summary(out: &mut impl Write, summary: &Summary) -> std::io::Result<()> {
+    writeln!(out)?;
+    writeln!(out, "{} === SUMMARY ===", "[INFO]".blue().bold())?;
+    if !summary.deps_only {
+        write_clean_totals(out, summary)?;
+    }
+
+    if !summary.skipped.is_empty() {
+        writeln!(
+            out,
+            "{} Skipped: {} project(s)",
+            "[WARNING]".yellow().bold(),
+            summary.skipped.len()
+        )?;
+        for skipped in &summary.skipped {
+            writeln!(out, "  {} {} ({})", "•".yellow(), skipped.path, skipped.reason)?;
+        }
+    }
+
+    if !summary.skipped_dirty.is_empty() {
+        writeln!(
+            out,
+            "{} Skipped with uncommitted changes: {} project(s)",
+            "[INFO]".blue().bold(),
+            summary.skipped_dirty.len()
+        )?;
+        for skipped in &summary.skipped_dirty {
+            writeln!(out, "  {} {} ({})", "•".blue(), skipped.path, skipped.reason)?;
+        }
+    }
+
+    if !summary.warnings.is_empty() {
+        writeln!(
+            out,
+            "{} Warnings: {}",
+            "[WARNING]".yellow().bold(),
+            summary.warnings.len()
+        )?;
+        for warning in &summary.warnings {
+            match warning.path {
+                Some(ref path) => writeln!(out, "  {} {}: {}", "•".yellow(), path, warning.message)?,
+                None => writeln!(out, "  {} {}", "•".yellow(), warning.message)?,
+            }
+        }
     }
 
     if summary.failed > 0 {
-        println!(
+        writeln!(
+            out,
             "{} Failed to clean: {} project(s)",
             "[ERROR]".red().bold(),
             summary.failed
-        );
+        )?;
     } else {
-        println!("{} All done!", "[SUCCESS]".green().bold());
+        writeln!(out, "{} All done!", "[SUCCESS]".green().bold())?;
+    }
+
+    if let Some(ref dep_summary) = summary.dep_summary {
+        write_dep_summary(out, dep_summary)?;
+    }
+    Ok(())
+}
+
+/// Escape text for use in XML attributes and character data
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Render a summary as JUnit XML
+///
+/// Each cleaned project is a passing test case, each failed project a failing one
+/// (with its error as the failure message) and each skipped project a skipped one.
+/// Freed bytes are attached as a `freed_bytes` property on every case.
+pub fn summary_to_junit(summary: &Summary) -> String {
+    let tests = summary.results.len() + summary.skipped.len();
+    let time = summary.phase_timings.cleaning_ms as f64 / 1000.0;
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"deepclean\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+        tests, summary.failed, summary.skipped.len(), time
+    ));
+    xml.push_str(&format!(
+        "  <testsuite name=\"deepclean\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{:.3}\">\n",
+        tests, summary.failed, summary.skipped.len(), time
+    ));
+    xml.push_str("    <properties>\n");
+    xml.push_str(&format!(
+        "      <property name=\"total_freed_bytes\" value=\"{}\"/>\n",
+        summary.total_freed_bytes
+    ));
+    xml.push_str("    </properties>\n");
+
+    for result in &summary.results {
+        xml.push_str(&format!(
+            "    <testcase classname=\"deepclean\" name=\"{}\">\n",
+            xml_escape(&result.path)
+        ));
+        xml.push_str(&format!(
+            "      <properties><property name=\"freed_bytes\" value=\"{}\"/></properties>\n",
+            result.freed_bytes
+        ));
+        if !result.success {
+            let error = xml_escape(result.error.as_deref().unwrap_or("unknown error"));
+            xml.push_str(&format!("      <failure message=\"{}\">{}</failure>\n", error, error));
+        }
+        xml.push_str("    </testcase>\n");
+    }
+    for skipped in &summary.skipped {
+        xml.push_str(&format!(
+            "    <testcase classname=\"deepclean\" name=\"{}\">\n",
+            xml_escape(&skipped.path)
+        ));
+        xml.push_str(&format!("      <skipped message=\"{}\"/>\n", xml_escape(&skipped.reason)));
+        xml.push_str("    </testcase>\n");
+    }
+
+    xml.push_str("  </testsuite>\n</testsuites>");
+    xml
+}
+
+/// Print how long each phase of the run took
+pub fn print_phase_timings(timings: &PhaseTimings) {
+    let _ = write_phase_timings(&mut std::io::stdout().lock(), timings);
+}
+
+/// Write how long each phase of the run took
+pub fn write_phase_timings(out: &mut impl Write, timings: &PhaseTimings) -> std::io::Result<()> {
+    let format_ms = |ms: u64| format!("{:.2}s", ms as f64 / 1000.0);
+    writeln!(
+        out,
+        "{} Phase timings: discovery {}, sizing {}, dependencies {}, cleaning {}",
+        "[INFO]".blue().bold(),
+        format_ms(timings.discovery_ms),
+        format_ms(timings.sizing_ms),
+        format_ms(timings.dependency_ms),
+        format_ms(timings.cleaning_ms)
+    )
+}
+
+/// Print observed deletion concurrency (shown at `-vv`)
+pub fn print_throttle_metrics(metrics: &ThrottleMetrics) {
+    println!(
+        "{} IO throttle: limit {}/{}, peak concurrent deletions {}, manual deletions {}, cargo cleans {}",
+        "[DEBUG]".cyan(),
+        metrics.current_limit,
+        metrics.max_jobs,
+        metrics.peak_active,
+        metrics.manual_deletes,
+        metrics.cargo_cleans
+    );
+}
+
+/// Write the dependency analysis section of the summary
+fn write_dep_summary(out: &mut impl Write, dep_summary: &DepSummary) -> std::io::Result<()> {
+    writeln!(out)?;
+    writeln!(out, "{} === DEPENDENCIES ===", "[INFO]".blue().bold())?;
+    writeln!(
+        out,
+        "{} Checked: {} project(s)",
+        "[INFO]".blue().bold(),
+        dep_summary.projects_checked
+    )?;
+    writeln!(
+        out,
+        "{} Unused dependencies found: {}",
+        "[INFO]".blue().bold(),
+        dep_summary.total_unused
+    )?;
+    if dep_summary.total_removed > 0 {
+        writeln!(
+            out,
+            "{} Unused dependencies removed: {}",
+            "[SUCCESS]".green().bold(),
+            dep_summary.total_removed
+        )?;
+    }
+    if dep_summary.total_rolled_back > 0 {
+        writeln!(
+            out,
+            "{} Removals rolled back after cargo check failed: {}",
+            "[WARNING]".yellow().bold(),
+            dep_summary.total_rolled_back
+        )?;
+    }
+    if dep_summary.total_duplicates > 0 {
+        writeln!(
+            out,
+            "{} Duplicated crates: {}",
+            "[INFO]".blue().bold(),
+            dep_summary.total_duplicates
+        )?;
+    }
+    if dep_summary.total_feature_findings > 0 {
+        writeln!(
+            out,
+            "{} Features that could be trimmed: {}",
+            "[INFO]".blue().bold(),
+            dep_summary.total_feature_findings
+        )?;
+    }
+    if dep_summary.total_stale_paths > 0 {
+        writeln!(
+            out,
+            "{} Stale path dependencies: {} ({} fixed)",
+            "[WARNING]".yellow().bold(),
+            dep_summary.total_stale_paths,
+            dep_summary.total_stale_paths_fixed
+        )?;
+    }
+    if dep_summary.total_dev_only > 0 {
+        writeln!(
+            out,
+            "{} Dependencies only used by tests, examples or benches: {} ({} moved)",
+            "[INFO]".blue().bold(),
+            dep_summary.total_dev_only,
+            dep_summary.total_moved_to_dev
+        )?;
+    }
+    if dep_summary.total_outdated > 0 {
+        writeln!(
+            out,
+            "{} Outdated dependencies: {}",
+            "[INFO]".blue().bold(),
+            dep_summary.total_outdated
+        )?;
+    }
+    if dep_summary.total_hoistable > 0 {
+        writeln!(
+            out,
+            "{} Dependencies to hoist into [workspace.dependencies]: {} ({} member entries rewritten)",
+            "[INFO]".blue().bold(),
+            dep_summary.total_hoistable,
+            dep_summary.total_hoisted
+        )?;
+    }
+    if dep_summary.total_lock_entries_pruned > 0 {
+        writeln!(
+            out,
+            "{} Cargo.lock entries pruned: {}",
+            "[SUCCESS]".green().bold(),
+            dep_summary.total_lock_entries_pruned
+        )?;
     }
+    if dep_summary.failed > 0 {
+        writeln!(
+            out,
+            "{} Dependency analysis failed: {} project(s)",
+            "[ERROR]".red().bold(),
+            dep_summary.failed
+        )?;
+    }
+    Ok(())
+}
+
+/// Print verbose output for a cleaned project, skipping already-clean ones when `hide_empty` is set
+pub fn print_verbose_cleaned(result: &CleanResult, hide_empty: bool) {
+    let _ = write_verbose_cleaned(&mut std::io::stdout().lock(), result, hide_empty);
 }
 
-/// Print verbose output for a cleaned project
-pub fn print_verbose_cleaned(result: &CleanResult) {
+/// Write the verbose lines for a cleaned project, see [`print_verbose_cleaned`]
+pub fn write_verbose_cleaned(out: &mut impl Write, result: &CleanResult, hide_empty: bool) -> std::io::Result<()> {
+    let age_range = match (result.oldest_artifact, result.newest_artifact) {
+        (Some(oldest), Some(newest)) => {
+            format!(", artifacts built {} to {}", format_age(oldest), format_age(newest))
+        }
+        _ => String::new(),
+    };
     if result.freed_bytes > 0 {
-        println!(
-            "{} Cleaned: {} (freed: {})",
+        writeln!(
+            out,
+            "{} Cleaned: {} (freed: {}{})",
             "[SUCCESS]".green().bold(),
             result.path,
-            format_bytes(result.freed_bytes)
-        );
-    } else {
-        println!(
-            "{} Cleaned: {} (already clean)",
+            format_bytes(result.freed_bytes),
+            age_range
+        )?;
+    } else if !hide_empty {
+        writeln!(
+            out,
+            "{} Cleaned: {} (already clean{})",
             "[SUCCESS]".green().bold(),
-            result.path
-        );
+            result.path,
+            age_range
+        )?;
+    }
+    if !result.cross_targets.is_empty() && (result.freed_bytes > 0 || !hide_empty) {
+        writeln!(
+            out,
+            "  {} cross-compilation targets: {}",
+            "•".blue(),
+            format_cross_targets(result.cross_targets.iter().map(|t| (t.triple.as_str(), t.size)))
+        )?;
+    }
+    if !result.freed_by_profile.is_empty() && (result.freed_bytes > 0 || !hide_empty) {
+        writeln!(out, "  {} profiles: {}", "•".blue(), format_freed_by(&result.freed_by_profile))?;
     }
+    if !result.freed_by_triple.is_empty() && (result.freed_bytes > 0 || !hide_empty) {
+        writeln!(
+            out,
+            "  {} freed by triple: {}",
+            "•".blue(),
+            format_cross_targets(result.freed_by_triple.iter().map(|(triple, size)| (triple.as_str(), *size)))
+        )?;
+    }
+    if !result.freed_by_framework.is_empty() && (result.freed_bytes > 0 || !hide_empty) {
+        writeln!(out, "  {} framework outputs: {}", "•".blue(), format_freed_by(&result.freed_by_framework))?;
+    }
+    Ok(())
 }
 
-/// Print error message
-pub fn print_error(project_path: &std::path::Path, error_msg: &str) {
+/// Print the one line `--workspace-summary` reports for a cleaned workspace
+pub fn print_workspace_summary(result: &CleanResult, hide_empty: bool) {
+    let _ = write_workspace_summary(&mut std::io::stdout().lock(), result, hide_empty);
+}
+
+/// Write the `--workspace-summary` line for a cleaned workspace, unless `hide_empty` hides it
+pub fn write_workspace_summary(out: &mut impl Write, result: &CleanResult, hide_empty: bool) -> std::io::Result<()> {
+    if result.freed_bytes > 0 || !hide_empty {
+        writeln!(out, "{} {}", "[SUCCESS]".green().bold(), workspace_summary_line(result))?;
+    }
+    Ok(())
+}
+
+/// `Cleaned workspace: <path> (freed: <size>, <n> member(s))`, without the member count
+/// when discovery didn't ask cargo for it
+fn workspace_summary_line(result: &CleanResult) -> String {
+    let members = match result.members.len() {
+        0 => String::new(),
+        count => format!(", {} member(s)", count),
+    };
+    format!("Cleaned workspace: {} (freed: {}{})", result.path, format_bytes(result.freed_bytes), members)
+}
+
+/// Print duplicated crates for a project with the packages that pull in each version
+pub fn print_duplicates(project_path: &Path, duplicates: &[DuplicateDependency]) {
+    println!(
+        "{} Found {} duplicated crate(s) in {}:",
+        "[INFO]".blue().bold(),
+        duplicates.len(),
+        project_path.display()
+    );
+    for duplicate in duplicates {
+        println!("  {} {}", "•".yellow(), duplicate.name.bright_yellow());
+        for version in &duplicate.versions {
+            if version.dependents.is_empty() {
+                println!("      {}", version.version);
+            } else {
+                println!("      {} (via {})", version.version, version.dependents.join(", "));
+            }
+            for chain in &version.chains {
+                println!("        {}", chain.join(" → ").dimmed());
+            }
+        }
+    }
+}
+
+/// Print a project's feature suggestions from `--features`
+pub fn print_feature_findings(project_path: &Path, findings: &[FeatureFinding]) {
+    println!(
+        "{} Found {} feature(s) that could be trimmed in {}:",
+        "[INFO]".blue().bold(),
+        findings.len(),
+        project_path.display()
+    );
+    for finding in findings {
+        println!("  {} {}: {}", "•".yellow(), finding.describe().bright_yellow(), finding.suggestion);
+    }
+}
+
+/// Print a project's path dependencies that point at missing directories
+pub fn print_stale_paths(project_path: &Path, stale: &[StalePathDependency]) {
+    println!(
+        "{} Found {} stale path dependency(ies) in {}:",
+        "[WARNING]".yellow().bold(),
+        stale.len(),
+        project_path.display()
+    );
+    for dep in stale {
+        println!("  {} {} ({}): {} not found", "•".yellow(), dep.name.bright_yellow(), dep.as_dependency().origin(), dep.path);
+    }
+}
+
+/// Write the workspace entries `--hoist-deps` would add, then each manifest's entries
+/// that would change to `workspace = true`
+pub fn write_hoistable(out: &mut impl Write, hoistable: &[HoistableDependency]) -> std::io::Result<()> {
+    for dep in hoistable {
+        let note = if dep.inherited_elsewhere { " (already declared)" } else { "" };
+        writeln!(out, "  {} [workspace.dependencies] {} = {}{}", "•".yellow(), dep.name.bright_yellow(), dep.workspace_entry, note)?;
+    }
+    let mut by_manifest: BTreeMap<&Path, (&str, Vec<String>)> = BTreeMap::new();
+    for dep in hoistable {
+        for entry in &dep.members {
+            by_manifest
+                .entry(&entry.manifest_path)
+                .or_insert((&entry.package, Vec::new()))
+                .1
+                .push(format!("{} {}", dep.name, entry.location));
+        }
+    }
+    for (manifest, (package, entries)) in by_manifest {
+        writeln!(out, "    {} ({}): {}", package, manifest.display(), entries.join(", "))?;
+    }
+    Ok(())
+}
+
+/// Print a workspace's dependencies that members could inherit
+pub fn print_hoistable(project_path: &Path, hoistable: &[HoistableDependency]) {
     println!(
+        "{} Found {} dependency(ies) to share through [workspace.dependencies] in {}:",
+        "[INFO]".blue().bold(),
+        hoistable.len(),
+        project_path.display()
+    );
+    let _ = write_hoistable(&mut std::io::stdout(), hoistable);
+}
+
+/// Write a project's outdated dependencies as an aligned table
+pub fn write_outdated(out: &mut impl Write, outdated: &[OutdatedDependency]) -> std::io::Result<()> {
+    let name = |dep: &OutdatedDependency| match &dep.package {
+        Some(package) => format!("{} ({})", dep.name, package),
+        None => dep.name.clone(),
+    };
+    let width = |header: &str, column: &dyn Fn(&OutdatedDependency) -> String| {
+        outdated.iter().map(|dep| column(dep).len()).max().unwrap_or(0).max(header.len())
+    };
+    let (name_width, project_width, compat_width, latest_width) = (
+        width("Name", &name),
+        width("Project", &|dep| dep.project.clone()),
+        width("Compat", &|dep| dep.compat.clone()),
+        width("Latest", &|dep| dep.latest.clone()),
+    );
+    writeln!(
+        out,
+        "    {:<name_width$}  {:<project_width$}  {:<compat_width$}  {:<latest_width$}  Kind",
+        "Name", "Project", "Compat", "Latest"
+    )?;
+    for dep in outdated {
+        writeln!(
+            out,
+            "    {:<name_width$}  {:<project_width$}  {:<compat_width$}  {:<latest_width$}  {}",
+            name(dep),
+            dep.project,
+            dep.compat,
+            dep.latest,
+            dep.kind.as_deref().unwrap_or("-")
+        )?;
+    }
+    Ok(())
+}
+
+/// Print a project's outdated dependencies
+pub fn print_outdated(project_path: &Path, outdated: &[OutdatedDependency]) {
+    println!(
+        "{} Found {} outdated dependency(ies) in {}:",
+        "[INFO]".blue().bold(),
+        outdated.len(),
+        project_path.display()
+    );
+    let _ = write_outdated(&mut std::io::stdout(), outdated);
+}
+
+/// Print a project's dependencies that belong in dev-dependencies, with the suggested move
+pub fn print_dev_only(project_path: &Path, misplaced: &[MisplacedDependency]) {
+    println!(
+        "{} Found {} dependency(ies) only used by tests, examples or benches in {}:",
+        "[INFO]".blue().bold(),
+        misplaced.len(),
+        project_path.display()
+    );
+    for dep in misplaced {
+        let origin = match &dep.package {
+            Some(package) => format!("{} {}", package, dep.location),
+            None => dep.location.clone(),
+        };
+        println!("  {} {} (from {})", "•".yellow(), dep.suggestion().bright_yellow(), origin);
+    }
+}
+
+/// Print a unified manifest diff, colored like `git diff`
+pub fn print_manifest_diff(diff: &str) {
+    for line in diff.lines() {
+        if line.starts_with("---") || line.starts_with("+++") {
+            println!("{}", line.bold());
+        } else if line.starts_with("@@") {
+            println!("{}", line.cyan());
+        } else if line.starts_with('+') {
+            println!("{}", line.green());
+        } else if line.starts_with('-') {
+            println!("{}", line.red());
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+/// Print error message
+pub fn print_error(project_path: &Path, error_msg: &str) {
+    let _ = write_error(&mut std::io::stdout().lock(), project_path, error_msg);
+}
+
+/// Write an error message for a project that failed to clean
+pub fn write_error(out: &mut impl Write, project_path: &Path, error_msg: &str) -> std::io::Result<()> {
+    writeln!(
+        out,
         "{} Failed to clean: {:?} - {}",
         "[ERROR]".red().bold(),
         project_path,
         error_msg
-    );
+    )
 }
 
+/// Write the deferred report for `--silent-on-success`
+///
+/// Nothing is written when every project was cleaned and no warnings were raised;
+/// otherwise each failure is listed followed by the full summary.
+pub fn write_silent_report(out: &mut impl Write, summary: &Summary) -> std::io::Result<()> {
+    if summary.failed == 0 && summary.warnings.is_empty() {
+        return Ok(());
+    }
+    for result in summary.results.iter().filter(|r| !r.success) {
+        write_error(out, Path::new(&result.path), result.error.as_deref().unwrap_or("unknown error"))?;
+    }
+    write_summary(out, summary)
+}
+
+/// Encode the outcome of a run as `--bitflag-exit` bits
+///
+/// "Nothing to clean" means no storage was freed (or would be freed in a dry run),
+/// which includes runs that found no projects at all. `total_freed_bytes` is `None` when
+/// the run only analyzed dependencies, which never counts as nothing to clean.
+pub fn bitflag_exit_code(failed: usize, found_unused_deps: bool, total_freed_bytes: Option<u64>) -> i32 {
+    let mut code = 0;
+    if failed > 0 {
+        code |= EXIT_BIT_FAILURES;
+    }
+    if found_unused_deps {
+        code |= EXIT_BIT_UNUSED_DEPS;
+    }
+    if total_freed_bytes == Some(0) {
+        code |= EXIT_BIT_NOTHING_TO_CLEAN;
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::{CargoInfo, CargoStatus};
+    use crate::triples::TripleSize;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_write_outdated() {
+        let dep = |name: &str, project: &str, latest: &str| OutdatedDependency {
+            name: name.to_string(),
+            project: project.to_string(),
+            compat: "---".to_string(),
+            latest: latest.to_string(),
+            kind: Some("Normal".to_string()),
+            package: None,
+        };
+        let mut out = Vec::new();
+        write_outdated(&mut out, &[dep("rand", "0.7.3", "0.8.5"), dep("serde_json", "1.0.1", "1.0.120")]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "    Name        Project  Compat  Latest   Kind\n    \
+             rand        0.7.3    ---     0.8.5    Normal\n    \
+             serde_json  1.0.1    ---     1.0.120  Normal\n"
+        );
+    }
+
+    #[test]
+    fn test_workspace_summary_line() {
+        let workspace = Project {
+            path: PathBuf::from("/projects/ws"),
+            is_workspace: true,
+            marker_only: false,
+            fuzz: false,
+            cargo: CargoStatus::Loaded(CargoInfo {
+                members: vec!["app".to_string(), "cli".to_string()],
+                virtual_manifest: true,
+                target_dir: PathBuf::from("/projects/ws/target"),
+            }),
+            overrides: Default::default(),
+        };
+        let result = CleanResult::cleaned(&workspace, 2048);
+        assert_eq!(workspace_summary_line(&result), "Cleaned workspace: /projects/ws (freed: 2.00 KB, 2 member(s))");
+        assert!(serde_json::to_string(&result).unwrap().contains("\"members\":[\"app\",\"cli\"]"));
+
+        // Without cargo's answer the member count is unknown
+        let result = CleanResult::cleaned(&Project { cargo: CargoStatus::Unknown, ..workspace }, 0);
+        assert_eq!(workspace_summary_line(&result), "Cleaned workspace: /projects/ws (freed: 0 B)");
+        assert!(!serde_json::to_string(&result).unwrap().contains("members"));
+
+        let mut out = Vec::new();
+        write_workspace_summary(&mut out, &result, true).unwrap();
+        assert!(out.is_empty());
+        write_workspace_summary(&mut out, &result, false).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("Cleaned workspace: /projects/ws (freed: 0 B)"));
+    }
+
+    #[test]
+    fn test_verbose_cleaned_hide_empty() {
+        let project = Project {
+            path: PathBuf::from("/projects/app"),
+            is_workspace: false,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: Default::default(),
+        };
+        let render = |result: &CleanResult, hide_empty| {
+            let mut out = Vec::new();
+            write_verbose_cleaned(&mut out, result, hide_empty).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let already_clean = CleanResult::cleaned(&project, 0);
+        assert_eq!(render(&already_clean, true), "");
+        assert!(render(&already_clean, false).contains("Cleaned: /projects/app (already clean)"));
+
+        let cleaned = CleanResult::cleaned(&project, 2048);
+        assert!(render(&cleaned, true).contains("Cleaned: /projects/app (freed: 2.00 KB)"));
+    }
+
+    #[test]
+    fn test_bitflag_exit_code() {
+        assert_eq!(bitflag_exit_code(0, false, Some(1024)), 0);
+        assert_eq!(bitflag_exit_code(2, false, Some(1024)), EXIT_BIT_FAILURES);
+        assert_eq!(bitflag_exit_code(0, true, Some(1024)), EXIT_BIT_UNUSED_DEPS);
+        assert_eq!(bitflag_exit_code(0, false, Some(0)), EXIT_BIT_NOTHING_TO_CLEAN);
+        assert_eq!(bitflag_exit_code(1, true, Some(0)), 7);
+        // `deps` cleans nothing by design
+        assert_eq!(bitflag_exit_code(0, false, None), 0);
+        assert_eq!(bitflag_exit_code(0, true, None), EXIT_BIT_UNUSED_DEPS);
+    }
+
+    #[test]
+    fn test_summary_to_json_combined() {
+        let mut summary = Summary {
+            total_projects: 1,
+            cleaned: 1,
+            failed: 0,
+            total_freed_bytes: 2048,
+            total_physical_freed_bytes: None,
+            results: vec![],
+            skipped: vec![],
+            skipped_dirty: vec![],
+            warnings: vec![],
+            phase_timings: PhaseTimings::default(),
+            freed_by_tag: BTreeMap::new(),
+            cross_target_bytes: BTreeMap::new(),
+            freed_by_profile: BTreeMap::new(),
+            freed_by_triple: BTreeMap::new(),
+            freed_by_framework: BTreeMap::new(),
+            freed_by_root: BTreeMap::new(),
+            total_doc_freed_bytes: None,
+            total_coverage_freed_bytes: None,
+            reclaim_check: None,
+            dep_summary: None,
+            deps_only: false,
+        };
+        let json: serde_json::Value = serde_json::from_str(&summary_to_json(&summary).unwrap()).unwrap();
+        assert_eq!(json["clean_summary"]["total_freed_bytes"], 2048);
+        assert!(json["dep_summary"].is_null());
+        assert!(json["clean_summary"].get("freed_by_tag").is_none());
+        assert!(json["clean_summary"].get("skipped_dirty").is_none());
+
+        let mut dep_summary = DepSummary::default();
+        dep_summary.record(
+            DependencyCleanResult {
+                path: "/projects/app".to_string(),
+                success: true,
+                unused_deps: vec![],
+                removed_count: 0,
+                rolled_back: vec![],
+                diff: None,
+                duplicates: vec![],
+                features: vec![],
+                stale_paths: vec![],
+                stale_paths_fixed: 0,
+                dev_only: vec![],
+                moved_to_dev: 0,
+                outdated: vec![],
+                hoistable: vec![],
+                hoisted: 0,
+                lock_entries_pruned: 0,
+                error: None,
+            },
+            true,
+        );
+        summary.dep_summary = Some(dep_summary);
+        let json: serde_json::Value = serde_json::from_str(&summary_to_json(&summary).unwrap()).unwrap();
+        assert_eq!(json["clean_summary"]["total_freed_bytes"], 2048);
+        assert_eq!(json["dep_summary"]["projects_checked"], 1);
+        assert_eq!(json["dep_summary"]["results"][0]["path"], "/projects/app");
+    }
+
+    #[test]
+    fn test_phase_timings() {
+        let timings = PhaseTimings { discovery_ms: 1500, sizing_ms: 250, dependency_ms: 0, cleaning_ms: 12_000 };
+        let mut out = Vec::new();
+        write_phase_timings(&mut out, &timings).unwrap();
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .contains("Phase timings: discovery 1.50s, sizing 0.25s, dependencies 0.00s, cleaning 12.00s"));
+
+        let summary = Summary { phase_timings: timings, ..Default::default() };
+        let json: serde_json::Value = serde_json::from_str(&summary_to_json(&summary).unwrap()).unwrap();
+        let phase_timings = &json["clean_summary"]["phase_timings"];
+        assert_eq!(phase_timings["discovery_ms"], 1500);
+        assert_eq!(phase_timings["sizing_ms"], 250);
+        assert_eq!(phase_timings["dependency_ms"], 0);
+        assert_eq!(phase_timings["cleaning_ms"], 12_000);
+    }
+
+    #[test]
+    fn test_summary_freed_by_tag() {
+        let mut a = Summary::default();
+        a.record_tags(vec!["services".to_string(), "tools".to_string()], 100);
+        let mut b = Summary::default();
+        b.record_tags(vec!["services".to_string()], 50);
+        b.record_tags(vec!["untagged".to_string()], 0);
+
+        let summary = a.merge(b);
+        let json: serde_json::Value = serde_json::from_str(&summary_to_json(&summary).unwrap()).unwrap();
+        assert_eq!(json["clean_summary"]["freed_by_tag"]["services"], 150);
+        assert_eq!(json["clean_summary"]["freed_by_tag"]["tools"], 100);
+        assert_eq!(json["clean_summary"]["freed_by_tag"]["untagged"], 0);
+    }
+
+    #[test]
+    fn test_summary_physical_freed() {
+        let result = |physical_freed_bytes| CleanResult {
+            path: "/projects/app".to_string(),
+            success: true,
+            freed_bytes: 1000,
+            error: None,
+            oldest_artifact: None,
+            newest_artifact: None,
+            cargo_warnings: Vec::new(),
+            unrecognized_files: Vec::new(),
+            tracked_files: Vec::new(),
+            physical_freed_bytes,
+            cross_targets: Vec::new(),
+            freed_by_profile: BTreeMap::new(),
+            freed_by_triple: Default::default(),
+            freed_by_framework: Default::default(),
+            root: None,
+            doc_freed_bytes: None,
+            coverage_freed_bytes: None,
+            kept_binaries: Vec::new(),
+            members: Vec::new(),
+            explain: Vec::new(),
+        };
+        let mut a = Summary::default();
+        a.record(result(None), false);
+        assert_eq!(a.total_physical_freed_bytes, None);
+
+        a.record(result(Some(400)), false);
+        let mut b = Summary::default();
+        b.record(result(Some(300)), false);
+        let summary = a.merge(b);
+        assert_eq!(summary.total_freed_bytes, 3000);
+        assert_eq!(summary.total_physical_freed_bytes, Some(700));
+
+        let mut out = Vec::new();
+        write_summary(&mut out, &summary).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("Physical space freed: 700 B"));
+    }
+
+    #[test]
+    fn test_summary_cross_targets() {
+        let result = |cross_targets| CleanResult {
+            path: "/projects/app".to_string(),
+            success: true,
+            freed_bytes: 1000,
+            error: None,
+            oldest_artifact: None,
+            newest_artifact: None,
+            cargo_warnings: Vec::new(),
+            unrecognized_files: Vec::new(),
+            tracked_files: Vec::new(),
+            physical_freed_bytes: None,
+            cross_targets,
+            freed_by_profile: BTreeMap::new(),
+            freed_by_triple: Default::default(),
+            freed_by_framework: Default::default(),
+            root: None,
+            doc_freed_bytes: None,
+            coverage_freed_bytes: None,
+            kept_binaries: Vec::new(),
+            members: Vec::new(),
+            explain: Vec::new(),
+        };
+        let wasm = |size| TripleSize { triple: "wasm32-unknown-unknown".to_string(), size, wasm: true };
+        let mut a = Summary::default();
+        a.record(result(vec![wasm(300)]), false);
+        let mut b = Summary::default();
+        b.record(result(vec![wasm(200)]), false);
+        b.record(result(Vec::new()), false);
+        let summary = a.merge(b);
+        assert_eq!(summary.cross_target_bytes["wasm32-unknown-unknown"], 500);
+
+        let mut out = Vec::new();
+        write_summary(&mut out, &summary).unwrap();
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .contains("Cross-compilation targets: wasm32-unknown-unknown 500 B [wasm]"));
+    }
+
+    #[test]
+    fn test_reclaim_check_discrepancy() {
+        let fs = |free_before, free_after| FilesystemReclaim { path: "/".to_string(), free_before, free_after };
+        let gib = 1024 * 1024 * 1024;
+
+        let check = ReclaimCheck::new(vec![fs(10 * gib, 11 * gib), fs(gib, gib)], gib);
+        assert_eq!(check.reclaimed_bytes, gib as i64);
+        assert!(!check.discrepancy);
+
+        // Hardlinked artifacts: reported freed, but nothing came back
+        let check = ReclaimCheck::new(vec![fs(10 * gib, 10 * gib)], gib);
+        assert!(check.discrepancy);
+
+        // Small runs are within the slack even when the share is large
+        let check = ReclaimCheck::new(vec![fs(gib, gib - 1024)], 4096);
+        assert_eq!(check.reclaimed_bytes, -1024);
+        assert!(!check.discrepancy);
+
+        let summary = Summary {
+            total_freed_bytes: gib,
+            reclaim_check: Some(ReclaimCheck::new(vec![fs(gib, gib)], gib)),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        write_summary(&mut out, &summary).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Free space reclaimed: 0 B on 1 filesystem(s)"));
+        assert!(out.contains("differs from the reported 1.00 GB"));
+    }
+
+    #[test]
+    fn test_write_would_clean() {
+        let built = crate::utils::unix_now() - 2 * 86400;
+        let project = |path: &str, target_dir: &str, newest_artifact| ScannedProject {
+            path: path.to_string(),
+            kind: "package",
+            target_dir: target_dir.to_string(),
+            target_size: 2048,
+            disk_usage: None,
+            oldest_artifact: newest_artifact,
+            newest_artifact,
+        };
+        let projects = vec![
+            project("/projects/ws", "/projects/ws/target", Some(built)),
+            // A member building into its workspace's target
+            project("/projects/ws/member", "/projects/ws/target", Some(built)),
+            project("/projects/fresh", "/projects/fresh/target", None),
+        ];
+
+        let mut out = Vec::new();
+        write_would_clean(&mut out, &projects).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 1);
+        assert!(out.contains("Would clean /projects/ws (last built 2d ago, 2.00 KB)"));
+    }
+
+    #[test]
+    fn test_write_scan() {
+        let project = |path: &str, target_size| ScannedProject {
+            path: path.to_string(),
+            kind: "package",
+            target_dir: format!("{}/target", path),
+            target_size,
+            disk_usage: None,
+            oldest_artifact: None,
+            newest_artifact: None,
+        };
+        let mut projects = vec![project("/projects/big", 2048), project("/projects/small", 1024)];
+        let skipped = vec![SkippedProject { path: "/projects/skip".to_string(), reason: "skip = true".to_string() }];
+
+        let mut out = Vec::new();
+        write_scan(&mut out, &projects, &skipped).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("   2.00 KB  /projects/big (package)"));
+        assert!(out.contains("2 project(s), 3.00 KB in target directories"));
+        assert!(out.contains("Skipped /projects/skip (skip = true)"));
+
+        let json: serde_json::Value = serde_json::from_str(&scan_to_json(&projects, &skipped).unwrap()).unwrap();
+        assert_eq!(json["total_target_bytes"], 3072);
+        assert_eq!(json["projects"][1]["path"], "/projects/small");
+        assert!(json.get("total_disk_usage").is_none());
+
+        projects[0].disk_usage = Some(4096);
+        projects[1].disk_usage = Some(4096);
+        let mut out = Vec::new();
+        write_scan(&mut out, &projects, &skipped).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("   2.00 KB  /projects/big (package, 4.00 KB on disk)"));
+        assert!(out.contains("2 project(s), 3.00 KB in target directories (8.00 KB on disk)"));
+        let json: serde_json::Value = serde_json::from_str(&scan_to_json(&projects, &skipped).unwrap()).unwrap();
+        assert_eq!(json["total_disk_usage"], 8192);
+    }
+
+    #[test]
+    fn test_write_silent_report() {
+        let mut summary = Summary {
+            cleaned: 1,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        write_silent_report(&mut out, &summary).unwrap();
+        assert!(out.is_empty());
+
+        summary.record(
+            CleanResult {
+                path: "/projects/broken".to_string(),
+                success: false,
+                freed_bytes: 0,
+                error: Some("permission denied".to_string()),
+                oldest_artifact: None,
+                newest_artifact: None,
+                cargo_warnings: Vec::new(),
+                unrecognized_files: Vec::new(),
+                tracked_files: Vec::new(),
+                physical_freed_bytes: None,
+                cross_targets: Vec::new(),
+                freed_by_profile: BTreeMap::new(),
+                freed_by_triple: Default::default(),
+                freed_by_framework: Default::default(),
+                root: None,
+                doc_freed_bytes: None,
+                coverage_freed_bytes: None,
+                kept_binaries: Vec::new(),
+                members: Vec::new(),
+                explain: Vec::new(),
+            },
+            true,
+        );
+        write_silent_report(&mut out, &summary).unwrap();
+        let report = String::from_utf8(out).unwrap();
+        assert!(report.contains("\"/projects/broken\" - permission denied"));
+        assert!(report.contains("=== SUMMARY ==="));
+    }
+
+    #[test]
+    fn test_summary_to_junit() {
+        let summary = Summary {
+            total_projects: 2,
+            cleaned: 1,
+            failed: 1,
+            total_freed_bytes: 4096,
+            total_physical_freed_bytes: None,
+            results: vec![
+                CleanResult {
+                    path: "/projects/a&b".to_string(),
+                    success: true,
+                    freed_bytes: 4096,
+                    error: None,
+                    oldest_artifact: None,
+                    newest_artifact: None,
+                    cargo_warnings: Vec::new(),
+                    unrecognized_files: Vec::new(),
+                    tracked_files: Vec::new(),
+                    physical_freed_bytes: None,
+                    cross_targets: Vec::new(),
+                    freed_by_profile: BTreeMap::new(),
+                    freed_by_triple: Default::default(),
+                    freed_by_framework: Default::default(),
+                    root: None,
+                    doc_freed_bytes: None,
+                    coverage_freed_bytes: None,
+                    kept_binaries: Vec::new(),
+                    members: Vec::new(),
+                    explain: Vec::new(),
+                },
+                CleanResult {
+                    path: "/projects/broken".to_string(),
+                    success: false,
+                    freed_bytes: 0,
+                    error: Some("Failed to remove <target>: \"denied\"".to_string()),
+                    oldest_artifact: None,
+                    newest_artifact: None,
+                    cargo_warnings: Vec::new(),
+                    unrecognized_files: Vec::new(),
+                    tracked_files: Vec::new(),
+                    physical_freed_bytes: None,
+                    cross_targets: Vec::new(),
+                    freed_by_profile: BTreeMap::new(),
+                    freed_by_triple: Default::default(),
+                    freed_by_framework: Default::default(),
+                    root: None,
+                    doc_freed_bytes: None,
+                    coverage_freed_bytes: None,
+                    kept_binaries: Vec::new(),
+                    members: Vec::new(),
+                    explain: Vec::new(),
+                },
+            ],
+            skipped: vec![],
+            skipped_dirty: vec![],
+            warnings: vec![],
+            phase_timings: PhaseTimings::default(),
+            freed_by_tag: BTreeMap::new(),
+            cross_target_bytes: BTreeMap::new(),
+            freed_by_profile: BTreeMap::new(),
+            freed_by_triple: BTreeMap::new(),
+            freed_by_root: BTreeMap::new(),
+            freed_by_framework: BTreeMap::new(),
+            total_doc_freed_bytes: None,
+            total_coverage_freed_bytes: None,
+            reclaim_check: None,
+            dep_summary: None,
+            deps_only: false,
+        };
+
+        let xml = summary_to_junit(&summary);
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains("tests=\"2\" failures=\"1\""));
+        assert!(xml.contains("name=\"/projects/a&amp;b\""));
+        assert!(xml.contains("<property name=\"freed_bytes\" value=\"4096\"/>"));
+        assert!(xml.contains("<failure message=\"Failed to remove &lt;target&gt;: &quot;denied&quot;\">"));
+        assert_eq!(xml.matches("<testcase").count(), xml.matches("</testcase>").count());
+    }
+
+    /// Writer that keeps its output for inspection after being boxed
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_result_stream_keeps_only_counters() {
+        let buffer = SharedBuffer::default();
+        let stream = ResultStream::new(OutputFormat::Csv, Box::new(buffer.clone())).unwrap();
+        let mut summary = Summary::default();
+        for (path, freed) in [("/projects/a", 10), ("/projects/b,c", 20)] {
+            let result = CleanResult {
+                path: path.to_string(),
+                success: true,
+                freed_bytes: freed,
+                error: None,
+                oldest_artifact: None,
+                newest_artifact: None,
+                cargo_warnings: Vec::new(),
+                unrecognized_files: Vec::new(),
+                tracked_files: Vec::new(),
+                physical_freed_bytes: None,
+                cross_targets: Vec::new(),
+                freed_by_profile: BTreeMap::new(),
+                freed_by_triple: Default::default(),
+                freed_by_framework: Default::default(),
+                root: None,
+                doc_freed_bytes: None,
+                coverage_freed_bytes: None,
+                kept_binaries: Vec::new(),
+                members: Vec::new(),
+                explain: Vec::new(),
+            };
+            stream.write_result(&result).unwrap();
+            summary.record(result, false);
+        }
+        stream.finish(&summary).unwrap();
+
+        assert!(summary.results.is_empty());
+        assert_eq!(summary.cleaned, 2);
+        assert_eq!(summary.total_freed_bytes, 30);
+        let csv = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            csv,
+            "path,success,freed_bytes,error\n/projects/a,true,10,\n\"/projects/b,c\",true,20,\n"
+        );
+    }
+
+    #[test]
+    fn test_result_stream_jsonl() {
+        let buffer = SharedBuffer::default();
+        let stream = ResultStream::new(OutputFormat::Jsonl, Box::new(buffer.clone())).unwrap();
+        let result = CleanResult {
+            path: "/projects/a".to_string(),
+            success: false,
+            freed_bytes: 0,
+            error: Some("boom".to_string()),
+            oldest_artifact: None,
+            newest_artifact: None,
+            cargo_warnings: Vec::new(),
+            unrecognized_files: Vec::new(),
+            tracked_files: Vec::new(),
+            physical_freed_bytes: None,
+            cross_targets: Vec::new(),
+            freed_by_profile: BTreeMap::new(),
+            freed_by_triple: Default::default(),
+            freed_by_framework: Default::default(),
+            root: None,
+            doc_freed_bytes: None,
+            coverage_freed_bytes: None,
+            kept_binaries: Vec::new(),
+            members: Vec::new(),
+            explain: Vec::new(),
+        };
+        stream.write_result(&result).unwrap();
+        let mut summary = Summary::default();
+        summary.record(result, false);
+        stream.finish(&summary).unwrap();
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["type"], "clean_result");
+        assert_eq!(lines[0]["error"], "boom");
+        assert_eq!(lines[1]["type"], "summary");
+        assert_eq!(lines[1]["failed"], 1);
+        assert_eq!(lines[1]["results"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_progress_display_caps_visible_bars() {
+        let display = ProgressDisplay::with_draw_target(5, 2, ProgressDrawTarget::hidden());
+        let bars: Vec<_> = (0..5)
+            .map(|i| display.start_project(Path::new(&format!("project-{}", i))))
+            .collect();
+        assert_eq!(bars.iter().filter(|b| b.is_some()).count(), 2);
+        assert_eq!(display.overflow.message(), "+3 more");
+
+        let mut bars = bars.into_iter();
+        display.finish_project(bars.next().unwrap());
+        // A freed slot is reused by the next project to start
+        assert!(display.start_project(Path::new("project-5")).is_some());
+
+        for pb in bars {
+            display.finish_project(pb);
+        }
+        let state = display.state.lock().unwrap();
+        assert_eq!(state.hidden, 0);
+        assert!(!state.overflow_shown);
+    }
+
+    #[test]
+    fn test_progress_display_concurrent_projects() {
+        use rayon::prelude::*;
+        let display = ProgressDisplay::with_draw_target(200, 4, ProgressDrawTarget::hidden());
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(8).build().unwrap();
+        pool.install(|| {
+            (0..200).into_par_iter().for_each(|i| {
+                let pb = display.start_project(Path::new(&format!("project-{}", i)));
+                assert!(display.state.lock().unwrap().visible <= 4);
+                display.finish_project(pb);
+            })
+        });
+        assert_eq!(display.overall.position(), 200);
+        let state = display.state.lock().unwrap();
+        assert_eq!((state.visible, state.hidden), (0, 0));
+        assert!(!state.overflow_shown);
+    }
+}
@@ -0,0 +1,293 @@
+use anyhow::{Context, Result};
+use crate::cleaner::{CleanOptions, CleanStrategy};
+use crate::config::ProjectOverrides;
+use crate::deps::UnusedDependency;
+use crate::project::Project;
+use crate::utils::{format_bytes, get_directory_stats, unix_now, DirectoryStats, SizeOptions};
+use std::path::{Path, PathBuf};
+
+/// Format version written to plan files; plans with another version are rejected
+pub const PLAN_VERSION: u32 = 1;
+
+/// Relative change of a target's size tolerated before a project counts as drifted
+const SIZE_DRIFT_TOLERANCE: f64 = 0.10;
+
+/// A reviewed cleanup plan written by `plan` and executed by `apply`
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct CleanupPlan {
+    pub version: u32,
+    /// When the plan was made, in seconds since the Unix epoch
+    pub created_at: u64,
+    pub root: PathBuf,
+    pub strategy: CleanStrategy,
+    /// Only these cross-compilation targets are cleaned (`--triples`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub triples: Vec<String>,
+    /// Every cross-compilation target is cleaned, and no host build (`--non-host-only`)
+    #[serde(default)]
+    pub non_host_only: bool,
+    /// Only these build profiles are cleaned (`--profile`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<String>,
+    /// Only documentation output is cleaned (`--doc-only`)
+    #[serde(default)]
+    pub doc_only: bool,
+    /// Only incremental compilation caches are cleaned (`--incremental-only`)
+    #[serde(default)]
+    pub incremental_only: bool,
+    /// Dep-info files are cleaned along with the incremental caches (`--dep-info`)
+    #[serde(default)]
+    pub dep_info: bool,
+    /// Final executables are kept (`--keep-binaries`)
+    #[serde(default)]
+    pub keep_binaries: bool,
+    /// Only files unused for this many seconds are cleaned (`--sweep-older-than`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sweep_older_than: Option<u64>,
+    /// Only coverage artifacts are cleaned (`--coverage`)
+    #[serde(default)]
+    pub coverage: bool,
+    /// Fuzz corpora are removed too (`--include-corpus`)
+    #[serde(default)]
+    pub include_corpus: bool,
+    /// Criterion benchmark data is removed too (`--clean-criterion`)
+    #[serde(default)]
+    pub clean_criterion: bool,
+    /// wasm-pack and trunk output directories are cleaned too (on unless `--no-framework-outputs`;
+    /// off for plans written before they were)
+    #[serde(default)]
+    pub framework_outputs: bool,
+    /// Sizes were measured as allocated disk blocks (`--on-disk`)
+    pub on_disk: bool,
+    /// Sizes left out mounts inside target directories (`--one-filesystem`; off for plans
+    /// written before it was recorded)
+    #[serde(default)]
+    pub one_filesystem: bool,
+    pub projects: Vec<PlannedProject>,
+}
+
+/// One project of a [`CleanupPlan`] and the state it was planned against
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct PlannedProject {
+    pub path: PathBuf,
+    pub is_workspace: bool,
+    pub marker_only: bool,
+    /// A cargo-fuzz crate, see [`Project::fuzz`]
+    #[serde(default)]
+    pub fuzz: bool,
+    /// The resolved target directory at planning time
+    pub target_dir: PathBuf,
+    /// Size of the target directory at planning time
+    pub target_size: u64,
+    pub expected_freed_bytes: u64,
+    /// Sidecar `keep` globs in effect at planning time
+    #[serde(default)]
+    pub keep: Vec<String>,
+    #[serde(default)]
+    pub dependency_removals: Vec<UnusedDependency>,
+}
+
+impl PlannedProject {
+    pub fn new(
+        project: &Project,
+        stats: &DirectoryStats,
+        expected_freed_bytes: u64,
+        dependency_removals: Vec<UnusedDependency>,
+    ) -> Self {
+        Self {
+            path: project.path.clone(),
+            is_workspace: project.is_workspace,
+            marker_only: project.marker_only,
+            fuzz: project.fuzz,
+            target_dir: project.target_dir(),
+            target_size: stats.size,
+            expected_freed_bytes,
+            keep: project.overrides.keep.clone(),
+            dependency_removals,
+        }
+    }
+
+    /// The project as it was discovered when planning
+    pub fn project(&self) -> Project {
+        Project {
+            path: self.path.clone(),
+            is_workspace: self.is_workspace,
+            marker_only: self.marker_only,
+            fuzz: self.fuzz,
+            cargo: Default::default(),
+            overrides: ProjectOverrides {
+                keep: self.keep.clone(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Describe how the project changed since planning, if it changed materially
+    ///
+    /// A project drifts when it no longer exists, its target now resolves elsewhere,
+    /// or its target size moved by more than [`SIZE_DRIFT_TOLERANCE`].
+    fn drift(&self, project: &Project, stats: &DirectoryStats) -> Option<String> {
+        let exists = if self.marker_only { self.path.is_dir() } else { self.path.join("Cargo.toml").is_file() };
+        if !exists {
+            return Some("project no longer exists".to_string());
+        }
+        let target_dir = project.target_dir();
+        if target_dir != self.target_dir {
+            return Some(format!("target directory moved from {:?} to {:?}", self.target_dir, target_dir));
+        }
+        let change = stats.size.abs_diff(self.target_size) as f64;
+        if change > self.target_size as f64 * SIZE_DRIFT_TOLERANCE {
+            return Some(format!(
+                "target size changed from {} to {}",
+                format_bytes(self.target_size),
+                format_bytes(stats.size)
+            ));
+        }
+        None
+    }
+}
+
+impl CleanupPlan {
+    /// A plan to clean `projects` the way `options` would
+    pub fn new(root: &Path, options: &CleanOptions, projects: Vec<PlannedProject>) -> Self {
+        Self {
+            version: PLAN_VERSION,
+            created_at: unix_now(),
+            root: root.to_path_buf(),
+            strategy: options.strategy.clone(),
+            triples: options.triples.clone(),
+            non_host_only: options.non_host_only,
+            profiles: options.profiles.clone(),
+            doc_only: options.doc_only,
+            incremental_only: options.incremental_only,
+            dep_info: options.dep_info,
+            keep_binaries: options.keep_binaries,
+            sweep_older_than: options.sweep_older_than,
+            coverage: options.coverage,
+            include_corpus: options.include_corpus,
+            clean_criterion: options.clean_criterion,
+            framework_outputs: options.framework_outputs,
+            on_disk: options.size_options.on_disk,
+            one_filesystem: options.size_options.one_filesystem,
+            projects,
+        }
+    }
+
+    /// Read a plan file written by `plan`
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read plan file: {:?}", path))?;
+        let plan: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid plan file: {:?}", path))?;
+        if plan.version != PLAN_VERSION {
+            anyhow::bail!(
+                "Unsupported plan version {} in {:?} (expected {})",
+                plan.version,
+                path,
+                PLAN_VERSION
+            );
+        }
+        Ok(plan)
+    }
+
+    pub fn size_options(&self) -> SizeOptions {
+        SizeOptions { on_disk: self.on_disk, one_filesystem: self.one_filesystem, ..Default::default() }
+    }
+
+    /// Re-measure every planned project and compare it with the plan
+    ///
+    /// Returns the projects with their current target stats, and a description of
+    /// each project that drifted since planning.
+    pub fn check(&self) -> (Vec<(Project, DirectoryStats)>, Vec<String>) {
+        let size_options = self.size_options();
+        let mut drifted = Vec::new();
+        let projects = self
+            .projects
+            .iter()
+            .map(|planned| {
+                let project = planned.project();
+                let stats = get_directory_stats(&project.target_dir(), &size_options).unwrap_or_default();
+                if let Some(drift) = planned.drift(&project, &stats) {
+                    drifted.push(format!("{}: {}", planned.path.display(), drift));
+                }
+                (project, stats)
+            })
+            .collect();
+        (projects, drifted)
+    }
+
+    /// Dependency removals recorded for `project`
+    pub fn dependency_removals(&self, project: &Path) -> Option<&[UnusedDependency]> {
+        self.projects
+            .iter()
+            .find(|planned| planned.path == project && !planned.dependency_removals.is_empty())
+            .map(|planned| planned.dependency_removals.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan_for(temp_dir: &tempfile::TempDir) -> CleanupPlan {
+        let project_dir = temp_dir.path().join("app");
+        std::fs::create_dir_all(project_dir.join("target")).unwrap();
+        std::fs::write(project_dir.join("Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+        std::fs::write(project_dir.join("target/artifact"), vec![0u8; 1000]).unwrap();
+        let project = Project {
+            path: project_dir,
+            is_workspace: false,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: ProjectOverrides::default(),
+        };
+        let stats = get_directory_stats(&project.target_dir(), &SizeOptions::default()).unwrap();
+        let planned = PlannedProject::new(&project, &stats, stats.size, Vec::new());
+        CleanupPlan::new(temp_dir.path(), &CleanOptions::default(), vec![planned])
+    }
+
+    #[test]
+    fn test_plan_round_trip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let plan = plan_for(&temp_dir);
+        let path = temp_dir.path().join("plan.json");
+        std::fs::write(&path, serde_json::to_string_pretty(&plan).unwrap()).unwrap();
+
+        let loaded = CleanupPlan::load(&path).unwrap();
+        assert_eq!(loaded.strategy, CleanStrategy::default());
+        assert_eq!(loaded.projects[0].target_size, 1000);
+        assert!(!loaded.size_options().one_filesystem);
+
+        // Re-measuring on apply must skip the same mounts planning did
+        let plan = CleanupPlan { one_filesystem: true, ..plan };
+        std::fs::write(&path, serde_json::to_string_pretty(&plan).unwrap()).unwrap();
+        assert!(CleanupPlan::load(&path).unwrap().size_options().one_filesystem);
+
+        std::fs::write(&path, serde_json::to_string(&plan).unwrap().replace("\"version\":1", "\"version\":99")).unwrap();
+        assert!(CleanupPlan::load(&path).unwrap_err().to_string().contains("Unsupported plan version"));
+    }
+
+    #[test]
+    fn test_plan_drift() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let plan = plan_for(&temp_dir);
+        let target_dir = temp_dir.path().join("app/target");
+
+        let (projects, drifted) = plan.check();
+        assert_eq!(projects[0].1.size, 1000);
+        assert!(drifted.is_empty());
+
+        // Small changes are tolerated
+        std::fs::write(target_dir.join("extra"), vec![0u8; 50]).unwrap();
+        assert!(plan.check().1.is_empty());
+
+        std::fs::write(target_dir.join("extra"), vec![0u8; 500]).unwrap();
+        let (_, drifted) = plan.check();
+        assert_eq!(drifted.len(), 1);
+        assert!(drifted[0].contains("target size changed"));
+
+        std::fs::remove_dir_all(temp_dir.path().join("app")).unwrap();
+        assert!(plan.check().1[0].contains("no longer exists"));
+    }
+}
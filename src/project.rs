@@ -1,97 +1,841 @@
 use anyhow::Result;
+use crate::cargo_config::{cargo_home, resolve_target_dir};
+use crate::config::ProjectOverrides;
+use crate::utils::same_device;
 use cargo_metadata::MetadataCommand;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
+use std::sync::{Arc, Mutex};
+use ignore::{DirEntry, WalkBuilder, WalkState};
+use indicatif::ProgressBar;
 
 #[derive(Debug, Clone)]
 pub struct Project {
     pub path: PathBuf,
     pub is_workspace: bool,
+    /// Found via a custom `--marker` file rather than a Cargo.toml
+    pub marker_only: bool,
+    /// A cargo-fuzz crate, with its `corpus` and `artifacts` directories next to its target
+    pub fuzz: bool,
+    /// What cargo reported for the manifest during discovery
+    pub cargo: CargoStatus,
+    /// Options from the project's `.deepclean` sidecar, if any
+    pub overrides: ProjectOverrides,
 }
 
-/// Find all Cargo projects in the given directory
-pub fn find_cargo_projects(root: &Path, exclude_patterns: &[String]) -> Result<Vec<Project>> {
-    let mut projects = Vec::new();
-    let mut seen_workspaces = HashSet::new();
+/// What `cargo metadata --no-deps` made of a project's manifest during discovery
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum CargoStatus {
+    /// Cargo wasn't asked: static discovery, marker files, fuzz crates and plans
+    #[default]
+    Unknown,
+    /// Cargo loaded the manifest
+    Loaded(CargoInfo),
+    /// Cargo couldn't load the manifest (invalid, templated, or without any targets),
+    /// with its error
+    Unbuildable(String),
+}
 
-    for entry in WalkDir::new(root)
-        .into_iter()
-        .filter_entry(|e| {
-            // Skip hidden directories and common exclusions
-            let name = e.file_name().to_string_lossy();
-            if name.starts_with('.') && name != "." && name != ".." {
-                return false;
-            }
+/// The workspace as cargo sees it
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CargoInfo {
+    /// Names of the workspace's member packages
+    pub members: Vec<String>,
+    /// The root manifest has a `[workspace]` table but no `[package]`
+    pub virtual_manifest: bool,
+    /// The directory cargo builds into, with every config and environment setting applied
+    pub target_dir: PathBuf,
+}
 
-            // Check exclude patterns
-            for pattern in exclude_patterns {
-                if glob::Pattern::new(pattern)
-                    .ok()
-                    .and_then(|p| {
-                        e.path()
-                            .strip_prefix(root)
-                            .ok()
-                            .and_then(|rel| Some(p.matches(&rel.to_string_lossy())))
-                    })
-                    .unwrap_or(false)
-                {
-                    return false;
+/// A build output directory that a web framework tool leaves beside the target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameworkOutput {
+    /// The tool that writes it, e.g. `wasm-pack` or `trunk`
+    pub tool: &'static str,
+    pub path: PathBuf,
+}
+
+impl Project {
+    /// The build artifact directory: cargo's own answer when discovery asked it, otherwise
+    /// resolved from `[build] target-dir` in Cargo config files and the environment
+    pub fn target_dir(&self) -> PathBuf {
+        match self.cargo {
+            CargoStatus::Loaded(ref info) => info.target_dir.clone(),
+            _ => resolve_target_dir(&self.path),
+        }
+    }
+
+    /// A short description of the project for progress output, e.g. `virtual workspace,
+    /// 3 members`
+    pub fn kind(&self) -> String {
+        match self.cargo {
+            _ if self.fuzz => "cargo-fuzz crate".to_string(),
+            _ if self.marker_only => "marker".to_string(),
+            CargoStatus::Loaded(ref info) if self.is_workspace => format!(
+                "{}workspace, {} member(s)",
+                if info.virtual_manifest { "virtual " } else { "" },
+                info.members.len()
+            ),
+            CargoStatus::Unbuildable(_) if self.is_workspace => "workspace, not loadable by cargo".to_string(),
+            CargoStatus::Unbuildable(_) => "package, not loadable by cargo".to_string(),
+            _ if self.is_workspace => "workspace".to_string(),
+            _ => "package".to_string(),
+        }
+    }
+
+    /// Existing wasm-pack and trunk output directories of the project
+    ///
+    /// Only directories whose tool can be recognized count: `pkg/` when it holds the
+    /// `package.json` and `.wasm` module wasm-pack writes, and for projects with a
+    /// `Trunk.toml` or a `data-trunk` link in `index.html`, trunk's `dist/` (or its
+    /// `[build] dist` setting) and `.trunk` staging cache.
+    pub fn framework_outputs(&self) -> Vec<FrameworkOutput> {
+        let mut outputs = Vec::new();
+        let pkg = self.path.join("pkg");
+        if pkg.join("package.json").is_file() && has_wasm_module(&pkg) {
+            outputs.push(FrameworkOutput { tool: "wasm-pack", path: pkg });
+        }
+        let trunk_toml = self.path.join("Trunk.toml");
+        let uses_trunk = trunk_toml.is_file()
+            || std::fs::read_to_string(self.path.join("index.html")).is_ok_and(|html| html.contains("data-trunk"));
+        if uses_trunk {
+            let dist = std::fs::read_to_string(&trunk_toml)
+                .ok()
+                .and_then(|content| content.parse::<toml::Table>().ok())
+                .and_then(|config| config.get("build")?.get("dist")?.as_str().map(PathBuf::from))
+                .filter(|dist| dist.is_relative() && !dist.components().any(|c| c == std::path::Component::ParentDir))
+                .unwrap_or_else(|| PathBuf::from("dist"));
+            for dir in [dist, PathBuf::from(".trunk")] {
+                let path = self.path.join(dir);
+                if path.is_dir() && path != self.path {
+                    outputs.push(FrameworkOutput { tool: "trunk", path });
                 }
             }
-            true
+        }
+        outputs
+    }
+
+    /// When the project's git repository was last active, in seconds since the Unix epoch
+    ///
+    /// That is the date of the last commit on HEAD, or with `reflog` the latest entry in
+    /// HEAD's reflog if later, which also covers checkouts, pulls and resets. `None` when
+    /// the project is not inside a repository with commits or git is not installed.
+    pub fn git_activity(&self, reflog: bool) -> Option<u64> {
+        let git = |args: &[&str]| -> Option<String> {
+            let output = std::process::Command::new("git").args(args).current_dir(&self.path).output().ok()?;
+            output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        };
+        let committed = git(&["log", "-1", "--format=%ct"])?.parse().ok()?;
+        // Reflog selectors come out as `HEAD@{<seconds>}` with `--date=unix`
+        let reflogged = reflog
+            .then(|| git(&["log", "--walk-reflogs", "-1", "--date=unix", "--format=%gd"]))
+            .flatten()
+            .and_then(|selector| selector.strip_prefix("HEAD@{")?.strip_suffix('}')?.parse::<u64>().ok());
+        Some(reflogged.map_or(committed, |reflogged| reflogged.max(committed)))
+    }
+
+    /// Number of modified, staged or untracked paths under the project in its git working
+    /// tree, ignoring its target directory
+    ///
+    /// `None` when the project is not inside a repository or git is not installed.
+    pub fn git_changes(&self) -> Option<usize> {
+        let mut args = vec!["status".to_string(), "--porcelain".to_string(), "-z".to_string(), "--".to_string(), ".".to_string()];
+        // An untracked target that isn't gitignored would otherwise make every built project dirty
+        if let Ok(target) = self.target_dir().strip_prefix(&self.path) {
+            if !target.as_os_str().is_empty() {
+                args.push(format!(":(exclude){}", target.to_string_lossy()));
+            }
+        }
+        let output = std::process::Command::new("git").args(&args).current_dir(&self.path).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        // Entries are NUL-terminated; renames add a second NUL-terminated field for the source
+        let mut changes = 0;
+        let mut fields = output.stdout.split(|&byte| byte == 0).filter(|field| !field.is_empty());
+        while let Some(entry) = fields.next() {
+            changes += 1;
+            if matches!(entry.first(), Some(b'R' | b'C')) {
+                fields.next();
+            }
+        }
+        Some(changes)
+    }
+}
+
+/// Whether `dir` directly holds a WebAssembly module
+fn has_wasm_module(dir: &Path) -> bool {
+    std::fs::read_dir(dir).is_ok_and(|entries| {
+        entries.filter_map(|e| e.ok()).any(|e| e.path().extension().is_some_and(|ext| ext == "wasm"))
+    })
+}
+
+/// Options controlling how projects are discovered
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveryOptions {
+    /// Glob patterns (relative to the root) of paths to skip
+    pub exclude_patterns: Vec<String>,
+    /// Glob patterns (relative to the root) a project's path must match one of, when any are given
+    pub include_patterns: Vec<String>,
+    /// Follow symlinked directories directly under the root to reach real projects
+    pub follow_root_symlinks: bool,
+    /// Follow symlinked directories anywhere in the tree; links looping back to one of
+    /// their ancestors are skipped
+    pub follow_symlinks: bool,
+    /// Extra file names that mark a directory as a project root, besides Cargo.toml
+    pub markers: Vec<String>,
+    /// Don't descend into directories on a different filesystem than the root (like `find -xdev`)
+    pub one_filesystem: bool,
+    /// Detect workspaces by parsing manifests instead of running `cargo metadata`
+    pub static_workspaces: bool,
+    /// Also discover vendored and third-party crates (see [`is_vendored_crate`])
+    pub include_vendored: bool,
+    /// Walk into paths that `.gitignore`, `.ignore` and git's exclude files ignore
+    pub no_ignore: bool,
+    /// Walk into hidden directories
+    pub hidden: bool,
+    /// How many directories below the root a project may be; unlimited when `None`
+    pub max_depth: Option<usize>,
+    /// Don't look for projects inside a project, except its workspace members and fuzz crate
+    pub prune_projects: bool,
+    /// Threads walking the tree; 0 or 1 walks lazily on the calling thread
+    pub threads: usize,
+    /// Advanced once for every directory the walk enters
+    pub progress: Option<ProgressBar>,
+    /// Collects every directory the walk looks at, and every manifest and marker file, so
+    /// a [`ProjectIndex`](crate::index::ProjectIndex) can tell when they change
+    pub visited: Option<Arc<Mutex<Vec<PathBuf>>>>,
+}
+
+/// Directory names whose contents are other people's code: `vendor/` (as written by
+/// `cargo vendor`, pruned only when it isn't a crate itself) and `node_modules/`
+const VENDORED_DIRS: &[&str] = &["vendor", "node_modules"];
+
+/// Files cargo leaves beside the manifests of crates it unpacked from a registry or git
+/// checkout (`.cargo-ok`) or copied with `cargo vendor` (`.cargo-checksum.json`)
+const VENDORED_CRATE_MARKERS: &[&str] = &[".cargo-ok", ".cargo-checksum.json"];
+
+/// Whether the walk should skip a vendored dependency directory
+fn is_vendored_dir(entry: &DirEntry) -> bool {
+    entry.file_type().is_some_and(|t| t.is_dir())
+        && VENDORED_DIRS.iter().any(|name| entry.file_name() == *name)
+        && !entry.path().join("Cargo.toml").exists()
+}
+
+/// Whether the crate in `dir` is third-party code rather than a project of the user's:
+/// it carries cargo's unpack or vendor markers, or lives in cargo's registry or git
+/// checkouts, a `node_modules` tree or a repository's submodule store (`.git/modules`)
+pub fn is_vendored_crate(dir: &Path) -> bool {
+    if VENDORED_CRATE_MARKERS.iter().any(|marker| dir.join(marker).exists()) {
+        return true;
+    }
+    if cargo_home().is_some_and(|home| dir.starts_with(home.join("registry")) || dir.starts_with(home.join("git"))) {
+        return true;
+    }
+    let components: Vec<_> = dir.components().map(|c| c.as_os_str()).collect();
+    components.iter().any(|c| *c == "node_modules")
+        || components.windows(2).any(|pair| {
+            (pair[0] == ".cargo" && (pair[1] == "registry" || pair[1] == "git")) || (pair[0] == ".git" && pair[1] == "modules")
         })
-    {
-        let entry = entry?;
-        if entry.file_name() == "Cargo.toml" {
-            let project_dir = entry.path().parent().unwrap().to_path_buf();
-
-            // Check if this is part of a workspace
-            let mut is_workspace_member = false;
-            let mut current = project_dir.parent();
-            while let Some(parent) = current {
-                let workspace_toml = parent.join("Cargo.toml");
-                if workspace_toml.exists() {
-                    // Try to parse as workspace
-                    if let Ok(metadata) = MetadataCommand::new()
-                        .manifest_path(&workspace_toml)
-                        .exec()
-                    {
-                        if metadata.workspace_root == parent {
-                            // This is a workspace member
-                            let workspace_path: PathBuf = metadata.workspace_root.into();
-                            if !seen_workspaces.contains(&workspace_path) {
-                                seen_workspaces.insert(workspace_path.clone());
-                                projects.push(Project {
-                                    path: workspace_path,
-                                    is_workspace: true,
-                                });
-                            }
-                            is_workspace_member = true;
-                            break;
-                        }
+}
+
+/// Check whether a walk entry should be skipped
+pub(crate) fn is_excluded(entry: &walkdir::DirEntry, root: &Path, exclude_patterns: &[String]) -> bool {
+    (entry.file_type().is_dir() && is_hidden(entry.path())) || matches_patterns(entry.path(), root, exclude_patterns)
+}
+
+/// Whether the last component of `path` is a hidden name
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .map(|name| name.to_string_lossy())
+        .is_some_and(|name| name.starts_with('.') && name != "." && name != "..")
+}
+
+/// Check whether `path`, relative to `root`, matches any of `patterns`; invalid ones never match
+fn matches_patterns(path: &Path, root: &Path, patterns: &[String]) -> bool {
+    for pattern in patterns {
+        if glob::Pattern::new(pattern)
+            .ok()
+            .and_then(|p| {
+                path.strip_prefix(root)
+                    .ok()
+                    .map(|rel| p.matches(&rel.to_string_lossy()))
+            })
+            .unwrap_or(false)
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// Whether the manifest at `manifest_path` has a `[workspace]` table
+fn has_workspace_table(manifest_path: &Path) -> bool {
+    std::fs::read_to_string(manifest_path)
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .is_some_and(|manifest| manifest.contains_key("workspace"))
+}
+
+/// The nearest directory, `project_dir` itself included, whose manifest has a
+/// `[workspace]` table
+fn static_workspace_root(project_dir: &Path) -> Option<PathBuf> {
+    project_dir
+        .ancestors()
+        .find(|dir| has_workspace_table(&dir.join("Cargo.toml")))
+        .map(Path::to_path_buf)
+}
+
+/// Whether the manifest at `manifest_path` belongs to a cargo-fuzz crate, which
+/// `cargo fuzz init` marks with `[package.metadata] cargo-fuzz = true`
+fn is_fuzz_crate(manifest_path: &Path) -> bool {
+    std::fs::read_to_string(manifest_path)
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .and_then(|manifest| manifest.get("package")?.get("metadata")?.get("cargo-fuzz")?.as_bool())
+        .unwrap_or(false)
+}
+
+/// Whether `dir` is build output, which discovery never walks: the `target/` beside a
+/// manifest, or any directory marked as a cache (`CACHEDIR.TAG`, which cargo and other
+/// tools write) or as a cargo target (`.rustc_info.json`)
+fn is_build_output_dir(dir: &Path) -> bool {
+    let beside_manifest = dir.file_name() == Some("target".as_ref())
+        && dir.parent().is_some_and(|parent| parent.join("Cargo.toml").is_file());
+    beside_manifest || dir.join("CACHEDIR.TAG").is_file() || dir.join(".rustc_info.json").is_file()
+}
+
+/// Whether `dir` sits directly inside a project without leading to one of its workspace
+/// members or its `fuzz/` crate, so `prune_projects` can skip it
+fn is_inside_project(dir: &Path) -> bool {
+    let Some(manifest_path) = dir.parent().map(|parent| parent.join("Cargo.toml")) else {
+        return false;
+    };
+    if !manifest_path.is_file() {
+        return false;
+    }
+    let Some(name) = dir.file_name().map(|name| name.to_string_lossy()) else {
+        return false;
+    };
+    if name == "fuzz" && is_fuzz_crate(&dir.join("Cargo.toml")) {
+        return false;
+    }
+    let manifest = std::fs::read_to_string(&manifest_path)
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok());
+    let members = manifest
+        .as_ref()
+        .and_then(|manifest| manifest.get("workspace")?.get("members")?.as_array().cloned())
+        .unwrap_or_default();
+    // Members are matched by their first path component, e.g. `crates` for `crates/*`
+    !members.iter().filter_map(|member| member.as_str()).any(|member| {
+        let first = member.split(['/', '\\']).find(|part| !part.is_empty() && *part != ".");
+        first.is_some_and(|first| glob::Pattern::new(first).is_ok_and(|pattern| pattern.matches(&name)))
+    })
+}
+
+/// Run `cargo metadata --no-deps` on the manifest in `project_dir`, adding the canonical
+/// directories of the workspace's members to `members` so cargo is not asked again when
+/// the walk reaches them
+///
+/// Returns the canonical workspace root (the package's own directory when it is
+/// standalone) with what cargo reported, or the first line of cargo's error.
+fn query_cargo(project_dir: &Path, members: &mut HashSet<PathBuf>) -> std::result::Result<(PathBuf, CargoInfo), String> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(project_dir.join("Cargo.toml"))
+        .no_deps()
+        .exec()
+        .map_err(|e| cargo_error_line(&e))?;
+    let packages = metadata.workspace_packages();
+    members.extend(
+        packages
+            .iter()
+            .filter_map(|package| package.manifest_path.parent()?.as_std_path().canonicalize().ok()),
+    );
+    let root = metadata.workspace_root.as_std_path();
+    let info = CargoInfo {
+        members: packages.iter().map(|package| package.name.to_string()).collect(),
+        virtual_manifest: metadata.root_package().is_none(),
+        target_dir: metadata.target_directory.clone().into_std_path_buf(),
+    };
+    Ok((root.canonicalize().unwrap_or_else(|_| root.to_path_buf()), info))
+}
+
+/// `path` expressed through `walked_dir` when it lies inside that directory's canonical
+/// form, so paths cargo reports compare equal to the ones the walk produced
+fn under_walked_path(walked_dir: &Path, path: &Path) -> PathBuf {
+    walked_dir
+        .canonicalize()
+        .ok()
+        .and_then(|canonical_dir| path.strip_prefix(canonical_dir).ok().map(|relative| walked_dir.join(relative)))
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// The first `error:` line of a failed `cargo metadata` run, without the prefix
+fn cargo_error_line(error: &cargo_metadata::Error) -> String {
+    let message = match error {
+        cargo_metadata::Error::CargoMetadata { stderr } => stderr.clone(),
+        _ => error.to_string(),
+    };
+    message
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("error: "))
+        .or_else(|| message.lines().find(|line| !line.trim().is_empty()))
+        .unwrap_or("cargo metadata failed")
+        .trim()
+        .to_string()
+}
+
+/// Resolve the project owning the manifest in `project_dir`, grouping workspace members
+/// under their workspace root so a shared target is measured and cleaned once
+///
+/// Membership comes from `cargo metadata`, which honors `package.workspace` and
+/// `exclude`, and the project records what cargo reported in [`Project::cargo`]. With
+/// `static_workspaces`, or when cargo can't load the manifest, the nearest `[workspace]`
+/// manifest up the tree is taken instead. Returns `None` for members of a workspace that
+/// was already reported.
+fn resolve_project(
+    project_dir: PathBuf,
+    seen_workspaces: &mut HashSet<PathBuf>,
+    known_members: &mut HashSet<PathBuf>,
+    static_workspaces: bool,
+) -> Option<Project> {
+    let canonical_dir = project_dir.canonicalize().unwrap_or_else(|_| project_dir.clone());
+    if known_members.contains(&canonical_dir) {
+        // Its workspace was reported when cargo listed it as a member
+        return None;
+    }
+    let (cargo_root, mut cargo) = match static_workspaces {
+        true => (None, CargoStatus::Unknown),
+        false => match query_cargo(&project_dir, known_members) {
+            Ok((root, info)) => (Some(root), CargoStatus::Loaded(info)),
+            Err(error) => (None, CargoStatus::Unbuildable(error)),
+        },
+    };
+    let workspace_root = match cargo_root {
+        // A standalone package is its own workspace root
+        Some(root) if root == canonical_dir && !has_workspace_table(&project_dir.join("Cargo.toml")) => None,
+        // Express roots above the project through the walked path, so symlinked roots still dedupe
+        Some(root) => Some(match canonical_dir.strip_prefix(&root) {
+            Ok(relative) => project_dir.ancestors().nth(relative.components().count()).map_or(root, Path::to_path_buf),
+            Err(_) => root,
+        }),
+        None => static_workspace_root(&project_dir),
+    };
+
+    if let CargoStatus::Loaded(ref mut info) = cargo {
+        let walked_root = workspace_root.as_deref().unwrap_or(&project_dir);
+        info.target_dir = under_walked_path(walked_root, &info.target_dir);
+    }
+
+    if let Some(workspace_path) = workspace_root {
+        if !seen_workspaces.insert(workspace_path.clone()) {
+            return None;
+        }
+        return Some(Project {
+            path: workspace_path,
+            is_workspace: true,
+            marker_only: false,
+            fuzz: false,
+            cargo,
+            overrides: ProjectOverrides::default(),
+        });
+    }
+
+    // Not a workspace member, add as standalone project
+    Some(Project {
+        path: project_dir,
+        is_workspace: false,
+        marker_only: false,
+        fuzz: false,
+        cargo,
+        overrides: ProjectOverrides::default(),
+    })
+}
+
+/// Whether a walk error is a followed symlink pointing back to one of its ancestors
+fn is_symlink_loop(error: &ignore::Error) -> bool {
+    match error {
+        ignore::Error::Loop { .. } => true,
+        ignore::Error::WithPath { err, .. } | ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            is_symlink_loop(err)
+        }
+        _ => false,
+    }
+}
+
+type EntryIter = Box<dyn Iterator<Item = Result<DirEntry, ignore::Error>>>;
+
+/// Lazily discovered projects, see [`discover_projects_iter`]
+pub struct ProjectIter {
+    root: PathBuf,
+    options: DiscoveryOptions,
+    pending_roots: VecDeque<PathBuf>,
+    walker: Option<(PathBuf, EntryIter)>,
+    seen_workspaces: HashSet<PathBuf>,
+    /// Canonical member directories of the workspaces cargo reported
+    known_members: HashSet<PathBuf>,
+    seen_projects: HashSet<PathBuf>,
+}
+
+impl ProjectIter {
+    fn walk(&self, walk_root: &Path) -> EntryIter {
+        let root = self.root.clone();
+        let exclude_patterns = self.options.exclude_patterns.clone();
+        let include_vendored = self.options.include_vendored;
+        let hidden = self.options.hidden;
+        let respect_ignore = !self.options.no_ignore;
+        let prune_projects = self.options.prune_projects;
+        let progress = self.options.progress.clone();
+        let visited = self.options.visited.clone();
+        let markers = self.options.markers.clone();
+        // A project's manifest is one level below its directory; followed root symlinks
+        // sit one level below the root
+        let max_depth = self.options.max_depth.map(|depth| match walk_root == self.root {
+            true => depth + 1,
+            false => depth,
+        });
+        let mut builder = WalkBuilder::new(walk_root);
+        builder
+            .follow_links(self.options.follow_symlinks)
+            .max_depth(max_depth)
+            .same_file_system(self.options.one_filesystem)
+            // Hidden files may be project markers, so only directories are checked below
+            .hidden(false)
+            .ignore(respect_ignore)
+            .git_ignore(respect_ignore)
+            .git_global(respect_ignore)
+            .git_exclude(respect_ignore)
+            .parents(respect_ignore)
+            .filter_entry(move |e| {
+                let is_dir = e.file_type().is_some_and(|t| t.is_dir());
+                let walked = e.depth() == 0
+                    || !((!hidden && is_dir && is_hidden(e.path()))
+                        || matches_patterns(e.path(), &root, &exclude_patterns)
+                        || (!include_vendored && is_vendored_dir(e))
+                        || (is_dir && is_build_output_dir(e.path()))
+                        || (prune_projects && is_dir && is_inside_project(e.path())));
+                if let Some(progress) = progress.as_ref().filter(|_| walked && is_dir) {
+                    progress.inc(1);
+                }
+                if let Some(visited) = visited.as_ref().filter(|_| walked) {
+                    if is_dir || e.file_name() == "Cargo.toml" || markers.iter().any(|m| e.file_name() == m.as_str()) {
+                        visited.lock().unwrap().push(e.path().to_path_buf());
+                    }
+                }
+                walked
+            });
+        if self.options.threads <= 1 {
+            return Box::new(builder.build());
+        }
+        Box::new(self.walk_parallel(builder).into_iter())
+    }
+
+    /// Walk with `threads` workers, keeping only the entries [`Self::handle_entry`] can
+    /// turn into projects or roots, sorted by path so results don't depend on scheduling
+    fn walk_parallel(&self, mut builder: WalkBuilder) -> Vec<Result<DirEntry, ignore::Error>> {
+        let markers = self.options.markers.clone();
+        let found = Mutex::new(Vec::new());
+        builder.threads(self.options.threads).build_parallel().run(|| {
+            let found = &found;
+            let markers = &markers;
+            Box::new(move |entry| {
+                let keep = match &entry {
+                    Ok(e) => {
+                        e.file_name() == "Cargo.toml"
+                            || markers.iter().any(|m| e.file_name() == m.as_str())
+                            || (e.depth() == 1 && e.path_is_symlink())
                     }
+                    Err(_) => true,
+                };
+                if keep {
+                    found.lock().unwrap().push(entry);
                 }
-                current = parent.parent();
+                WalkState::Continue
+            })
+        });
+        let mut found = found.into_inner().unwrap();
+        found.sort_by(|a, b| match (a, b) {
+            (Ok(a), Ok(b)) => a.path().cmp(b.path()),
+            (a, b) => a.is_err().cmp(&b.is_err()),
+        });
+        found
+    }
+
+    /// Turn a walk entry into a project, queueing symlinked roots for later walks
+    fn handle_entry(&mut self, walk_root: &Path, entry: DirEntry) -> Option<Project> {
+        // Links are walked in place when every symlink is followed
+        if self.options.follow_root_symlinks
+            && !self.options.follow_symlinks
+            && walk_root == self.root
+            && entry.depth() == 1
+            && entry.path_is_symlink()
+            && entry.path().is_dir()
+        {
+            if !self.options.one_filesystem || same_device(&self.root, entry.path()) {
+                self.pending_roots.push_back(entry.into_path());
             }
+            return None;
+        }
+
+        let is_manifest = entry.file_name() == "Cargo.toml";
+        let is_marker = entry.file_type().is_some_and(|t| t.is_file()) && self.options.markers.iter().any(|m| entry.file_name() == m.as_str());
+        if (is_manifest || is_marker)
+            && !self.options.include_vendored
+            && entry.path().parent().is_some_and(is_vendored_crate)
+        {
+            return None;
+        }
 
-            // If not a workspace member, add as standalone project
-            if !is_workspace_member {
-                projects.push(Project {
+        if is_manifest {
+            let project_dir = self.project_dir(&entry);
+            // Fuzz crates build into their own target even inside a workspace
+            if is_fuzz_crate(entry.path()) {
+                return Some(Project {
                     path: project_dir,
                     is_workspace: false,
+                    marker_only: false,
+                    fuzz: true,
+                    cargo: Default::default(),
+                    overrides: ProjectOverrides::default(),
                 });
             }
+            resolve_project(
+                project_dir,
+                &mut self.seen_workspaces,
+                &mut self.known_members,
+                self.options.static_workspaces,
+            )
+        } else if is_marker {
+            // Directories with a Cargo.toml are picked up through the manifest instead
+            if entry.path().with_file_name("Cargo.toml").exists() {
+                return None;
+            }
+            Some(Project {
+                path: self.project_dir(&entry),
+                is_workspace: false,
+                marker_only: true,
+                fuzz: false,
+                cargo: Default::default(),
+                overrides: ProjectOverrides::default(),
+            })
+        } else {
+            None
+        }
+    }
+
+    fn project_dir(&self, entry: &DirEntry) -> PathBuf {
+        let project_dir = entry.path().parent().unwrap().to_path_buf();
+        if self.options.follow_root_symlinks || self.options.follow_symlinks {
+            // Resolve links so the same project reached twice is deduped
+            project_dir.canonicalize().unwrap_or(project_dir)
+        } else {
+            project_dir
         }
     }
+}
+
+impl Iterator for ProjectIter {
+    type Item = Result<Project>;
 
-    // Remove duplicates
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (walk_root, mut walker) = match self.walker.take() {
+                Some(walker) => walker,
+                None => {
+                    let walk_root = self.pending_roots.pop_front()?;
+                    let walker = self.walk(&walk_root);
+                    (walk_root, walker)
+                }
+            };
+            let entry = walker.next();
+            let project = match entry {
+                None => continue,
+                // A followed link back to an ancestor would walk the same tree forever
+                Some(Err(e)) if is_symlink_loop(&e) => None,
+                Some(Err(e)) => {
+                    self.walker = Some((walk_root, walker));
+                    return Some(Err(e.into()));
+                }
+                Some(Ok(entry)) => self.handle_entry(&walk_root, entry),
+            };
+            self.walker = Some((walk_root, walker));
+            // Included subtrees can sit anywhere, so the walk goes everywhere and projects are filtered
+            let included = |project: &Project| {
+                self.options.include_patterns.is_empty()
+                    || matches_patterns(&project.path, &self.root, &self.options.include_patterns)
+            };
+            if let Some(project) = project.filter(included) {
+                if self.seen_projects.insert(project.path.clone()) {
+                    return Some(Ok(project));
+                }
+            }
+        }
+    }
+}
+
+/// Discover Cargo projects lazily, yielding each one as soon as it is found
+///
+/// Exclusions are applied during the walk, so excluded subtrees are never visited.
+/// The root itself is walked first; with `follow_root_symlinks`, symlinked
+/// directories directly under it are walked afterwards (a symlink is followed when it
+/// is the walk root) without following links deeper in the tree. Paths ignored by
+/// `.gitignore`, `.ignore` or git's exclude files, and hidden directories, are skipped
+/// unless `no_ignore` and `hidden` are set. With more than one of `threads`, each root is
+/// walked in parallel before its projects are yielded.
+pub fn discover_projects_iter(root: &Path, options: &DiscoveryOptions) -> ProjectIter {
+    ProjectIter {
+        root: root.to_path_buf(),
+        options: options.clone(),
+        pending_roots: VecDeque::from([root.to_path_buf()]),
+        walker: None,
+        seen_workspaces: HashSet::new(),
+        known_members: HashSet::new(),
+        seen_projects: HashSet::new(),
+    }
+}
+
+/// Find all Cargo projects in the given directory, sorted by path
+pub fn find_cargo_projects(root: &Path, options: &DiscoveryOptions) -> Result<Vec<Project>> {
+    let mut projects = discover_projects_iter(root, options).collect::<Result<Vec<_>>>()?;
     projects.sort_by_key(|p| p.path.clone());
-    projects.dedup_by_key(|p| p.path.clone());
+    Ok(projects)
+}
 
+/// Find projects under several roots, merged and sorted by path
+///
+/// Roots should be canonical and deduplicated with [`dedup_roots`], so no project is
+/// reached twice.
+pub fn find_cargo_projects_in(roots: &[PathBuf], options: &DiscoveryOptions) -> Result<Vec<Project>> {
+    let mut projects = Vec::new();
+    for root in roots {
+        projects.extend(find_cargo_projects(root, options)?);
+    }
+    projects.sort_by_key(|p| p.path.clone());
+    projects.dedup_by(|a, b| a.path == b.path);
     Ok(projects)
 }
 
+/// Projects for an explicit list of directories, skipping discovery
+///
+/// Every path must be a directory with a `Cargo.toml`; otherwise nothing is returned.
+/// Workspace members resolve to their workspace, like in discovery, so listing several
+/// members of one workspace cleans its shared target once.
+pub fn projects_from_paths(paths: &[PathBuf], options: &DiscoveryOptions) -> Result<Vec<Project>> {
+    let mut dirs = Vec::new();
+    for path in paths {
+        let dir = path
+            .canonicalize()
+            .map_err(|e| anyhow::anyhow!("Listed project {:?} can't be read: {}", path, e))?;
+        if !dir.join("Cargo.toml").is_file() {
+            anyhow::bail!("Listed project {:?} is not a Cargo project (no Cargo.toml)", path);
+        }
+        dirs.push(dir);
+    }
+
+    let mut seen_workspaces = HashSet::new();
+    let mut known_members = HashSet::new();
+    let mut projects = Vec::new();
+    for dir in dirs {
+        if is_fuzz_crate(&dir.join("Cargo.toml")) {
+            projects.push(Project {
+                path: dir,
+                is_workspace: false,
+                marker_only: false,
+                fuzz: true,
+                cargo: Default::default(),
+                overrides: ProjectOverrides::default(),
+            });
+        } else {
+            projects.extend(resolve_project(dir, &mut seen_workspaces, &mut known_members, options.static_workspaces));
+        }
+    }
+    projects.sort_by_key(|p| p.path.clone());
+    projects.dedup_by(|a, b| a.path == b.path);
+    Ok(projects)
+}
+
+/// Sort canonical roots and drop duplicates and roots nested inside another root,
+/// whose projects the outer root's walk already covers
+pub fn dedup_roots(mut roots: Vec<PathBuf>) -> Vec<PathBuf> {
+    roots.sort();
+    let mut kept: Vec<PathBuf> = Vec::new();
+    for root in roots {
+        if !kept.iter().any(|outer| root.starts_with(outer)) {
+            kept.push(root);
+        }
+    }
+    kept
+}
+
+/// The root of [`dedup_roots`]' output that `path` lies under, falling back to the first
+/// root for projects reached through a followed root symlink
+pub fn root_of<'a>(path: &Path, roots: &'a [PathBuf]) -> &'a Path {
+    roots.iter().find(|root| path.starts_with(root)).unwrap_or(&roots[0])
+}
+
+/// Check whether `path`, relative to `root`, matches any of `patterns`
+pub fn matches_relative(path: &Path, root: &Path, patterns: &[glob::Pattern]) -> bool {
+    path.strip_prefix(root)
+        .map(|rel| patterns.iter().any(|p| p.matches(&rel.to_string_lossy())))
+        .unwrap_or(false)
+}
+
+/// Check whether `path` lies inside the project directory, comparing canonical paths
+pub fn contains_path(project: &Project, path: &Path) -> bool {
+    match (project.path.canonicalize(), path.canonicalize()) {
+        (Ok(project_path), Ok(path)) => path.starts_with(project_path),
+        _ => false,
+    }
+}
+
+/// Check whether an existing target directory lies outside every discovered project
+///
+/// Such a target (e.g. from a global `CARGO_TARGET_DIR`) may hold artifacts of
+/// unrelated builds, so cleaning it wholesale is unsafe.
+pub fn is_shared_target(target_dir: &Path, projects: &[Project]) -> bool {
+    target_dir.exists() && !projects.iter().any(|project| contains_path(project, target_dir))
+}
+
+/// Split off projects whose target directory is the same as an earlier project's, e.g.
+/// packages pointing `[build] target-dir` at one shared directory
+///
+/// Returns the projects to measure and clean, and each dropped project with the path of
+/// the kept project it shares a target with.
+pub fn dedup_shared_targets(projects: Vec<Project>) -> (Vec<Project>, Vec<(Project, PathBuf)>) {
+    let mut owners: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut kept = Vec::new();
+    let mut shared = Vec::new();
+    for project in projects {
+        let target_dir = project.target_dir();
+        let target_dir = target_dir.canonicalize().unwrap_or(target_dir);
+        match owners.get(&target_dir) {
+            Some(owner) => shared.push((project, owner.clone())),
+            None => {
+                owners.insert(target_dir, project.path.clone());
+                kept.push(project);
+            }
+        }
+    }
+    (kept, shared)
+}
+
+/// Check whether a project's resolved dependency graph contains the named crate
+///
+/// Requires a full `cargo metadata` resolution, so this fails for projects whose
+/// dependencies cannot be resolved (e.g. offline with an incomplete lockfile).
+pub fn depends_on(project: &Project, crate_name: &str) -> Result<bool> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(project.path.join("Cargo.toml"))
+        .exec()?;
+    let wanted = crate_name.replace('-', "_");
+    Ok(metadata
+        .packages
+        .iter()
+        .filter(|p| !metadata.workspace_members.contains(&p.id))
+        .any(|p| p.name.replace('-', "_") == wanted))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -101,10 +845,53 @@ mod tests {
     #[test]
     fn test_find_cargo_projects_empty() {
         let temp_dir = TempDir::new().unwrap();
-        let projects = find_cargo_projects(temp_dir.path(), &[]).unwrap();
+        let projects = find_cargo_projects(temp_dir.path(), &DiscoveryOptions::default()).unwrap();
         assert_eq!(projects.len(), 0);
     }
 
+    #[test]
+    fn test_framework_outputs_need_a_recognized_tool() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = Project {
+            path: temp_dir.path().to_path_buf(),
+            is_workspace: false,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: ProjectOverrides::default(),
+        };
+        fs::create_dir_all(project.path.join("pkg")).unwrap();
+        fs::write(project.path.join("pkg/package.json"), "{}").unwrap();
+        fs::create_dir_all(project.path.join("dist")).unwrap();
+        fs::create_dir_all(project.path.join("www")).unwrap();
+        assert!(project.framework_outputs().is_empty());
+
+        fs::write(project.path.join("pkg/app_bg.wasm"), "").unwrap();
+        fs::write(project.path.join("Trunk.toml"), "[build]\ndist = \"www\"\n").unwrap();
+        let outputs: Vec<_> = project.framework_outputs().into_iter().map(|o| (o.tool, o.path)).collect();
+        assert_eq!(outputs, [("wasm-pack", project.path.join("pkg")), ("trunk", project.path.join("www"))]);
+    }
+
+    #[test]
+    fn test_fuzz_crate_is_its_own_project() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("Cargo.toml"), "[workspace]\nmembers = [\"lib\"]\n").unwrap();
+        fs::create_dir_all(root.join("lib")).unwrap();
+        fs::write(root.join("lib/Cargo.toml"), "[package]\nname = \"lib\"\nversion = \"0.1.0\"\n").unwrap();
+        fs::create_dir_all(root.join("fuzz")).unwrap();
+        fs::write(
+            root.join("fuzz/Cargo.toml"),
+            "[package]\nname = \"lib-fuzz\"\nversion = \"0.0.0\"\n\n[package.metadata]\ncargo-fuzz = true\n\n[workspace]\nmembers = [\".\"]\n",
+        )
+        .unwrap();
+
+        let options = DiscoveryOptions { static_workspaces: true, ..Default::default() };
+        let projects = find_cargo_projects(root, &options).unwrap();
+        let found: Vec<_> = projects.iter().map(|p| (p.path.strip_prefix(root).unwrap().to_path_buf(), p.fuzz)).collect();
+        assert_eq!(found, [(PathBuf::new(), false), (PathBuf::from("fuzz"), true)]);
+    }
+
     #[test]
     fn test_find_cargo_projects_standalone() {
         let temp_dir = TempDir::new().unwrap();
@@ -119,7 +906,7 @@ mod tests {
         fs::create_dir(project_dir.join("src")).unwrap();
         fs::write(project_dir.join("src/main.rs"), "fn main() {}").unwrap();
 
-        let projects = find_cargo_projects(temp_dir.path(), &[]).unwrap();
+        let projects = find_cargo_projects(temp_dir.path(), &DiscoveryOptions::default()).unwrap();
         // Note: The test might find 0 or 1 depending on cargo-metadata behavior
         // The important thing is it doesn't crash
         assert!(projects.len() <= 1);
@@ -127,5 +914,507 @@ mod tests {
             assert_eq!(projects[0].path, project_dir);
         }
     }
-}
 
+    #[test]
+    fn test_find_cargo_projects_skips_vendored() {
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = |dir: &Path, name: &str| {
+            fs::create_dir_all(dir).unwrap();
+            fs::write(dir.join("Cargo.toml"), format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n", name)).unwrap();
+        };
+        manifest(&temp_dir.path().join("app"), "app");
+        manifest(&temp_dir.path().join("app/vendor/serde"), "serde");
+        manifest(&temp_dir.path().join("web/node_modules/wasm-lib"), "wasm-lib");
+        manifest(&temp_dir.path().join("copied/libc"), "libc");
+        fs::write(temp_dir.path().join("copied/libc/.cargo-checksum.json"), "{}").unwrap();
+        // A crate that is itself called `vendor` is still the user's
+        manifest(&temp_dir.path().join("vendor"), "vendor");
+
+        let options = DiscoveryOptions { static_workspaces: true, ..Default::default() };
+        let projects = find_cargo_projects(temp_dir.path(), &options).unwrap();
+        let paths: Vec<_> = projects.iter().map(|p| p.path.strip_prefix(temp_dir.path()).unwrap().to_path_buf()).collect();
+        assert_eq!(paths, vec![PathBuf::from("app"), PathBuf::from("vendor")]);
+
+        let options = DiscoveryOptions { include_vendored: true, ..options };
+        assert_eq!(find_cargo_projects(temp_dir.path(), &options).unwrap().len(), 5);
+        assert!(is_vendored_crate(Path::new("/home/me/repo/.git/modules/dep/crate")));
+    }
+
+    #[test]
+    fn test_find_cargo_projects_respects_ignore_files() {
+        let temp_dir = TempDir::new().unwrap();
+        for dir in ["app", "scratch/old", ".backup/app"] {
+            fs::create_dir_all(temp_dir.path().join(dir)).unwrap();
+            fs::write(temp_dir.path().join(dir).join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\n").unwrap();
+        }
+        fs::write(temp_dir.path().join(".ignore"), "scratch/\n").unwrap();
+
+        let options = DiscoveryOptions { static_workspaces: true, ..Default::default() };
+        let projects = find_cargo_projects(temp_dir.path(), &options).unwrap();
+        assert_eq!(projects.iter().map(|p| p.path.clone()).collect::<Vec<_>>(), vec![temp_dir.path().join("app")]);
+
+        let options = DiscoveryOptions { no_ignore: true, hidden: true, ..options };
+        assert_eq!(find_cargo_projects(temp_dir.path(), &options).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_find_cargo_projects_include_patterns() {
+        let temp_dir = TempDir::new().unwrap();
+        for dir in ["experiments/a", "work/experiments/b", "apps/c"] {
+            fs::create_dir_all(temp_dir.path().join(dir)).unwrap();
+            fs::write(temp_dir.path().join(dir).join("Cargo.toml"), "[package]\nname = \"p\"\nversion = \"0.1.0\"\n").unwrap();
+        }
+
+        let options = DiscoveryOptions {
+            exclude_patterns: vec!["work".to_string()],
+            include_patterns: vec!["**/experiments/**".to_string(), "experiments/*".to_string()],
+            static_workspaces: true,
+            ..Default::default()
+        };
+        let projects = find_cargo_projects(temp_dir.path(), &options).unwrap();
+        assert_eq!(projects.iter().map(|p| p.path.clone()).collect::<Vec<_>>(), vec![temp_dir.path().join("experiments/a")]);
+    }
+
+    #[test]
+    fn test_projects_from_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("crates/core")).unwrap();
+        fs::create_dir_all(temp_dir.path().join("notes")).unwrap();
+        fs::write(temp_dir.path().join("Cargo.toml"), "[workspace]\nmembers = [\"crates/*\"]\n").unwrap();
+        fs::write(temp_dir.path().join("crates/core/Cargo.toml"), "[package]\nname = \"core\"\nversion = \"0.1.0\"\n").unwrap();
+        let options = DiscoveryOptions { static_workspaces: true, ..Default::default() };
+
+        // Members stand for their workspace, listed once
+        let listed = [temp_dir.path().join("crates/core"), temp_dir.path().to_path_buf()];
+        let projects = projects_from_paths(&listed, &options).unwrap();
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].path, temp_dir.path().canonicalize().unwrap());
+        assert!(projects[0].is_workspace);
+
+        let error = projects_from_paths(&[temp_dir.path().join("notes")], &options).unwrap_err();
+        assert!(error.to_string().contains("is not a Cargo project"));
+        assert!(projects_from_paths(&[temp_dir.path().join("missing")], &options).is_err());
+    }
+
+    #[test]
+    fn test_find_cargo_projects_depth_and_pruning() {
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = |dir: &str, content: &str| {
+            fs::create_dir_all(temp_dir.path().join(dir)).unwrap();
+            fs::write(temp_dir.path().join(dir).join("Cargo.toml"), content).unwrap();
+        };
+        let package = "[package]\nname = \"p\"\nversion = \"0.1.0\"\n";
+        manifest("ws", "[workspace]\nmembers = [\"crates/*\"]\n");
+        manifest("ws/crates/core", package);
+        manifest("ws/scratch/copy", package);
+        manifest("app", package);
+        manifest("app/examples/demo", package);
+        manifest("app/fuzz", "[package]\nname = \"fuzz\"\nversion = \"0.0.0\"\n\n[package.metadata]\ncargo-fuzz = true\n");
+        // Packaged crates in a target are build output, never projects
+        manifest("app/target/package/p-0.1.0", package);
+        manifest("deep/er/still", package);
+        let relative = |projects: Vec<Project>| -> Vec<PathBuf> {
+            projects.iter().map(|p| p.path.strip_prefix(temp_dir.path()).unwrap().to_path_buf()).collect()
+        };
+
+        let options = DiscoveryOptions { static_workspaces: true, ..Default::default() };
+        let all = relative(find_cargo_projects(temp_dir.path(), &options).unwrap());
+        assert_eq!(all.len(), 5);
+        assert!(!all.iter().any(|p| p.starts_with("app/target")));
+
+        // A parallel walk finds the same projects, and counts directories as it goes
+        let progress = ProgressBar::hidden();
+        let parallel = DiscoveryOptions { threads: 4, progress: Some(progress.clone()), ..options.clone() };
+        assert_eq!(relative(find_cargo_projects(temp_dir.path(), &parallel).unwrap()), all);
+        assert!(progress.position() >= 10);
+
+        for threads in [1, 4] {
+            let pruned = DiscoveryOptions { prune_projects: true, threads, ..options.clone() };
+            let paths = relative(find_cargo_projects(temp_dir.path(), &pruned).unwrap());
+            assert_eq!(paths, ["app", "app/fuzz", "deep/er/still", "ws"].map(PathBuf::from));
+        }
+
+        let shallow = DiscoveryOptions { max_depth: Some(1), ..options };
+        let paths = relative(find_cargo_projects(temp_dir.path(), &shallow).unwrap());
+        assert_eq!(paths, ["app", "ws"].map(PathBuf::from));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_cargo_projects_follow_symlinks() {
+        let root_dir = TempDir::new().unwrap();
+        let elsewhere = TempDir::new().unwrap();
+        let linked = elsewhere.path().join("linked");
+        fs::create_dir_all(&linked).unwrap();
+        fs::write(linked.join("Cargo.toml"), "[package]\nname = \"linked\"\nversion = \"0.1.0\"\n").unwrap();
+        fs::create_dir_all(root_dir.path().join("group")).unwrap();
+        std::os::unix::fs::symlink(&linked, root_dir.path().join("group/linked")).unwrap();
+        std::os::unix::fs::symlink(&linked, root_dir.path().join("group/again")).unwrap();
+        // Points back at its own ancestor
+        std::os::unix::fs::symlink(root_dir.path(), root_dir.path().join("group/loop")).unwrap();
+
+        let options = DiscoveryOptions { static_workspaces: true, ..Default::default() };
+        assert!(find_cargo_projects(root_dir.path(), &options).unwrap().is_empty());
+
+        let options = DiscoveryOptions { follow_symlinks: true, ..options };
+        let projects = find_cargo_projects(root_dir.path(), &options).unwrap();
+        assert_eq!(projects.iter().map(|p| p.path.clone()).collect::<Vec<_>>(), vec![linked.canonicalize().unwrap()]);
+    }
+
+    #[test]
+    fn test_dedup_roots_and_root_of() {
+        let roots = dedup_roots(vec![
+            PathBuf::from("/work"),
+            PathBuf::from("/src/app"),
+            PathBuf::from("/src"),
+            PathBuf::from("/work"),
+        ]);
+        assert_eq!(roots, vec![PathBuf::from("/src"), PathBuf::from("/work")]);
+        assert_eq!(root_of(Path::new("/work/api"), &roots), Path::new("/work"));
+        assert_eq!(root_of(Path::new("/elsewhere/linked"), &roots), Path::new("/src"));
+    }
+
+    #[test]
+    fn test_find_cargo_projects_static_workspaces() {
+        let temp_dir = TempDir::new().unwrap();
+        let workspace_dir = temp_dir.path().join("ws");
+        fs::create_dir_all(workspace_dir.join("crates/a")).unwrap();
+        fs::create_dir_all(workspace_dir.join("crates/b")).unwrap();
+        fs::write(workspace_dir.join("Cargo.toml"), "[workspace]\nmembers = [\"crates/*\"]\n").unwrap();
+        for name in ["a", "b"] {
+            fs::write(
+                workspace_dir.join("crates").join(name).join("Cargo.toml"),
+                format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n", name),
+            ).unwrap();
+        }
+
+        // Members have no sources, so only manifest parsing can group them
+        let options = DiscoveryOptions {
+            static_workspaces: true,
+            ..Default::default()
+        };
+        let projects = find_cargo_projects(temp_dir.path(), &options).unwrap();
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].path, workspace_dir);
+    }
+
+    #[test]
+    fn test_cargo_metadata_groups_members_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let workspace_dir = temp_dir.path().join("ws");
+        fs::create_dir_all(&workspace_dir).unwrap();
+        fs::write(
+            workspace_dir.join("Cargo.toml"),
+            "[package]\nname = \"root\"\nversion = \"0.1.0\"\n\n[workspace]\nmembers = [\"crates/a\"]\nexclude = [\"crates/b\"]\n",
+        )
+        .unwrap();
+        for name in ["", "crates/a", "crates/b"] {
+            let package_dir = workspace_dir.join(name);
+            fs::create_dir_all(package_dir.join("src")).unwrap();
+            fs::write(package_dir.join("src/lib.rs"), "").unwrap();
+            if !name.is_empty() {
+                let package = name.trim_start_matches("crates/");
+                fs::write(package_dir.join("Cargo.toml"), format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n", package)).unwrap();
+            }
+        }
+
+        fs::create_dir_all(temp_dir.path().join("template")).unwrap();
+        fs::write(temp_dir.path().join("template/Cargo.toml"), "[package]\nname = \"{{project-name}}\"\n").unwrap();
+
+        // `crates/b` is excluded, so it is a standalone package with its own target
+        let projects = find_cargo_projects(temp_dir.path(), &DiscoveryOptions::default()).unwrap();
+        let found: Vec<_> = projects.iter().map(|p| (p.path.clone(), p.is_workspace)).collect();
+        assert_eq!(
+            found,
+            [
+                (temp_dir.path().join("template"), false),
+                (workspace_dir.clone(), true),
+                (workspace_dir.join("crates/b"), false)
+            ]
+        );
+        assert!(matches!(projects[0].cargo, CargoStatus::Unbuildable(_)));
+        let CargoStatus::Loaded(ref info) = projects[1].cargo else {
+            panic!("cargo should load the workspace: {:?}", projects[1].cargo);
+        };
+        let mut members = info.members.clone();
+        members.sort();
+        assert_eq!((members, info.virtual_manifest), (vec!["a".to_string(), "root".to_string()], false));
+        assert_eq!(projects[1].target_dir(), workspace_dir.join("target"));
+        assert_eq!(projects[1].kind(), "workspace, 2 member(s)");
+    }
+
+    #[test]
+    fn test_dedup_shared_targets() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = |name: &str| {
+            let path = temp_dir.path().join(name);
+            fs::create_dir_all(path.join(".cargo")).unwrap();
+            Project { path, is_workspace: false, marker_only: false, fuzz: false, cargo: Default::default(), overrides: ProjectOverrides::default() }
+        };
+        let (one, two, three) = (project("one"), project("two"), project("three"));
+        fs::create_dir(temp_dir.path().join("shared-target")).unwrap();
+        for shared in [&one, &two] {
+            fs::write(shared.path.join(".cargo/config.toml"), "[build]\ntarget-dir = \"../shared-target\"\n").unwrap();
+        }
+
+        let (kept, shared) = dedup_shared_targets(vec![one.clone(), two.clone(), three.clone()]);
+        assert_eq!(kept.iter().map(|p| p.path.clone()).collect::<Vec<_>>(), [one.path.clone(), three.path]);
+        assert_eq!(shared.len(), 1);
+        assert_eq!((&shared[0].0.path, &shared[0].1), (&two.path, &one.path));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_cargo_projects_follow_root_symlinks() {
+        let real_dir = TempDir::new().unwrap();
+        let root_dir = TempDir::new().unwrap();
+        for name in ["alpha", "beta"] {
+            let project_dir = real_dir.path().join(name);
+            fs::create_dir_all(project_dir.join("src")).unwrap();
+            fs::write(
+                project_dir.join("Cargo.toml"),
+                format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n", name),
+            ).unwrap();
+            fs::write(project_dir.join("src/main.rs"), "fn main() {}").unwrap();
+            std::os::unix::fs::symlink(&project_dir, root_dir.path().join(name)).unwrap();
+        }
+        // A second link to the same project must not produce a duplicate
+        std::os::unix::fs::symlink(real_dir.path().join("alpha"), root_dir.path().join("alpha-again")).unwrap();
+
+        let projects = find_cargo_projects(root_dir.path(), &DiscoveryOptions::default()).unwrap();
+        assert!(projects.is_empty());
+
+        let options = DiscoveryOptions {
+            follow_root_symlinks: true,
+            ..Default::default()
+        };
+        let projects = find_cargo_projects(root_dir.path(), &options).unwrap();
+        let paths: Vec<PathBuf> = projects.into_iter().map(|p| p.path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                real_dir.path().join("alpha").canonicalize().unwrap(),
+                real_dir.path().join("beta").canonicalize().unwrap(),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_cargo_projects_one_filesystem() {
+        let root_dir = TempDir::new().unwrap();
+        let nested = root_dir.path().join("group/app");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\n").unwrap();
+
+        // Subdirectories on the root's filesystem are still walked
+        let options = DiscoveryOptions {
+            one_filesystem: true,
+            follow_root_symlinks: true,
+            ..Default::default()
+        };
+        let projects = find_cargo_projects(root_dir.path(), &options).unwrap();
+        assert_eq!(projects.len(), 1);
+
+        // A symlinked root on another device (tmpfs /dev/shm, when available) is pruned
+        let Ok(other_fs) = TempDir::new_in("/dev/shm") else {
+            return;
+        };
+        if same_device(root_dir.path(), other_fs.path()) {
+            return;
+        }
+        let remote = other_fs.path().join("remote");
+        fs::create_dir(&remote).unwrap();
+        fs::write(remote.join("Cargo.toml"), "[package]\nname = \"remote\"\nversion = \"0.1.0\"\n").unwrap();
+        std::os::unix::fs::symlink(&remote, root_dir.path().join("remote")).unwrap();
+
+        assert_eq!(find_cargo_projects(root_dir.path(), &options).unwrap().len(), 1);
+        let options = DiscoveryOptions { one_filesystem: false, ..options };
+        assert_eq!(find_cargo_projects(root_dir.path(), &options).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_depends_on_path_dependency() {
+        let temp_dir = TempDir::new().unwrap();
+        let lib_dir = temp_dir.path().join("shared-lib");
+        fs::create_dir_all(lib_dir.join("src")).unwrap();
+        fs::write(
+            lib_dir.join("Cargo.toml"),
+            "[package]\nname = \"shared-lib\"\nversion = \"0.1.0\"\n",
+        ).unwrap();
+        fs::write(lib_dir.join("src/lib.rs"), "").unwrap();
+
+        let app_dir = temp_dir.path().join("app");
+        fs::create_dir_all(app_dir.join("src")).unwrap();
+        fs::write(
+            app_dir.join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\nshared-lib = { path = \"../shared-lib\" }\n",
+        ).unwrap();
+        fs::write(app_dir.join("src/main.rs"), "fn main() {}").unwrap();
+
+        let app = Project { path: app_dir, is_workspace: false, marker_only: false, fuzz: false, cargo: Default::default(), overrides: ProjectOverrides::default() };
+        assert!(depends_on(&app, "shared-lib").unwrap());
+        assert!(depends_on(&app, "shared_lib").unwrap());
+        assert!(!depends_on(&app, "tokio").unwrap());
+
+        let lib = Project { path: lib_dir, is_workspace: false, marker_only: false, fuzz: false, cargo: Default::default(), overrides: ProjectOverrides::default() };
+        assert!(!depends_on(&lib, "shared-lib").unwrap());
+    }
+
+    #[test]
+    fn test_matches_relative() {
+        let patterns = vec![glob::Pattern::new("**/tests/fixtures/*").unwrap()];
+        let root = Path::new("/repo");
+        assert!(matches_relative(Path::new("/repo/crates/app/tests/fixtures/unused"), root, &patterns));
+        assert!(!matches_relative(Path::new("/repo/crates/app"), root, &patterns));
+        assert!(!matches_relative(Path::new("/elsewhere/tests/fixtures/x"), root, &patterns));
+    }
+
+    #[test]
+    fn test_contains_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_dir = temp_dir.path().join("app");
+        let sibling_dir = temp_dir.path().join("app2");
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        fs::create_dir(&sibling_dir).unwrap();
+        let project = Project { path: project_dir.clone(), is_workspace: false, marker_only: false, fuzz: false, cargo: Default::default(), overrides: ProjectOverrides::default() };
+
+        assert!(contains_path(&project, &project_dir));
+        assert!(contains_path(&project, &project_dir.join("src/../src")));
+        assert!(!contains_path(&project, &sibling_dir));
+        assert!(!contains_path(&project, temp_dir.path()));
+    }
+
+    #[test]
+    fn test_is_shared_target() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_dir = temp_dir.path().join("app");
+        let shared_dir = temp_dir.path().join("shared-target");
+        fs::create_dir_all(project_dir.join("target")).unwrap();
+        fs::create_dir(&shared_dir).unwrap();
+        let projects = vec![Project { path: project_dir.clone(), is_workspace: false, marker_only: false, fuzz: false, cargo: Default::default(), overrides: ProjectOverrides::default() }];
+
+        assert!(!is_shared_target(&project_dir.join("target"), &projects));
+        assert!(is_shared_target(&shared_dir, &projects));
+        assert!(!is_shared_target(&temp_dir.path().join("missing"), &projects));
+    }
+
+    #[test]
+    fn test_find_cargo_projects_custom_marker() {
+        let temp_dir = TempDir::new().unwrap();
+        let marked_dir = temp_dir.path().join("marked");
+        fs::create_dir(&marked_dir).unwrap();
+        fs::write(marked_dir.join(".deepclean-root"), "").unwrap();
+
+        let projects = find_cargo_projects(temp_dir.path(), &DiscoveryOptions::default()).unwrap();
+        assert!(projects.is_empty());
+
+        let options = DiscoveryOptions {
+            markers: vec![".deepclean-root".to_string()],
+            ..Default::default()
+        };
+        let projects = find_cargo_projects(temp_dir.path(), &options).unwrap();
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].path, marked_dir);
+        assert!(projects[0].marker_only);
+    }
+
+    #[test]
+    fn test_discover_projects_iter_matches_collecting_api() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["one", "two", "skipped/three"] {
+            let project_dir = temp_dir.path().join(name);
+            fs::create_dir_all(project_dir.join("src")).unwrap();
+            fs::write(
+                project_dir.join("Cargo.toml"),
+                "[package]\nname = \"p\"\nversion = \"0.1.0\"\n",
+            ).unwrap();
+        }
+        let options = DiscoveryOptions {
+            exclude_patterns: vec!["skipped".to_string()],
+            ..Default::default()
+        };
+
+        let mut iter = discover_projects_iter(temp_dir.path(), &options);
+        assert!(iter.next().unwrap().is_ok());
+
+        let mut streamed: Vec<PathBuf> = discover_projects_iter(temp_dir.path(), &options)
+            .map(|p| p.unwrap().path)
+            .collect();
+        streamed.sort();
+        let collected: Vec<PathBuf> = find_cargo_projects(temp_dir.path(), &options)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.path)
+            .collect();
+        assert_eq!(streamed, collected);
+        assert_eq!(collected, vec![temp_dir.path().join("one"), temp_dir.path().join("two")]);
+    }
+
+    #[test]
+    fn test_git_activity() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = Project {
+            path: temp_dir.path().to_path_buf(),
+            is_workspace: false,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: Default::default(),
+        };
+        let git = |date: &str, args: &[&str]| {
+            std::process::Command::new("git")
+                .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+                .args(args)
+                .current_dir(temp_dir.path())
+                .env("GIT_COMMITTER_DATE", date)
+                .output()
+                .is_ok_and(|output| output.status.success())
+        };
+        if !git("@0 +0000", &["init", "-q"]) {
+            return; // git is not installed
+        }
+        assert_eq!(project.git_activity(true), None);
+
+        assert!(git("@1000000000 +0000", &["commit", "-q", "--allow-empty", "-m", "first"]));
+        assert!(git("@2000000000 +0000", &["checkout", "-q", "-b", "other"]));
+        assert_eq!(project.git_activity(false), Some(1_000_000_000));
+        assert_eq!(project.git_activity(true), Some(2_000_000_000));
+    }
+
+    #[test]
+    fn test_git_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = Project {
+            path: temp_dir.path().to_path_buf(),
+            is_workspace: false,
+            marker_only: false,
+            fuzz: false,
+            cargo: Default::default(),
+            overrides: Default::default(),
+        };
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+                .args(args)
+                .current_dir(temp_dir.path())
+                .output()
+                .is_ok_and(|output| output.status.success())
+        };
+        assert_eq!(project.git_changes(), None);
+        if !git(&["init", "-q"]) {
+            return; // git is not installed
+        }
+        fs::write(temp_dir.path().join("Cargo.toml"), "[package]\nname = \"p\"\n").unwrap();
+        fs::create_dir_all(temp_dir.path().join("target/debug")).unwrap();
+        fs::write(temp_dir.path().join("target/debug/app"), "bin").unwrap();
+        assert_eq!(project.git_changes(), Some(1));
+
+        assert!(git(&["add", "Cargo.toml"]));
+        assert!(git(&["commit", "-q", "-m", "init"]));
+        assert_eq!(project.git_changes(), Some(0));
+        assert!(git(&["mv", "Cargo.toml", "Renamed.toml"]));
+        fs::write(temp_dir.path().join("notes.txt"), "wip").unwrap();
+        assert_eq!(project.git_changes(), Some(2));
+    }
+}
@@ -0,0 +1,353 @@
+//! Periodic cleanup jobs for `schedule`: systemd user timers, crontab entries, launchd
+//! agents and Task Scheduler tasks running a deepclean command
+//!
+//! This module only renders the job definitions; installing them is left to the caller.
+
+use crate::utils::shell_quote;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Name of the systemd units and the Task Scheduler task
+pub const JOB_NAME: &str = "deepclean";
+
+/// Label of the launchd agent, which also names its plist
+pub const LAUNCHD_LABEL: &str = "com.github.yingkitw.deepclean";
+
+/// Lines fencing deepclean's entry in a crontab, so it can be replaced or removed alone
+const CRON_BEGIN: &str = "# BEGIN deepclean schedule";
+const CRON_END: &str = "# END deepclean schedule";
+
+/// How often a scheduled job runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Frequency {
+    Daily,
+    /// On Sundays
+    Weekly,
+    /// On the first of the month
+    Monthly,
+}
+
+/// The system service that runs a scheduled job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Backend {
+    /// A systemd user timer and service in `~/.config/systemd/user`
+    Systemd,
+    /// An entry in the user's crontab
+    Cron,
+    /// A launchd agent in `~/Library/LaunchAgents`
+    Launchd,
+    /// A Windows Task Scheduler task
+    TaskScheduler,
+}
+
+impl Backend {
+    /// The scheduler normally used here: launchd on macOS, Task Scheduler on Windows and,
+    /// elsewhere, a systemd user timer when systemd is running, or cron
+    pub fn detect() -> Self {
+        if cfg!(target_os = "macos") {
+            Backend::Launchd
+        } else if cfg!(windows) {
+            Backend::TaskScheduler
+        } else if Path::new("/run/systemd/system").is_dir() {
+            Backend::Systemd
+        } else {
+            Backend::Cron
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Systemd => "systemd",
+            Backend::Cron => "cron",
+            Backend::Launchd => "launchd",
+            Backend::TaskScheduler => "Task Scheduler",
+        }
+    }
+}
+
+/// A deepclean command run at a fixed local time
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub frequency: Frequency,
+    pub hour: u32,
+    pub minute: u32,
+    /// The deepclean executable
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    /// `PATH` for the job, so `cargo` is found where schedulers start with a minimal one
+    pub path_env: Option<String>,
+    /// Where launchd appends the job's output
+    pub log: Option<PathBuf>,
+}
+
+impl Schedule {
+    /// When the job runs, e.g. "weekly on Sunday at 03:00"
+    pub fn describe(&self) -> String {
+        let time = format!("{:02}:{:02}", self.hour, self.minute);
+        match self.frequency {
+            Frequency::Daily => format!("daily at {}", time),
+            Frequency::Weekly => format!("weekly on Sunday at {}", time),
+            Frequency::Monthly => format!("monthly on the 1st at {}", time),
+        }
+    }
+
+    /// `<JOB_NAME>.service`, running the command once
+    pub fn systemd_service(&self) -> String {
+        let mut command = systemd_quote(&self.program.to_string_lossy());
+        for arg in &self.args {
+            command.push(' ');
+            command.push_str(&systemd_quote(arg));
+        }
+        let mut unit = format!(
+            "[Unit]\nDescription=deepclean cleanup, {}\n\n[Service]\nType=oneshot\nExecStart={}\n",
+            self.describe(),
+            command
+        );
+        if let Some(ref path) = self.path_env {
+            unit.push_str(&format!("Environment={}\n", systemd_quote(&format!("PATH={}", path))));
+        }
+        unit
+    }
+
+    /// `<JOB_NAME>.timer`, starting the service on schedule and catching up on missed runs
+    pub fn systemd_timer(&self) -> String {
+        let day = match self.frequency {
+            Frequency::Daily => "*-*-*",
+            Frequency::Weekly => "Sun *-*-*",
+            Frequency::Monthly => "*-*-01",
+        };
+        format!(
+            "[Unit]\nDescription=deepclean cleanup, {}\n\n[Timer]\nOnCalendar={} {:02}:{:02}:00\nPersistent=true\n\n[Install]\nWantedBy=timers.target\n",
+            self.describe(),
+            day,
+            self.hour,
+            self.minute
+        )
+    }
+
+    /// deepclean's fenced crontab entry
+    pub fn cron_entry(&self) -> String {
+        let (day_of_month, day_of_week) = match self.frequency {
+            Frequency::Daily => ("*", "*"),
+            Frequency::Weekly => ("*", "0"),
+            Frequency::Monthly => ("1", "*"),
+        };
+        let mut command = String::new();
+        if let Some(ref path) = self.path_env {
+            command.push_str(&format!("PATH={} ", shell_quote(path)));
+        }
+        command.push_str(&shell_quote(&self.program.to_string_lossy()));
+        for arg in &self.args {
+            command.push(' ');
+            command.push_str(&shell_quote(arg));
+        }
+        // cron turns unescaped `%` into newlines
+        format!(
+            "{}\n# {}\n{} {} {} * {} {}\n{}\n",
+            CRON_BEGIN,
+            self.describe(),
+            self.minute,
+            self.hour,
+            day_of_month,
+            day_of_week,
+            command.replace('%', "\\%"),
+            CRON_END
+        )
+    }
+
+    /// The launchd agent's property list
+    pub fn launchd_plist(&self) -> String {
+        let mut arguments = format!("        <string>{}</string>\n", xml_escape(&self.program.to_string_lossy()));
+        for arg in &self.args {
+            arguments.push_str(&format!("        <string>{}</string>\n", xml_escape(arg)));
+        }
+        let mut interval = format!(
+            "        <key>Hour</key>\n        <integer>{}</integer>\n        <key>Minute</key>\n        <integer>{}</integer>\n",
+            self.hour, self.minute
+        );
+        match self.frequency {
+            Frequency::Daily => {}
+            Frequency::Weekly => interval.push_str("        <key>Weekday</key>\n        <integer>0</integer>\n"),
+            Frequency::Monthly => interval.push_str("        <key>Day</key>\n        <integer>1</integer>\n"),
+        }
+        let mut extra = String::new();
+        if let Some(ref path) = self.path_env {
+            extra.push_str(&format!(
+                "    <key>EnvironmentVariables</key>\n    <dict>\n        <key>PATH</key>\n        <string>{}</string>\n    </dict>\n",
+                xml_escape(path)
+            ));
+        }
+        if let Some(ref log) = self.log {
+            let log = xml_escape(&log.to_string_lossy());
+            extra.push_str(&format!(
+                "    <key>StandardOutPath</key>\n    <string>{}</string>\n    <key>StandardErrorPath</key>\n    <string>{}</string>\n",
+                log, log
+            ));
+        }
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+             <!-- deepclean cleanup, {} -->\n\
+             <plist version=\"1.0\">\n<dict>\n    <key>Label</key>\n    <string>{}</string>\n\
+             \x20   <key>ProgramArguments</key>\n    <array>\n{}    </array>\n\
+             \x20   <key>StartCalendarInterval</key>\n    <dict>\n{}    </dict>\n{}</dict>\n</plist>\n",
+            xml_escape(&self.describe()),
+            LAUNCHD_LABEL,
+            arguments,
+            interval,
+            extra
+        )
+    }
+
+    /// Arguments to `schtasks` creating (or replacing) the task
+    pub fn schtasks_create_args(&self) -> Vec<String> {
+        let mut command = windows_quote(&self.program.to_string_lossy());
+        for arg in &self.args {
+            command.push(' ');
+            command.push_str(&windows_quote(arg));
+        }
+        let mut args: Vec<String> = ["/Create", "/TN", JOB_NAME, "/TR", &command].map(String::from).to_vec();
+        match self.frequency {
+            Frequency::Daily => args.extend(["/SC", "DAILY"].map(String::from)),
+            Frequency::Weekly => args.extend(["/SC", "WEEKLY", "/D", "SUN"].map(String::from)),
+            Frequency::Monthly => args.extend(["/SC", "MONTHLY", "/D", "1"].map(String::from)),
+        }
+        args.extend(["/ST".to_string(), format!("{:02}:{:02}", self.hour, self.minute), "/F".to_string()]);
+        args
+    }
+}
+
+/// Parse a 24-hour `HH:MM` time of day
+pub fn parse_time(time: &str) -> Result<(u32, u32)> {
+    let parsed = time
+        .split_once(':')
+        .and_then(|(hour, minute)| Some((hour.parse().ok()?, minute.parse().ok()?)))
+        .filter(|&(hour, minute)| hour < 24 && minute < 60);
+    parsed.ok_or_else(|| anyhow::anyhow!("Invalid time {:?}; expected HH:MM, e.g. 03:00", time))
+}
+
+/// `crontab` with deepclean's entry replaced by `entry`, or removed when it is `None`
+pub fn replace_cron_entry(crontab: &str, entry: Option<&str>) -> String {
+    let mut kept = String::new();
+    let mut inside = false;
+    for line in crontab.lines() {
+        match line.trim() {
+            CRON_BEGIN => inside = true,
+            CRON_END => inside = false,
+            _ if !inside => {
+                kept.push_str(line);
+                kept.push('\n');
+            }
+            _ => {}
+        }
+    }
+    if let Some(entry) = entry {
+        kept.push_str(entry);
+    }
+    kept
+}
+
+/// deepclean's fenced entry in `crontab`, if it has one
+pub fn cron_entry_in(crontab: &str) -> Option<String> {
+    let start = crontab.find(CRON_BEGIN)?;
+    let end = crontab[start..].find(CRON_END)? + start + CRON_END.len();
+    Some(format!("{}\n", &crontab[start..end]))
+}
+
+/// Directory of the user's systemd units: `$XDG_CONFIG_HOME/systemd/user`, or under `~/.config`
+pub fn systemd_unit_dir() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("systemd").join("user"))
+}
+
+/// Path of the launchd agent's plist in `~/Library/LaunchAgents`
+pub fn launch_agent_path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME")?;
+    Some(PathBuf::from(home).join("Library/LaunchAgents").join(format!("{}.plist", LAUNCHD_LABEL)))
+}
+
+/// Quote a word for a systemd `ExecStart=` or `Environment=` line, escaping specifiers
+fn systemd_quote(word: &str) -> String {
+    let escaped = word.replace('\\', "\\\\").replace('"', "\\\"").replace('%', "%%").replace('$', "$$");
+    format!("\"{}\"", escaped)
+}
+
+/// Quote a word for a Windows command line
+fn windows_quote(word: &str) -> String {
+    match word.is_empty() || word.contains([' ', '\t', '"']) {
+        true => format!("\"{}\"", word.replace('"', "\\\"")),
+        false => word.to_string(),
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(frequency: Frequency) -> Schedule {
+        Schedule {
+            frequency,
+            hour: 3,
+            minute: 5,
+            program: PathBuf::from("/home/me/.cargo/bin/cargo-deepclean"),
+            args: vec!["/home/me/my code".to_string(), "--min-size".to_string(), "1GB".to_string()],
+            path_env: Some("/home/me/.cargo/bin:/usr/bin".to_string()),
+            log: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("03:00").unwrap(), (3, 0));
+        assert_eq!(parse_time("23:59").unwrap(), (23, 59));
+        assert!(parse_time("24:00").is_err());
+        assert!(parse_time("3").is_err());
+    }
+
+    #[test]
+    fn test_systemd_units() {
+        let weekly = schedule(Frequency::Weekly);
+        let service = weekly.systemd_service();
+        assert!(service.contains(
+            "ExecStart=\"/home/me/.cargo/bin/cargo-deepclean\" \"/home/me/my code\" \"--min-size\" \"1GB\"\n"
+        ));
+        assert!(service.contains("Environment=\"PATH=/home/me/.cargo/bin:/usr/bin\"\n"));
+        assert!(weekly.systemd_timer().contains("OnCalendar=Sun *-*-* 03:05:00\n"));
+        assert!(schedule(Frequency::Monthly).systemd_timer().contains("OnCalendar=*-*-01 03:05:00\n"));
+        assert_eq!(systemd_quote("50%$"), "\"50%%$$\"");
+    }
+
+    #[test]
+    fn test_cron_entry_replaced_and_removed() {
+        let entry = Schedule { path_env: None, ..schedule(Frequency::Daily) }.cron_entry();
+        assert!(entry.contains("\n5 3 * * * /home/me/.cargo/bin/cargo-deepclean '/home/me/my code' --min-size 1GB\n"));
+
+        let existing = "MAILTO=me\n0 * * * * backup\n";
+        let installed = replace_cron_entry(existing, Some(&entry));
+        assert_eq!(cron_entry_in(&installed).as_deref(), Some(entry.as_str()));
+        let weekly = schedule(Frequency::Weekly).cron_entry();
+        let replaced = replace_cron_entry(&installed, Some(&weekly));
+        assert_eq!(replaced.matches(CRON_BEGIN).count(), 1);
+        assert!(replaced.contains("\n5 3 * * 0 PATH=/home/me/.cargo/bin:/usr/bin "));
+        assert_eq!(replace_cron_entry(&replaced, None), existing);
+        assert_eq!(cron_entry_in(existing), None);
+    }
+
+    #[test]
+    fn test_launchd_and_schtasks() {
+        let plist = schedule(Frequency::Monthly).launchd_plist();
+        assert!(plist.contains("<string>/home/me/my code</string>"));
+        assert!(plist.contains("<key>Day</key>\n        <integer>1</integer>"));
+        assert!(plist.contains("<key>Label</key>\n    <string>com.github.yingkitw.deepclean</string>"));
+        let args = schedule(Frequency::Weekly).schtasks_create_args();
+        assert_eq!(args[4], "/home/me/.cargo/bin/cargo-deepclean \"/home/me/my code\" --min-size 1GB");
+        assert_eq!(args[5..], ["/SC", "WEEKLY", "/D", "SUN", "/ST", "03:05", "/F"]);
+    }
+}
@@ -0,0 +1,131 @@
+use std::sync::{Condvar, Mutex};
+
+/// Minimum number of completed cleans before the throttle starts adapting
+const MIN_SAMPLES: usize = 4;
+
+#[derive(Debug, Default)]
+struct ThrottleState {
+    active: usize,
+    peak_active: usize,
+    cargo_cleans: usize,
+    manual_deletes: usize,
+}
+
+/// Snapshot of the throttle's observed behavior, shown at `-vv`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThrottleMetrics {
+    pub max_jobs: usize,
+    pub current_limit: usize,
+    pub peak_active: usize,
+    pub cargo_cleans: usize,
+    pub manual_deletes: usize,
+}
+
+/// Bounds how many manual target deletions (`remove_dir_all`) run at once
+///
+/// Starts at `max_jobs` (`--io-jobs`). Once at least four cleans have completed, if
+/// more than half of them fell back to manual deletion the run is treated as
+/// IO-bound and the limit is halved (minimum 1) so slow disks are not saturated.
+/// The `cargo clean` path is not throttled.
+#[derive(Debug)]
+pub struct IoThrottle {
+    max_jobs: usize,
+    state: Mutex<ThrottleState>,
+    available: Condvar,
+}
+
+/// A slot for one manual deletion, released on drop
+pub struct IoPermit<'a> {
+    throttle: &'a IoThrottle,
+}
+
+impl IoThrottle {
+    pub fn new(max_jobs: usize) -> Self {
+        Self {
+            max_jobs: max_jobs.max(1),
+            state: Mutex::new(ThrottleState::default()),
+            available: Condvar::new(),
+        }
+    }
+
+    fn limit(&self, state: &ThrottleState) -> usize {
+        let completed = state.cargo_cleans + state.manual_deletes;
+        if completed >= MIN_SAMPLES && state.manual_deletes * 2 > completed {
+            (self.max_jobs / 2).max(1)
+        } else {
+            self.max_jobs
+        }
+    }
+
+    /// Record a project cleaned through `cargo clean`
+    pub fn record_cargo_clean(&self) {
+        self.state.lock().unwrap().cargo_cleans += 1;
+    }
+
+    /// Wait for a deletion slot; the deletion counts as manual for adaptation
+    pub fn acquire(&self) -> IoPermit<'_> {
+        let mut state = self.state.lock().unwrap();
+        while state.active >= self.limit(&state) {
+            state = self.available.wait(state).unwrap();
+        }
+        state.active += 1;
+        state.manual_deletes += 1;
+        state.peak_active = state.peak_active.max(state.active);
+        IoPermit { throttle: self }
+    }
+
+    pub fn metrics(&self) -> ThrottleMetrics {
+        let state = self.state.lock().unwrap();
+        ThrottleMetrics {
+            max_jobs: self.max_jobs,
+            current_limit: self.limit(&state),
+            peak_active: state.peak_active,
+            cargo_cleans: state.cargo_cleans,
+            manual_deletes: state.manual_deletes,
+        }
+    }
+}
+
+impl Drop for IoPermit<'_> {
+    fn drop(&mut self) {
+        self.throttle.state.lock().unwrap().active -= 1;
+        self.throttle.available.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_io_throttle_bounds_concurrency() {
+        let throttle = IoThrottle::new(2);
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let _permit = throttle.acquire();
+                    std::thread::sleep(Duration::from_millis(5));
+                });
+            }
+        });
+        let metrics = throttle.metrics();
+        assert!(metrics.peak_active <= 2);
+        assert_eq!(metrics.manual_deletes, 8);
+    }
+
+    #[test]
+    fn test_io_throttle_adapts_to_manual_deletes() {
+        let throttle = IoThrottle::new(8);
+        for _ in 0..3 {
+            throttle.record_cargo_clean();
+        }
+        assert_eq!(throttle.metrics().current_limit, 8);
+
+        for _ in 0..4 {
+            drop(throttle.acquire());
+        }
+        // 4 of 7 cleans were manual deletions
+        assert_eq!(throttle.metrics().current_limit, 4);
+    }
+}
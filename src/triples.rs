@@ -0,0 +1,85 @@
+use crate::utils::{get_directory_size, SizeOptions};
+use std::path::Path;
+
+/// Size of one cross-compilation output directory, `target/<triple>`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TripleSize {
+    pub triple: String,
+    pub size: u64,
+    /// The triple builds WebAssembly (`wasm32-*`, `wasm64-*`)
+    pub wasm: bool,
+}
+
+/// Whether `triple` names a WebAssembly target
+pub fn is_wasm(triple: &str) -> bool {
+    triple.starts_with("wasm")
+}
+
+/// Target triples with their own output directory under `target_dir`
+///
+/// Cargo writes `--target` builds to `target/<triple>/<profile>`, while host builds
+/// go straight to `target/<profile>`. A child directory counts as a triple when it
+/// is not a profile itself but one of its own children is, i.e. holds a
+/// `.fingerprint` directory. Sorted by name.
+pub fn find_triples(target_dir: &Path) -> Vec<String> {
+    let is_profile = |dir: &Path| dir.join(".fingerprint").is_dir();
+    let Ok(entries) = std::fs::read_dir(target_dir) else {
+        return Vec::new();
+    };
+    let mut triples: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .filter(|entry| {
+            let dir = entry.path();
+            !is_profile(&dir)
+                && std::fs::read_dir(&dir)
+                    .map(|children| children.flatten().any(|child| is_profile(&child.path())))
+                    .unwrap_or(false)
+        })
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .collect();
+    triples.sort();
+    triples
+}
+
+/// Size every cross-compilation output directory under `target_dir`
+pub fn triple_sizes(target_dir: &Path, options: &SizeOptions) -> Vec<TripleSize> {
+    find_triples(target_dir)
+        .into_iter()
+        .map(|triple| TripleSize {
+            size: get_directory_size(&target_dir.join(&triple), options).unwrap_or(0),
+            wasm: is_wasm(&triple),
+            triple,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_triples() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let target_dir = temp_dir.path();
+        for dir in [
+            "debug/.fingerprint",
+            "release/.fingerprint",
+            "wasm32-unknown-unknown/release/.fingerprint",
+            "x86_64-pc-windows-gnu/debug/.fingerprint",
+            "doc/app",
+        ] {
+            std::fs::create_dir_all(target_dir.join(dir)).unwrap();
+        }
+        std::fs::write(target_dir.join("wasm32-unknown-unknown/release/app.wasm"), vec![0u8; 300]).unwrap();
+
+        assert_eq!(find_triples(target_dir), vec!["wasm32-unknown-unknown", "x86_64-pc-windows-gnu"]);
+        let sizes = triple_sizes(target_dir, &SizeOptions::default());
+        assert_eq!(
+            sizes[0],
+            TripleSize { triple: "wasm32-unknown-unknown".to_string(), size: 300, wasm: true }
+        );
+        assert!(!sizes[1].wasm);
+        assert!(find_triples(&target_dir.join("missing")).is_empty());
+    }
+}
@@ -1,6 +1,8 @@
-use anyhow::Result;
-use std::path::Path;
-use walkdir::WalkDir;
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 /// Format bytes into human-readable string
 pub fn format_bytes(bytes: u64) -> String {
@@ -20,23 +22,495 @@ pub fn format_bytes(bytes: u64) -> String {
     }
 }
 
+/// Render a program and its arguments as a copy-pasteable shell command line
+pub fn format_command(program: &str, args: &[String]) -> String {
+    let mut line = shell_quote(program);
+    for arg in args {
+        line.push(' ');
+        line.push_str(&shell_quote(arg));
+    }
+    line
+}
+
+/// Quote a word for `sh`, leaving plain words alone
+pub fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty() && word.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=,+@".contains(c));
+    match plain {
+        true => word.to_string(),
+        false => format!("'{}'", word.replace('\'', "'\\''")),
+    }
+}
+
+/// Render a path, hex-escaping (`\xNN`) any bytes that are not valid UTF-8
+pub fn escape_non_utf8(path: &Path) -> String {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let mut escaped = String::new();
+        for chunk in path.as_os_str().as_bytes().utf8_chunks() {
+            escaped.push_str(chunk.valid());
+            for byte in chunk.invalid() {
+                escaped.push_str(&format!("\\x{:02x}", byte));
+            }
+        }
+        escaped
+    }
+    #[cfg(not(unix))]
+    path.to_string_lossy().to_string()
+}
+
+/// How file sizes are measured
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SizeOptions {
+    /// Count allocated blocks (`blocks * 512`) instead of apparent length; Unix only
+    pub on_disk: bool,
+    /// Record access times instead of modification times in [`DirectoryStats`]
+    pub by_atime: bool,
+    /// Leave out subdirectories on another filesystem than the measured directory
+    pub one_filesystem: bool,
+}
+
+/// Size of a single file according to `options`
+pub fn file_size(metadata: &std::fs::Metadata, options: &SizeOptions) -> u64 {
+    #[cfg(unix)]
+    if options.on_disk {
+        use std::os::unix::fs::MetadataExt;
+        return metadata.blocks() * 512;
+    }
+    #[cfg(not(unix))]
+    let _ = options;
+    metadata.len()
+}
+
+/// Bytes allocated to a file on disk (`blocks * 512`); its apparent length off Unix
+pub fn allocated_size(metadata: &std::fs::Metadata) -> u64 {
+    file_size(metadata, &SizeOptions { on_disk: true, ..Default::default() })
+}
+
+/// Whether this is the first of a file's hard links seen in a walk, so it is counted once
+fn first_link(metadata: &std::fs::Metadata, seen: &Mutex<HashSet<(u64, u64)>>) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.nlink() <= 1 || seen.lock().unwrap().insert((metadata.dev(), metadata.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (metadata, seen);
+        true
+    }
+}
+
+/// Magic number `statfs` reports for btrfs
+#[cfg(target_os = "linux")]
+const BTRFS_SUPER_MAGIC: u32 = 0x9123_683E;
+
+/// Why allocated block counts under `path` may not be net of transparent compression
+///
+/// Block counts reflect compression on ZFS and APFS, but btrfs reports blocks before
+/// compression.
+#[cfg(unix)]
+pub fn physical_size_caveat(path: &Path) -> Option<&'static str> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+        // SAFETY: `statfs` is plain old data, so all-zero is a valid value; `c_path` is
+        // NUL-terminated and `stats` is a valid out-pointer
+        let mut stats: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(c_path.as_ptr(), &mut stats) } == 0 && stats.f_type as u32 == BTRFS_SUPER_MAGIC {
+            return Some("btrfs does not expose compressed sizes; physical sizes are allocated blocks before compression");
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = path;
+    None
+}
+
+/// Non-Unix platforms expose no block counts, so physical sizes fall back to apparent sizes
+#[cfg(not(unix))]
+pub fn physical_size_caveat(_path: &Path) -> Option<&'static str> {
+    Some("block counts are not available on this platform; physical sizes are apparent sizes")
+}
+
+/// Size and artifact age range of a directory
+///
+/// The timestamps are access times instead when [`SizeOptions::by_atime`] is set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DirectoryStats {
+    pub size: u64,
+    /// Bytes allocated on disk, whatever [`SizeOptions::on_disk`] says
+    pub disk_usage: u64,
+    /// Modification time of the oldest file, in seconds since the Unix epoch
+    pub oldest_mtime: Option<u64>,
+    /// Modification time of the newest file, in seconds since the Unix epoch
+    pub newest_mtime: Option<u64>,
+}
+
+impl DirectoryStats {
+    fn add_file(&mut self, metadata: &std::fs::Metadata, options: &SizeOptions) {
+        self.size += file_size(metadata, options);
+        self.disk_usage += allocated_size(metadata);
+        if let Some(mtime) = file_timestamp(metadata, options) {
+            self.oldest_mtime = Some(self.oldest_mtime.map_or(mtime, |t| t.min(mtime)));
+            self.newest_mtime = Some(self.newest_mtime.map_or(mtime, |t| t.max(mtime)));
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Self {
+            size: self.size + other.size,
+            disk_usage: self.disk_usage + other.disk_usage,
+            oldest_mtime: self.oldest_mtime.into_iter().chain(other.oldest_mtime).min(),
+            newest_mtime: self.newest_mtime.into_iter().chain(other.newest_mtime).max(),
+        }
+    }
+}
+
 /// Get the total size of a directory in bytes
-pub fn get_directory_size(path: &Path) -> Result<u64> {
-    let mut total = 0u64;
+pub fn get_directory_size(path: &Path, options: &SizeOptions) -> Result<u64> {
+    get_directory_size_excluding(path, &[], options)
+}
+
+/// Get the total size of a directory in bytes, skipping the given subtrees
+///
+/// `excluded` paths are relative to `path` (e.g. `target`, `.git`). Excluded
+/// directories are pruned from the walk, so their contents are never visited.
+pub fn get_directory_size_excluding(path: &Path, excluded: &[&Path], options: &SizeOptions) -> Result<u64> {
+    Ok(get_directory_stats_excluding(path, excluded, options)?.size)
+}
+
+/// Get the size and oldest/newest file modification times of a directory
+///
+/// Both timestamps are `None` when the directory holds no files. `path` itself may be a
+/// symlink, which is measured where it points; symlinks inside it are never followed, so
+/// nothing is counted twice and link cycles can't occur. Subdirectories are walked in
+/// parallel on the rayon thread pool.
+pub fn get_directory_stats(path: &Path, options: &SizeOptions) -> Result<DirectoryStats> {
+    get_directory_stats_excluding(path, &[], options)
+}
+
+fn get_directory_stats_excluding(path: &Path, excluded: &[&Path], options: &SizeOptions) -> Result<DirectoryStats> {
     if !path.exists() {
-        return Ok(0);
+        return Ok(DirectoryStats::default());
+    }
+    let device = match options.one_filesystem {
+        true => Some(device_of(&std::fs::metadata(path)?)),
+        false => None,
+    };
+    let walk = StatsWalk {
+        root: path,
+        excluded,
+        options,
+        device,
+        // Hard links to one file (as some build caches make) take its space once
+        seen_links: Mutex::new(HashSet::new()),
+    };
+    walk.dir(path)
+}
+
+/// Shared state of one [`get_directory_stats`] walk
+struct StatsWalk<'a> {
+    root: &'a Path,
+    excluded: &'a [&'a Path],
+    options: &'a SizeOptions,
+    /// Device of the root when subdirectories on other filesystems are left out
+    device: Option<u64>,
+    seen_links: Mutex<HashSet<(u64, u64)>>,
+}
+
+impl StatsWalk<'_> {
+    /// Files directly in `dir`, plus its subdirectories measured in parallel
+    fn dir(&self, dir: &Path) -> Result<DirectoryStats> {
+        let mut stats = DirectoryStats::default();
+        let mut subdirs = Vec::new();
+        for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {:?}", dir))? {
+            let entry = entry.with_context(|| format!("Failed to read {:?}", dir))?;
+            let path = entry.path();
+            if path.strip_prefix(self.root).is_ok_and(|rel| self.excluded.contains(&rel)) {
+                continue;
+            }
+            // Entry types and metadata don't follow symlinks
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                if self.device.is_none_or(|device| entry.metadata().is_ok_and(|m| device_of(&m) == device)) {
+                    subdirs.push(path);
+                }
+            } else if file_type.is_file() {
+                let metadata = entry.metadata().with_context(|| format!("Failed to read metadata of {:?}", path))?;
+                if first_link(&metadata, &self.seen_links) {
+                    stats.add_file(&metadata, self.options);
+                }
+            }
+        }
+        let nested = subdirs.par_iter().map(|subdir| self.dir(subdir)).collect::<Result<Vec<_>>>()?;
+        Ok(nested.into_iter().fold(stats, DirectoryStats::merge))
+    }
+}
+
+/// Device a file lives on; every file is on device 0 where this isn't known
+fn device_of(metadata: &std::fs::Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.dev()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        0
+    }
+}
+
+/// Whether access times under `path` are unreliable because it is mounted `noatime`
+///
+/// Only detectable on Linux; elsewhere access times are assumed to be maintained.
+pub fn atime_unreliable(path: &Path) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+            return false;
+        };
+        // SAFETY: `statvfs` is plain old data, so all-zero is a valid value; `c_path` is
+        // NUL-terminated and `stats` is a valid out-pointer
+        let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+        unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) == 0 && stats.f_flag & libc::ST_NOATIME != 0 }
     }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = path;
+        false
+    }
+}
 
-    for entry in WalkDir::new(path) {
-        let entry = entry?;
-        if entry.file_type().is_file() {
-            total += entry.metadata()?.len();
+/// Free space available to unprivileged users on the filesystem holding `path`
+///
+/// `None` when the filesystem can't be queried, and always on non-Unix platforms.
+pub fn free_space(path: &Path) -> Option<u64> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+        // SAFETY: `statvfs` is plain old data, so all-zero is a valid value; `c_path` is
+        // NUL-terminated and `stats` is a valid out-pointer
+        let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) } != 0 {
+            return None;
         }
+        #[allow(clippy::unnecessary_cast)]
+        Some(stats.f_bavail as u64 * stats.f_frsize as u64)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
     }
-    Ok(total)
 }
 
-/// Parse size string (e.g., "100MB", "1GB") to bytes
+/// `statfs` magic numbers of network filesystems on Linux, with the name reported for each
+#[cfg(target_os = "linux")]
+const NETWORK_FS_MAGICS: &[(u32, &str)] = &[
+    (0x6969, "NFS"),
+    (0x517B, "SMB"),
+    (0xFF53_4D42, "CIFS"),
+    (0xFE53_4D42, "SMB2"),
+    (0x5346_414F, "AFS"),
+    (0x00C3_6400, "Ceph"),
+    (0x0BD0_0BD0, "Lustre"),
+    (0x4750_4653, "GPFS"),
+];
+
+/// `statfs` type names of network filesystems on macOS
+#[cfg(target_os = "macos")]
+const NETWORK_FS_NAMES: &[&str] = &["nfs", "smbfs", "afpfs", "webdav", "cifs"];
+
+/// The kind of network filesystem holding `path` (e.g. `NFS`), or `None` when it is
+/// local or can't be told
+///
+/// Detected on Linux and macOS; elsewhere every filesystem counts as local.
+pub fn network_filesystem(path: &Path) -> Option<&'static str> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+        // SAFETY: `statfs` is plain old data, so all-zero is a valid value; `c_path` is
+        // NUL-terminated and `stats` is a valid out-pointer
+        let mut stats: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(c_path.as_ptr(), &mut stats) } != 0 {
+            return None;
+        }
+        network_fs_by_magic(stats.f_type as u32)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+        // SAFETY: as above; `f_fstypename` is NUL-terminated by the kernel
+        let mut stats: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(c_path.as_ptr(), &mut stats) } != 0 {
+            return None;
+        }
+        let name = unsafe { std::ffi::CStr::from_ptr(stats.f_fstypename.as_ptr()) }.to_string_lossy();
+        network_fs_by_name(&name)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// The network filesystem a Linux `statfs` magic number stands for, if any
+#[cfg(target_os = "linux")]
+fn network_fs_by_magic(magic: u32) -> Option<&'static str> {
+    NETWORK_FS_MAGICS.iter().find(|(network, _)| *network == magic).map(|(_, name)| *name)
+}
+
+/// The network filesystem a macOS `statfs` type name stands for, if any
+#[cfg(target_os = "macos")]
+fn network_fs_by_name(name: &str) -> Option<&'static str> {
+    NETWORK_FS_NAMES.iter().find(|network| **network == name).copied()
+}
+
+/// One path per filesystem among `paths`, for sampling free space once per device
+///
+/// Paths that don't exist are skipped. Without device ids (non-Unix) every path is kept.
+pub fn one_path_per_device(paths: impl IntoIterator<Item = PathBuf>) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    paths
+        .into_iter()
+        .filter(|path| {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                std::fs::metadata(path).is_ok_and(|m| seen.insert(m.dev()))
+            }
+            #[cfg(not(unix))]
+            {
+                path.exists() && seen.insert(path.clone())
+            }
+        })
+        .collect()
+}
+
+/// Whether two paths live on the same device, following symlinks
+///
+/// Always true on non-Unix platforms, where device ids aren't available.
+#[cfg(unix)]
+pub fn same_device(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn same_device(_a: &Path, _b: &Path) -> bool {
+    true
+}
+
+/// deepclean's directory under the user's cache directory: `$XDG_CACHE_HOME`, falling
+/// back to `%LOCALAPPDATA%` and then `~/.cache`
+pub fn cache_dir() -> Option<PathBuf> {
+    let cache_dir = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(cache_dir.join("deepclean"))
+}
+
+/// A file's modification time, or access time with [`SizeOptions::by_atime`], in seconds
+/// since the Unix epoch
+pub fn file_timestamp(metadata: &std::fs::Metadata, options: &SizeOptions) -> Option<u64> {
+    let timestamp = if options.by_atime { metadata.accessed() } else { metadata.modified() };
+    timestamp
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+/// Current time in seconds since the Unix epoch
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Format a timestamp (seconds since the Unix epoch) as UTC, e.g. "2024-03-01T12:00:00Z"
+pub fn format_utc(timestamp: u64) -> String {
+    // Civil date from days since the epoch, after Howard Hinnant's `civil_from_days`
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let seconds = timestamp % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Format the time elapsed since `timestamp` (seconds since the Unix epoch) as e.g. "3d ago"
+pub fn format_age(timestamp: u64) -> String {
+    format_duration_ago(unix_now().saturating_sub(timestamp))
+}
+
+fn format_duration_ago(seconds: u64) -> String {
+    format!("{} ago", format_duration(seconds))
+}
+
+/// Format a number of seconds in its largest whole unit, e.g. "45m" or "30d"
+pub fn format_duration(seconds: u64) -> String {
+    match seconds {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86400),
+    }
+}
+
+/// Normalize a comma decimal separator (e.g. "1,5") to a dot
+///
+/// A single comma is accepted as a decimal separator unless it is followed by exactly
+/// three digits, which is rejected as ambiguous with a thousands separator ("1,500").
+/// Multiple commas, or a comma mixed with a dot, are always rejected.
+fn normalize_decimal_separator(number_str: &str) -> Result<String> {
+    use anyhow::anyhow;
+    let comma_count = number_str.matches(',').count();
+    if comma_count == 0 {
+        return Ok(number_str.to_string());
+    }
+    if comma_count > 1 || number_str.contains('.') {
+        return Err(anyhow!(
+            "Ambiguous number in size: {} (thousands separators are not supported)",
+            number_str
+        ));
+    }
+    let fraction = &number_str[number_str.find(',').unwrap() + 1..];
+    if fraction.len() == 3 && fraction.chars().all(|c| c.is_ascii_digit()) {
+        return Err(anyhow!(
+            "Ambiguous number in size: {} (use '.' for decimals, thousands separators are not supported)",
+            number_str
+        ));
+    }
+    Ok(number_str.replace(',', "."))
+}
+
+/// Parse size string (e.g., "100MB", "1GB", "1,5GB") to bytes
 pub fn parse_size(size_str: &str) -> Result<u64> {
     use anyhow::anyhow;
     let size_str = size_str.trim().to_uppercase();
@@ -56,8 +530,7 @@ pub fn parse_size(size_str: &str) -> Result<u64> {
         return Err(anyhow!("Invalid size format: expected format like '100MB' or '1GB'"));
     };
 
-    let number: f64 = number_str
-        .trim()
+    let number: f64 = normalize_decimal_separator(number_str.trim())?
         .parse()
         .map_err(|_| anyhow!("Invalid number in size: {}", number_str))?;
 
@@ -73,10 +546,42 @@ pub fn parse_size(size_str: &str) -> Result<u64> {
     Ok((number * multiplier as f64) as u64)
 }
 
+/// Parse a duration such as "90s", "45m", "12h", "30d" or "2w" to seconds
+pub fn parse_duration(duration_str: &str) -> Result<u64> {
+    let duration_str = duration_str.trim().to_lowercase();
+    let split = duration_str
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow::anyhow!("Missing unit in duration: expected format like '30d' or '12h'"))?;
+    let (number, unit) = duration_str.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid number in duration: {:?}", duration_str))?;
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        _ => anyhow::bail!("Unknown unit in duration: {:?} (expected s, m, h, d or w)", unit),
+    };
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| anyhow::anyhow!("Duration out of range: {:?}", duration_str))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[cfg(unix)]
+    #[test]
+    fn test_escape_non_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"/projects/caf\xe9/app"));
+        assert_eq!(escape_non_utf8(path), "/projects/caf\\xe9/app");
+        assert_eq!(escape_non_utf8(Path::new("/projects/café")), "/projects/café");
+    }
+
     #[test]
     fn test_format_bytes() {
         assert_eq!(format_bytes(0), "0 B");
@@ -97,9 +602,173 @@ mod tests {
         assert!(parse_size("invalid").is_err());
     }
 
+    #[test]
+    fn test_parse_size_comma_decimal() {
+        assert_eq!(parse_size("1,5GB").unwrap(), parse_size("1.5GB").unwrap());
+        assert_eq!(parse_size("2,25 MB").unwrap(), parse_size("2.25MB").unwrap());
+        // A comma followed by three digits could be a thousands separator
+        assert!(parse_size("1,500MB").is_err());
+        assert!(parse_size("1,000,000B").is_err());
+        assert!(parse_size("1.000,5MB").is_err());
+    }
+
+    #[test]
+    fn test_format_command() {
+        let args = vec!["clean".to_string(), "--manifest-path".to_string(), "/my projects/Cargo.toml".to_string()];
+        assert_eq!(
+            format_command("cargo", &args),
+            "cargo clean --manifest-path '/my projects/Cargo.toml'"
+        );
+        assert_eq!(format_command("cargo", &["it's".to_string()]), "cargo 'it'\\''s'");
+        assert_eq!(format_command("cargo", &["a;b".to_string(), "*".to_string()]), "cargo 'a;b' '*'");
+    }
+
+    #[test]
+    fn test_get_directory_size_excluding() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target/debug")).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("docs/target")).unwrap();
+        std::fs::write(root.join("src/main.rs"), vec![0u8; 100]).unwrap();
+        std::fs::write(root.join("target/debug/app"), vec![0u8; 1000]).unwrap();
+        std::fs::write(root.join(".git/HEAD"), vec![0u8; 10]).unwrap();
+        std::fs::write(root.join("docs/target/page.md"), vec![0u8; 1]).unwrap();
+
+        assert_eq!(get_directory_size(root, &SizeOptions::default()).unwrap(), 1111);
+        assert_eq!(
+            get_directory_size_excluding(root, &[Path::new("target"), Path::new(".git")], &SizeOptions::default()).unwrap(),
+            101
+        );
+        // Exclusions match relative paths, not bare names anywhere in the tree
+        assert_eq!(
+            get_directory_size_excluding(root, &[Path::new("docs/target")], &SizeOptions::default()).unwrap(),
+            1110
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_get_directory_size_on_disk() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        // A sparse file: 1 MiB apparent length with no data blocks written
+        let sparse = std::fs::File::create(temp_dir.path().join("sparse")).unwrap();
+        sparse.set_len(1024 * 1024).unwrap();
+
+        let apparent = get_directory_size(temp_dir.path(), &SizeOptions::default()).unwrap();
+        let on_disk = get_directory_size(temp_dir.path(), &SizeOptions { on_disk: true, ..Default::default() }).unwrap();
+        assert_eq!(apparent, 1024 * 1024);
+        assert!(on_disk < apparent, "sparse file occupies {} bytes on disk", on_disk);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_get_directory_stats_counts_hard_links_once() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("deps")).unwrap();
+        std::fs::write(temp_dir.path().join("deps/libfoo.rlib"), vec![0u8; 4096]).unwrap();
+        std::fs::hard_link(temp_dir.path().join("deps/libfoo.rlib"), temp_dir.path().join("libfoo.rlib")).unwrap();
+
+        let stats = get_directory_stats(temp_dir.path(), &SizeOptions::default()).unwrap();
+        assert_eq!(stats.size, 4096);
+        let allocated = allocated_size(&std::fs::metadata(temp_dir.path().join("libfoo.rlib")).unwrap());
+        assert_eq!(stats.disk_usage, allocated);
+    }
+
+    #[test]
+    fn test_get_directory_stats_mtimes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(
+            get_directory_stats(temp_dir.path(), &SizeOptions::default()).unwrap(),
+            DirectoryStats::default()
+        );
+
+        let old = temp_dir.path().join("old");
+        let new = temp_dir.path().join("new");
+        std::fs::write(&old, "a").unwrap();
+        std::fs::write(&new, "bb").unwrap();
+        let epoch = std::time::UNIX_EPOCH;
+        std::fs::File::options().write(true).open(&old).unwrap()
+            .set_modified(epoch + std::time::Duration::from_secs(1_000)).unwrap();
+        std::fs::File::options().write(true).open(&new).unwrap()
+            .set_modified(epoch + std::time::Duration::from_secs(5_000)).unwrap();
+
+        let stats = get_directory_stats(temp_dir.path(), &SizeOptions::default()).unwrap();
+        assert_eq!(stats.size, 3);
+        assert_eq!(stats.oldest_mtime, Some(1_000));
+        assert_eq!(stats.newest_mtime, Some(5_000));
+    }
+
+    #[test]
+    fn test_get_directory_stats_by_atime() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file = temp_dir.path().join("artifact");
+        std::fs::write(&file, "a").unwrap();
+        let epoch = std::time::UNIX_EPOCH;
+        let times = std::fs::FileTimes::new()
+            .set_accessed(epoch + std::time::Duration::from_secs(2_000))
+            .set_modified(epoch + std::time::Duration::from_secs(9_000));
+        std::fs::File::options().write(true).open(&file).unwrap().set_times(times).unwrap();
+
+        let by_mtime = get_directory_stats(temp_dir.path(), &SizeOptions::default()).unwrap();
+        assert_eq!(by_mtime.newest_mtime, Some(9_000));
+
+        // Reading metadata doesn't touch access times, so they survive the walk
+        let options = SizeOptions { by_atime: true, ..Default::default() };
+        let by_atime = get_directory_stats(temp_dir.path(), &options).unwrap();
+        assert_eq!(by_atime.oldest_mtime, Some(2_000));
+        assert_eq!(by_atime.newest_mtime, Some(2_000));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_network_fs_by_magic() {
+        assert_eq!(network_fs_by_magic(0x6969), Some("NFS"));
+        assert_eq!(network_fs_by_magic(0xFF53_4D42), Some("CIFS"));
+        // ext4 and tmpfs are local
+        assert_eq!(network_fs_by_magic(0xEF53), None);
+        assert_eq!(network_fs_by_magic(0x0102_1994), None);
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_network_fs_by_name() {
+        assert_eq!(network_fs_by_name("nfs"), Some("nfs"));
+        assert_eq!(network_fs_by_name("smbfs"), Some("smbfs"));
+        assert_eq!(network_fs_by_name("apfs"), None);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90s").unwrap(), 90);
+        assert_eq!(parse_duration("12h").unwrap(), 12 * 3600);
+        assert_eq!(parse_duration(" 30D ").unwrap(), 30 * 86400);
+        assert_eq!(parse_duration("2w").unwrap(), 14 * 86400);
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("3y").is_err());
+        assert!(parse_duration("99999999999999999w").unwrap_err().to_string().contains("out of range"));
+    }
+
+    #[test]
+    fn test_format_duration_ago() {
+        assert_eq!(format_duration_ago(5), "5s ago");
+        assert_eq!(format_duration_ago(120), "2m ago");
+        assert_eq!(format_duration_ago(7200), "2h ago");
+        assert_eq!(format_duration_ago(86400 * 40), "40d ago");
+    }
+
+    #[test]
+    fn test_format_utc() {
+        assert_eq!(format_utc(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_utc(1_709_294_461), "2024-03-01T12:01:01Z");
+    }
+
     #[test]
     fn test_get_directory_size_nonexistent() {
-        let size = get_directory_size(Path::new("/nonexistent/path"));
+        let size = get_directory_size(Path::new("/nonexistent/path"), &SizeOptions::default());
         assert!(size.is_ok());
         assert_eq!(size.unwrap(), 0);
     }
@@ -0,0 +1,69 @@
+use std::sync::Mutex;
+
+/// A non-fatal problem encountered during a run
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Warning {
+    /// Project the warning relates to, if any
+    pub path: Option<String>,
+    pub message: String,
+}
+
+/// Thread-safe collector for warnings raised by parallel workers
+#[derive(Debug, Default)]
+pub struct WarningCollector {
+    warnings: Mutex<Vec<Warning>>,
+}
+
+impl WarningCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a warning; safe to call from any worker thread
+    pub fn push(&self, path: Option<&std::path::Path>, message: impl Into<String>) {
+        let warning = Warning {
+            path: path.map(|p| p.to_string_lossy().to_string()),
+            message: message.into(),
+        };
+        // A panicking worker must not cause other workers' warnings to be lost
+        self.warnings
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(warning);
+    }
+
+    /// Consume the collector, returning every recorded warning
+    pub fn into_warnings(self) -> Vec<Warning> {
+        self.warnings
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::prelude::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_warning_collector_parallel_stress() {
+        let collector = WarningCollector::new();
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(8).build().unwrap();
+        pool.install(|| {
+            (0..10_000).into_par_iter().for_each(|i| {
+                let path = format!("/projects/p{}", i % 100);
+                collector.push(Some(Path::new(&path)), format!("warning {}", i));
+            });
+        });
+
+        let warnings = collector.into_warnings();
+        assert_eq!(warnings.len(), 10_000);
+        let mut ids: Vec<usize> = warnings
+            .iter()
+            .map(|w| w.message.trim_start_matches("warning ").parse().unwrap())
+            .collect();
+        ids.sort_unstable();
+        assert!(ids.iter().enumerate().all(|(i, id)| i == *id));
+    }
+}